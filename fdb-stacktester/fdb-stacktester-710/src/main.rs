@@ -2041,6 +2041,12 @@ impl StackMachine {
         res.set_reverse(reverse);
         res.set_mode(mode);
 
+        // Binding tester does not have a notion of prefetch. Enable
+        // it for even limits (including no limit), so that both the
+        // prefetch and non-prefetch states of the range result state
+        // machines get exercised.
+        res.set_prefetch(limit % 2 == 0);
+
         res
     }
 
@@ -0,0 +1,535 @@
+//! Provides [`BulkWriter`] for loading large amounts of data.
+//!
+//! FDB limits the size of a transaction to 10MB and its duration to
+//! 5 seconds. [`BulkWriter`] takes a stream of [`BulkMutation`]s and
+//! splits it into multiple transactions, each of which is committed
+//! once its approximate size (as reported by
+//! [`get_approximate_size`]) reaches a configurable limit.
+//!
+//! Transactions are committed concurrently, with a bounded number of
+//! commits in flight. Commits in flight make progress while the next
+//! transaction is being filled. As [`BulkMutation`]s are idempotent,
+//! a transaction that fails with a retryable error (including
+//! `commit_unknown_result`) is retried by applying its mutations
+//! again.
+//!
+//! **Note:** Mutations in different transactions are not applied
+//! atomically, and the order in which transactions are committed is
+//! not defined. If the same key appears more than once in the stream,
+//! the final value of the key is not defined.
+//!
+//! [`get_approximate_size`]: crate::transaction::Transaction::get_approximate_size
+
+use bytes::Bytes;
+
+use futures::future::{self, Either};
+use futures::stream::FuturesUnordered;
+use futures::{Stream, StreamExt};
+
+use std::convert::TryInto;
+use std::time::{Duration, Instant};
+
+use crate::database::FdbDatabase;
+use crate::error::FdbResult;
+use crate::transaction::{FdbTransaction, Transaction};
+use crate::{Key, Value};
+
+#[cfg(feature = "fdb-7_1")]
+use crate::tenant::FdbTenant;
+
+/// A mutation that can be written using [`BulkWriter`].
+///
+/// Only idempotent mutations are provided. When a commit fails with
+/// `commit_unknown_result`, the transaction might have been
+/// committed, and its mutations are applied again. Atomic operations
+/// such as [`MutationType::Add`] would then be applied twice, so
+/// they are not supported by [`BulkWriter`].
+///
+/// [`MutationType::Add`]: crate::transaction::MutationType::Add
+#[derive(Clone, Debug)]
+pub enum BulkMutation {
+    /// Set [`Key`] to [`Value`].
+    Set(Key, Value),
+    /// Clear [`Key`].
+    Clear(Key),
+}
+
+impl BulkMutation {
+    fn apply<T>(&self, tr: &T)
+    where
+        T: Transaction,
+    {
+        match self {
+            BulkMutation::Set(key, value) => tr.set(key.clone(), value.clone()),
+            BulkMutation::Clear(key) => tr.clear(key.clone()),
+        }
+    }
+
+    // Number of bytes of keys and values in the mutation.
+    fn size(&self) -> usize {
+        match self {
+            BulkMutation::Set(key, value) => {
+                Bytes::from(key.clone()).len() + Bytes::from(value.clone()).len()
+            }
+            BulkMutation::Clear(key) => Bytes::from(key.clone()).len(),
+        }
+    }
+}
+
+/// [`BulkWriterOptions`] specify how [`BulkWriter`] splits and
+/// commits transactions.
+///
+/// There are two parameters for which accessors methods are provided.
+///
+/// 1. Transaction size is the approximate size in bytes at which a
+///    transaction is committed and a new transaction is started.
+///
+/// 2. Max in flight is the maximum number of transactions that are
+///    being committed concurrently. A value of zero is treated as
+///    one.
+///
+/// To create a value of [`BulkWriterOptions`] type, use
+/// [`Default::default`] method. The default value represents - a
+/// transaction size of 1MB and a max in flight of 4.
+#[derive(Clone, Debug)]
+pub struct BulkWriterOptions {
+    transaction_size: i64,
+    max_in_flight: usize,
+}
+
+impl BulkWriterOptions {
+    /// Set transaction size
+    pub fn set_transaction_size(&mut self, transaction_size: i64) {
+        self.transaction_size = transaction_size;
+    }
+
+    /// Get transaction size
+    pub fn get_transaction_size(&self) -> i64 {
+        self.transaction_size
+    }
+
+    /// Set max in flight
+    pub fn set_max_in_flight(&mut self, max_in_flight: usize) {
+        self.max_in_flight = max_in_flight;
+    }
+
+    /// Get max in flight
+    pub fn get_max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+}
+
+impl Default for BulkWriterOptions {
+    fn default() -> BulkWriterOptions {
+        BulkWriterOptions {
+            transaction_size: 1_000_000,
+            max_in_flight: 4,
+        }
+    }
+}
+
+/// Progress of a [`BulkWriter::write`] operation.
+#[derive(Clone, Debug)]
+pub struct BulkWriterProgress {
+    mutations: u64,
+    bytes: u64,
+    transactions: u64,
+    retries: u64,
+    elapsed: Duration,
+}
+
+impl BulkWriterProgress {
+    /// Get the number of mutations committed.
+    pub fn get_mutations(&self) -> u64 {
+        self.mutations
+    }
+
+    /// Get the number of bytes of keys and values committed.
+    pub fn get_bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Get the number of transactions committed.
+    pub fn get_transactions(&self) -> u64 {
+        self.transactions
+    }
+
+    /// Get the number of times a transaction was retried.
+    pub fn get_retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// Get the time elapsed since the write was started.
+    pub fn get_elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Get the number of mutations committed per second.
+    pub fn get_mutations_per_second(&self) -> f64 {
+        per_second(self.mutations, self.elapsed)
+    }
+
+    /// Get the number of bytes of keys and values committed per
+    /// second.
+    pub fn get_bytes_per_second(&self) -> f64 {
+        per_second(self.bytes, self.elapsed)
+    }
+
+    fn new() -> BulkWriterProgress {
+        BulkWriterProgress {
+            mutations: 0,
+            bytes: 0,
+            transactions: 0,
+            retries: 0,
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    fn add(&mut self, batch: &BulkWriterBatchResult, elapsed: Duration) {
+        self.mutations += batch.mutations;
+        self.bytes += batch.bytes;
+        self.transactions += 1;
+        self.retries += batch.retries;
+        self.elapsed = elapsed;
+    }
+}
+
+fn per_second(count: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs == 0.0 {
+        0.0
+    } else {
+        count as f64 / secs
+    }
+}
+
+#[derive(Clone, Debug)]
+enum BulkWriterContext {
+    Database(FdbDatabase),
+    #[cfg(feature = "fdb-7_1")]
+    Tenant(FdbTenant),
+}
+
+impl BulkWriterContext {
+    fn create_transaction(&self) -> FdbResult<FdbTransaction> {
+        match self {
+            BulkWriterContext::Database(fdb_database) => fdb_database.create_transaction(),
+            #[cfg(feature = "fdb-7_1")]
+            BulkWriterContext::Tenant(fdb_tenant) => fdb_tenant.create_transaction(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BulkWriterBatchResult {
+    mutations: u64,
+    bytes: u64,
+    retries: u64,
+}
+
+/// Writes a stream of [`BulkMutation`]s to the database, using
+/// multiple transactions.
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct BulkWriter {
+    context: BulkWriterContext,
+    options: BulkWriterOptions,
+}
+
+impl BulkWriter {
+    /// Create a [`BulkWriter`] that writes to [`FdbDatabase`].
+    pub fn new(fdb_database: FdbDatabase, options: BulkWriterOptions) -> BulkWriter {
+        BulkWriter {
+            context: BulkWriterContext::Database(fdb_database),
+            options,
+        }
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    /// Create a [`BulkWriter`] that writes to [`FdbTenant`].
+    pub fn new_with_tenant(fdb_tenant: FdbTenant, options: BulkWriterOptions) -> BulkWriter {
+        BulkWriter {
+            context: BulkWriterContext::Tenant(fdb_tenant),
+            options,
+        }
+    }
+
+    /// Write all the [`BulkMutation`]s in `mutations`.
+    ///
+    /// Returns the final [`BulkWriterProgress`] once all the
+    /// transactions have been committed. In case of a non-retryable
+    /// error, the error is returned and transactions that are being
+    /// committed are cancelled.
+    pub async fn write<S>(&self, mutations: S) -> FdbResult<BulkWriterProgress>
+    where
+        S: Stream<Item = BulkMutation> + Unpin,
+    {
+        self.write_with_progress(mutations, |_| {}).await
+    }
+
+    /// Same as [`write`], except that `f` is called with the
+    /// [`BulkWriterProgress`] each time a transaction is committed.
+    ///
+    /// [`write`]: BulkWriter::write
+    pub async fn write_with_progress<S, F>(
+        &self,
+        mut mutations: S,
+        mut f: F,
+    ) -> FdbResult<BulkWriterProgress>
+    where
+        S: Stream<Item = BulkMutation> + Unpin,
+        F: FnMut(&BulkWriterProgress),
+    {
+        let start = Instant::now();
+        let max_in_flight = self.options.get_max_in_flight().max(1);
+        let transaction_size = self.options.get_transaction_size();
+
+        let mut progress = BulkWriterProgress::new();
+        let mut in_flight = FuturesUnordered::new();
+
+        let mut tr = self.context.create_transaction()?;
+        let mut batch = Vec::new();
+
+        // Sum of `BulkMutation::size` in `batch`. This is a lower
+        // bound of the approximate size of the transaction, so we
+        // only ask for the approximate size once this reaches
+        // `transaction_size`.
+        let mut batch_bytes: i64 = 0;
+
+        loop {
+            let mutation = if in_flight.is_empty() {
+                mutations.next().await
+            } else {
+                // Poll the commits in flight while waiting for the
+                // next mutation, so that they make progress.
+                match future::select(in_flight.next(), mutations.next()).await {
+                    Either::Left((res, _)) => {
+                        // Safety: `in_flight` is not empty.
+                        progress.add(&res.unwrap()?, start.elapsed());
+                        f(&progress);
+                        continue;
+                    }
+                    Either::Right((mutation, _)) => mutation,
+                }
+            };
+
+            let mutation = match mutation {
+                Some(mutation) => mutation,
+                None => break,
+            };
+
+            mutation.apply(&tr);
+            batch_bytes += TryInto::<i64>::try_into(mutation.size()).unwrap();
+            batch.push(mutation);
+
+            if batch_bytes >= transaction_size
+                && tr.get_approximate_size().await? >= transaction_size
+            {
+                while in_flight.len() >= max_in_flight {
+                    // Safety: `in_flight` is not empty.
+                    let res = in_flight.next().await.unwrap()?;
+                    progress.add(&res, start.elapsed());
+                    f(&progress);
+                }
+
+                let next_tr = self.context.create_transaction()?;
+
+                in_flight.push(commit_batch(
+                    std::mem::replace(&mut tr, next_tr),
+                    std::mem::take(&mut batch),
+                ));
+                batch_bytes = 0;
+            }
+        }
+
+        if !batch.is_empty() {
+            in_flight.push(commit_batch(tr, batch));
+        }
+
+        while let Some(res) = in_flight.next().await {
+            progress.add(&res?, start.elapsed());
+            f(&progress);
+        }
+
+        progress.elapsed = start.elapsed();
+
+        Ok(progress)
+    }
+}
+
+// `tr` must have `batch` already applied to it. On a retryable error,
+// `batch` is applied again after `on_error` has reset `tr`.
+async fn commit_batch<T>(tr: T, batch: Vec<BulkMutation>) -> FdbResult<BulkWriterBatchResult>
+where
+    T: Transaction,
+{
+    let mut retries = 0;
+
+    while let Err(e) = unsafe { tr.commit() }.await {
+        unsafe { tr.on_error(e) }.await?;
+
+        retries += 1;
+        batch.iter().for_each(|mutation| mutation.apply(&tr));
+    }
+
+    Ok(BulkWriterBatchResult {
+        mutations: batch.len().try_into().unwrap(),
+        bytes: batch
+            .iter()
+            .map(|mutation| TryInto::<u64>::try_into(mutation.size()).unwrap())
+            .sum(),
+        retries,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use std::time::Duration;
+
+    use crate::{Key, Value};
+
+    use super::{
+        BulkMutation, BulkWriter, BulkWriterBatchResult, BulkWriterOptions, BulkWriterProgress,
+    };
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    BulkWriter:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    BulkMutation:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn bulk_mutation_size() {
+        assert_eq!(
+            BulkMutation::Set(
+                Key::from(Bytes::from_static(b"hello")),
+                Value::from(Bytes::from_static(b"world!"))
+            )
+            .size(),
+            11
+        );
+        assert_eq!(
+            BulkMutation::Clear(Key::from(Bytes::from_static(b"hello"))).size(),
+            5
+        );
+    }
+
+    #[test]
+    fn bulk_writer_options() {
+        let mut options = BulkWriterOptions::default();
+        assert_eq!(options.get_transaction_size(), 1_000_000);
+        assert_eq!(options.get_max_in_flight(), 4);
+
+        options.set_transaction_size(100);
+        options.set_max_in_flight(1);
+        assert_eq!(options.get_transaction_size(), 100);
+        assert_eq!(options.get_max_in_flight(), 1);
+    }
+
+    #[test]
+    fn bulk_writer_progress() {
+        let mut progress = BulkWriterProgress::new();
+        assert_eq!(progress.get_mutations_per_second(), 0.0);
+
+        progress.add(
+            &BulkWriterBatchResult {
+                mutations: 10,
+                bytes: 100,
+                retries: 1,
+            },
+            Duration::from_secs(1),
+        );
+        progress.add(
+            &BulkWriterBatchResult {
+                mutations: 30,
+                bytes: 300,
+                retries: 0,
+            },
+            Duration::from_secs(2),
+        );
+
+        assert_eq!(progress.get_mutations(), 40);
+        assert_eq!(progress.get_bytes(), 400);
+        assert_eq!(progress.get_transactions(), 2);
+        assert_eq!(progress.get_retries(), 1);
+        assert_eq!(progress.get_elapsed(), Duration::from_secs(2));
+        assert_eq!(progress.get_mutations_per_second(), 20.0);
+        assert_eq!(progress.get_bytes_per_second(), 200.0);
+    }
+
+    #[cfg(all(feature = "memory", feature = "fault"))]
+    #[tokio::test]
+    async fn commit_batch_retry() {
+        use crate::error::FdbError;
+        use crate::fault::{FaultInjector, FaultPoint};
+        use crate::memory::MemoryDatabase;
+        use crate::transaction::{ReadTransaction, Transaction};
+
+        use super::commit_batch;
+
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        tr.set(Bytes::from_static(b"b"), Bytes::from_static(b"old"));
+        unsafe { tr.commit() }.await.unwrap();
+
+        let injector = FaultInjector::new(0);
+
+        // First commit fails without committing, second commit
+        // succeeds but reports `commit_unknown_result`.
+        injector.fail_on_calls(FaultPoint::Commit, 1020, vec![0]);
+        injector.fail_on_calls(FaultPoint::AfterCommit, 1021, vec![0]);
+
+        let batch = vec![
+            BulkMutation::Set(
+                Key::from(Bytes::from_static(b"a")),
+                Value::from(Bytes::from_static(b"1")),
+            ),
+            BulkMutation::Clear(Key::from(Bytes::from_static(b"b"))),
+        ];
+
+        let tr = injector.wrap(db.create_transaction().unwrap());
+        batch.iter().for_each(|mutation| mutation.apply(&tr));
+
+        let res = commit_batch(tr, batch).await.unwrap();
+        assert_eq!(res.mutations, 2);
+        assert_eq!(res.bytes, 3);
+        assert_eq!(res.retries, 2);
+
+        let tr = db.create_transaction().unwrap();
+        assert_eq!(
+            tr.get(Bytes::from_static(b"a")).await.unwrap(),
+            Some(Value::from(Bytes::from_static(b"1")))
+        );
+        assert_eq!(tr.get(Bytes::from_static(b"b")).await.unwrap(), None);
+
+        // Non-retryable error is returned.
+        injector.fail_on_calls(FaultPoint::Commit, 2101, vec![3]);
+
+        let tr = injector.wrap(db.create_transaction().unwrap());
+
+        assert_eq!(
+            commit_batch(tr, Vec::new()).await.unwrap_err(),
+            FdbError::new(2101)
+        );
+    }
+}
@@ -7,16 +7,27 @@ use std::ptr::{self, NonNull};
 use std::sync::Arc;
 
 use crate::database::DatabaseOption;
-use crate::error::{check, FdbError, FdbResult};
+use crate::error::{check, FdbResult};
 use crate::range::{Range, RangeOptions};
-use crate::transaction::{
-    FdbReadTransaction, FdbTransaction, ReadTransaction, Transaction, TransactionOption,
-};
+use crate::transaction::retry_loop;
+use crate::transaction::{FdbReadTransaction, FdbTransaction, ReadTransaction, TransactionOption};
 use crate::Key;
 
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
+#[cfg(feature = "fdb-7_1")]
+use uuid::Uuid;
+
 #[cfg(feature = "fdb-7_1")]
 use std::convert::TryInto;
 
+#[cfg(feature = "fdb-7_1")]
+use std::time::Duration;
+
+#[cfg(feature = "fdb-7_1")]
+use crate::future::{FdbFutureI64, FdbFutureU64, FdbFutureUnit};
+
 #[cfg(feature = "fdb-7_1")]
 use crate::Tenant;
 
@@ -39,7 +50,7 @@ use crate::tenant::FdbTenant;
 ///
 /// A [`FdbDatabase`] can be created using [`open_database`] function.
 ///
-/// [`commit`]: FdbTransaction::commit
+/// [`commit`]: crate::transaction::Transaction::commit
 /// [`read`]: FdbDatabase::read
 /// [`run`]: FdbDatabase::run
 /// [`open_database`]: crate::open_database
@@ -149,7 +160,12 @@ impl FdbDatabase {
     /// **Note:** Opening a tenant does not check its existence in the
     /// cluster.
     pub fn open_tenant(&self, tenant_name: impl Into<Tenant>) -> FdbResult<FdbTenant> {
-        let t = Bytes::from(tenant_name.into());
+        let tenant_name = tenant_name.into();
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("open_tenant", tenant = ?tenant_name).entered();
+
+        let t = Bytes::from(tenant_name);
         let tenant_name = t.as_ref().as_ptr();
         let tenant_name_length = t.as_ref().len().try_into().unwrap();
 
@@ -176,6 +192,128 @@ impl FdbDatabase {
         })
     }
 
+    /// Reboots the worker process at `address`.
+    ///
+    /// If `check` is `true`, the worker checks the integrity of its
+    /// data when it restarts. If `duration` is non-zero, the worker
+    /// is suspended for `duration` (in seconds) before it is
+    /// rebooted.
+    ///
+    /// Returns `true` if the worker was found and asked to reboot.
+    #[cfg(feature = "fdb-7_1")]
+    pub async fn reboot_worker(
+        &self,
+        address: &str,
+        check: bool,
+        duration: Duration,
+    ) -> FdbResult<bool> {
+        let address_length = address.len().try_into().unwrap();
+        let duration = duration.as_secs().try_into().unwrap_or(i32::MAX);
+
+        // Safety: It is safe to unwrap here because if we have given
+        // out an `FdbDatabase` then `c_ptr` *must* be
+        // `Some<Arc<...>>`.
+        let fut = FdbFutureI64::new(unsafe {
+            fdb_sys::fdb_database_reboot_worker(
+                (*(self.c_ptr.as_ref().unwrap())).as_ptr(),
+                address.as_ptr(),
+                address_length,
+                if check { 1 } else { 0 },
+                duration,
+            )
+        });
+
+        fut.await.map(|x| x != 0)
+    }
+
+    /// Forces the database to recover into the datacenter `dcid`.
+    ///
+    /// **Warning:** Mutations that were committed, but not yet
+    /// replicated to `dcid`, are lost. This is meant to be used when
+    /// the primary datacenter has failed.
+    #[cfg(feature = "fdb-7_1")]
+    pub async fn force_recovery_with_data_loss(&self, dcid: &str) -> FdbResult<()> {
+        let dcid_length = dcid.len().try_into().unwrap();
+
+        // Safety: It is safe to unwrap here because if we have given
+        // out an `FdbDatabase` then `c_ptr` *must* be
+        // `Some<Arc<...>>`.
+        let fut = FdbFutureUnit::new(unsafe {
+            fdb_sys::fdb_database_force_recovery_with_data_loss(
+                (*(self.c_ptr.as_ref().unwrap())).as_ptr(),
+                dcid.as_ptr(),
+                dcid_length,
+            )
+        });
+
+        fut.await
+    }
+
+    /// Creates a snapshot of the database, identified by `uid`.
+    ///
+    /// `snap_command` is the command (and its arguments) run on each
+    /// process to snapshot its data, such as a script that copies
+    /// the data directory.
+    #[cfg(feature = "fdb-7_1")]
+    pub async fn create_snapshot(&self, uid: Uuid, snap_command: &str) -> FdbResult<()> {
+        let uid = uid.to_simple().to_string();
+        let uid_length = uid.len().try_into().unwrap();
+        let snap_command_length = snap_command.len().try_into().unwrap();
+
+        // Safety: It is safe to unwrap here because if we have given
+        // out an `FdbDatabase` then `c_ptr` *must* be
+        // `Some<Arc<...>>`.
+        let fut = FdbFutureUnit::new(unsafe {
+            fdb_sys::fdb_database_create_snapshot(
+                (*(self.c_ptr.as_ref().unwrap())).as_ptr(),
+                uid.as_ptr(),
+                uid_length,
+                snap_command.as_ptr(),
+                snap_command_length,
+            )
+        });
+
+        fut.await
+    }
+
+    /// Returns a value between `0.0` and `1.0`, which is the fraction
+    /// of time the client network thread is busy.
+    ///
+    /// This is a recent average, and not the busyness at this
+    /// instant.
+    #[cfg(feature = "fdb-7_1")]
+    pub fn get_main_thread_busyness(&self) -> f64 {
+        // Safety: It is safe to unwrap here because if we have given
+        // out an `FdbDatabase` then `c_ptr` *must* be
+        // `Some<Arc<...>>`.
+        unsafe {
+            fdb_sys::fdb_database_get_main_thread_busyness(
+                (*(self.c_ptr.as_ref().unwrap())).as_ptr(),
+            )
+        }
+    }
+
+    /// Returns the protocol version of the cluster.
+    ///
+    /// If `expected_version` is non-zero, this waits until the
+    /// protocol version of the cluster is different from
+    /// `expected_version`. This can be used to detect upgrades of the
+    /// cluster.
+    #[cfg(feature = "fdb-7_1")]
+    pub async fn get_server_protocol(&self, expected_version: u64) -> FdbResult<u64> {
+        // Safety: It is safe to unwrap here because if we have given
+        // out an `FdbDatabase` then `c_ptr` *must* be
+        // `Some<Arc<...>>`.
+        let fut = FdbFutureU64::new(unsafe {
+            fdb_sys::fdb_database_get_server_protocol(
+                (*(self.c_ptr.as_ref().unwrap())).as_ptr(),
+                expected_version,
+            )
+        });
+
+        fut.await
+    }
+
     // In Java following method is on `Interface TransactionContext`.
 
     /// Runs a closure in the context that takes a [`FdbTransaction`].
@@ -187,47 +325,67 @@ impl FdbDatabase {
     /// encountered. Therefore the closure should be prepared to be
     /// called more than once. This consideration means that the
     /// closure should use caution when modifying state.
-    pub async fn run<T, F, Fut>(&self, mut f: F) -> FdbResult<T>
+    pub async fn run<T, F, Fut>(&self, f: F) -> FdbResult<T>
     where
         F: FnMut(FdbTransaction) -> Fut,
         Fut: Future<Output = FdbResult<T>>,
     {
         let t = self.create_transaction()?;
 
-        loop {
-            let ret_val = f(t.clone()).await;
-
-            // Closure returned an error
-            if let Err(e) = ret_val {
-                if FdbError::layer_error(e.code()) {
-                    // Check if it is a layer error. If so, just
-                    // return it.
-                    return Err(e);
-                } else if let Err(e1) = unsafe { t.on_error(e) }.await {
-                    // Check if `on_error` returned an error. This
-                    // means we have a non-retryable error.
-                    return Err(e1);
-                } else {
-                    continue;
-                }
-            }
+        let fut = retry_loop::run(t, f);
 
-            // No error from closure. Attempt to commit the
-            // transaction.
-            if let Err(e) = unsafe { t.commit() }.await {
-                // Commit returned an error
-                if let Err(e1) = unsafe { t.on_error(e) }.await {
-                    // Check if `on_error` returned an error. This
-                    // means we have a non-retryable error.
-                    return Err(e1);
-                } else {
-                    continue;
-                }
-            }
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(tracing::debug_span!("run"));
 
-            // Commit successful, return `Ok(T)`
-            return ret_val;
-        }
+        fut.await
+    }
+
+    /// Runs a closure in the context that takes a [`FdbTransaction`],
+    /// and reports the key ranges that caused the transaction to
+    /// conflict.
+    ///
+    /// This is the same as [`run`], except that
+    /// [`TransactionOption::ReportConflictingKeys`] is set on the
+    /// transaction, and `on_conflict` is called with the conflicting
+    /// key ranges each time [`commit`] fails with a `not_committed`
+    /// (1020) error, before the closure is retried. If retries are
+    /// exhausted (for example, when
+    /// [`TransactionOption::RetryLimit`] is set), the last call to
+    /// `on_conflict` has the key ranges that caused the returned
+    /// error.
+    ///
+    /// Errors while getting the conflicting key ranges are ignored.
+    ///
+    /// # Note
+    ///
+    /// The closure `FnMut: FnMut(FdbTransaction) -> Fut` will run
+    /// multiple times (retry) when certain errors are
+    /// encountered. Therefore the closure should be prepared to be
+    /// called more than once. This consideration means that the
+    /// closure should use caution when modifying state.
+    ///
+    /// [`run`]: FdbDatabase::run
+    /// [`commit`]: crate::transaction::Transaction::commit
+    /// [`TransactionOption::ReportConflictingKeys`]: crate::transaction::TransactionOption::ReportConflictingKeys
+    /// [`TransactionOption::RetryLimit`]: crate::transaction::TransactionOption::RetryLimit
+    pub async fn run_with_conflicting_keys<T, F, Fut, C>(
+        &self,
+        f: F,
+        on_conflict: C,
+    ) -> FdbResult<T>
+    where
+        F: FnMut(FdbTransaction) -> Fut,
+        Fut: Future<Output = FdbResult<T>>,
+        C: FnMut(Vec<Range>),
+    {
+        let t = self.create_transaction()?;
+
+        let fut = retry_loop::run_with_conflicting_keys(t, f, Some(on_conflict));
+
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(tracing::debug_span!("run"));
+
+        fut.await
     }
 
     // In Java following method is on `Interface
@@ -248,34 +406,19 @@ impl FdbDatabase {
     // `FnMut(FdbReadTransaction) -> Fut` because we are not allowing
     // any mutations to occur. We are only concerned about retrying in
     // case of retryable errors.
-    pub async fn read<T, F, Fut>(&self, mut f: F) -> FdbResult<T>
+    pub async fn read<T, F, Fut>(&self, f: F) -> FdbResult<T>
     where
         F: FnMut(FdbReadTransaction) -> Fut,
         Fut: Future<Output = FdbResult<T>>,
     {
         let t = self.create_transaction()?.snapshot();
-        loop {
-            let ret_val = f(t.clone()).await;
-
-            // Closure returned an error
-            if let Err(e) = ret_val {
-                if FdbError::layer_error(e.code()) {
-                    // Check if it is a layer error. If so, just
-                    // return it.
-                    return Err(e);
-                } else if let Err(e1) = unsafe { t.on_error(e) }.await {
-                    // Check if `on_error` returned an error. This
-                    // means we have a non-retryable error.
-                    return Err(e1);
-                } else {
-                    continue;
-                }
-            }
 
-            // We don't need to commit read transaction, return
-            // `Ok(T)`
-            return ret_val;
-        }
+        let fut = retry_loop::read(t, f);
+
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(tracing::debug_span!("read"));
+
+        fut.await
     }
 
     /// Set options on a [`FdbDatabase`].
@@ -9,6 +9,7 @@
 //! [`commit`]: crate::transaction::Transaction::commit
 
 mod fdb_database;
+mod shard_map;
 
 #[doc(hidden)]
 pub mod open_database;
@@ -16,3 +17,4 @@ pub mod open_database;
 pub use crate::option::DatabaseOption;
 
 pub use fdb_database::FdbDatabase;
+pub use shard_map::{Shard, ShardMap};
@@ -0,0 +1,359 @@
+use bytes::Bytes;
+
+use parking_lot::Mutex;
+
+use std::ffi::CString;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::database::FdbDatabase;
+use crate::error::FdbResult;
+use crate::range::Range;
+use crate::transaction::ReadTransaction;
+use crate::Key;
+
+/// A contiguous range of keys stored on a single set of storage
+/// servers.
+#[derive(Clone, Debug)]
+pub struct Shard {
+    range: Range,
+    addresses: Vec<CString>,
+}
+
+impl Shard {
+    /// Gets a reference to the [`Range`] of keys in the [`Shard`].
+    pub fn get_range_ref(&self) -> &Range {
+        &self.range
+    }
+
+    /// Gets a reference to the public network addresses of the
+    /// storage servers responsible for the [`Shard`].
+    pub fn get_addresses_ref(&self) -> &Vec<CString> {
+        &self.addresses
+    }
+
+    /// Extract [`Range`] and addresses from [`Shard`].
+    pub fn into_parts(self) -> (Range, Vec<CString>) {
+        (self.range, self.addresses)
+    }
+
+    fn new(range: Range, addresses: Vec<CString>) -> Shard {
+        Shard { range, addresses }
+    }
+}
+
+/// A cache of shard boundaries and storage server addresses of a
+/// [`FdbDatabase`].
+///
+/// [`ShardMap`] can be used to find the [`Shard`] that a key belongs
+/// to and to group a batch of keys by [`Shard`], so that work can be
+/// partitioned along storage boundaries.
+///
+/// The cached shard boundaries and addresses are reloaded from the
+/// database when they are older than the time-to-live provided to
+/// [`ShardMap::new`]. Shard boundaries can change at any time, so the
+/// information returned by [`ShardMap`] should only be used as a
+/// hint.
+///
+/// Cloning a [`ShardMap`] is cheap, and the clones share the same
+/// cache.
+#[derive(Clone, Debug)]
+pub struct ShardMap {
+    fdb_database: FdbDatabase,
+    ttl: Duration,
+    shards: Arc<Mutex<Option<ShardMapShards>>>,
+}
+
+impl ShardMap {
+    /// Create a new [`ShardMap`] for [`FdbDatabase`]. The cached
+    /// shard boundaries and addresses are reloaded once they are
+    /// older than `ttl`.
+    pub fn new(fdb_database: FdbDatabase, ttl: Duration) -> ShardMap {
+        ShardMap {
+            fdb_database,
+            ttl,
+            shards: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Reload the shard boundaries and addresses from the database.
+    pub async fn refresh(&self) -> FdbResult<()> {
+        self.load().await.map(|_| ())
+    }
+
+    /// Discard the cached shard boundaries and addresses. They will
+    /// be reloaded from the database when next needed.
+    pub fn invalidate(&self) {
+        *self.shards.lock() = None;
+    }
+
+    /// Get all the [`Shard`]s in the database, in key order.
+    pub async fn get_shards(&self) -> FdbResult<Vec<Shard>> {
+        self.get_shard_map_shards()
+            .await
+            .map(|s| s.shards.as_ref().clone())
+    }
+
+    /// Get the [`Shard`] that `key` belongs to.
+    pub async fn get_shard(&self, key: impl Into<Key>) -> FdbResult<Shard> {
+        let key = Bytes::from(key.into());
+
+        self.get_shard_map_shards()
+            .await
+            .map(|s| s.shards[s.find(&key)].clone())
+    }
+
+    /// Group `keys` by the [`Shard`] that they belong to.
+    ///
+    /// The groups are returned in key order of the [`Shard`]s. Within
+    /// each group, the keys are in the same order as `keys`. Shards
+    /// without any keys are not returned.
+    pub async fn group_by_shard<I, K>(&self, keys: I) -> FdbResult<Vec<(Shard, Vec<Key>)>>
+    where
+        I: IntoIterator<Item = K>,
+        K: Into<Key>,
+    {
+        let s = self.get_shard_map_shards().await?;
+
+        Ok(s.group(keys.into_iter().map(Into::into)))
+    }
+
+    async fn get_shard_map_shards(&self) -> FdbResult<ShardMapShards> {
+        let cached = self
+            .shards
+            .lock()
+            .as_ref()
+            .filter(|s| s.loaded_at.elapsed() < self.ttl)
+            .cloned();
+
+        match cached {
+            Some(s) => Ok(s),
+            None => self.load().await,
+        }
+    }
+
+    async fn load(&self) -> FdbResult<ShardMapShards> {
+        // Cover the whole keyspace, including the system keys.
+        let mut boundaries = self
+            .fdb_database
+            .get_boundary_keys(Bytes::new(), Bytes::from_static(b"\xFF\xFF"), 0, 0)
+            .await?
+            .into_iter()
+            .map(Bytes::from)
+            .collect::<Vec<_>>();
+
+        if boundaries.first().map(|b| !b.is_empty()).unwrap_or(true) {
+            boundaries.insert(0, Bytes::new());
+        }
+
+        let addresses = self
+            .fdb_database
+            .read(|tr| {
+                let boundaries = boundaries.clone();
+                async move {
+                    // Issue all the reads before waiting on them.
+                    let fdb_futures = boundaries
+                        .into_iter()
+                        .map(|b| tr.get_addresses_for_key(b))
+                        .collect::<Vec<_>>();
+
+                    let mut addresses = Vec::with_capacity(fdb_futures.len());
+                    for fdb_future in fdb_futures {
+                        addresses.push(fdb_future.await?);
+                    }
+
+                    Ok(addresses)
+                }
+            })
+            .await?;
+
+        let s = ShardMapShards::new(boundaries, addresses);
+
+        *self.shards.lock() = Some(s.clone());
+
+        Ok(s)
+    }
+}
+
+// Shards sorted by their begin key. `boundaries[i]` is the begin key
+// of `shards[i]`. The first boundary is always the empty key.
+#[derive(Clone, Debug)]
+struct ShardMapShards {
+    boundaries: Arc<Vec<Bytes>>,
+    shards: Arc<Vec<Shard>>,
+    loaded_at: Instant,
+}
+
+impl ShardMapShards {
+    fn new(boundaries: Vec<Bytes>, addresses: Vec<Vec<CString>>) -> ShardMapShards {
+        let ends = boundaries
+            .iter()
+            .skip(1)
+            .cloned()
+            .chain(std::iter::once(Bytes::from_static(b"\xFF\xFF")));
+
+        let shards = boundaries
+            .iter()
+            .cloned()
+            .zip(ends)
+            .zip(addresses)
+            .map(|((begin, end), addresses)| Shard::new(Range::new(begin, end), addresses))
+            .collect();
+
+        ShardMapShards {
+            boundaries: Arc::new(boundaries),
+            shards: Arc::new(shards),
+            loaded_at: Instant::now(),
+        }
+    }
+
+    // Index of the shard containing `key`, which is the last shard
+    // whose begin key is less than or equal to `key`.
+    fn find(&self, key: &Bytes) -> usize {
+        match self.boundaries.binary_search(key) {
+            Ok(i) => i,
+            // Safety: The first boundary is the empty key, which is
+            // less than or equal to any key. So `i` is at least `1`.
+            Err(i) => i - 1,
+        }
+    }
+
+    fn group(&self, keys: impl Iterator<Item = Key>) -> Vec<(Shard, Vec<Key>)> {
+        let mut groups: Vec<Vec<Key>> = vec![Vec::new(); self.shards.len()];
+
+        for key in keys {
+            let i = self.find(&Bytes::from(key.clone()));
+            groups[i].push(key);
+        }
+
+        self.shards
+            .iter()
+            .cloned()
+            .zip(groups)
+            .filter(|(_, keys)| !keys.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use std::ffi::CString;
+
+    use crate::range::Range;
+    use crate::Key;
+
+    use super::{Shard, ShardMap, ShardMapShards};
+
+    fn shard_map_shards() -> ShardMapShards {
+        ShardMapShards::new(
+            vec![
+                Bytes::new(),
+                Bytes::from_static(b"g"),
+                Bytes::from_static(b"p"),
+            ],
+            vec![
+                vec![CString::new("127.0.0.1:4500").unwrap()],
+                vec![CString::new("127.0.0.1:4501").unwrap()],
+                vec![CString::new("127.0.0.1:4502").unwrap()],
+            ],
+        )
+    }
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Shard:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    ShardMap:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn shard_map_shards_new() {
+        let s = shard_map_shards();
+
+        assert_eq!(
+            s.shards[0].get_range_ref(),
+            &Range::new(Bytes::new(), Bytes::from_static(b"g"))
+        );
+        assert_eq!(
+            s.shards[1].get_range_ref(),
+            &Range::new(Bytes::from_static(b"g"), Bytes::from_static(b"p"))
+        );
+        assert_eq!(
+            s.shards[2].get_range_ref(),
+            &Range::new(Bytes::from_static(b"p"), Bytes::from_static(b"\xFF\xFF"))
+        );
+        assert_eq!(
+            s.shards[2].get_addresses_ref(),
+            &vec![CString::new("127.0.0.1:4502").unwrap()]
+        );
+    }
+
+    #[test]
+    fn shard_map_shards_find() {
+        let s = shard_map_shards();
+
+        assert_eq!(s.find(&Bytes::new()), 0);
+        assert_eq!(s.find(&Bytes::from_static(b"a")), 0);
+        assert_eq!(s.find(&Bytes::from_static(b"g")), 1);
+        assert_eq!(s.find(&Bytes::from_static(b"g\x00")), 1);
+        assert_eq!(s.find(&Bytes::from_static(b"p")), 2);
+        assert_eq!(s.find(&Bytes::from_static(b"\xFF\x02")), 2);
+    }
+
+    #[test]
+    fn shard_map_shards_group() {
+        let s = shard_map_shards();
+
+        let groups = s.group(
+            vec![
+                Bytes::from_static(b"z"),
+                Bytes::from_static(b"a"),
+                Bytes::from_static(b"q"),
+                Bytes::from_static(b"b"),
+            ]
+            .into_iter()
+            .map(Key::from),
+        );
+
+        assert_eq!(groups.len(), 2);
+
+        assert_eq!(
+            groups[0].0.get_range_ref(),
+            &Range::new(Bytes::new(), Bytes::from_static(b"g"))
+        );
+        assert_eq!(
+            groups[0].1,
+            vec![
+                Key::from(Bytes::from_static(b"a")),
+                Key::from(Bytes::from_static(b"b"))
+            ]
+        );
+
+        assert_eq!(
+            groups[1].0.get_range_ref(),
+            &Range::new(Bytes::from_static(b"p"), Bytes::from_static(b"\xFF\xFF"))
+        );
+        assert_eq!(
+            groups[1].1,
+            vec![
+                Key::from(Bytes::from_static(b"z")),
+                Key::from(Bytes::from_static(b"q"))
+            ]
+        );
+    }
+}
@@ -19,6 +19,18 @@ use crate::option::ErrorPredicate;
 // 100 - `database` module
 // 110 - `tuple` module
 // 120 - `subspace` module
+// 130 - `range` module
+// 140 - `layer::blob` module
+// 150 - `layer::queue` module
+// 160 - `layer::work_queue` module
+// 170 - `layer::lock` module
+// 180 - `layer::leader_election` module
+// 190 - `layer::ranked_set` module
+// 200 - `layer::index` module
+// 210 - `mapped_key_value` module
+// 220 - `fault` module
+// 230 - `status` module
+// 240 - `management` module
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct FdbError {
     /// FoundationDB error code `fdb_error_t`
@@ -72,6 +84,94 @@ pub const SUBSPACE_PACK_WITH_VERSIONSTAMP_PREFIX_INCOMPLETE: i32 = 120;
 /// [`Subspace`]:  crate::subspace::Subspace
 pub const SUBSPACE_UNPACK_KEY_MISMATCH: i32 = 121;
 
+/// Error occurred when trying to decode a [`RangeContinuation`]
+/// from [`Bytes`].
+///
+/// [`RangeContinuation`]: crate::range::RangeContinuation
+/// [`Bytes`]: bytes::Bytes
+pub const RANGE_CONTINUATION_INVALID: i32 = 130;
+
+/// Error occurred when an offset or size provided to a [`Blob`]
+/// operation is out of bounds.
+///
+/// [`Blob`]: crate::layer::blob::Blob
+pub const BLOB_INVALID_OFFSET: i32 = 140;
+
+/// Error occurred when a [`Blob`] was modified by another writer while
+/// it was being written using multiple transactions.
+///
+/// [`Blob`]: crate::layer::blob::Blob
+pub const BLOB_CONCURRENT_MODIFICATION: i32 = 141;
+
+/// Error occurred when a [`WorkItem`] is no longer leased by the
+/// worker, either because it was acknowledged or because its lease
+/// expired and it was claimed by another worker.
+///
+/// [`WorkItem`]: crate::layer::work_queue::WorkItem
+pub const WORK_QUEUE_LEASE_LOST: i32 = 160;
+
+/// Error occurred when a [`LockLease`] is no longer held by its
+/// owner, either because it was released or because it expired and
+/// the lock was acquired by another owner.
+///
+/// [`LockLease`]: crate::layer::lock::LockLease
+pub const LOCK_LEASE_LOST: i32 = 170;
+
+/// Error occurred when a fencing token provided to
+/// [`LeaderElection::check_leader`] does not belong to the current
+/// leader.
+///
+/// [`LeaderElection::check_leader`]: crate::layer::leader_election::LeaderElection::check_leader
+pub const LEADER_ELECTION_NOT_LEADER: i32 = 180;
+
+/// Error occurred when trying to insert an empty key into a
+/// [`RankedSet`]. The empty key is reserved.
+///
+/// [`RankedSet`]: crate::layer::ranked_set::RankedSet
+pub const RANKED_SET_EMPTY_KEY: i32 = 190;
+
+/// Error occurred when an [`Index`] with the given name does not exist
+/// in an [`IndexedSubspace`].
+///
+/// [`Index`]: crate::layer::index::Index
+/// [`IndexedSubspace`]: crate::layer::index::IndexedSubspace
+pub const INDEX_NOT_FOUND: i32 = 200;
+
+/// Error occurred when a primary key provided to an
+/// [`IndexedSubspace`] does not have the expected number of elements.
+///
+/// [`IndexedSubspace`]: crate::layer::index::IndexedSubspace
+pub const INDEX_INVALID_PRIMARY_KEY: i32 = 201;
+
+/// Error occurred when a literal string or byte string added to a
+/// [`MapperBuilder`] contains a `{` or `}` that is not escaped as `{{`
+/// or `}}`.
+///
+/// [`MapperBuilder`]: crate::MapperBuilder
+#[cfg(feature = "fdb-7_1")]
+pub const MAPPER_INVALID_LITERAL: i32 = 210;
+
+/// Error occurred when the probability given to a [`FaultInjector`]
+/// is not between `0.0` and `1.0`.
+///
+/// [`FaultInjector`]: crate::fault::FaultInjector
+#[cfg(feature = "fault")]
+pub const FAULT_INVALID_PROBABILITY: i32 = 220;
+
+/// Error occurred when the status JSON could not be read or parsed
+/// into [`FdbStatus`].
+///
+/// [`FdbStatus`]: crate::status::FdbStatus
+#[cfg(feature = "status")]
+pub const STATUS_INVALID_JSON: i32 = 230;
+
+/// Error occurred when a value read by [`ClusterManagement`] could
+/// not be decoded.
+///
+/// [`ClusterManagement`]: crate::management::ClusterManagement
+#[cfg(feature = "fdb-7_1")]
+pub const MANAGEMENT_INVALID_VALUE: i32 = 240;
+
 /// Alias for [`Result`]`<T,`[`FdbError`]`>`
 ///
 /// [`Result`]: std::result::Result
@@ -0,0 +1,298 @@
+use parking_lot::Mutex;
+
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::error::{FdbError, FdbResult, FAULT_INVALID_PROBABILITY};
+use crate::fault::FaultTransaction;
+use crate::transaction::{retry_loop, Transaction};
+
+/// Point at which a [`FaultInjector`] injects a fault.
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::fault
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FaultPoint {
+    /// A read fails without being issued. This includes
+    /// [`get_read_version`], but not [`on_error`].
+    ///
+    /// [`get_read_version`]: crate::transaction::ReadTransaction::get_read_version
+    /// [`on_error`]: crate::transaction::ReadTransaction::on_error
+    Read,
+    /// A commit fails without committing.
+    Commit,
+    /// A commit succeeds, but an error is returned.
+    AfterCommit,
+}
+
+impl FaultPoint {
+    fn index(self) -> usize {
+        match self {
+            FaultPoint::Read => 0,
+            FaultPoint::Commit => 1,
+            FaultPoint::AfterCommit => 2,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Trigger {
+    Probability(f64),
+    Calls(Vec<u64>),
+}
+
+#[derive(Debug)]
+struct Fault {
+    point: FaultPoint,
+    error_code: i32,
+    trigger: Trigger,
+}
+
+// `calls` is the number of times each `FaultPoint` was reached,
+// indexed by `FaultPoint::index`.
+#[derive(Debug)]
+struct State {
+    faults: Vec<Fault>,
+    calls: [u64; 3],
+    injected: u64,
+    rng: u64,
+}
+
+/// Injects faults into transactions wrapped using [`wrap`].
+///
+/// Cloning a [`FaultInjector`] returns a handle to the same
+/// injector, so faults and call counts are shared.
+///
+/// See [module] documentation for details.
+///
+/// [`wrap`]: FaultInjector::wrap
+/// [module]: crate::fault
+#[derive(Clone, Debug)]
+pub struct FaultInjector {
+    state: Arc<Mutex<State>>,
+}
+
+impl FaultInjector {
+    /// Create a new [`FaultInjector`] with no faults. `seed` is used
+    /// to seed the random number generator used by
+    /// [`fail_with_probability`].
+    ///
+    /// [`fail_with_probability`]: FaultInjector::fail_with_probability
+    pub fn new(seed: u64) -> FaultInjector {
+        FaultInjector {
+            state: Arc::new(Mutex::new(State {
+                faults: Vec::new(),
+                calls: [0; 3],
+                injected: 0,
+                // xorshift must not be seeded with `0`.
+                rng: (seed ^ 0x9e37_79b9_7f4a_7c15) | 1,
+            })),
+        }
+    }
+
+    /// Fail operations at `point` with `error_code`, with the given
+    /// `probability`.
+    ///
+    /// Returns an error with [`FAULT_INVALID_PROBABILITY`] code if
+    /// `probability` is not between `0.0` and `1.0`.
+    pub fn fail_with_probability(
+        &self,
+        point: FaultPoint,
+        error_code: i32,
+        probability: f64,
+    ) -> FdbResult<()> {
+        if !(0.0..=1.0).contains(&probability) {
+            return Err(FdbError::new(FAULT_INVALID_PROBABILITY));
+        }
+
+        self.state.lock().faults.push(Fault {
+            point,
+            error_code,
+            trigger: Trigger::Probability(probability),
+        });
+
+        Ok(())
+    }
+
+    /// Fail operations at `point` with `error_code`, when they are
+    /// the `n`th call at `point` for any `n` in `calls`.
+    ///
+    /// Calls are counted from `0`, across all transactions wrapped
+    /// by this [`FaultInjector`].
+    pub fn fail_on_calls<I>(&self, point: FaultPoint, error_code: i32, calls: I)
+    where
+        I: IntoIterator<Item = u64>,
+    {
+        self.state.lock().faults.push(Fault {
+            point,
+            error_code,
+            trigger: Trigger::Calls(calls.into_iter().collect()),
+        });
+    }
+
+    /// Remove all faults. Call counts are not reset.
+    pub fn clear(&self) {
+        self.state.lock().faults.clear();
+    }
+
+    /// Returns the number of faults that have been injected.
+    pub fn get_injected_count(&self) -> u64 {
+        self.state.lock().injected
+    }
+
+    /// Wrap `tr` in a [`FaultTransaction`] that injects the faults of
+    /// this [`FaultInjector`].
+    pub fn wrap<T>(&self, tr: T) -> FaultTransaction<T> {
+        FaultTransaction::new(tr, self.clone())
+    }
+
+    /// Runs a closure in the context that takes a
+    /// [`FaultTransaction`] wrapping `tr`.
+    ///
+    /// Like [`FdbDatabase::run`], the closure will run multiple times
+    /// (retry) when certain errors are encountered. Unlike
+    /// [`FdbDatabase::run`], the commit is done using the
+    /// [`FaultTransaction`], so that [`FaultPoint::Commit`] and
+    /// [`FaultPoint::AfterCommit`] faults are injected.
+    ///
+    /// [`FdbDatabase::run`]: crate::database::FdbDatabase::run
+    pub async fn run<Tr, T, F, Fut>(&self, tr: Tr, f: F) -> FdbResult<T>
+    where
+        Tr: Transaction + Clone,
+        F: FnMut(FaultTransaction<Tr>) -> Fut,
+        Fut: Future<Output = FdbResult<T>>,
+    {
+        retry_loop::run(self.wrap(tr), f).await
+    }
+
+    // Counts a call at `point`, and returns the error to inject, if
+    // any.
+    pub(super) fn next_fault(&self, point: FaultPoint) -> Option<FdbError> {
+        let mut guard = self.state.lock();
+        let state = &mut *guard;
+
+        let call = state.calls[point.index()];
+        state.calls[point.index()] += 1;
+
+        let rng = &mut state.rng;
+        let res = state
+            .faults
+            .iter()
+            .find(|fault| {
+                fault.point == point
+                    && match fault.trigger {
+                        Trigger::Probability(p) => next_f64(rng) < p,
+                        Trigger::Calls(ref calls) => calls.contains(&call),
+                    }
+            })
+            .map(|fault| FdbError::new(fault.error_code));
+
+        if res.is_some() {
+            state.injected += 1;
+        }
+
+        res
+    }
+}
+
+// xorshift64*, which is good enough for deciding whether to inject a
+// fault. It returns a value in `[0, 1)`.
+fn next_f64(rng: &mut u64) -> f64 {
+    *rng ^= *rng >> 12;
+    *rng ^= *rng << 25;
+    *rng ^= *rng >> 27;
+    let x = rng.wrapping_mul(0x2545_f491_4f6c_dd1d);
+    (x >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use crate::error::{FdbError, FAULT_INVALID_PROBABILITY};
+
+    use super::{FaultInjector, FaultPoint};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FaultInjector:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn fail_on_calls() {
+        let injector = FaultInjector::new(0);
+        injector.fail_on_calls(FaultPoint::Commit, 1020, vec![0, 2]);
+
+        let res = (0..4)
+            .map(|_| injector.next_fault(FaultPoint::Commit))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            res,
+            vec![
+                Some(FdbError::new(1020)),
+                None,
+                Some(FdbError::new(1020)),
+                None
+            ]
+        );
+
+        // Other points are counted separately.
+        assert_eq!(injector.next_fault(FaultPoint::Read), None);
+        assert_eq!(injector.get_injected_count(), 2);
+
+        injector.clear();
+        assert_eq!(injector.next_fault(FaultPoint::Commit), None);
+    }
+
+    #[test]
+    fn fail_with_probability() {
+        let injector = FaultInjector::new(42);
+        injector
+            .fail_with_probability(FaultPoint::Read, 1007, 0.0)
+            .unwrap();
+        assert!((0..100).all(|_| injector.next_fault(FaultPoint::Read).is_none()));
+
+        injector.clear();
+        injector
+            .fail_with_probability(FaultPoint::Read, 1007, 1.0)
+            .unwrap();
+        assert!(
+            (0..100).all(|_| injector.next_fault(FaultPoint::Read) == Some(FdbError::new(1007)))
+        );
+
+        injector.clear();
+        injector
+            .fail_with_probability(FaultPoint::Read, 1007, 0.5)
+            .unwrap();
+        let n = (0..1000)
+            .filter(|_| injector.next_fault(FaultPoint::Read).is_some())
+            .count();
+        assert!(n > 400 && n < 600);
+
+        // Same seed, same faults.
+        let a = FaultInjector::new(7);
+        let b = FaultInjector::new(7);
+        for injector in &[&a, &b] {
+            injector
+                .fail_with_probability(FaultPoint::Read, 1007, 0.5)
+                .unwrap();
+        }
+        assert!((0..100).all(|_| a.next_fault(FaultPoint::Read) == b.next_fault(FaultPoint::Read)));
+
+        assert_eq!(
+            injector.fail_with_probability(FaultPoint::Read, 1007, 1.5),
+            Err(FdbError::new(FAULT_INVALID_PROBABILITY))
+        );
+        assert_eq!(
+            injector.fail_with_probability(FaultPoint::Read, 1007, f64::NAN),
+            Err(FdbError::new(FAULT_INVALID_PROBABILITY))
+        );
+    }
+}
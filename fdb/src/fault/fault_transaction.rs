@@ -0,0 +1,448 @@
+use bytes::Bytes;
+
+use futures::Stream;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::error::{FdbError, FdbResult};
+use crate::fault::{FaultInjector, FaultPoint};
+use crate::range::{Range, RangeOptions};
+use crate::transaction::{MutationType, ReadTransaction, Transaction, TransactionOption};
+use crate::{Key, KeySelector, Value};
+
+#[cfg(feature = "fdb-7_1")]
+use crate::Mapper;
+
+/// A transaction wrapped by a [`FaultInjector`].
+///
+/// [`FaultTransaction`] implements [`ReadTransaction`] when `T`
+/// implements [`ReadTransaction`], and [`Transaction`] when `T`
+/// implements [`Transaction`]. Methods are forwarded to `T`, except
+/// when a fault is injected.
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::fault
+#[derive(Clone, Debug)]
+pub struct FaultTransaction<T> {
+    inner: T,
+    injector: FaultInjector,
+}
+
+impl<T> FaultTransaction<T> {
+    /// Gets a reference to the wrapped transaction.
+    pub fn get_inner_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Extract the wrapped transaction from [`FaultTransaction`].
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    pub(super) fn new(inner: T, injector: FaultInjector) -> FaultTransaction<T> {
+        FaultTransaction { inner, injector }
+    }
+
+    // Calls `f` to issue the read, unless a fault is injected.
+    fn read<F>(&self, f: impl FnOnce(&T) -> F) -> FaultFuture<F> {
+        match self.injector.next_fault(FaultPoint::Read) {
+            Some(e) => FaultFuture::new(None, Some(e)),
+            None => FaultFuture::new(Some(f(&self.inner)), None),
+        }
+    }
+
+    // Calls `f` to issue the read, unless a fault is injected.
+    fn read_stream<S>(&self, f: impl FnOnce(&T) -> S) -> FaultStream<S> {
+        match self.injector.next_fault(FaultPoint::Read) {
+            Some(e) => FaultStream::new(None, Some(e)),
+            None => FaultStream::new(Some(f(&self.inner)), None),
+        }
+    }
+}
+
+impl<T> ReadTransaction for FaultTransaction<T>
+where
+    T: ReadTransaction,
+{
+    type FutureUnit = FaultFuture<T::FutureUnit>;
+    type FutureMaybeValue = FaultFuture<T::FutureMaybeValue>;
+    type FutureCStringArray = FaultFuture<T::FutureCStringArray>;
+    type FutureI64 = FaultFuture<T::FutureI64>;
+    type FutureKey = FaultFuture<T::FutureKey>;
+    #[cfg(feature = "fdb-7_1")]
+    type StreamMappedKeyValue = FaultStream<T::StreamMappedKeyValue>;
+    type StreamKeyValue = FaultStream<T::StreamKeyValue>;
+    #[cfg(feature = "fdb-7_1")]
+    type FutureKeyArray = FaultFuture<T::FutureKeyArray>;
+
+    unsafe fn on_error(&self, e: FdbError) -> FaultFuture<T::FutureUnit> {
+        FaultFuture::new(Some(self.inner.on_error(e)), None)
+    }
+
+    fn get(&self, key: impl Into<Key>) -> FaultFuture<T::FutureMaybeValue> {
+        self.read(|tr| tr.get(key))
+    }
+
+    fn get_addresses_for_key(&self, key: impl Into<Key>) -> FaultFuture<T::FutureCStringArray> {
+        self.read(|tr| tr.get_addresses_for_key(key))
+    }
+
+    fn get_estimated_range_size_bytes(&self, range: Range) -> FaultFuture<T::FutureI64> {
+        self.read(|tr| tr.get_estimated_range_size_bytes(range))
+    }
+
+    fn get_key(&self, selector: KeySelector) -> FaultFuture<T::FutureKey> {
+        self.read(|tr| tr.get_key(selector))
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_mapped_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        mapper: impl Into<Mapper>,
+        options: RangeOptions,
+    ) -> FaultStream<T::StreamMappedKeyValue> {
+        self.read_stream(|tr| tr.get_mapped_range(begin, end, mapper, options))
+    }
+
+    fn get_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+    ) -> FaultStream<T::StreamKeyValue> {
+        self.read_stream(|tr| tr.get_range(begin, end, options))
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_range_split_points(
+        &self,
+        begin: impl Into<Key>,
+        end: impl Into<Key>,
+        chunk_size: i64,
+    ) -> FaultFuture<T::FutureKeyArray> {
+        self.read(|tr| tr.get_range_split_points(begin, end, chunk_size))
+    }
+
+    unsafe fn get_read_version(&self) -> FaultFuture<T::FutureI64> {
+        self.read(|tr| tr.get_read_version())
+    }
+
+    fn set_option(&self, option: TransactionOption) -> FdbResult<()> {
+        self.inner.set_option(option)
+    }
+
+    unsafe fn set_read_version(&self, version: i64) {
+        self.inner.set_read_version(version)
+    }
+}
+
+impl<T> Transaction for FaultTransaction<T>
+where
+    T: Transaction,
+{
+    type CommittedVersion = T::CommittedVersion;
+    type TransactionVersionstamp = T::TransactionVersionstamp;
+    type Snapshot = FaultTransaction<T::Snapshot>;
+
+    fn add_read_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
+        self.inner.add_read_conflict_key(key)
+    }
+
+    fn add_read_conflict_range(&self, range: Range) -> FdbResult<()> {
+        self.inner.add_read_conflict_range(range)
+    }
+
+    fn add_write_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
+        self.inner.add_write_conflict_key(key)
+    }
+
+    fn add_write_conflict_range(&self, range: Range) -> FdbResult<()> {
+        self.inner.add_write_conflict_range(range)
+    }
+
+    unsafe fn cancel(&self) {
+        self.inner.cancel()
+    }
+
+    fn clear(&self, key: impl Into<Key>) {
+        self.inner.clear(key)
+    }
+
+    fn clear_range(&self, range: Range) {
+        self.inner.clear_range(range)
+    }
+
+    // A `Commit` fault is checked before an `AfterCommit` fault, so
+    // that `AfterCommit` calls are only counted when the commit is
+    // issued.
+    unsafe fn commit(&self) -> FaultFuture<T::FutureUnit> {
+        match self.injector.next_fault(FaultPoint::Commit) {
+            Some(e) => FaultFuture::new(None, Some(e)),
+            None => FaultFuture::new(
+                Some(self.inner.commit()),
+                self.injector.next_fault(FaultPoint::AfterCommit),
+            ),
+        }
+    }
+
+    fn get_approximate_size(&self) -> FaultFuture<T::FutureI64> {
+        FaultFuture::new(Some(self.inner.get_approximate_size()), None)
+    }
+
+    unsafe fn get_committed_version(&self) -> T::CommittedVersion {
+        self.inner.get_committed_version()
+    }
+
+    unsafe fn get_versionstamp(&self) -> T::TransactionVersionstamp {
+        self.inner.get_versionstamp()
+    }
+
+    unsafe fn mutate(&self, optype: MutationType, key: impl Into<Key>, param: Bytes) {
+        self.inner.mutate(optype, key, param)
+    }
+
+    unsafe fn reset(&self) {
+        self.inner.reset()
+    }
+
+    fn set(&self, key: impl Into<Key>, value: impl Into<Value>) {
+        self.inner.set(key, value)
+    }
+
+    // Reads on the snapshot share the `FaultInjector`.
+    fn snapshot(&self) -> FaultTransaction<T::Snapshot> {
+        FaultTransaction::new(self.inner.snapshot(), self.injector.clone())
+    }
+
+    fn watch(&self, key: impl Into<Key>) -> FaultFuture<T::FutureUnit> {
+        FaultFuture::new(Some(self.inner.watch(key)), None)
+    }
+}
+
+/// Future returned by [`FaultTransaction`] methods.
+///
+/// When a fault is injected before the operation is issued, the
+/// future resolves to the injected error. When a fault is injected
+/// after the operation, the future waits for the wrapped future and
+/// then resolves to the injected error, unless the wrapped future
+/// returned an error.
+//
+// The wrapped future is boxed, so that `FaultFuture` is `Unpin` even
+// when `F` is not.
+#[derive(Debug)]
+pub struct FaultFuture<F> {
+    inner: Option<Pin<Box<F>>>,
+    error: Option<FdbError>,
+}
+
+impl<F> FaultFuture<F> {
+    fn new(inner: Option<F>, error: Option<FdbError>) -> FaultFuture<F> {
+        FaultFuture {
+            inner: inner.map(Box::pin),
+            error,
+        }
+    }
+}
+
+impl<F, T> Future for FaultFuture<F>
+where
+    F: Future<Output = FdbResult<T>>,
+{
+    type Output = FdbResult<T>;
+
+    fn poll(self: Pin<&mut FaultFuture<F>>, cx: &mut Context<'_>) -> Poll<FdbResult<T>> {
+        let this = self.get_mut();
+
+        match this.inner.as_mut() {
+            // `error` is always `Some(...)` when there is no wrapped
+            // future.
+            None => Poll::Ready(Err(this.error.unwrap())),
+            Some(inner) => match inner.as_mut().poll(cx) {
+                Poll::Ready(Ok(t)) => Poll::Ready(match this.error {
+                    Some(e) => Err(e),
+                    None => Ok(t),
+                }),
+                res => res,
+            },
+        }
+    }
+}
+
+/// Stream returned by [`FaultTransaction`] methods.
+///
+/// When a fault is injected, the stream yields the injected error
+/// and ends.
+#[derive(Debug)]
+pub struct FaultStream<S> {
+    inner: Option<S>,
+    error: Option<FdbError>,
+}
+
+impl<S> FaultStream<S> {
+    fn new(inner: Option<S>, error: Option<FdbError>) -> FaultStream<S> {
+        FaultStream { inner, error }
+    }
+}
+
+impl<S, T> Stream for FaultStream<S>
+where
+    S: Stream<Item = FdbResult<T>> + Unpin,
+{
+    type Item = FdbResult<T>;
+
+    fn poll_next(
+        mut self: Pin<&mut FaultStream<S>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<T>>> {
+        if let Some(e) = self.error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        match self.inner.as_mut() {
+            Some(inner) => Pin::new(inner).poll_next(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use crate::future::{FdbFutureUnit, FdbStreamKeyValue};
+    use crate::transaction::FdbTransaction;
+
+    use super::{FaultFuture, FaultStream, FaultTransaction};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FaultTransaction<FdbTransaction>:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FaultFuture<FdbFutureUnit>:
+	        Send &
+		Unpin &
+		!Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FaultStream<FdbStreamKeyValue>:
+	        Send &
+		Unpin &
+		!Clone &
+		!Copy));
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn reads() {
+        use bytes::Bytes;
+
+        use tokio_stream::StreamExt;
+
+        use crate::error::FdbError;
+        use crate::fault::{FaultInjector, FaultPoint};
+        use crate::memory::MemoryDatabase;
+        use crate::range::RangeOptions;
+        use crate::transaction::{ReadTransaction, Transaction};
+        use crate::KeySelector;
+
+        let db = MemoryDatabase::new();
+        let injector = FaultInjector::new(0);
+        injector.fail_on_calls(FaultPoint::Read, 1007, vec![0, 1]);
+
+        let tr = injector.wrap(db.create_transaction().unwrap());
+        tr.set(Bytes::from_static(b"key"), Bytes::from_static(b"value"));
+
+        assert_eq!(
+            tr.get(Bytes::from_static(b"key")).await,
+            Err(FdbError::new(1007))
+        );
+
+        let res = tr
+            .get_range(
+                KeySelector::first_greater_or_equal(Bytes::new()),
+                KeySelector::first_greater_or_equal(Bytes::from_static(b"\xff")),
+                RangeOptions::default(),
+            )
+            .map(|kv| kv.map(|_| ()))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(res, vec![Err(FdbError::new(1007))]);
+
+        assert!(tr.get(Bytes::from_static(b"key")).await.unwrap().is_some());
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn run_with_commit_unknown_result() {
+        use bytes::Bytes;
+
+        use crate::error::FdbError;
+        use crate::fault::{FaultInjector, FaultPoint};
+        use crate::memory::MemoryDatabase;
+        use crate::transaction::{MutationType, ReadTransaction, Transaction};
+        use crate::Value;
+
+        let db = MemoryDatabase::new();
+        let injector = FaultInjector::new(0);
+
+        // First commit fails without committing, second commit
+        // succeeds but reports `commit_unknown_result`.
+        injector.fail_on_calls(FaultPoint::Commit, 1020, vec![0]);
+        injector.fail_on_calls(FaultPoint::AfterCommit, 1021, vec![0]);
+
+        let mut attempts = 0;
+
+        injector
+            .run(db.create_transaction().unwrap(), |tr| {
+                attempts += 1;
+                async move {
+                    // Not idempotent.
+                    unsafe {
+                        tr.mutate(
+                            MutationType::Add,
+                            Bytes::from_static(b"counter"),
+                            Bytes::from_static(&[1]),
+                        );
+                    }
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 3);
+        assert_eq!(injector.get_injected_count(), 2);
+
+        // The increment was applied twice.
+        let res = db
+            .read(|tr| async move { tr.get(Bytes::from_static(b"counter")).await })
+            .await
+            .unwrap();
+        assert_eq!(res, Some(Value::from(Bytes::from_static(&[2]))));
+
+        // Non-retryable errors are returned.
+        injector.fail_on_calls(FaultPoint::Commit, 2000, vec![3]);
+        let res = injector
+            .run(
+                db.create_transaction().unwrap(),
+                |_tr| async move { Ok(()) },
+            )
+            .await;
+        assert_eq!(res, Err(FdbError::new(2000)));
+    }
+}
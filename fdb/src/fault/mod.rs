@@ -0,0 +1,63 @@
+//! Provides [`FaultInjector`] type, for testing how transaction
+//! closures behave when they are retried.
+//!
+//! Closures passed to [`FdbDatabase::run`] can run more than once,
+//! and a commit that returns an error might still have succeeded. A
+//! [`FaultInjector`] makes chosen operations fail with a given
+//! [`FdbError`] code, so that these cases can be tested without
+//! waiting for them to happen in a real cluster. This module is
+//! available when the `fault` feature is enabled.
+//!
+//! Faults are injected at a [`FaultPoint`].
+//!
+//! - [`FaultPoint::Read`] - A read, such as [`get`] or
+//!   [`get_range`], fails without being issued.
+//!
+//! - [`FaultPoint::Commit`] - [`commit`] fails without committing.
+//!   Use `not_committed` (`1020`) or `transaction_too_old` (`1007`).
+//!
+//! - [`FaultPoint::AfterCommit`] - [`commit`] succeeds, but an error
+//!   is returned. Use `commit_unknown_result` (`1021`) to check that
+//!   the closure is idempotent.
+//!
+//! A fault is triggered either with a given probability
+//! ([`fail_with_probability`]) or on a given schedule of calls
+//! ([`fail_on_calls`]). The random number generator is seeded, so
+//! that a failing test can be reproduced.
+//!
+//! [`FaultInjector::wrap`] wraps any type that implements
+//! [`ReadTransaction`] or [`Transaction`] in a [`FaultTransaction`],
+//! which implements the same traits. [`FaultInjector::run`] is a
+//! retry loop like [`FdbDatabase::run`], that commits using the
+//! [`FaultTransaction`]. The closure must therefore be generic over
+//! [`Transaction`] trait. Both [`FdbTransaction`] and
+//! `MemoryTransaction` can be used.
+//!
+//! ```ignore
+//! let injector = FaultInjector::new(seed);
+//! injector.fail_on_calls(FaultPoint::AfterCommit, 1021, vec![0]);
+//!
+//! injector
+//!     .run(db.create_transaction()?, |tr| async move {
+//!         // transaction body, generic over `Transaction`
+//!         body(&tr).await
+//!     })
+//!     .await?;
+//! ```
+//!
+//! [`FdbDatabase::run`]: crate::database::FdbDatabase::run
+//! [`FdbError`]: crate::error::FdbError
+//! [`get`]: crate::transaction::ReadTransaction::get
+//! [`get_range`]: crate::transaction::ReadTransaction::get_range
+//! [`commit`]: crate::transaction::Transaction::commit
+//! [`fail_with_probability`]: FaultInjector::fail_with_probability
+//! [`fail_on_calls`]: FaultInjector::fail_on_calls
+//! [`ReadTransaction`]: crate::transaction::ReadTransaction
+//! [`Transaction`]: crate::transaction::Transaction
+//! [`FdbTransaction`]: crate::transaction::FdbTransaction
+
+mod fault_injector;
+mod fault_transaction;
+
+pub use fault_injector::{FaultInjector, FaultPoint};
+pub use fault_transaction::{FaultFuture, FaultStream, FaultTransaction};
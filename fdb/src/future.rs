@@ -6,9 +6,11 @@ use bytes::Bytes;
 use futures::task::AtomicWaker;
 use futures::Stream;
 
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::ffi;
 use std::ffi::{CStr, CString};
+use std::fmt;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::marker::Unpin;
@@ -18,11 +20,15 @@ use std::slice;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
 use crate::error::{check, FdbResult};
 use crate::range::{
-    fdb_transaction_get_range, KeyValueArray, RangeOptions, RangeResultStateMachine, StreamingMode,
+    fdb_transaction_get_range, KeyValueArray, KeyValueBatch, RangeOptions, RangeResultStateMachine,
+    StreamingMode,
 };
-use crate::transaction::FdbTransaction;
+use crate::transaction::{FdbTransaction, ReadTransaction};
 use crate::{Key, KeySelector, KeyValue, Value};
 
 #[cfg(feature = "fdb-7_1")]
@@ -36,6 +42,9 @@ use crate::mapped_range::{
 #[cfg(feature = "fdb-7_1")]
 use crate::range::Range;
 
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, Operation};
+
 /// A [`FdbFuture`] represents a value (or error) to be available at
 /// some other time.
 ///
@@ -75,11 +84,17 @@ use crate::range::Range;
 // `Arc<Task>` like value, and hence it will keep the task alive, even
 // though the `.wake()` might not call `poll`, as `FdbFuture<T>`
 // would be long gone by then.
+//
+// When `metrics` feature is enabled, `latency_metric` is set for
+// futures whose latency is recorded. The latency is recorded when
+// `poll` returns `Poll::Ready(...)`.
 #[derive(Debug)]
 pub struct FdbFuture<T> {
     c_ptr: Option<NonNull<fdb_sys::FDBFuture>>,
     callback_set: bool,
     waker: Option<Arc<AtomicWaker>>,
+    #[cfg(feature = "metrics")]
+    latency_metric: Option<(Operation, Instant)>,
     _marker: PhantomData<T>,
 }
 
@@ -107,11 +122,27 @@ impl<T> FdbFuture<T> {
             c_ptr: Some(NonNull::new(c_ptr).expect("c_ptr cannot be null")),
             callback_set: false,
             waker: Some(Arc::new(AtomicWaker::new())),
+            #[cfg(feature = "metrics")]
+            latency_metric: None,
             _marker: PhantomData,
         }
     }
 }
 
+#[cfg(feature = "metrics")]
+impl<T> FdbFuture<T> {
+    pub(crate) fn with_latency_metric(mut self, operation: Operation) -> FdbFuture<T> {
+        self.latency_metric = Some((operation, Instant::now()));
+        self
+    }
+
+    fn record_latency_metric(&mut self) {
+        if let Some((operation, start)) = self.latency_metric.take() {
+            metrics::record_latency(operation, start.elapsed());
+        }
+    }
+}
+
 // # Safety
 //
 // `FdbFuture` does not implement `Copy` or `Clone` traits. Also
@@ -169,6 +200,9 @@ where
             // again.
             fdb_fut_ref.waker = None;
 
+            #[cfg(feature = "metrics")]
+            fdb_fut_ref.record_latency_metric();
+
             Poll::Ready(unsafe { FdbFutureGet::get(fut_c_ptr) })
         } else {
             // FDB future is not ready
@@ -191,6 +225,9 @@ where
                 // polled again.
                 fdb_fut_ref.waker = None;
 
+                #[cfg(feature = "metrics")]
+                fdb_fut_ref.record_latency_metric();
+
                 Poll::Ready(unsafe { FdbFutureGet::get(fut_c_ptr) })
             } else if !fdb_fut_ref.callback_set {
                 let arc_atomic_waker_copy_ptr = Arc::into_raw(arc_atomic_waker_ref.clone());
@@ -267,6 +304,19 @@ impl FdbFutureGet for i64 {
     }
 }
 
+/// Represents the asynchronous result of a function that returns a
+/// protocol version.
+#[cfg(feature = "fdb-7_1")]
+pub type FdbFutureU64 = FdbFuture<u64>;
+
+#[cfg(feature = "fdb-7_1")]
+impl FdbFutureGet for u64 {
+    unsafe fn get(future: *mut fdb_sys::FDBFuture) -> FdbResult<u64> {
+        let mut out = 0;
+        check(fdb_sys::fdb_future_get_uint64(future, &mut out)).map(|_| out)
+    }
+}
+
 /// Represents the asynchronous result of a function that returns a
 /// [`Key`] from a database.
 pub type FdbFutureKey = FdbFuture<Key>;
@@ -309,6 +359,9 @@ impl FdbFutureGet for Option<Value> {
             &mut out_value_length,
         ))
         .map(|_| {
+            #[cfg(feature = "metrics")]
+            metrics::record_bytes_read(out_value_length.try_into().unwrap());
+
             if out_present != 0 {
                 Some(
                     Bytes::copy_from_slice(if out_value_length == 0 {
@@ -406,24 +459,33 @@ impl FdbFutureGet for KeyValueArray {
         .map(|_| {
             let mut kvs = Vec::with_capacity(out_count.try_into().unwrap());
 
+            #[cfg(feature = "metrics")]
+            let mut bytes_read = 0;
+
             (0..out_count).into_iter().for_each(|i| {
                 let kv = out_kv.offset(i.try_into().unwrap());
 
                 let key = Bytes::copy_from_slice(slice::from_raw_parts(
                     (*kv).key,
                     (*kv).key_length.try_into().unwrap(),
-                ))
-                .into();
+                ));
 
                 let value = Bytes::copy_from_slice(slice::from_raw_parts(
                     (*kv).value,
                     (*kv).value_length.try_into().unwrap(),
-                ))
-                .into();
+                ));
 
-                kvs.push(KeyValue::new(key, value));
+                #[cfg(feature = "metrics")]
+                {
+                    bytes_read += key.len() + value.len();
+                }
+
+                kvs.push(KeyValue::new(key.into(), value.into()));
             });
 
+            #[cfg(feature = "metrics")]
+            metrics::record_bytes_read(bytes_read);
+
             // non-zero is `true`.
             KeyValueArray::new(kvs, out_count, out_more != 0)
         })
@@ -516,6 +578,174 @@ impl FdbFutureGet for MappedKeyValueArray {
     }
 }
 
+// Keeps at most `limit` futures in flight, issuing a new one for the
+// next input when a future in flight completes. Results are returned
+// in the same order as the inputs.
+struct FutureBuffered<I, F> {
+    inputs: VecDeque<I>,
+    in_flight: VecDeque<F>,
+    limit: usize,
+}
+
+impl<I, F, T> FutureBuffered<I, F>
+where
+    F: Future<Output = FdbResult<T>> + Unpin,
+{
+    // A `limit` of `0` is treated as `1`.
+    fn new(inputs: VecDeque<I>, limit: usize) -> FutureBuffered<I, F> {
+        FutureBuffered {
+            inputs,
+            in_flight: VecDeque::new(),
+            limit: limit.max(1),
+        }
+    }
+
+    fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut f: impl FnMut(I) -> F,
+    ) -> Poll<Option<FdbResult<T>>> {
+        while self.in_flight.len() < self.limit {
+            match self.inputs.pop_front() {
+                Some(input) => self.in_flight.push_back(f(input)),
+                None => break,
+            }
+        }
+
+        match self.in_flight.front_mut() {
+            Some(fut) => match Pin::new(fut).poll(cx) {
+                Poll::Ready(res) => {
+                    self.in_flight.pop_front();
+
+                    if res.is_err() {
+                        // Once there is an error, we are done. The
+                        // futures in flight are cancelled when they
+                        // are dropped.
+                        self.inputs.clear();
+                        self.in_flight.clear();
+                    }
+
+                    Poll::Ready(Some(res))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+impl<I, F> fmt::Debug for FutureBuffered<I, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FutureBuffered")
+            .field("inputs", &self.inputs.len())
+            .field("in_flight", &self.in_flight.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+/// A stream of values (or lack of values), one for each key provided
+/// to [`get_many`].
+///
+/// The values are read using [`get`] of the transaction `T`.
+///
+/// [`get_many`]: crate::transaction::ReadTransaction::get_many
+/// [`get`]: crate::transaction::ReadTransaction::get
+#[derive(Debug)]
+pub struct FdbStreamMaybeValue<'a, T>
+where
+    T: ReadTransaction,
+{
+    transaction: &'a T,
+    future_buffered: FutureBuffered<Key, T::FutureMaybeValue>,
+}
+
+impl<'a, T> FdbStreamMaybeValue<'a, T>
+where
+    T: ReadTransaction,
+{
+    pub(crate) fn new(
+        transaction: &'a T,
+        keys: VecDeque<Key>,
+        limit: usize,
+    ) -> FdbStreamMaybeValue<'a, T> {
+        FdbStreamMaybeValue {
+            transaction,
+            future_buffered: FutureBuffered::new(keys, limit),
+        }
+    }
+}
+
+impl<'a, T> Stream for FdbStreamMaybeValue<'a, T>
+where
+    T: ReadTransaction,
+{
+    type Item = FdbResult<Option<Value>>;
+
+    fn poll_next(
+        self: Pin<&mut FdbStreamMaybeValue<'a, T>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<Option<Value>>>> {
+        let FdbStreamMaybeValue {
+            transaction,
+            future_buffered,
+        } = self.get_mut();
+
+        future_buffered.poll_next(cx, |key| transaction.get(key))
+    }
+}
+
+/// A stream of [`Key`]s, one for each [`KeySelector`] provided to
+/// [`get_key_many`].
+///
+/// The keys are read using [`get_key`] of the transaction `T`.
+///
+/// [`get_key_many`]: crate::transaction::ReadTransaction::get_key_many
+/// [`get_key`]: crate::transaction::ReadTransaction::get_key
+#[derive(Debug)]
+pub struct FdbStreamKey<'a, T>
+where
+    T: ReadTransaction,
+{
+    transaction: &'a T,
+    future_buffered: FutureBuffered<KeySelector, T::FutureKey>,
+}
+
+impl<'a, T> FdbStreamKey<'a, T>
+where
+    T: ReadTransaction,
+{
+    pub(crate) fn new(
+        transaction: &'a T,
+        selectors: VecDeque<KeySelector>,
+        limit: usize,
+    ) -> FdbStreamKey<'a, T> {
+        FdbStreamKey {
+            transaction,
+            future_buffered: FutureBuffered::new(selectors, limit),
+        }
+    }
+}
+
+impl<'a, T> Stream for FdbStreamKey<'a, T>
+where
+    T: ReadTransaction,
+{
+    type Item = FdbResult<Key>;
+
+    fn poll_next(
+        self: Pin<&mut FdbStreamKey<'a, T>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<Key>>> {
+        let FdbStreamKey {
+            transaction,
+            future_buffered,
+        } = self.get_mut();
+
+        future_buffered.poll_next(cx, |selector| transaction.get_key(selector))
+    }
+}
+
 /// A stream of [`KeyValue`]s.
 #[derive(Debug)]
 pub struct FdbStreamKeyValue {
@@ -543,10 +773,14 @@ impl FdbStreamKeyValue {
 
         let reverse = options.get_reverse();
 
+        let target_bytes = options.get_target_bytes();
+
+        let prefetch = options.get_prefetch();
+
         // `iteration` is only valid when mode is
         // `StreamingMode::Iterator`. It is ignored in other modes.
         let iteration = if options.get_mode() == StreamingMode::Iterator {
-            Some(1)
+            Some(options.get_iteration())
         } else {
             None
         };
@@ -555,7 +789,7 @@ impl FdbStreamKeyValue {
             transaction.get_c_api_ptr(),
             begin.clone(),
             end.clone(),
-            RangeOptions::new(limit.unwrap_or(0), mode, reverse),
+            RangeOptions::new(limit.unwrap_or(0), target_bytes, mode, reverse),
             iteration.unwrap_or(0),
             snapshot,
         );
@@ -568,6 +802,8 @@ impl FdbStreamKeyValue {
             iteration,
             reverse,
             limit,
+            target_bytes,
+            prefetch,
             snapshot,
             fdb_future_key_value_array,
         );
@@ -576,6 +812,20 @@ impl FdbStreamKeyValue {
             range_result_state_machine,
         }
     }
+
+    /// Convert [`FdbStreamKeyValue`] into a stream of
+    /// [`KeyValueBatch`]es.
+    ///
+    /// Instead of returning the key-value pairs one at a time, each
+    /// item of the returned stream contains all the key-value pairs
+    /// received from a single range read. Key-value pairs that have
+    /// already been returned by [`FdbStreamKeyValue`] are not
+    /// included.
+    pub fn into_batches(self) -> FdbStreamKeyValueBatch {
+        FdbStreamKeyValueBatch {
+            range_result_state_machine: self.range_result_state_machine,
+        }
+    }
 }
 
 impl Stream for FdbStreamKeyValue {
@@ -589,6 +839,23 @@ impl Stream for FdbStreamKeyValue {
     }
 }
 
+/// A stream of [`KeyValueBatch`]es.
+#[derive(Debug)]
+pub struct FdbStreamKeyValueBatch {
+    range_result_state_machine: RangeResultStateMachine,
+}
+
+impl Stream for FdbStreamKeyValueBatch {
+    type Item = FdbResult<KeyValueBatch>;
+
+    fn poll_next(
+        mut self: Pin<&mut FdbStreamKeyValueBatch>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<KeyValueBatch>>> {
+        Pin::new(&mut self.range_result_state_machine).poll_next_batch(cx)
+    }
+}
+
 #[cfg(feature = "fdb-7_1")]
 /// A stream of [`MappedKeyValue`]s.
 #[derive(Debug)]
@@ -619,10 +886,14 @@ impl FdbStreamMappedKeyValue {
 
         let reverse = options.get_reverse();
 
+        let target_bytes = options.get_target_bytes();
+
+        let prefetch = options.get_prefetch();
+
         // `iteration` is only valid when mode is
         // `StreamingMode::Iterator`. It is ignored in other modes.
         let iteration = if options.get_mode() == StreamingMode::Iterator {
-            Some(1)
+            Some(options.get_iteration())
         } else {
             None
         };
@@ -632,7 +903,7 @@ impl FdbStreamMappedKeyValue {
             begin.clone(),
             end.clone(),
             mapper.clone(),
-            RangeOptions::new(limit.unwrap_or(0), mode, reverse),
+            RangeOptions::new(limit.unwrap_or(0), target_bytes, mode, reverse),
             iteration.unwrap_or(0),
             snapshot,
         );
@@ -646,6 +917,8 @@ impl FdbStreamMappedKeyValue {
             iteration,
             reverse,
             limit,
+            target_bytes,
+            prefetch,
             snapshot,
             fdb_future_mapped_key_value_array,
         );
@@ -670,23 +943,33 @@ impl Stream for FdbStreamMappedKeyValue {
 
 #[cfg(test)]
 mod tests {
-    use futures::task::AtomicWaker;
+    use futures::channel::oneshot;
+    use futures::future::{FutureExt, Map};
+    use futures::task::{self, AtomicWaker};
     use futures::Stream;
 
     use impls::impls;
 
+    use std::collections::VecDeque;
     use std::future::Future;
     use std::marker::PhantomData;
     use std::ptr::NonNull;
     use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use crate::error::{FdbError, FdbResult};
+    use crate::transaction::FdbTransaction;
 
     use super::{
         FdbFutureCStringArray, FdbFutureI64, FdbFutureKey, FdbFutureKeyValueArray,
-        FdbFutureMaybeValue, FdbFutureUnit, FdbStreamKeyValue,
+        FdbFutureMaybeValue, FdbFutureUnit, FdbStreamKey, FdbStreamKeyValue,
+        FdbStreamKeyValueBatch, FdbStreamMaybeValue, FutureBuffered,
     };
 
     #[cfg(feature = "fdb-7_1")]
-    use super::{FdbFutureKeyArray, FdbFutureMappedKeyValueArray, FdbStreamMappedKeyValue};
+    use super::{
+        FdbFutureKeyArray, FdbFutureMappedKeyValueArray, FdbFutureU64, FdbStreamMappedKeyValue,
+    };
 
     #[test]
     fn impls() {
@@ -746,6 +1029,39 @@ mod tests {
 		!Clone &
 		!Copy));
 
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FdbStreamMaybeValue<'static, FdbTransaction>:
+	        Send &
+	        Stream &
+		!Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FdbStreamKey<'static, FdbTransaction>:
+	        Send &
+	        Stream &
+		!Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FdbStreamKeyValueBatch:
+	        Send &
+	        Stream &
+		!Clone &
+		!Copy));
+
+        #[cfg(feature = "fdb-7_1")]
+        #[rustfmt::skip]
+        assert!(impls!(
+	    FdbFutureU64:
+	        Send &
+	        Future &
+		!Clone &
+		!Copy));
+
         #[cfg(feature = "fdb-7_1")]
         #[rustfmt::skip]
         assert!(impls!(
@@ -802,4 +1118,84 @@ mod tests {
 
         trait_bounds_for_fdb_transaction(d);
     }
+
+    type TestFuture = Map<
+        oneshot::Receiver<FdbResult<i32>>,
+        fn(Result<FdbResult<i32>, oneshot::Canceled>) -> FdbResult<i32>,
+    >;
+
+    // Returns a future that is completed using the sender, which is
+    // added to `issued` along with `i`.
+    fn test_future(issued: &mut Vec<(i32, oneshot::Sender<FdbResult<i32>>)>, i: i32) -> TestFuture {
+        let (tx, rx) = oneshot::channel();
+        issued.push((i, tx));
+        rx.map(|res| res.unwrap())
+    }
+
+    #[test]
+    fn future_buffered() {
+        let waker = task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut issued = Vec::new();
+        let mut senders = VecDeque::new();
+
+        let mut future_buffered =
+            FutureBuffered::<i32, TestFuture>::new((0..5).collect::<VecDeque<_>>(), 2);
+
+        // At most `limit` futures are in flight.
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Pending);
+        assert_eq!(
+            issued.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        senders.extend(issued.drain(..));
+
+        // Results are returned in the same order as the inputs, even
+        // when the futures complete in a different order.
+        let (_, tx1) = senders.pop_back().unwrap();
+        tx1.send(Ok(1)).unwrap();
+
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Pending);
+        assert!(issued.is_empty());
+
+        let (_, tx0) = senders.pop_front().unwrap();
+        tx0.send(Ok(0)).unwrap();
+
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Ready(Some(Ok(0))));
+        assert!(issued.is_empty());
+
+        // A new future is issued once a future in flight completes.
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Ready(Some(Ok(1))));
+        assert_eq!(issued.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![2]);
+        senders.extend(issued.drain(..));
+
+        // After an error, the stream ends and the remaining inputs
+        // are not issued.
+        let (_, tx2) = senders.pop_front().unwrap();
+        tx2.send(Err(FdbError::new(1020))).unwrap();
+
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Ready(Some(Err(FdbError::new(1020)))));
+        assert_eq!(issued.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![3]);
+
+        // The future for `3` was cancelled.
+        assert!(issued.pop().unwrap().1.is_canceled());
+
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Ready(None));
+        assert!(issued.is_empty());
+
+        // A `limit` of `0` is treated as `1`.
+        let mut future_buffered =
+            FutureBuffered::<i32, TestFuture>::new((0..5).collect::<VecDeque<_>>(), 0);
+
+        let res = future_buffered.poll_next(&mut cx, |i| test_future(&mut issued, i));
+        assert_eq!(res, Poll::Pending);
+        assert_eq!(issued.iter().map(|(i, _)| *i).collect::<Vec<_>>(), vec![0]);
+    }
 }
@@ -74,7 +74,8 @@ impl KeyValue {
         (self.key, self.value)
     }
 
-    pub(crate) fn new(key: Key, value: Value) -> KeyValue {
+    /// Create a new [`KeyValue`].
+    pub fn new(key: Key, value: Value) -> KeyValue {
         KeyValue { key, value }
     }
 }
@@ -0,0 +1,705 @@
+//! Provides [`Blob`] type for storing large values.
+//!
+//! FDB values are limited to 100KB. [`Blob`] splits a large value into
+//! chunks, each of which is stored as a separate key-value pair in a
+//! [`Subspace`]. The size of the blob and optional application
+//! metadata are stored alongside the chunks.
+//!
+//! The following keys are used within the [`Subspace`].
+//!
+//! - `("A",)` - Application metadata.
+//! - `("D", offset)` - Chunk of data starting at `offset`.
+//! - `("S",)` - Size of the blob.
+
+use bytes::{BufMut, Bytes, BytesMut};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+use tokio_stream::StreamExt;
+
+use std::cmp;
+use std::convert::TryInto;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::database::FdbDatabase;
+use crate::error::{FdbError, FdbResult, BLOB_CONCURRENT_MODIFICATION, BLOB_INVALID_OFFSET};
+use crate::range::{Range, RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{ReadTransaction, Transaction};
+use crate::tuple::Tuple;
+use crate::{Key, KeySelector};
+
+/// Maximum size of a chunk in bytes.
+pub const CHUNK_SIZE: usize = 10_000;
+
+// Number of bytes read by `BlobReader` in each transaction read.
+const READ_SIZE: i64 = 100_000;
+
+/// A large value stored as multiple chunks in a [`Subspace`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct Blob {
+    subspace: Subspace,
+}
+
+impl Blob {
+    /// Create a new [`Blob`] stored in [`Subspace`].
+    pub fn new(subspace: Subspace) -> Blob {
+        Blob { subspace }
+    }
+
+    /// Gets a reference to the [`Subspace`] of the [`Blob`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Get the size of the [`Blob`] in bytes. A [`Blob`] that does
+    /// not exist has a size of `0`.
+    pub async fn get_size<T>(&self, tr: &T) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        match tr.get(self.size_key()).await? {
+            Some(v) => Tuple::from_bytes(v)?.get_i64(0),
+            None => Ok(0),
+        }
+    }
+
+    /// Get the application metadata of the [`Blob`].
+    pub async fn get_metadata<T>(&self, tr: &T) -> FdbResult<Option<Bytes>>
+    where
+        T: ReadTransaction,
+    {
+        Ok(tr.get(self.metadata_key()).await?.map(Bytes::from))
+    }
+
+    /// Set the application metadata of the [`Blob`].
+    pub fn set_metadata<T>(&self, tr: &T, metadata: Bytes)
+    where
+        T: Transaction,
+    {
+        tr.set(self.metadata_key(), metadata);
+    }
+
+    /// Read up to `n` bytes of the [`Blob`] starting at `offset`.
+    ///
+    /// Fewer than `n` bytes are returned when the end of the [`Blob`]
+    /// is reached. An empty [`Bytes`] is returned when `offset` is at
+    /// or beyond the end of the [`Blob`].
+    pub async fn read<T>(&self, tr: &T, offset: i64, n: i64) -> FdbResult<Bytes>
+    where
+        T: ReadTransaction,
+    {
+        if offset < 0 || n < 0 {
+            return Err(FdbError::new(BLOB_INVALID_OFFSET));
+        }
+
+        let end = cmp::min(offset.saturating_add(n), self.get_size(tr).await?);
+
+        if offset >= end {
+            return Ok(Bytes::new());
+        }
+
+        let mut res = BytesMut::with_capacity((end - offset).try_into().unwrap());
+
+        let mut range_stream = tr.get_range(
+            KeySelector::last_less_or_equal(self.data_key(offset)),
+            KeySelector::first_greater_or_equal(self.data_key(end)),
+            {
+                let mut ro = RangeOptions::default();
+                ro.set_mode(StreamingMode::WantAll);
+                ro
+            },
+        );
+
+        while let Some(x) = range_stream.next().await {
+            let (key, value) = x?.into_parts();
+            let key = Bytes::from(key);
+
+            // `last_less_or_equal` can select a key that is not a
+            // chunk.
+            if !self.data_subspace().contains(&key) {
+                continue;
+            }
+
+            let chunk_offset = self.data_subspace().unpack(&key)?.get_i64(0)?;
+            let chunk = Bytes::from(value);
+            let chunk_end = chunk_offset + TryInto::<i64>::try_into(chunk.len()).unwrap();
+
+            let copy_begin = cmp::max(offset, chunk_offset);
+            let copy_end = cmp::min(end, chunk_end);
+
+            if copy_begin < copy_end {
+                res.put(chunk.slice(
+                    TryInto::<usize>::try_into(copy_begin - chunk_offset).unwrap()
+                        ..TryInto::<usize>::try_into(copy_end - chunk_offset).unwrap(),
+                ));
+            }
+        }
+
+        Ok(res.into())
+    }
+
+    /// Read the entire [`Blob`].
+    pub async fn read_all<T>(&self, tr: &T) -> FdbResult<Bytes>
+    where
+        T: ReadTransaction,
+    {
+        self.read(tr, 0, i64::MAX).await
+    }
+
+    /// Append `data` to the end of the [`Blob`], creating the
+    /// [`Blob`] if it does not exist.
+    pub async fn append<T>(&self, tr: &T, data: Bytes) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        let size = self.get_size(tr).await?;
+        self.append_at(tr, size, data);
+        Ok(())
+    }
+
+    /// Truncate the [`Blob`] to `new_size` bytes.
+    ///
+    /// `new_size` must not be greater than the current size of the
+    /// [`Blob`].
+    pub async fn truncate<T>(&self, tr: &T, new_size: i64) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        let size = self.get_size(tr).await?;
+
+        if new_size < 0 || new_size > size {
+            return Err(FdbError::new(BLOB_INVALID_OFFSET));
+        }
+
+        if new_size == size {
+            return Ok(());
+        }
+
+        // Shorten the chunk that `new_size` falls in, if it starts
+        // before `new_size`.
+        let key = Bytes::from(
+            tr.get_key(KeySelector::last_less_or_equal(self.data_key(new_size)))
+                .await?,
+        );
+
+        if self.data_subspace().contains(&key) {
+            let chunk_offset = self.data_subspace().unpack(&key)?.get_i64(0)?;
+
+            if chunk_offset < new_size {
+                if let Some(value) = tr.get(key.clone()).await? {
+                    let chunk = Bytes::from(value);
+                    let len = TryInto::<usize>::try_into(new_size - chunk_offset).unwrap();
+
+                    if chunk.len() > len {
+                        tr.set(key, chunk.slice(..len));
+                    }
+                }
+            }
+        }
+
+        let (_, end) = self.data_subspace().range(&Tuple::new()).into_parts();
+        tr.clear_range(Range::new(self.data_key(new_size), end));
+
+        self.set_size(tr, new_size);
+
+        Ok(())
+    }
+
+    /// Delete the [`Blob`], including its metadata.
+    pub fn delete<T>(&self, tr: &T)
+    where
+        T: Transaction,
+    {
+        tr.clear_range(self.subspace.range(&Tuple::new()));
+    }
+
+    /// Create a [`BlobReader`] that reads the [`Blob`] using `tr`.
+    ///
+    /// See [`BlobReader`] for the limits of reading in a single
+    /// transaction.
+    pub fn reader<T>(&self, tr: T) -> BlobReader<T>
+    where
+        T: ReadTransaction + Clone + Send + Sync + 'static,
+    {
+        BlobReader::new(self.clone(), tr)
+    }
+
+    /// Append `data` to the end of the [`Blob`], using as many
+    /// transactions as needed, each writing up to `transaction_size`
+    /// bytes.
+    ///
+    /// The append is not atomic. If another writer modifies the
+    /// [`Blob`] at the same time, an error with
+    /// [`BLOB_CONCURRENT_MODIFICATION`] code is returned.
+    pub async fn append_db(
+        &self,
+        fdb_database: &FdbDatabase,
+        data: Bytes,
+        transaction_size: usize,
+    ) -> FdbResult<()> {
+        let mut offset = fdb_database
+            .read(|tr| async move { self.get_size(&tr).await })
+            .await?;
+
+        // Round up `transaction_size` to a multiple of `CHUNK_SIZE`.
+        let piece_size = cmp::max(1, (transaction_size + CHUNK_SIZE - 1) / CHUNK_SIZE) * CHUNK_SIZE;
+
+        let mut data = data;
+        while !data.is_empty() {
+            let piece = data.split_to(cmp::min(piece_size, data.len()));
+            let piece_end = offset + TryInto::<i64>::try_into(piece.len()).unwrap();
+
+            fdb_database
+                .run(|tr| {
+                    let piece = piece.clone();
+                    async move {
+                        let size = self.get_size(&tr).await?;
+
+                        if size == offset {
+                            self.append_at(&tr, offset, piece);
+                            Ok(())
+                        } else if size == piece_end {
+                            // A previous attempt was committed, but
+                            // we got a `commit_unknown_result` error.
+                            Ok(())
+                        } else {
+                            Err(FdbError::new(BLOB_CONCURRENT_MODIFICATION))
+                        }
+                    }
+                })
+                .await?;
+
+            offset = piece_end;
+        }
+
+        Ok(())
+    }
+
+    /// Replace the contents of the [`Blob`] with `data`, using as
+    /// many transactions as needed, each writing up to
+    /// `transaction_size` bytes. Metadata is removed.
+    ///
+    /// The write is not atomic. Readers can observe a partially
+    /// written [`Blob`].
+    pub async fn write_db(
+        &self,
+        fdb_database: &FdbDatabase,
+        data: Bytes,
+        transaction_size: usize,
+    ) -> FdbResult<()> {
+        fdb_database
+            .run(|tr| async move {
+                self.delete(&tr);
+                Ok(())
+            })
+            .await?;
+
+        self.append_db(fdb_database, data, transaction_size).await
+    }
+
+    fn append_at<T>(&self, tr: &T, offset: i64, data: Bytes)
+    where
+        T: Transaction,
+    {
+        let size = offset + TryInto::<i64>::try_into(data.len()).unwrap();
+
+        let mut chunk_offset = offset;
+        let mut data = data;
+        while !data.is_empty() {
+            let chunk = data.split_to(cmp::min(CHUNK_SIZE, data.len()));
+            let chunk_len = TryInto::<i64>::try_into(chunk.len()).unwrap();
+
+            tr.set(self.data_key(chunk_offset), chunk);
+            chunk_offset += chunk_len;
+        }
+
+        self.set_size(tr, size);
+    }
+
+    fn set_size<T>(&self, tr: &T, size: i64)
+    where
+        T: Transaction,
+    {
+        let mut t = Tuple::new();
+        t.add_i64(size);
+        tr.set(self.size_key(), t.pack());
+    }
+
+    fn metadata_key(&self) -> Key {
+        let mut t = Tuple::new();
+        t.add_string(String::from("A"));
+        self.subspace.subspace(&t).pack().into()
+    }
+
+    fn data_subspace(&self) -> Subspace {
+        let mut t = Tuple::new();
+        t.add_string(String::from("D"));
+        self.subspace.subspace(&t)
+    }
+
+    fn data_key(&self, offset: i64) -> Key {
+        let mut t = Tuple::new();
+        t.add_i64(offset);
+        self.data_subspace().subspace(&t).pack().into()
+    }
+
+    fn size_key(&self) -> Key {
+        let mut t = Tuple::new();
+        t.add_string(String::from("S"));
+        self.subspace.subspace(&t).pack().into()
+    }
+}
+
+/// Reads a [`Blob`] as a stream of bytes.
+///
+/// [`BlobReader`] implements [`AsyncRead`] trait. It is created using
+/// [`Blob::reader`].
+///
+/// **Note:** All reads are done using the transaction given to
+/// [`Blob::reader`], so the whole [`Blob`] is read at a single read
+/// version. A transaction cannot read for more than five seconds,
+/// after which reads fail with `transaction_too_old` (1007)
+/// error. [`BlobReader`] is therefore only suited for a [`Blob`] that
+/// can be read within that limit. Larger [`Blob`]s can be read in
+/// pieces using [`Blob::read`], each in its own transaction.
+pub struct BlobReader<T> {
+    blob: Blob,
+    tr: T,
+    offset: i64,
+    buf: Bytes,
+    fdb_future: Option<Pin<Box<dyn Future<Output = FdbResult<Bytes>> + Send>>>,
+}
+
+impl<T> BlobReader<T>
+where
+    T: ReadTransaction + Clone + Send + Sync + 'static,
+{
+    /// Get the offset in the [`Blob`] of the next byte that will be
+    /// read.
+    pub fn get_offset(&self) -> i64 {
+        self.offset - TryInto::<i64>::try_into(self.buf.len()).unwrap()
+    }
+
+    fn new(blob: Blob, tr: T) -> BlobReader<T> {
+        BlobReader {
+            blob,
+            tr,
+            offset: 0,
+            buf: Bytes::new(),
+            fdb_future: None,
+        }
+    }
+}
+
+impl<T> fmt::Debug for BlobReader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlobReader")
+            .field("blob", &self.blob)
+            .field("offset", &self.offset)
+            .field("buf", &self.buf)
+            .finish()
+    }
+}
+
+impl<T> AsyncRead for BlobReader<T>
+where
+    T: ReadTransaction + Clone + Send + Sync + Unpin + 'static,
+{
+    fn poll_read(
+        self: Pin<&mut BlobReader<T>>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.buf.is_empty() {
+            if this.fdb_future.is_none() {
+                let blob = this.blob.clone();
+                let tr = this.tr.clone();
+                let offset = this.offset;
+
+                this.fdb_future = Some(Box::pin(
+                    async move { blob.read(&tr, offset, READ_SIZE).await },
+                ));
+            }
+
+            // Safety: We have set `fdb_future` above.
+            match this.fdb_future.as_mut().unwrap().as_mut().poll(cx) {
+                Poll::Ready(res) => {
+                    this.fdb_future = None;
+
+                    let b = res.map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                    this.offset += TryInto::<i64>::try_into(b.len()).unwrap();
+                    this.buf = b;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        // When `buf` is still empty, we have reached the end of the
+        // `Blob`.
+        let n = cmp::min(buf.remaining(), this.buf.len());
+        buf.put_slice(&this.buf.split_to(n));
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::subspace::Subspace;
+    use crate::transaction::FdbReadTransaction;
+    use crate::tuple::Tuple;
+
+    #[cfg(feature = "memory")]
+    use crate::transaction::Transaction;
+
+    use super::{Blob, BlobReader};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Blob:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    BlobReader<FdbReadTransaction>:
+	        Send &
+		!Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn data_key_order() {
+        let blob = Blob::new(Subspace::new(Bytes::from_static(b"blob")));
+
+        // Chunks must sort by offset, and between the metadata key
+        // and the size key.
+        let metadata_key = Bytes::from(blob.metadata_key());
+        let data_key_0 = Bytes::from(blob.data_key(0));
+        let data_key_1 = Bytes::from(blob.data_key(9_999));
+        let data_key_2 = Bytes::from(blob.data_key(10_000));
+        let size_key = Bytes::from(blob.size_key());
+
+        assert!(metadata_key < data_key_0);
+        assert!(data_key_0 < data_key_1);
+        assert!(data_key_1 < data_key_2);
+        assert!(data_key_2 < size_key);
+
+        assert!(blob.data_subspace().contains(&data_key_2));
+        assert!(!blob.data_subspace().contains(&size_key));
+
+        let mut t = Tuple::new();
+        t.add_i64(10_000);
+        assert_eq!(blob.data_subspace().unpack(&data_key_2).unwrap(), t);
+    }
+
+    // Returns the offset and length of each chunk of `blob`.
+    #[cfg(feature = "memory")]
+    async fn chunks<T>(blob: &Blob, tr: &T) -> Vec<(i64, usize)>
+    where
+        T: crate::transaction::ReadTransaction,
+    {
+        use tokio_stream::StreamExt;
+
+        use crate::range::RangeOptions;
+
+        blob.data_subspace()
+            .range(&Tuple::new())
+            .into_stream(tr, RangeOptions::default())
+            .map(|kv| {
+                let (key, value) = kv.unwrap().into_parts();
+                let offset = blob
+                    .data_subspace()
+                    .unpack(&Bytes::from(key))
+                    .unwrap()
+                    .get_i64(0)
+                    .unwrap();
+                (offset, Bytes::from(value).len())
+            })
+            .collect::<Vec<_>>()
+            .await
+    }
+
+    #[cfg(feature = "memory")]
+    fn test_data(len: usize) -> Bytes {
+        (0..len)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<u8>>()
+            .into()
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn read_append() {
+        use crate::error::{FdbError, BLOB_INVALID_OFFSET};
+        use crate::memory::MemoryDatabase;
+
+        let db = MemoryDatabase::new();
+        let blob = Blob::new(Subspace::new(Bytes::from_static(b"blob")));
+        let data = test_data(25_000);
+
+        let tr = db.create_transaction().unwrap();
+        assert_eq!(blob.get_size(&tr).await.unwrap(), 0);
+        assert_eq!(blob.read_all(&tr).await.unwrap(), Bytes::new());
+
+        // Second append reads the size written by the first append.
+        blob.append(&tr, data.slice(..12_345)).await.unwrap();
+        blob.append(&tr, data.slice(12_345..)).await.unwrap();
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        assert_eq!(blob.get_size(&tr).await.unwrap(), 25_000);
+
+        // Each append is split into chunks of up to `CHUNK_SIZE`.
+        assert_eq!(
+            chunks(&blob, &tr).await,
+            vec![
+                (0, 10_000),
+                (10_000, 2_345),
+                (12_345, 10_000),
+                (22_345, 2_655)
+            ]
+        );
+
+        assert_eq!(blob.read_all(&tr).await.unwrap(), data);
+
+        // Reads within a chunk, across chunks and past the end.
+        for (offset, n, expected) in vec![
+            (0, 10, data.slice(..10)),
+            (9_990, 20, data.slice(9_990..10_010)),
+            (12_340, 10, data.slice(12_340..12_350)),
+            (5_000, 20_000, data.slice(5_000..)),
+            (24_990, 100, data.slice(24_990..)),
+            (25_000, 10, Bytes::new()),
+            (30_000, 10, Bytes::new()),
+            (100, 0, Bytes::new()),
+        ] {
+            assert_eq!(blob.read(&tr, offset, n).await.unwrap(), expected);
+        }
+
+        assert_eq!(
+            blob.read(&tr, -1, 10).await.unwrap_err(),
+            FdbError::new(BLOB_INVALID_OFFSET)
+        );
+        assert_eq!(
+            blob.read(&tr, 0, -1).await.unwrap_err(),
+            FdbError::new(BLOB_INVALID_OFFSET)
+        );
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn truncate_delete() {
+        use crate::error::{FdbError, BLOB_INVALID_OFFSET};
+        use crate::memory::MemoryDatabase;
+
+        let db = MemoryDatabase::new();
+        let blob = Blob::new(Subspace::new(Bytes::from_static(b"blob")));
+        let data = test_data(25_000);
+
+        let tr = db.create_transaction().unwrap();
+        blob.append(&tr, data.clone()).await.unwrap();
+        blob.set_metadata(&tr, Bytes::from_static(b"metadata"));
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+
+        for new_size in &[-1, 25_001] {
+            assert_eq!(
+                blob.truncate(&tr, *new_size).await.unwrap_err(),
+                FdbError::new(BLOB_INVALID_OFFSET)
+            );
+        }
+
+        // Truncating to the current size does nothing.
+        blob.truncate(&tr, 25_000).await.unwrap();
+        assert_eq!(blob.read_all(&tr).await.unwrap(), data);
+
+        // Within a chunk, the chunk is shortened.
+        blob.truncate(&tr, 15_000).await.unwrap();
+        assert_eq!(blob.get_size(&tr).await.unwrap(), 15_000);
+        assert_eq!(chunks(&blob, &tr).await, vec![(0, 10_000), (10_000, 5_000)]);
+        assert_eq!(blob.read_all(&tr).await.unwrap(), data.slice(..15_000));
+
+        // At a chunk boundary.
+        blob.truncate(&tr, 10_000).await.unwrap();
+        assert_eq!(chunks(&blob, &tr).await, vec![(0, 10_000)]);
+        assert_eq!(blob.read_all(&tr).await.unwrap(), data.slice(..10_000));
+
+        // Appending after a truncate.
+        blob.append(&tr, data.slice(10_000..10_010)).await.unwrap();
+        assert_eq!(blob.read_all(&tr).await.unwrap(), data.slice(..10_010));
+
+        blob.truncate(&tr, 0).await.unwrap();
+        assert_eq!(blob.get_size(&tr).await.unwrap(), 0);
+        assert_eq!(chunks(&blob, &tr).await, Vec::new());
+
+        // Metadata is kept until the blob is deleted.
+        assert_eq!(
+            blob.get_metadata(&tr).await.unwrap(),
+            Some(Bytes::from_static(b"metadata"))
+        );
+
+        blob.delete(&tr);
+        assert_eq!(blob.get_metadata(&tr).await.unwrap(), None);
+        assert_eq!(blob.get_size(&tr).await.unwrap(), 0);
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn blob_reader() {
+        use tokio::io::AsyncReadExt;
+
+        use crate::memory::MemoryDatabase;
+
+        let db = MemoryDatabase::new();
+        let blob = Blob::new(Subspace::new(Bytes::from_static(b"blob")));
+
+        // Larger than `READ_SIZE`, so that more than one read is
+        // needed.
+        let data = test_data(250_000);
+
+        let tr = db.create_transaction().unwrap();
+
+        let mut buf = Vec::new();
+        let n = blob.reader(tr.clone()).read_to_end(&mut buf).await.unwrap();
+        assert_eq!(n, 0);
+
+        blob.append(&tr, data.clone()).await.unwrap();
+
+        let mut reader = blob.reader(tr.clone());
+        assert_eq!(reader.get_offset(), 0);
+
+        let mut head = [0; 7];
+        reader.read_exact(&mut head).await.unwrap();
+        assert_eq!(&head[..], &data[..7]);
+        assert_eq!(reader.get_offset(), 7);
+
+        let mut rest = Vec::new();
+        let n = reader.read_to_end(&mut rest).await.unwrap();
+        assert_eq!(n, 250_000 - 7);
+        assert_eq!(&rest[..], &data[7..]);
+        assert_eq!(reader.get_offset(), 250_000);
+
+        // Reading at the end returns `0` bytes.
+        assert_eq!(reader.read(&mut head).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,396 @@
+//! Provides [`Counter`] type for high-throughput counters.
+//!
+//! Incrementing a single key using [`MutationType::Add`] from many
+//! clients makes that key a hotspot on a single storage server.
+//! [`Counter`] spreads increments over multiple shard keys, and reads
+//! the total by summing the shards.
+//!
+//! Over time, most shards hold a non-zero value. [`Counter::coalesce`]
+//! folds the shards into the first shard using atomic operations, so
+//! that reads touch fewer keys. It does not conflict with concurrent
+//! increments and can be run periodically.
+//!
+//! The following keys are used within the [`Subspace`].
+//!
+//! - `(shard,)` - Value of the shard, as a little-endian 64-bit
+//!   integer.
+
+use bytes::Bytes;
+
+use tokio_stream::StreamExt;
+
+use std::collections::hash_map::RandomState;
+use std::convert::TryInto;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::FdbResult;
+use crate::range::{RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{MutationType, ReadTransaction, Transaction};
+use crate::tuple::Tuple;
+use crate::Key;
+
+use super::decode_i64;
+
+/// A counter spread over multiple shards in a [`Subspace`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct Counter {
+    subspace: Subspace,
+    shards: usize,
+    next_shard: Arc<AtomicUsize>,
+}
+
+impl Counter {
+    /// Create a new [`Counter`] stored in [`Subspace`], spreading
+    /// increments over `shards` shard keys.
+    ///
+    /// All [`Counter`]s for the same [`Subspace`] should use the same
+    /// number of shards. Using fewer shards than a previous [`Counter`]
+    /// does not lose any value, but the extra shards are only folded
+    /// into the first shard by [`Counter::coalesce`].
+    pub fn new(subspace: Subspace, shards: usize) -> Counter {
+        // Start each `Counter` at a random shard, so that clients do
+        // not all start incrementing the same shard.
+        let seed = RandomState::new().build_hasher().finish();
+
+        Counter {
+            subspace,
+            shards: shards.max(1),
+            next_shard: Arc::new(AtomicUsize::new(seed as usize)),
+        }
+    }
+
+    /// Gets a reference to the [`Subspace`] of the [`Counter`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Add `delta` to the [`Counter`].
+    ///
+    /// This does not add any read conflicts, so concurrent calls to
+    /// [`add`] never conflict.
+    ///
+    /// [`add`]: Counter::add
+    pub fn add<T>(&self, tr: &T, delta: i64)
+    where
+        T: Transaction,
+    {
+        let shard = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.shards;
+        self.add_shard(tr, shard.try_into().unwrap(), delta);
+    }
+
+    /// Get the value of the [`Counter`].
+    ///
+    /// The shards are read using `tr`, so if `tr` is committed, no
+    /// other transaction modified the [`Counter`] in between.
+    pub async fn get<T>(&self, tr: &T) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        Ok(self
+            .get_shards(tr)
+            .await?
+            .into_iter()
+            .fold(0_i64, |acc, (_, v)| acc.wrapping_add(v)))
+    }
+
+    /// Get the value of the [`Counter`] using a snapshot read.
+    ///
+    /// This does not add any read conflicts, so it does not conflict
+    /// with concurrent calls to [`add`].
+    ///
+    /// [`add`]: Counter::add
+    pub async fn get_snapshot<T>(&self, tr: &T) -> FdbResult<i64>
+    where
+        T: Transaction,
+    {
+        self.get(&tr.snapshot()).await
+    }
+
+    /// Fold all the shards of the [`Counter`] into the first shard.
+    ///
+    /// The shards are read using a snapshot read, and each value read
+    /// is moved into the first shard using atomic operations. So
+    /// [`coalesce`] does not conflict with concurrent calls to
+    /// [`add`], and the value of the [`Counter`] is not changed.
+    ///
+    /// [`add`]: Counter::add
+    /// [`coalesce`]: Counter::coalesce
+    pub async fn coalesce<T>(&self, tr: &T) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        let mut total = 0_i64;
+
+        for (shard, v) in self.get_shards(&tr.snapshot()).await? {
+            if shard != 0 && v != 0 {
+                self.add_shard(tr, shard, v.wrapping_neg());
+
+                // Remove the shard when it becomes zero.
+                //
+                // Safety: `CompareAndClear` is not `AppendIfFits`.
+                unsafe {
+                    tr.mutate(
+                        MutationType::CompareAndClear,
+                        self.shard_key(shard),
+                        Bytes::copy_from_slice(&0_i64.to_le_bytes()),
+                    );
+                }
+
+                total = total.wrapping_add(v);
+            }
+        }
+
+        self.add_shard(tr, 0, total);
+
+        Ok(())
+    }
+
+    /// Clear the [`Counter`], setting its value to `0`.
+    pub fn clear<T>(&self, tr: &T)
+    where
+        T: Transaction,
+    {
+        tr.clear_range(self.subspace.range(&Tuple::new()));
+    }
+
+    async fn get_shards<T>(&self, tr: &T) -> FdbResult<Vec<(i64, i64)>>
+    where
+        T: ReadTransaction,
+    {
+        let mut range_stream = self.subspace.range(&Tuple::new()).into_stream(tr, {
+            let mut ro = RangeOptions::default();
+            ro.set_mode(StreamingMode::WantAll);
+            ro
+        });
+
+        let mut res = Vec::new();
+
+        while let Some(x) = range_stream.next().await {
+            let (key, value) = x?.into_parts();
+            let shard = self.subspace.unpack(&key.into())?.get_i64(0)?;
+            res.push((shard, decode_i64(value.into())));
+        }
+
+        Ok(res)
+    }
+
+    fn add_shard<T>(&self, tr: &T, shard: i64, delta: i64)
+    where
+        T: Transaction,
+    {
+        if delta != 0 {
+            // Safety: `Add` is not `AppendIfFits`.
+            unsafe {
+                tr.mutate(
+                    MutationType::Add,
+                    self.shard_key(shard),
+                    Bytes::copy_from_slice(&delta.to_le_bytes()),
+                );
+            }
+        }
+    }
+
+    fn shard_key(&self, shard: i64) -> Key {
+        let mut t = Tuple::new();
+        t.add_i64(shard);
+        self.subspace.subspace(&t).pack().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::subspace::Subspace;
+
+    use super::Counter;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Counter:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn counter_shards() {
+        let counter = Counter::new(Subspace::new(Bytes::from_static(b"counter")), 0);
+        assert_eq!(counter.shards, 1);
+
+        let counter = Counter::new(Subspace::new(Bytes::from_static(b"counter")), 8);
+        assert!(counter
+            .subspace
+            .contains(&Bytes::from(counter.shard_key(7))));
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction() {
+        use crate::memory::MemoryDatabase;
+
+        let db = MemoryDatabase::new();
+        let counter = Counter::new(Subspace::new(Bytes::from_static(b"counter")), 4);
+
+        // Every add goes to the next shard.
+        for delta in &[1, 2, 3, -4, 10] {
+            db.run(|tr| {
+                let counter = counter.clone();
+                async move {
+                    counter.add(&tr, *delta);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+        }
+
+        let (value, snapshot_value) = db
+            .run(|tr| {
+                let counter = counter.clone();
+                async move { Ok((counter.get(&tr).await?, counter.get_snapshot(&tr).await?)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 12);
+        assert_eq!(snapshot_value, 12);
+
+        let shards = db
+            .read(|tr| {
+                let counter = counter.clone();
+                async move { counter.get_shards(&tr).await }
+            })
+            .await
+            .unwrap();
+        assert_eq!(shards.len(), 4);
+
+        // `coalesce` folds the shards into the first shard, without
+        // changing the value.
+        db.run(|tr| {
+            let counter = counter.clone();
+            async move { counter.coalesce(&tr).await }
+        })
+        .await
+        .unwrap();
+
+        let (value, shards) = db
+            .read(|tr| {
+                let counter = counter.clone();
+                async move { Ok((counter.get(&tr).await?, counter.get_shards(&tr).await?)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, 12);
+        assert_eq!(shards, vec![(0, 12)]);
+
+        db.run(|tr| {
+            let counter = counter.clone();
+            async move {
+                counter.clear(&tr);
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+
+        let value = db
+            .read(|tr| {
+                let counter = counter.clone();
+                async move { counter.get(&tr).await }
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 0);
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction_coalesce_concurrent_adds() {
+        use crate::error::FdbResult;
+        use crate::memory::MemoryDatabase;
+        use crate::transaction::Transaction;
+
+        const ADDS: i64 = 50;
+
+        let db = MemoryDatabase::new();
+        let counter = Counter::new(Subspace::new(Bytes::from_static(b"counter")), 8);
+
+        let mut join_handles = Vec::new();
+
+        for _ in 0..4 {
+            let (db, counter) = (db.clone(), counter.clone());
+
+            join_handles.push(tokio::spawn(async move {
+                for i in 1..=ADDS {
+                    db.run(|tr| {
+                        let counter = counter.clone();
+                        async move {
+                            counter.add(&tr, i);
+                            Ok(())
+                        }
+                    })
+                    .await?;
+                    tokio::task::yield_now().await;
+                }
+                FdbResult::Ok(())
+            }));
+        }
+
+        // Interleave `coalesce` with the adds. Commit each `coalesce`
+        // between reading the shards and an add by another
+        // transaction, which must not be lost.
+        for _ in 0..ADDS {
+            let tr = db.create_transaction().unwrap();
+            counter.coalesce(&tr).await.unwrap();
+
+            db.run(|tr| {
+                let counter = counter.clone();
+                async move {
+                    counter.add(&tr, 1);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+            unsafe { tr.commit() }.await.unwrap();
+            tokio::task::yield_now().await;
+        }
+
+        for join_handle in join_handles {
+            join_handle.await.unwrap().unwrap();
+        }
+
+        db.run(|tr| {
+            let counter = counter.clone();
+            async move { counter.coalesce(&tr).await }
+        })
+        .await
+        .unwrap();
+
+        let (value, shards) = db
+            .read(|tr| {
+                let counter = counter.clone();
+                async move { Ok((counter.get(&tr).await?, counter.get_shards(&tr).await?)) }
+            })
+            .await
+            .unwrap();
+
+        let expected = 4 * ADDS * (ADDS + 1) / 2 + ADDS;
+        assert_eq!(value, expected);
+        assert_eq!(shards, vec![(0, expected)]);
+    }
+}
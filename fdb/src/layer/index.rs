@@ -0,0 +1,572 @@
+//! Provides [`IndexedSubspace`] and [`Index`] types for maintaining
+//! secondary indexes.
+//!
+//! An [`IndexedSubspace`] stores records in a primary [`Subspace`],
+//! keyed by a primary key [`Tuple`]. Each [`Index`] has a key
+//! extractor function that returns the index values of a record.
+//! When a record is written or deleted using [`IndexedSubspace`], its
+//! index entries are updated in the same transaction.
+//!
+//! The following keys are used.
+//!
+//! - `(primary key...)` in the primary [`Subspace`] - Record.
+//! - `((index values...), primary key...)` in the [`Subspace`] of each
+//!   [`Index`] - Index entry, with an empty value.
+//!
+//! Index values are stored as a nested [`Tuple`], so that lookups
+//! match the index values exactly.
+//!
+//! With `fdb-7_1` feature, [`IndexedSubspace::lookup`] uses
+#![cfg_attr(
+    feature = "fdb-7_1",
+    doc = "[`get_mapped_range`](crate::transaction::ReadTransaction::get_mapped_range)"
+)]
+#![cfg_attr(not(feature = "fdb-7_1"), doc = "`get_mapped_range`")]
+//! to read index entries and records in a single round trip. This
+//! requires the prefixes of the primary [`Subspace`] and of the
+//! [`Subspace`] of the [`Index`] to be packed [`Tuple`]s.
+
+use bytes::Bytes;
+
+#[cfg(not(feature = "fdb-7_1"))]
+use bytes::{BufMut, BytesMut};
+
+use tokio_stream::StreamExt;
+
+use std::fmt;
+use std::sync::Arc;
+
+use crate::error::{FdbError, FdbResult, INDEX_INVALID_PRIMARY_KEY, INDEX_NOT_FOUND};
+use crate::range::{RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{ReadTransaction, Transaction};
+use crate::tuple::Tuple;
+use crate::Key;
+
+#[cfg(feature = "fdb-7_1")]
+use crate::{Mapper, MapperBuilder};
+
+// Number of records read at the same time by `lookup`, when
+// `get_mapped_range` is not available.
+#[cfg(not(feature = "fdb-7_1"))]
+const LOOKUP_IN_FLIGHT: usize = 16;
+
+type IndexKeyExtractor = dyn Fn(&Tuple, &Bytes) -> Vec<Tuple> + Send + Sync;
+
+/// A secondary index of an [`IndexedSubspace`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone)]
+pub struct Index {
+    name: String,
+    subspace: Subspace,
+    extractor: Arc<IndexKeyExtractor>,
+}
+
+impl Index {
+    /// Create a new [`Index`] named `name` stored in [`Subspace`].
+    ///
+    /// `extractor` is called with the primary key and value of a
+    /// record, and returns the index values of the record. A record
+    /// can have zero or more index values.
+    pub fn new<F>(name: String, subspace: Subspace, extractor: F) -> Index
+    where
+        F: Fn(&Tuple, &Bytes) -> Vec<Tuple> + Send + Sync + 'static,
+    {
+        Index {
+            name,
+            subspace,
+            extractor: Arc::new(extractor),
+        }
+    }
+
+    /// Gets a reference to the name of the [`Index`].
+    pub fn get_name_ref(&self) -> &String {
+        &self.name
+    }
+
+    /// Gets a reference to the [`Subspace`] of the [`Index`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    fn values_subspace(&self, values: &Tuple) -> Subspace {
+        let mut t = Tuple::new();
+        t.add_tuple(values.clone());
+        self.subspace.subspace(&t)
+    }
+
+    fn entry_keys(&self, primary_key: &Tuple, value: &Bytes) -> Vec<Key> {
+        (self.extractor)(primary_key, value)
+            .iter()
+            .map(|values| {
+                self.values_subspace(values)
+                    .subspace(primary_key)
+                    .pack()
+                    .into()
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for Index {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Index")
+            .field("name", &self.name)
+            .field("subspace", &self.subspace)
+            .finish()
+    }
+}
+
+/// Records stored in a primary [`Subspace`], with secondary
+/// [`Index`]es.
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct IndexedSubspace {
+    primary: Subspace,
+    primary_key_len: usize,
+    indexes: Vec<Index>,
+}
+
+impl IndexedSubspace {
+    /// Create a new [`IndexedSubspace`] that stores records in
+    /// `primary` [`Subspace`], with primary keys of `primary_key_len`
+    /// elements, and maintains `indexes`.
+    pub fn new(primary: Subspace, primary_key_len: usize, indexes: Vec<Index>) -> IndexedSubspace {
+        IndexedSubspace {
+            primary,
+            primary_key_len,
+            indexes,
+        }
+    }
+
+    /// Gets a reference to the primary [`Subspace`].
+    pub fn get_primary_subspace_ref(&self) -> &Subspace {
+        &self.primary
+    }
+
+    /// Gets a reference to the [`Index`] named `name`.
+    pub fn get_index_ref(&self, name: &str) -> Option<&Index> {
+        self.indexes.iter().find(|i| i.name == name)
+    }
+
+    /// Get the record with `primary_key`.
+    pub async fn get<T>(&self, tr: &T, primary_key: &Tuple) -> FdbResult<Option<Bytes>>
+    where
+        T: ReadTransaction,
+    {
+        Ok(tr
+            .get(self.record_key(primary_key)?)
+            .await?
+            .map(Bytes::from))
+    }
+
+    /// Write the record with `primary_key`, and update its index
+    /// entries.
+    pub async fn set<T>(&self, tr: &T, primary_key: &Tuple, value: Bytes) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        self.clear_entries(tr, primary_key).await?;
+
+        for index in &self.indexes {
+            for key in index.entry_keys(primary_key, &value) {
+                tr.set(key, Bytes::new());
+            }
+        }
+
+        tr.set(self.record_key(primary_key)?, value);
+
+        Ok(())
+    }
+
+    /// Delete the record with `primary_key`, and its index entries.
+    pub async fn delete<T>(&self, tr: &T, primary_key: &Tuple) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        self.clear_entries(tr, primary_key).await?;
+        tr.clear(self.record_key(primary_key)?);
+        Ok(())
+    }
+
+    /// Get the primary keys and records whose index values in the
+    /// [`Index`] named `name` are `values`.
+    ///
+    /// If the [`Index`] does not exist, an error with
+    /// [`INDEX_NOT_FOUND`] code is returned.
+    pub async fn lookup<T>(
+        &self,
+        tr: &T,
+        name: &str,
+        values: &Tuple,
+    ) -> FdbResult<Vec<(Tuple, Bytes)>>
+    where
+        T: ReadTransaction,
+    {
+        let index = self
+            .get_index_ref(name)
+            .ok_or_else(|| FdbError::new(INDEX_NOT_FOUND))?;
+
+        self.lookup_index(tr, index, values).await
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    async fn lookup_index<T>(
+        &self,
+        tr: &T,
+        index: &Index,
+        values: &Tuple,
+    ) -> FdbResult<Vec<(Tuple, Bytes)>>
+    where
+        T: ReadTransaction,
+    {
+        let mapper = self.mapper(index)?;
+
+        let mut mapped_range_stream = index
+            .values_subspace(values)
+            .range(&Tuple::new())
+            .into_mapped_stream(tr, mapper, {
+                let mut ro = RangeOptions::default();
+                ro.set_mode(StreamingMode::WantAll);
+                ro
+            });
+
+        let mut res = Vec::new();
+
+        while let Some(x) = mapped_range_stream.next().await {
+            // Index entries without a record are skipped.
+            for kv in x?.into_range_result() {
+                let (key, value) = kv.into_parts();
+                res.push((self.primary.unpack(&key.into())?, value.into()));
+            }
+        }
+
+        Ok(res)
+    }
+
+    #[cfg(not(feature = "fdb-7_1"))]
+    async fn lookup_index<T>(
+        &self,
+        tr: &T,
+        index: &Index,
+        values: &Tuple,
+    ) -> FdbResult<Vec<(Tuple, Bytes)>>
+    where
+        T: ReadTransaction,
+    {
+        let values_subspace = index.values_subspace(values);
+        let prefix_len = values_subspace.pack().len();
+
+        let mut range_stream = values_subspace.range(&Tuple::new()).into_stream(tr, {
+            let mut ro = RangeOptions::default();
+            ro.set_mode(StreamingMode::WantAll);
+            ro
+        });
+
+        // The primary key is the suffix of the index entry key.
+        let mut primary_keys = Vec::new();
+        while let Some(x) = range_stream.next().await {
+            let key = Bytes::from(x?.into_key());
+            primary_keys.push(key.slice(prefix_len..));
+        }
+
+        let mut maybe_value_stream = tr.get_many(
+            primary_keys.iter().map(|pk| {
+                let mut b = BytesMut::new();
+                b.put(self.primary.pack());
+                b.put(pk.clone());
+                b.freeze()
+            }),
+            LOOKUP_IN_FLIGHT,
+        );
+
+        let mut res = Vec::new();
+
+        for pk in primary_keys {
+            // Safety: `get_many` returns one item for each key.
+            if let Some(value) = maybe_value_stream.next().await.unwrap()? {
+                res.push((Tuple::from_bytes(pk)?, value.into()));
+            }
+        }
+
+        Ok(res)
+    }
+
+    // `("prefix"..., "{K[i]}"...)`, where `i` are the positions of the
+    // primary key elements in the index entry key. There is no range
+    // suffix, so that only the record with the exact primary key is
+    // read, and not the records whose primary keys it is a prefix of.
+    #[cfg(feature = "fdb-7_1")]
+    fn mapper(&self, index: &Index) -> FdbResult<Mapper> {
+        let begin = Tuple::from_bytes(index.subspace.pack())?.size() + 1;
+
+        let mut builder = MapperBuilder::new();
+        builder.add_literal(Tuple::from_bytes(self.primary.pack())?);
+        for i in begin..begin + self.primary_key_len {
+            builder.add_key_element(i);
+        }
+
+        builder.build()
+    }
+
+    async fn clear_entries<T>(&self, tr: &T, primary_key: &Tuple) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        if let Some(old_value) = self.get(tr, primary_key).await? {
+            for index in &self.indexes {
+                for key in index.entry_keys(primary_key, &old_value) {
+                    tr.clear(key);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn record_key(&self, primary_key: &Tuple) -> FdbResult<Key> {
+        if primary_key.size() != self.primary_key_len {
+            return Err(FdbError::new(INDEX_INVALID_PRIMARY_KEY));
+        }
+
+        Ok(self.primary.subspace(primary_key).pack().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::subspace::Subspace;
+    use crate::tuple::Tuple;
+
+    use super::{Index, IndexedSubspace};
+
+    fn indexed_subspace() -> IndexedSubspace {
+        let subspace = |s: &str| {
+            let mut t = Tuple::new();
+            t.add_string(s.to_string());
+            Subspace::new(t.pack())
+        };
+
+        IndexedSubspace::new(
+            subspace("RECORD"),
+            1,
+            vec![Index::new(
+                String::from("by_value"),
+                subspace("INDEX"),
+                |_, value| {
+                    let mut t = Tuple::new();
+                    t.add_bytes(value.clone());
+                    vec![t]
+                },
+            )],
+        )
+    }
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Index:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    IndexedSubspace:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn entry_keys() {
+        let indexed_subspace = indexed_subspace();
+        let index = indexed_subspace.get_index_ref("by_value").unwrap();
+
+        let mut primary_key = Tuple::new();
+        primary_key.add_i64(7);
+
+        let keys = index.entry_keys(&primary_key, &Bytes::from_static(b"v"));
+        assert_eq!(keys.len(), 1);
+
+        // ("INDEX", (b"v",), 7)
+        let mut values = Tuple::new();
+        values.add_bytes(Bytes::from_static(b"v"));
+
+        let mut t = Tuple::new();
+        t.add_string(String::from("INDEX"));
+        t.add_tuple(values);
+        t.add_i64(7);
+
+        assert_eq!(Bytes::from(keys[0].clone()), t.pack());
+
+        assert!(indexed_subspace.get_index_ref("missing").is_none());
+    }
+
+    #[test]
+    fn record_key() {
+        let indexed_subspace = indexed_subspace();
+
+        let mut primary_key = Tuple::new();
+        primary_key.add_i64(7);
+        assert!(indexed_subspace.record_key(&primary_key).is_ok());
+
+        primary_key.add_i64(8);
+        assert!(indexed_subspace.record_key(&primary_key).is_err());
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    #[test]
+    fn mapper() {
+        let indexed_subspace = indexed_subspace();
+        let index = indexed_subspace.get_index_ref("by_value").unwrap();
+
+        // Index entry key is `("INDEX", (values...), primary_key)`.
+        let mut t = Tuple::new();
+        t.add_string(String::from("RECORD"));
+        t.add_string(String::from("{K[2]}"));
+
+        assert_eq!(Tuple::from(indexed_subspace.mapper(index).unwrap()), t);
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction() {
+        use crate::memory::MemoryDatabase;
+        use crate::transaction::Transaction;
+
+        let db = MemoryDatabase::new();
+        let indexed_subspace = indexed_subspace();
+
+        let primary_key = |b: &'static [u8]| {
+            let mut t = Tuple::new();
+            t.add_bytes(Bytes::from_static(b));
+            t
+        };
+        let values = |b: &'static [u8]| {
+            let mut t = Tuple::new();
+            t.add_bytes(Bytes::from_static(b));
+            t
+        };
+
+        // The packed primary key `(b"a",)` is a prefix of the packed
+        // primary key `(b"a\x00b",)`.
+        for (pk, value) in &[
+            (&b"a"[..], &b"x"[..]),
+            (&b"a\x00b"[..], &b"y"[..]),
+            (&b"b"[..], &b"x"[..]),
+        ] {
+            db.run(|tr| {
+                let indexed_subspace = indexed_subspace.clone();
+                async move {
+                    indexed_subspace
+                        .set(&tr, &primary_key(pk), Bytes::from_static(value))
+                        .await
+                }
+            })
+            .await
+            .unwrap();
+        }
+
+        let lookup = |v: &'static [u8]| {
+            let (db, indexed_subspace) = (db.clone(), indexed_subspace.clone());
+            async move {
+                db.read(|tr| {
+                    let indexed_subspace = indexed_subspace.clone();
+                    async move { indexed_subspace.lookup(&tr, "by_value", &values(v)).await }
+                })
+                .await
+                .unwrap()
+            }
+        };
+
+        assert_eq!(
+            lookup(b"x").await,
+            vec![
+                (primary_key(b"a"), Bytes::from_static(b"x")),
+                (primary_key(b"b"), Bytes::from_static(b"x")),
+            ]
+        );
+        assert_eq!(
+            lookup(b"y").await,
+            vec![(primary_key(b"a\x00b"), Bytes::from_static(b"y"))]
+        );
+        assert_eq!(lookup(b"z").await, vec![]);
+
+        // Overwriting a record moves its index entry.
+        db.run(|tr| {
+            let indexed_subspace = indexed_subspace.clone();
+            async move {
+                indexed_subspace
+                    .set(&tr, &primary_key(b"a"), Bytes::from_static(b"z"))
+                    .await
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(
+            lookup(b"x").await,
+            vec![(primary_key(b"b"), Bytes::from_static(b"x"))]
+        );
+        assert_eq!(
+            lookup(b"z").await,
+            vec![(primary_key(b"a"), Bytes::from_static(b"z"))]
+        );
+
+        // Deleting a record deletes its index entry.
+        db.run(|tr| {
+            let indexed_subspace = indexed_subspace.clone();
+            async move { indexed_subspace.delete(&tr, &primary_key(b"b")).await }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(lookup(b"x").await, vec![]);
+
+        let record = db
+            .read(|tr| {
+                let indexed_subspace = indexed_subspace.clone();
+                async move { indexed_subspace.get(&tr, &primary_key(b"b")).await }
+            })
+            .await
+            .unwrap();
+        assert_eq!(record, None);
+
+        // Index entries without a record are skipped.
+        let tr = db.create_transaction().unwrap();
+        let index = indexed_subspace.get_index_ref("by_value").unwrap();
+        for key in index.entry_keys(&primary_key(b"c"), &Bytes::from_static(b"z")) {
+            tr.set(key, Bytes::new());
+        }
+        unsafe { tr.commit() }.await.unwrap();
+
+        assert_eq!(
+            lookup(b"z").await,
+            vec![(primary_key(b"a"), Bytes::from_static(b"z"))]
+        );
+
+        assert_eq!(
+            db.read(|tr| {
+                let indexed_subspace = indexed_subspace.clone();
+                async move { indexed_subspace.lookup(&tr, "missing", &values(b"z")).await }
+            })
+            .await
+            .unwrap_err()
+            .code(),
+            crate::error::INDEX_NOT_FOUND
+        );
+    }
+}
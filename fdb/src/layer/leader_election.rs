@@ -0,0 +1,574 @@
+//! Provides [`LeaderElection`] type for electing a single leader
+//! among candidates.
+//!
+//! Each candidate registers itself under a versionstamped key, so
+//! that candidates are ordered by the time at which they registered,
+//! and keeps its registration alive by periodically updating its
+//! expiry. The leader holds a [`Lock`] lease, which it renews while it
+//! is alive.
+//!
+//! When the leader's lease expires, or the leader resigns, the
+//! earliest registered candidate that is still alive becomes the new
+//! leader. Candidates watch the leader key, so that they notice
+//! leadership changes without polling.
+//!
+//! Every new leader gets a new fencing token, which can be checked
+//! using [`LeaderElection::check_leader`] in the transactions that do
+//! the leader's work.
+//!
+//! Candidate ids must be unique. Expiry times are in milliseconds
+//! since the UNIX epoch and are taken from the clock of the
+//! candidates.
+//!
+//! The following keys are used within the [`Subspace`].
+//!
+//! - `("C", versionstamp)` - Candidate, stored as a tuple of `(id,
+//!   expiry)`.
+//! - `("L",)` - Leader, stored as a [`Lock`] lease.
+
+use bytes::Bytes;
+
+use futures::Stream;
+
+use parking_lot::Mutex;
+
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+
+use tokio_stream::StreamExt;
+
+use std::cmp;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use crate::database::FdbDatabase;
+use crate::error::{FdbError, FdbResult, LEADER_ELECTION_NOT_LEADER};
+use crate::range::{RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{MutationType, Transaction};
+use crate::tuple::{Tuple, Versionstamp};
+use crate::Key;
+
+use super::lock::{Lock, LockLease};
+use super::{duration_millis, now_millis};
+
+/// A change in leadership observed by a [`Candidacy`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum LeaderEvent {
+    /// The candidate was elected leader, with the given fencing
+    /// token.
+    Elected(i64),
+    /// The candidate is no longer the leader.
+    Lost,
+    /// Another candidate, with the given id, is the leader.
+    LeaderIs(Bytes),
+}
+
+/// Leader election among candidates, stored in a [`Subspace`] of a
+/// [`FdbDatabase`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct LeaderElection {
+    fdb_database: FdbDatabase,
+    subspace: Subspace,
+    lock: Lock,
+}
+
+impl LeaderElection {
+    /// Create a new [`LeaderElection`] stored in [`Subspace`] of
+    /// [`FdbDatabase`].
+    pub fn new(fdb_database: FdbDatabase, subspace: Subspace) -> LeaderElection {
+        let lock = Lock::new(fdb_database.clone(), subspace.clone(), String::from("L"));
+
+        LeaderElection {
+            fdb_database,
+            subspace,
+            lock,
+        }
+    }
+
+    /// Gets a reference to the [`Subspace`] of the
+    /// [`LeaderElection`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Get the id of the current leader, if there is one.
+    pub async fn get_leader(&self) -> FdbResult<Option<Bytes>> {
+        Ok(self
+            .lock
+            .get_lease()
+            .await?
+            .map(|l| l.get_owner_ref().clone()))
+    }
+
+    /// Register a candidate with `id` and campaign for leadership.
+    ///
+    /// The returned [`Candidacy`] is a stream of [`LeaderEvent`]s.
+    /// The registration and, when elected, the leader lease are kept
+    /// alive for `lease` and renewed every third of `lease` in the
+    /// background. When the [`Candidacy`] is dropped, the candidate
+    /// resigns.
+    ///
+    /// This method must be called from within a Tokio runtime.
+    pub fn campaign(&self, id: Bytes, lease: Duration) -> Candidacy {
+        Candidacy::new(self.clone(), id, lease)
+    }
+
+    /// Check that `token` is the fencing token of the current leader,
+    /// using `tr`.
+    ///
+    /// The leader key is added to the read conflict ranges of `tr`. So
+    /// if `tr` is committed, leadership did not change between this
+    /// check and the commit. If the leader with `token` is no longer
+    /// the leader, an error with [`LEADER_ELECTION_NOT_LEADER`] code is
+    /// returned.
+    ///
+    /// **Note:** The expiry of the leader lease is checked against the
+    /// local clock when this method is called, so the lease may have
+    /// expired by the time `tr` is committed. The commit still fails
+    /// if another candidate has been elected in the meantime.
+    pub async fn check_leader<T>(&self, tr: &T, token: i64) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        let now = now_millis();
+
+        tr.add_read_conflict_key(self.lock.lock_key())?;
+
+        match self.lock.get_lease_tr(tr).await? {
+            Some(l) if l.get_token() == token && l.is_held(now) => Ok(()),
+            _ => Err(FdbError::new(LEADER_ELECTION_NOT_LEADER)),
+        }
+    }
+
+    async fn register(&self, id: &Bytes, lease_millis: i64) -> FdbResult<Key> {
+        let tr_version = self
+            .fdb_database
+            .run(|tr| async move {
+                let mut t = Tuple::new();
+                t.add_versionstamp(Versionstamp::incomplete(0));
+
+                let key = self.candidate_subspace().pack_with_versionstamp(&t)?;
+
+                // Safety: `SetVersionstampedKey` is not
+                // `AppendIfFits`.
+                unsafe {
+                    tr.mutate(
+                        MutationType::SetVersionstampedKey,
+                        key,
+                        candidate_value(id.clone(), now_millis().saturating_add(lease_millis)),
+                    );
+                }
+
+                Ok(unsafe { tr.get_versionstamp() })
+            })
+            .await?
+            .get()
+            .await?;
+
+        Ok(self.candidate_key(Versionstamp::complete(tr_version, 0)))
+    }
+
+    // Keep the candidate alive, and become the leader if the leader
+    // lease has expired and we are the earliest live candidate.
+    // Returns the current leader lease, or `None` while we wait for
+    // an earlier candidate to become the leader.
+    async fn step<T>(
+        &self,
+        tr: &T,
+        id: &Bytes,
+        candidate_key: &Key,
+        lease_millis: i64,
+    ) -> FdbResult<Option<LockLease>>
+    where
+        T: Transaction,
+    {
+        let now = now_millis();
+        let expiry = now.saturating_add(lease_millis);
+
+        tr.set(candidate_key.clone(), candidate_value(id.clone(), expiry));
+
+        match self.lock.get_lease_tr(tr).await? {
+            // The leader renews its own lease.
+            Some(l) if l.is_held(now) && l.get_owner_ref() == id => {}
+            Some(l) if l.is_held(now) => return Ok(Some(l)),
+            // A candidate only acquires the lease for itself, using
+            // its own clock and lease. An earlier candidate that has
+            // resigned, or is no longer campaigning, is therefore
+            // never elected. Our own candidate key is visible to the
+            // snapshot read in `first_live_candidate`, so there is
+            // always a first live candidate.
+            _ => {
+                if self.first_live_candidate(tr, now).await?.as_ref() != Some(id) {
+                    return Ok(None);
+                }
+            }
+        }
+
+        match self
+            .lock
+            .try_acquire_tr(tr, id.clone(), now, expiry)
+            .await?
+        {
+            Ok(l) => Ok(Some(l)),
+            // `try_acquire_tr` only fails when the lease is held by
+            // another owner, which we have checked above.
+            Err(_) => Err(FdbError::new(LEADER_ELECTION_NOT_LEADER)),
+        }
+    }
+
+    // Clears candidates that have expired. Candidates are read using
+    // a snapshot read, so that we do not conflict with the
+    // heartbeats of other candidates.
+    async fn first_live_candidate<T>(&self, tr: &T, now: i64) -> FdbResult<Option<Bytes>>
+    where
+        T: Transaction,
+    {
+        let mut range_stream =
+            self.candidate_subspace()
+                .range(&Tuple::new())
+                .into_stream(&tr.snapshot(), {
+                    let mut ro = RangeOptions::default();
+                    ro.set_mode(StreamingMode::Iterator);
+                    ro
+                });
+
+        while let Some(x) = range_stream.next().await {
+            let (key, value) = x?.into_parts();
+            let t = Tuple::from_bytes(value)?;
+
+            if t.get_i64(1)? > now {
+                return Ok(Some(t.get_bytes_ref(0)?.clone()));
+            }
+
+            tr.clear(key);
+        }
+
+        Ok(None)
+    }
+
+    async fn resign(&self, candidate_key: Option<Key>, lock_lease: Option<LockLease>) {
+        if let Some(candidate_key) = candidate_key {
+            let _ = self
+                .fdb_database
+                .run(|tr| {
+                    let candidate_key = candidate_key.clone();
+                    async move {
+                        tr.clear(candidate_key);
+                        Ok(())
+                    }
+                })
+                .await;
+        }
+
+        if let Some(lock_lease) = lock_lease {
+            let _ = self.lock.release(&lock_lease).await;
+        }
+    }
+
+    fn candidate_subspace(&self) -> Subspace {
+        let mut t = Tuple::new();
+        t.add_string(String::from("C"));
+        self.subspace.subspace(&t)
+    }
+
+    fn candidate_key(&self, versionstamp: Versionstamp) -> Key {
+        let mut t = Tuple::new();
+        t.add_versionstamp(versionstamp);
+        self.candidate_subspace().subspace(&t).pack().into()
+    }
+}
+
+// Initial delay before retrying after an error. The delay doubles on
+// every consecutive error, up to a third of the lease.
+const MIN_BACKOFF: Duration = Duration::from_millis(10);
+
+/// A candidate in a [`LeaderElection`].
+///
+/// [`Candidacy`] is created using [`LeaderElection::campaign`] and
+/// implements [`Stream`] trait, yielding a [`LeaderEvent`] whenever
+/// leadership changes.
+///
+/// Errors are also yielded by the stream, after which the candidate
+/// keeps campaigning, retrying with exponential backoff. If the
+/// candidate is the leader and its leader lease expires while
+/// retrying, [`LeaderEvent::Lost`] is yielded.
+#[derive(Debug)]
+pub struct Candidacy {
+    receiver: UnboundedReceiver<FdbResult<LeaderEvent>>,
+    join_handle: JoinHandle<()>,
+    leader_election: LeaderElection,
+    state: Arc<Mutex<CandidacyState>>,
+}
+
+// State shared with the background task, which is needed to resign.
+#[derive(Debug, Default)]
+struct CandidacyState {
+    candidate_key: Option<Key>,
+    lock_lease: Option<LockLease>,
+}
+
+impl Candidacy {
+    fn new(leader_election: LeaderElection, id: Bytes, lease: Duration) -> Candidacy {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let state = Arc::new(Mutex::new(CandidacyState::default()));
+
+        let join_handle = tokio::spawn({
+            let leader_election = leader_election.clone();
+            let state = state.clone();
+
+            async move {
+                Candidacy::campaign(&leader_election, id, lease, &sender, &state).await;
+            }
+        });
+
+        Candidacy {
+            receiver,
+            join_handle,
+            leader_election,
+            state,
+        }
+    }
+
+    // Campaigns until `Candidacy` is dropped, retrying with
+    // exponential backoff on errors.
+    async fn campaign(
+        leader_election: &LeaderElection,
+        id: Bytes,
+        lease: Duration,
+        sender: &UnboundedSender<FdbResult<LeaderEvent>>,
+        state: &Mutex<CandidacyState>,
+    ) {
+        let mut current: Option<LockLease> = None;
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            match Candidacy::campaign_step(leader_election, &id, lease, sender, state, &mut current)
+                .await
+            {
+                Ok(true) => backoff = MIN_BACKOFF,
+                // `Candidacy` has been dropped.
+                Ok(false) => return,
+                Err(e) => {
+                    if sender.send(Err(e)).is_err() {
+                        return;
+                    }
+
+                    // Our leader lease has expired without being
+                    // renewed, so another candidate can become the
+                    // leader.
+                    if current
+                        .as_ref()
+                        .map(|l| l.get_owner_ref() == &id && !l.is_held(now_millis()))
+                        .unwrap_or(false)
+                    {
+                        current = None;
+                        state.lock().lock_lease = None;
+
+                        if sender.send(Ok(LeaderEvent::Lost)).is_err() {
+                            return;
+                        }
+                    }
+
+                    tokio::time::sleep(backoff).await;
+                    backoff = next_backoff(backoff, lease);
+                }
+            }
+        }
+    }
+
+    // Registers the candidate if needed, keeps it alive, sends the
+    // events for leadership changes and waits until the next step.
+    // Returns `false` when `Candidacy` has been dropped.
+    async fn campaign_step(
+        leader_election: &LeaderElection,
+        id: &Bytes,
+        lease: Duration,
+        sender: &UnboundedSender<FdbResult<LeaderEvent>>,
+        state: &Mutex<CandidacyState>,
+        current: &mut Option<LockLease>,
+    ) -> FdbResult<bool> {
+        let lease_millis = duration_millis(lease);
+
+        let candidate_key = state.lock().candidate_key.clone();
+
+        let candidate_key = match candidate_key {
+            Some(candidate_key) => candidate_key,
+            None => {
+                let candidate_key = leader_election.register(id, lease_millis).await?;
+                state.lock().candidate_key = Some(candidate_key.clone());
+                candidate_key
+            }
+        };
+
+        let (lock_lease, watch) = leader_election
+            .fdb_database
+            .run(|tr| {
+                let candidate_key = candidate_key.clone();
+                async move {
+                    let l = leader_election
+                        .step(&tr, id, &candidate_key, lease_millis)
+                        .await?;
+                    Ok((l, tr.watch(leader_election.lock.lock_key())))
+                }
+            })
+            .await?;
+
+        let was_leader = current
+            .as_ref()
+            .map(|l| l.get_owner_ref() == id)
+            .unwrap_or(false);
+        let is_leader = lock_lease
+            .as_ref()
+            .map(|l| l.get_owner_ref() == id)
+            .unwrap_or(false);
+        let changed = match (current.as_ref(), lock_lease.as_ref()) {
+            (Some(c), Some(l)) => {
+                c.get_token() != l.get_token() || c.get_owner_ref() != l.get_owner_ref()
+            }
+            (None, None) => false,
+            _ => true,
+        };
+
+        state.lock().lock_lease = lock_lease.clone().filter(|_| is_leader);
+
+        let mut events = Vec::new();
+        if changed {
+            if was_leader {
+                events.push(LeaderEvent::Lost);
+            }
+            // There is no event while there is no leader.
+            if let Some(l) = lock_lease.as_ref() {
+                if is_leader {
+                    events.push(LeaderEvent::Elected(l.get_token()));
+                } else {
+                    events.push(LeaderEvent::LeaderIs(l.get_owner_ref().clone()));
+                }
+            }
+        }
+
+        for event in events {
+            if sender.send(Ok(event)).is_err() {
+                return Ok(false);
+            }
+        }
+
+        // Wake up to renew, when the leader lease expires, or when the
+        // leader key changes.
+        let wait = match lock_lease.as_ref() {
+            Some(l) => cmp::min(
+                lease / 3,
+                Duration::from_millis(cmp::max(0, l.get_expiry() - now_millis()) as u64),
+            ),
+            None => lease / 3,
+        };
+
+        *current = lock_lease;
+
+        if let Ok(res) = tokio::time::timeout(wait, watch).await {
+            res?;
+        }
+
+        Ok(true)
+    }
+}
+
+impl Stream for Candidacy {
+    type Item = FdbResult<LeaderEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for Candidacy {
+    fn drop(&mut self) {
+        self.join_handle.abort();
+
+        let leader_election = self.leader_election.clone();
+        let (candidate_key, lock_lease) = {
+            let mut state = self.state.lock();
+            (state.candidate_key.take(), state.lock_lease.take())
+        };
+
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move {
+                leader_election.resign(candidate_key, lock_lease).await;
+            });
+        }
+    }
+}
+
+fn next_backoff(backoff: Duration, lease: Duration) -> Duration {
+    cmp::max(MIN_BACKOFF, cmp::min(backoff * 2, lease / 3))
+}
+
+fn candidate_value(id: Bytes, expiry: i64) -> Bytes {
+    let mut t = Tuple::new();
+    t.add_bytes(id);
+    t.add_i64(expiry);
+    t.pack()
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use std::time::Duration;
+
+    use super::{next_backoff, Candidacy, LeaderElection, LeaderEvent, MIN_BACKOFF};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    LeaderEvent:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    LeaderElection:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Candidacy:
+	        Send &
+		Sync &
+		!Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn backoff() {
+        let lease = Duration::from_millis(300);
+
+        assert_eq!(next_backoff(MIN_BACKOFF, lease), MIN_BACKOFF * 2);
+        assert_eq!(
+            next_backoff(Duration::from_millis(80), lease),
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            next_backoff(Duration::from_millis(100), lease),
+            Duration::from_millis(100)
+        );
+
+        // Backoff is at least `MIN_BACKOFF`, even for short leases.
+        assert_eq!(
+            next_backoff(MIN_BACKOFF, Duration::from_millis(3)),
+            MIN_BACKOFF
+        );
+    }
+}
@@ -0,0 +1,653 @@
+//! Provides [`Lock`] type for distributed locks with leases.
+//!
+//! A [`Lock`] is held by an owner for a lease period. The owner must
+//! renew the lease before it expires, otherwise the [`Lock`] can be
+//! acquired by another owner. As the lease is stored in the database,
+//! a [`Lock`] held by a process that crashes becomes available once
+//! its lease expires.
+//!
+//! Every time the [`Lock`] is acquired by a new owner, its fencing
+//! token is incremented. The fencing token can be checked using
+//! [`Lock::check_lease`] in the transactions that do the work
+//! protected by the [`Lock`], so that work from an owner that has
+//! lost the [`Lock`] is never committed.
+//!
+//! Expiry times are in milliseconds since the UNIX epoch and are
+//! taken from the clock of the owner. Clocks of owners should be kept
+//! reasonably in sync.
+//!
+//! The following key is used within the [`Subspace`].
+//!
+//! - `(name,)` - Lease, stored as a tuple of `(owner, expiry,
+//!   token)`. A released lease has an expiry of `0`.
+
+use bytes::Bytes;
+
+use parking_lot::Mutex;
+
+use tokio::task::JoinHandle;
+
+use std::cmp;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::database::FdbDatabase;
+use crate::error::{FdbError, FdbResult, LOCK_LEASE_LOST};
+use crate::subspace::Subspace;
+use crate::transaction::{ReadTransaction, Transaction};
+use crate::tuple::Tuple;
+use crate::Key;
+
+use super::{duration_millis, now_millis};
+
+/// A lease on a [`Lock`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LockLease {
+    owner: Bytes,
+    expiry: i64,
+    token: i64,
+}
+
+impl LockLease {
+    /// Gets a reference to the owner of the [`LockLease`].
+    pub fn get_owner_ref(&self) -> &Bytes {
+        &self.owner
+    }
+
+    /// Get the time at which the [`LockLease`] expires, in
+    /// milliseconds since the UNIX epoch.
+    pub fn get_expiry(&self) -> i64 {
+        self.expiry
+    }
+
+    /// Get the fencing token of the [`LockLease`].
+    pub fn get_token(&self) -> i64 {
+        self.token
+    }
+
+    fn new(owner: Bytes, expiry: i64, token: i64) -> LockLease {
+        LockLease {
+            owner,
+            expiry,
+            token,
+        }
+    }
+
+    fn from_bytes(b: Bytes) -> FdbResult<LockLease> {
+        let t = Tuple::from_bytes(b)?;
+
+        Ok(LockLease::new(
+            t.get_bytes_ref(0)?.clone(),
+            t.get_i64(1)?,
+            t.get_i64(2)?,
+        ))
+    }
+
+    fn to_bytes(&self) -> Bytes {
+        let mut t = Tuple::new();
+        t.add_bytes(self.owner.clone());
+        t.add_i64(self.expiry);
+        t.add_i64(self.token);
+        t.pack()
+    }
+
+    pub(crate) fn is_held(&self, now: i64) -> bool {
+        self.expiry > now
+    }
+}
+
+/// A named distributed lock stored in a [`Subspace`] of a
+/// [`FdbDatabase`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct Lock {
+    fdb_database: FdbDatabase,
+    subspace: Subspace,
+    name: String,
+}
+
+impl Lock {
+    /// Create a new [`Lock`] named `name` stored in [`Subspace`] of
+    /// [`FdbDatabase`].
+    pub fn new(fdb_database: FdbDatabase, subspace: Subspace, name: String) -> Lock {
+        Lock {
+            fdb_database,
+            subspace,
+            name,
+        }
+    }
+
+    /// Gets a reference to the name of the [`Lock`].
+    pub fn get_name_ref(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the current [`LockLease`], if the [`Lock`] is held.
+    pub async fn get_lease(&self) -> FdbResult<Option<LockLease>> {
+        self.fdb_database
+            .read(|tr| async move {
+                let now = now_millis();
+                Ok(self.get_lease_tr(&tr).await?.filter(|l| l.is_held(now)))
+            })
+            .await
+    }
+
+    /// Try to acquire the [`Lock`] for `owner` for `lease`.
+    ///
+    /// Returns [`None`] if the [`Lock`] is held by another owner. If
+    /// the [`Lock`] is already held by `owner`, its lease is renewed
+    /// and the fencing token is not changed.
+    pub async fn try_acquire(&self, owner: Bytes, lease: Duration) -> FdbResult<Option<LockLease>> {
+        self.fdb_database
+            .run(|tr| {
+                let owner = owner.clone();
+                async move {
+                    let now = now_millis();
+                    Ok(self
+                        .try_acquire_tr(&tr, owner, now, now.saturating_add(duration_millis(lease)))
+                        .await?
+                        .ok())
+                }
+            })
+            .await
+    }
+
+    /// Acquire the [`Lock`] for `owner` for `lease`, waiting until the
+    /// [`Lock`] is available.
+    ///
+    /// While the [`Lock`] is held by another owner, a watch on the
+    /// [`Lock`] key is used to wake up when it is released. We also
+    /// wake up when the lease of the other owner expires.
+    pub async fn acquire(&self, owner: Bytes, lease: Duration) -> FdbResult<LockLease> {
+        loop {
+            let res = self
+                .fdb_database
+                .run(|tr| {
+                    let owner = owner.clone();
+                    async move {
+                        let now = now_millis();
+
+                        match self
+                            .try_acquire_tr(
+                                &tr,
+                                owner,
+                                now,
+                                now.saturating_add(duration_millis(lease)),
+                            )
+                            .await?
+                        {
+                            Ok(lock_lease) => Ok(Ok(lock_lease)),
+                            Err(expiry) => {
+                                let wait = Duration::from_millis(cmp::max(0, expiry - now) as u64);
+                                Ok(Err((tr.watch(self.lock_key()), wait)))
+                            }
+                        }
+                    }
+                })
+                .await?;
+
+            match res {
+                Ok(lock_lease) => return Ok(lock_lease),
+                Err((watch, wait)) => {
+                    // When the lease of the other owner expires
+                    // without the `Lock` key being modified,
+                    // `timeout` returns an error, which we ignore.
+                    if let Ok(res) = tokio::time::timeout(wait, watch).await {
+                        res?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Acquire the [`Lock`] for `owner` for `lease`, waiting until the
+    /// [`Lock`] is available, and return a [`LockGuard`] that renews
+    /// the lease in the background.
+    ///
+    /// This method must be called from within a Tokio runtime.
+    pub async fn acquire_guard(&self, owner: Bytes, lease: Duration) -> FdbResult<LockGuard> {
+        let lock_lease = self.acquire(owner, lease).await?;
+        Ok(LockGuard::new(self.clone(), lock_lease, lease))
+    }
+
+    /// Renew `lock_lease` for `lease` from now.
+    ///
+    /// Returns a new [`LockLease`] with the updated expiry. If
+    /// `lock_lease` is no longer held, an error with
+    /// [`LOCK_LEASE_LOST`] code is returned.
+    pub async fn renew(&self, lock_lease: &LockLease, lease: Duration) -> FdbResult<LockLease> {
+        let new_lock_lease = LockLease::new(
+            lock_lease.owner.clone(),
+            now_millis().saturating_add(duration_millis(lease)),
+            lock_lease.token,
+        );
+
+        self.fdb_database
+            .run(|tr| {
+                let new_lock_lease = new_lock_lease.clone();
+                async move {
+                    // A lease that has expired can still be renewed,
+                    // as long as no other owner has acquired the
+                    // `Lock`, which would have changed the token.
+                    match self.get_lease_tr(&tr).await? {
+                        Some(l)
+                            if l.token == new_lock_lease.token
+                                && l.owner == new_lock_lease.owner
+                                && l.expiry != 0 =>
+                        {
+                            tr.set(self.lock_key(), new_lock_lease.to_bytes());
+                            Ok(new_lock_lease)
+                        }
+                        _ => Err(FdbError::new(LOCK_LEASE_LOST)),
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Release `lock_lease`.
+    ///
+    /// If `lock_lease` is no longer held, an error with
+    /// [`LOCK_LEASE_LOST`] code is returned.
+    pub async fn release(&self, lock_lease: &LockLease) -> FdbResult<()> {
+        self.fdb_database
+            .run(|tr| async move {
+                match self.get_lease_tr(&tr).await? {
+                    Some(l) if l.token == lock_lease.token && l.owner == lock_lease.owner => {
+                        // When `expiry` is `0`, a previous attempt was
+                        // committed, but we got a
+                        // `commit_unknown_result` error.
+                        if l.expiry != 0 {
+                            tr.set(
+                                self.lock_key(),
+                                LockLease::new(l.owner, 0, l.token).to_bytes(),
+                            );
+                        }
+                        Ok(())
+                    }
+                    _ => Err(FdbError::new(LOCK_LEASE_LOST)),
+                }
+            })
+            .await
+    }
+
+    /// Check that `lock_lease` is still held, using `tr`.
+    ///
+    /// The [`Lock`] key is added to the read conflict ranges of `tr`.
+    /// So if `tr` is committed, the [`Lock`] was not released or
+    /// acquired by another owner between this check and the commit.
+    /// If `lock_lease` is no longer held, an error with
+    /// [`LOCK_LEASE_LOST`] code is returned.
+    ///
+    /// **Note:** The expiry is checked against the local clock when
+    /// this method is called, so the lease may have expired by the
+    /// time `tr` is committed. The commit still fails if another owner
+    /// has acquired the [`Lock`] in the meantime.
+    pub async fn check_lease<T>(&self, tr: &T, lock_lease: &LockLease) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        let now = now_millis();
+
+        tr.add_read_conflict_key(self.lock_key())?;
+
+        match self.get_lease_tr(tr).await? {
+            Some(l) if l.token == lock_lease.token && l.is_held(now) => Ok(()),
+            _ => Err(FdbError::new(LOCK_LEASE_LOST)),
+        }
+    }
+
+    // Returns the expiry of the lease held by another owner, when the
+    // `Lock` cannot be acquired.
+    pub(crate) async fn try_acquire_tr<T>(
+        &self,
+        tr: &T,
+        owner: Bytes,
+        now: i64,
+        expiry: i64,
+    ) -> FdbResult<Result<LockLease, i64>>
+    where
+        T: Transaction,
+    {
+        let token = match self.get_lease_tr(tr).await? {
+            Some(l) if l.is_held(now) && l.owner != owner => return Ok(Err(l.expiry)),
+            Some(l) if l.is_held(now) => l.token,
+            Some(l) => l.token + 1,
+            None => 1,
+        };
+
+        let lock_lease = LockLease::new(owner, expiry, token);
+        tr.set(self.lock_key(), lock_lease.to_bytes());
+
+        Ok(Ok(lock_lease))
+    }
+
+    pub(crate) async fn get_lease_tr<T>(&self, tr: &T) -> FdbResult<Option<LockLease>>
+    where
+        T: ReadTransaction,
+    {
+        match tr.get(self.lock_key()).await? {
+            Some(v) => Ok(Some(LockLease::from_bytes(v.into())?)),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn lock_key(&self) -> Key {
+        let mut t = Tuple::new();
+        t.add_string(self.name.clone());
+        self.subspace.subspace(&t).pack().into()
+    }
+}
+
+/// Holds a [`LockLease`] and renews it in the background.
+///
+/// [`LockGuard`] is created using [`Lock::acquire_guard`]. The lease
+/// is renewed every third of the lease period. If the lease expires
+/// before it could be renewed, the [`LockLease`] is considered lost
+/// and renewal stops. When the [`LockGuard`] is dropped, renewal
+/// stops and the lease is released in the background.
+#[derive(Debug)]
+pub struct LockGuard {
+    lock: Lock,
+    lock_lease: Arc<Mutex<LockLease>>,
+    lost: Arc<AtomicBool>,
+    renew_error: Arc<Mutex<Option<FdbError>>>,
+    join_handle: Option<JoinHandle<()>>,
+}
+
+impl LockGuard {
+    /// Get the current [`LockLease`].
+    pub fn get_lease(&self) -> LockLease {
+        self.lock_lease.lock().clone()
+    }
+
+    /// Get the fencing token of the [`LockLease`].
+    pub fn get_token(&self) -> i64 {
+        self.lock_lease.lock().token
+    }
+
+    /// Returns `true` if the background renewal found that the
+    /// [`LockLease`] is no longer held, or if the [`LockLease`] has
+    /// expired without being renewed.
+    pub fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::SeqCst) || !self.lock_lease.lock().is_held(now_millis())
+    }
+
+    /// Get the error returned by the last background renewal, if it
+    /// failed.
+    pub fn get_renew_error(&self) -> Option<FdbError> {
+        *self.renew_error.lock()
+    }
+
+    /// Stop renewing and release the [`LockLease`].
+    pub async fn release(mut self) -> FdbResult<()> {
+        // Safety: `join_handle` is only taken here and in `drop`.
+        self.join_handle.take().unwrap().abort();
+        self.lock.release(&self.get_lease()).await
+    }
+
+    fn new(lock: Lock, lock_lease: LockLease, lease: Duration) -> LockGuard {
+        let lock_lease = Arc::new(Mutex::new(lock_lease));
+        let lost = Arc::new(AtomicBool::new(false));
+        let renew_error = Arc::new(Mutex::new(None));
+
+        let join_handle = tokio::spawn({
+            let lock = lock.clone();
+
+            renew_loop(
+                move |current| {
+                    let lock = lock.clone();
+                    async move { lock.renew(&current, lease).await }
+                },
+                lease,
+                lock_lease.clone(),
+                lost.clone(),
+                renew_error.clone(),
+            )
+        });
+
+        LockGuard {
+            lock,
+            lock_lease,
+            lost,
+            renew_error,
+            join_handle: Some(join_handle),
+        }
+    }
+}
+
+// Renews the lease in `lock_lease` using `renew` every third of
+// `lease`, until the lease is lost.
+//
+// The lease is lost when `renew` returns an error with
+// `LOCK_LEASE_LOST` code, or when the lease expires before it could
+// be renewed. Other errors are stored in `renew_error` and renewal is
+// tried again.
+async fn renew_loop<F, Fut>(
+    renew: F,
+    lease: Duration,
+    lock_lease: Arc<Mutex<LockLease>>,
+    lost: Arc<AtomicBool>,
+    renew_error: Arc<Mutex<Option<FdbError>>>,
+) where
+    F: Fn(LockLease) -> Fut,
+    Fut: Future<Output = FdbResult<LockLease>>,
+{
+    loop {
+        tokio::time::sleep(lease / 3).await;
+
+        let current = lock_lease.lock().clone();
+
+        // `renew` retries retryable errors, so we stop waiting for it
+        // once the lease has expired.
+        let remaining = cmp::max(0, current.expiry.saturating_sub(now_millis()));
+
+        match tokio::time::timeout(
+            Duration::from_millis(remaining as u64),
+            renew(current.clone()),
+        )
+        .await
+        {
+            Ok(Ok(l)) => {
+                *lock_lease.lock() = l;
+                *renew_error.lock() = None;
+            }
+            Ok(Err(e)) => {
+                *renew_error.lock() = Some(e);
+
+                if e.code() == LOCK_LEASE_LOST || !current.is_held(now_millis()) {
+                    lost.store(true, Ordering::SeqCst);
+                    return;
+                }
+            }
+            Err(_) => {
+                lost.store(true, Ordering::SeqCst);
+                return;
+            }
+        }
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        if let Some(join_handle) = self.join_handle.take() {
+            join_handle.abort();
+
+            if !self.is_lost() {
+                let lock = self.lock.clone();
+                let lock_lease = self.get_lease();
+
+                if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                    handle.spawn(async move {
+                        let _ = lock.release(&lock_lease).await;
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+    use parking_lot::Mutex;
+
+    use std::future;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::{renew_loop, Lock, LockGuard, LockLease};
+    use crate::error::{FdbError, FdbResult, LOCK_LEASE_LOST};
+    use crate::layer::{duration_millis, now_millis};
+
+    const LEASE: Duration = Duration::from_millis(60);
+
+    // Runs `renew_loop` with `renew` for a lease that expires after
+    // `LEASE`, until it returns or `timeout` has elapsed. Returns the
+    // number of renewals, whether the lease was lost and the last
+    // renewal error.
+    async fn run_renew_loop<Fut>(
+        renew: impl Fn(LockLease) -> Fut,
+        timeout: Duration,
+    ) -> (usize, bool, Option<FdbError>)
+    where
+        Fut: std::future::Future<Output = FdbResult<LockLease>>,
+    {
+        let lock_lease = Arc::new(Mutex::new(LockLease::new(
+            Bytes::from_static(b"owner"),
+            now_millis() + duration_millis(LEASE),
+            1,
+        )));
+        let lost = Arc::new(AtomicBool::new(false));
+        let renew_error = Arc::new(Mutex::new(None));
+        let renewals = AtomicUsize::new(0);
+
+        let _ = tokio::time::timeout(
+            timeout,
+            renew_loop(
+                |current| {
+                    renewals.fetch_add(1, Ordering::SeqCst);
+                    renew(current)
+                },
+                LEASE,
+                lock_lease,
+                lost.clone(),
+                renew_error.clone(),
+            ),
+        )
+        .await;
+
+        let renewals = renewals.load(Ordering::SeqCst);
+        let lost = lost.load(Ordering::SeqCst);
+        let renew_error = *renew_error.lock();
+        (renewals, lost, renew_error)
+    }
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    LockLease:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Lock:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    LockGuard:
+	        Send &
+		Sync &
+		!Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn lock_lease_bytes() {
+        let lock_lease = LockLease::new(Bytes::from_static(b"owner"), 1_000, 3);
+
+        assert_eq!(
+            LockLease::from_bytes(lock_lease.to_bytes()).unwrap(),
+            lock_lease
+        );
+    }
+
+    #[test]
+    fn lock_lease_is_held() {
+        let lock_lease = LockLease::new(Bytes::from_static(b"owner"), 1_000, 3);
+
+        assert!(lock_lease.is_held(999));
+        assert!(!lock_lease.is_held(1_000));
+
+        // Released lease.
+        assert!(!LockLease::new(Bytes::from_static(b"owner"), 0, 3).is_held(0));
+    }
+
+    #[tokio::test]
+    async fn renew_loop_renews() {
+        let (renewals, lost, renew_error) = run_renew_loop(
+            |current| async move {
+                Ok(LockLease::new(
+                    current.owner,
+                    now_millis() + duration_millis(LEASE),
+                    current.token,
+                ))
+            },
+            LEASE * 4,
+        )
+        .await;
+
+        // The lease is renewed past its initial expiry.
+        assert!(renewals >= 4);
+        assert!(!lost);
+        assert_eq!(renew_error, None);
+    }
+
+    #[tokio::test]
+    async fn renew_loop_lease_lost() {
+        let (renewals, lost, renew_error) =
+            run_renew_loop(|_| async { Err(FdbError::new(LOCK_LEASE_LOST)) }, LEASE * 4).await;
+
+        assert_eq!(renewals, 1);
+        assert!(lost);
+        assert_eq!(renew_error, Some(FdbError::new(LOCK_LEASE_LOST)));
+    }
+
+    #[tokio::test]
+    async fn renew_loop_errors_until_expiry() {
+        // Errors other than `LOCK_LEASE_LOST` are retried, until the
+        // lease expires.
+        let (renewals, lost, renew_error) =
+            run_renew_loop(|_| async { Err(FdbError::new(2101)) }, LEASE * 4).await;
+
+        assert!(renewals >= 2);
+        assert!(lost);
+        assert_eq!(renew_error, Some(FdbError::new(2101)));
+    }
+
+    #[tokio::test]
+    async fn renew_loop_pending_until_expiry() {
+        // A renewal that does not complete before the lease expires
+        // loses the lease.
+        let (renewals, lost, renew_error) = run_renew_loop(|_| future::pending(), LEASE * 4).await;
+
+        assert_eq!(renewals, 1);
+        assert!(lost);
+        assert_eq!(renew_error, None);
+    }
+}
@@ -0,0 +1,82 @@
+//! Provides layers built on top of FDB.
+//!
+//! Layers use [`Subspace`] to store their data alongside application
+//! data, and use [`Transaction`] for their operations, so they can be
+//! composed with other reads and writes in the same transaction.
+//!
+//! [`Subspace`]: crate::subspace::Subspace
+//! [`Transaction`]: crate::transaction::Transaction
+
+pub mod blob;
+pub mod counter;
+pub mod index;
+pub mod leader_election;
+pub mod lock;
+pub mod queue;
+pub mod ranked_set;
+pub mod work_queue;
+
+use bytes::Bytes;
+
+use uuid::Uuid;
+
+use std::cmp;
+use std::convert::TryInto;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Layers that store deadlines use milliseconds since the UNIX epoch,
+// taken from the local clock.
+pub(crate) fn now_millis() -> i64 {
+    duration_millis(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_else(|_| Duration::from_secs(0)),
+    )
+}
+
+pub(crate) fn duration_millis(d: Duration) -> i64 {
+    d.as_millis().try_into().unwrap_or(i64::MAX)
+}
+
+// Returns a random index less than `n`. Layers use it to spread
+// concurrent workers over the items at the front of a queue.
+pub(crate) fn random_index(n: usize) -> usize {
+    assert!(n > 0);
+    (Uuid::new_v4().as_u128() % (n as u128)) as usize
+}
+
+// Decodes a little-endian 64-bit integer updated using `Add`
+// mutation. Like `Add`, missing bytes are treated as zero.
+pub(crate) fn decode_i64(b: Bytes) -> i64 {
+    let mut buf = [0; 8];
+    let n = cmp::min(buf.len(), b.len());
+    buf[..n].copy_from_slice(&b[..n]);
+    i64::from_le_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::{decode_i64, random_index};
+
+    #[test]
+    fn decode_i64_bytes() {
+        assert_eq!(decode_i64(Bytes::new()), 0);
+        assert_eq!(decode_i64(Bytes::from_static(b"\x01")), 1);
+        assert_eq!(
+            decode_i64(Bytes::copy_from_slice(&(-5_i64).to_le_bytes())),
+            -5
+        );
+        assert_eq!(
+            decode_i64(Bytes::copy_from_slice(&i64::MAX.to_le_bytes())),
+            i64::MAX
+        );
+    }
+
+    #[test]
+    fn random_index_bounds() {
+        assert_eq!(random_index(1), 0);
+        assert!((0..100).all(|_| random_index(3) < 3));
+    }
+}
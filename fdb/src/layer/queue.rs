@@ -0,0 +1,461 @@
+//! Provides [`Queue`] type for durable first-in, first-out queues.
+//!
+//! Items are stored under keys containing a [`Versionstamp`], so that
+//! they are ordered by the commit version of the transaction that
+//! pushed them. As versionstamped keys are assigned when the
+//! transaction commits, pushes never conflict with each other or with
+//! pops. Items pushed in the same transaction are ordered by a
+//! sequence number that is incremented on every push within the
+//! process.
+//!
+//! Pops read the front of the queue using snapshot reads and only add
+//! a read conflict on the items that they remove. So, concurrent pops
+//! conflict only when they try to remove the same item, in which case
+//! one of the transactions is retried. To avoid every pop trying to
+//! remove the first item, a pop starts at a random offset within the
+//! first `pop_window` items (see [`Queue::new_with_pop_window`]).
+//! Therefore, items are popped in approximately first-in, first-out
+//! order. An item can be popped before at most `pop_window - 1` items
+//! that were pushed before it. A `pop_window` of `1` gives strict
+//! first-in, first-out order, at the cost of concurrent pops
+//! conflicting with each other.
+//!
+//! The following keys are used within the [`Subspace`].
+//!
+//! - `("C",)` - Number of items in the queue, as a little-endian
+//!   64-bit integer updated using atomic operations.
+//! - `("I", versionstamp, sequence)` - Item.
+//!
+//! [`Versionstamp`]: crate::tuple::Versionstamp
+
+use bytes::Bytes;
+
+use tokio_stream::StreamExt;
+
+use std::cmp;
+use std::convert::TryInto;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+use crate::error::FdbResult;
+use crate::range::{RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{MutationType, ReadTransaction, Transaction};
+use crate::tuple::{Tuple, Versionstamp};
+use crate::Key;
+
+use super::{decode_i64, random_index};
+
+/// Number of items at the front of a [`Queue`] that pops are spread
+/// over, when the [`Queue`] is created using [`Queue::new`].
+pub const DEFAULT_POP_WINDOW: usize = 16;
+
+// Orders items pushed in the same transaction. A transaction is only
+// used within a single process, so a process-wide counter is
+// sufficient.
+static PUSH_SEQUENCE: AtomicI64 = AtomicI64::new(0);
+
+/// A durable first-in, first-out queue stored in a [`Subspace`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct Queue {
+    subspace: Subspace,
+    pop_window: usize,
+}
+
+impl Queue {
+    /// Create a new [`Queue`] stored in [`Subspace`], with a pop
+    /// window of [`DEFAULT_POP_WINDOW`].
+    pub fn new(subspace: Subspace) -> Queue {
+        Queue::new_with_pop_window(subspace, DEFAULT_POP_WINDOW)
+    }
+
+    /// Create a new [`Queue`] stored in [`Subspace`], whose pops are
+    /// spread over the first `pop_window` items.
+    ///
+    /// A `pop_window` of `0` is treated as `1`.
+    pub fn new_with_pop_window(subspace: Subspace, pop_window: usize) -> Queue {
+        Queue {
+            subspace,
+            pop_window: cmp::max(pop_window, 1),
+        }
+    }
+
+    /// Gets a reference to the [`Subspace`] of the [`Queue`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Get the number of items at the front of the [`Queue`] that pops
+    /// are spread over.
+    pub fn get_pop_window(&self) -> usize {
+        self.pop_window
+    }
+
+    /// Push `item` to the back of the [`Queue`].
+    ///
+    /// Items pushed in a transaction cannot be read using the same
+    /// transaction.
+    pub fn push<T>(&self, tr: &T, item: Bytes) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        self.push_many(tr, std::iter::once(item))
+    }
+
+    /// Push `items` to the back of the [`Queue`], in order.
+    ///
+    /// Items pushed in a transaction cannot be read using the same
+    /// transaction.
+    pub fn push_many<T, I>(&self, tr: &T, items: I) -> FdbResult<()>
+    where
+        T: Transaction,
+        I: IntoIterator<Item = Bytes>,
+    {
+        let items = items.into_iter().collect::<Vec<_>>();
+
+        let n: i64 = items.len().try_into().unwrap();
+
+        let sequence = PUSH_SEQUENCE.fetch_add(n, Ordering::Relaxed);
+
+        let mut keys_items = Vec::with_capacity(items.len());
+
+        for (i, item) in (0..).zip(items) {
+            keys_items.push((self.item_key_with_versionstamp(sequence + i)?, item));
+        }
+
+        for (key, item) in keys_items {
+            // Safety: `SetVersionstampedKey` is not `AppendIfFits`.
+            unsafe {
+                tr.mutate(MutationType::SetVersionstampedKey, key, item);
+            }
+        }
+
+        self.add_len(tr, n);
+
+        Ok(())
+    }
+
+    /// Get the item at the front of the [`Queue`] without removing
+    /// it.
+    ///
+    /// Returns [`None`] if the [`Queue`] is empty.
+    pub async fn peek<T>(&self, tr: &T) -> FdbResult<Option<Bytes>>
+    where
+        T: ReadTransaction,
+    {
+        Ok(self.get_items(tr, 1).await?.pop().map(|(_, item)| item))
+    }
+
+    /// Remove and return the item at the front of the [`Queue`].
+    ///
+    /// Returns [`None`] if the [`Queue`] is empty.
+    pub async fn pop<T>(&self, tr: &T) -> FdbResult<Option<Bytes>>
+    where
+        T: Transaction,
+    {
+        Ok(self.pop_many(tr, 1).await?.pop())
+    }
+
+    /// Remove and return up to `n` items from the front of the
+    /// [`Queue`], in order.
+    ///
+    /// The items are removed starting at a random offset within the
+    /// first `pop_window` items. Fewer than `n` items are returned
+    /// when the [`Queue`] has fewer than `n` items.
+    pub async fn pop_many<T>(&self, tr: &T, n: usize) -> FdbResult<Vec<Bytes>>
+    where
+        T: Transaction,
+    {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        // Snapshot read, so that we do not conflict with pushes to the
+        // back of the queue, or with pops that remove other items.
+        let keys_items = self
+            .get_items(&tr.snapshot(), n.saturating_add(self.pop_window - 1))
+            .await?;
+
+        // When there are fewer than `n + pop_window - 1` items, the
+        // offset is chosen such that we still remove `n` items, if
+        // possible.
+        let offset = random_index(cmp::min(
+            self.pop_window,
+            keys_items.len().saturating_sub(n) + 1,
+        ));
+
+        let mut res = Vec::with_capacity(n);
+
+        for (key, item) in keys_items.into_iter().skip(offset).take(n) {
+            tr.add_read_conflict_key(key.clone())?;
+            tr.clear(key);
+            res.push(item);
+        }
+
+        self.add_len(tr, -TryInto::<i64>::try_into(res.len()).unwrap());
+
+        Ok(res)
+    }
+
+    /// Get the number of items in the [`Queue`].
+    ///
+    /// Reading the number of items using `tr` conflicts with every
+    /// push and pop. Use [`snapshot`] to get an estimate without
+    /// adding a read conflict.
+    ///
+    /// [`snapshot`]: crate::transaction::Transaction::snapshot
+    pub async fn get_len<T>(&self, tr: &T) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        match tr.get(self.len_key()).await? {
+            Some(v) => Ok(decode_i64(v.into())),
+            None => Ok(0),
+        }
+    }
+
+    /// Remove all items from the [`Queue`].
+    pub fn clear<T>(&self, tr: &T)
+    where
+        T: Transaction,
+    {
+        tr.clear_range(self.subspace.range(&Tuple::new()));
+    }
+
+    async fn get_items<T>(&self, tr: &T, n: usize) -> FdbResult<Vec<(Key, Bytes)>>
+    where
+        T: ReadTransaction,
+    {
+        if n == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut range_stream = self.item_subspace().range(&Tuple::new()).into_stream(tr, {
+            let mut ro = RangeOptions::default();
+            ro.set_limit(n.try_into().unwrap_or(i32::MAX));
+            ro.set_mode(StreamingMode::WantAll);
+            ro
+        });
+
+        let mut res = Vec::new();
+
+        while let Some(x) = range_stream.next().await {
+            let (key, value) = x?.into_parts();
+            res.push((key, Bytes::from(value)));
+        }
+
+        Ok(res)
+    }
+
+    fn add_len<T>(&self, tr: &T, n: i64)
+    where
+        T: Transaction,
+    {
+        if n != 0 {
+            // Safety: `Add` is not `AppendIfFits`.
+            unsafe {
+                tr.mutate(
+                    MutationType::Add,
+                    self.len_key(),
+                    Bytes::copy_from_slice(&n.to_le_bytes()),
+                );
+            }
+        }
+    }
+
+    fn len_key(&self) -> Key {
+        let mut t = Tuple::new();
+        t.add_string(String::from("C"));
+        self.subspace.subspace(&t).pack().into()
+    }
+
+    fn item_subspace(&self) -> Subspace {
+        let mut t = Tuple::new();
+        t.add_string(String::from("I"));
+        self.subspace.subspace(&t)
+    }
+
+    fn item_key_with_versionstamp(&self, sequence: i64) -> FdbResult<Bytes> {
+        let mut t = Tuple::new();
+        t.add_versionstamp(Versionstamp::incomplete(0));
+        t.add_i64(sequence);
+        self.item_subspace().pack_with_versionstamp(&t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use super::Queue;
+    use crate::subspace::Subspace;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Queue:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn item_key_with_versionstamp() {
+        let queue = Queue::new(Subspace::new(Bytes::from_static(b"queue")));
+
+        // Items pushed in the same transaction must sort by sequence
+        // number.
+        let item_key_0 = queue.item_key_with_versionstamp(0).unwrap();
+        let item_key_1 = queue.item_key_with_versionstamp(1).unwrap();
+
+        assert!(item_key_0 < item_key_1);
+        assert!(queue.item_subspace().contains(&item_key_0));
+
+        // Items must sort after the length key.
+        assert!(Bytes::from(queue.len_key()) < queue.item_subspace().pack());
+        assert!(!queue
+            .item_subspace()
+            .contains(&Bytes::from(queue.len_key())));
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction() {
+        use crate::memory::MemoryDatabase;
+
+        let db = MemoryDatabase::new();
+
+        // A pop window of `1` pops items in order.
+        let queue = Queue::new_with_pop_window(Subspace::new(Bytes::from_static(b"queue")), 1);
+
+        db.run(|tr| {
+            let queue = queue.clone();
+            async move {
+                queue.push_many(
+                    &tr,
+                    vec![Bytes::from_static(b"a"), Bytes::from_static(b"b")],
+                )
+            }
+        })
+        .await
+        .unwrap();
+
+        db.run(|tr| {
+            let queue = queue.clone();
+            async move { queue.push(&tr, Bytes::from_static(b"c")) }
+        })
+        .await
+        .unwrap();
+
+        let (len, item) = db
+            .read(|tr| {
+                let queue = queue.clone();
+                async move { Ok((queue.get_len(&tr).await?, queue.peek(&tr).await?)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(len, 3);
+        assert_eq!(item, Some(Bytes::from_static(b"a")));
+
+        let (item, items) = db
+            .run(|tr| {
+                let queue = queue.clone();
+                async move { Ok((queue.pop(&tr).await?, queue.pop_many(&tr, 5).await?)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(item, Some(Bytes::from_static(b"a")));
+        assert_eq!(
+            items,
+            vec![Bytes::from_static(b"b"), Bytes::from_static(b"c")]
+        );
+
+        let (len, item) = db
+            .read(|tr| {
+                let queue = queue.clone();
+                async move { Ok((queue.get_len(&tr).await?, queue.peek(&tr).await?)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(len, 0);
+        assert_eq!(item, None);
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction_push_twice() {
+        use crate::memory::MemoryDatabase;
+
+        let db = MemoryDatabase::new();
+        let queue = Queue::new(Subspace::new(Bytes::from_static(b"queue")));
+
+        // Every push in a transaction must add an item, in the order
+        // of the pushes.
+        db.run(|tr| {
+            let queue = queue.clone();
+            async move {
+                queue.push(&tr, Bytes::from_static(b"a"))?;
+                queue.push(&tr, Bytes::from_static(b"b"))?;
+                queue.push_many(
+                    &tr,
+                    vec![Bytes::from_static(b"c"), Bytes::from_static(b"d")],
+                )?;
+                queue.push(&tr, Bytes::from_static(b"e"))
+            }
+        })
+        .await
+        .unwrap();
+
+        db.run(|tr| {
+            let queue = queue.clone();
+            async move { queue.push(&tr, Bytes::from_static(b"f")) }
+        })
+        .await
+        .unwrap();
+
+        let (len, items) = db
+            .read(|tr| {
+                let queue = queue.clone();
+                async move { Ok((queue.get_len(&tr).await?, queue.get_items(&tr, 10).await?)) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(len, 6);
+        assert_eq!(
+            items.into_iter().map(|(_, item)| item).collect::<Vec<_>>(),
+            vec![
+                Bytes::from_static(b"a"),
+                Bytes::from_static(b"b"),
+                Bytes::from_static(b"c"),
+                Bytes::from_static(b"d"),
+                Bytes::from_static(b"e"),
+                Bytes::from_static(b"f"),
+            ]
+        );
+    }
+
+    #[test]
+    fn pop_window() {
+        let subspace = Subspace::new(Bytes::from_static(b"queue"));
+
+        assert_eq!(
+            Queue::new(subspace.clone()).get_pop_window(),
+            super::DEFAULT_POP_WINDOW
+        );
+        assert_eq!(
+            Queue::new_with_pop_window(subspace.clone(), 0).get_pop_window(),
+            1
+        );
+        assert_eq!(Queue::new_with_pop_window(subspace, 4).get_pop_window(), 4);
+    }
+}
@@ -0,0 +1,585 @@
+//! Provides [`RankedSet`] type for sets of keys that support rank
+//! queries.
+//!
+//! [`RankedSet`] is a skip list stored in a [`Subspace`]. Every key
+//! is stored at level `0`, and each level above has roughly a quarter
+//! of the keys of the level below, chosen using a hash of the key.
+//! Each key at a level stores the number of keys at level `0`
+//! between itself (inclusive) and the next key at that level
+//! (exclusive). This allows [`RankedSet::rank`] and
+//! [`RankedSet::get_nth`] to skip over most of the keys.
+//!
+//! Counts are updated using atomic operations where possible, and
+//! the previous key at each level is found using a snapshot read
+//! with a narrow read conflict range, so that concurrent inserts and
+//! deletes of unrelated keys rarely conflict.
+//!
+//! The following keys are used within the [`Subspace`].
+//!
+//! - `(level, key)` - Count of `key` at `level`, as a little-endian
+//!   64-bit integer. The empty key is present at every level and
+//!   marks the beginning of the level.
+
+use bytes::Bytes;
+
+use tokio_stream::StreamExt;
+
+use std::collections::hash_map::DefaultHasher;
+use std::convert::TryInto;
+use std::hash::{Hash, Hasher};
+
+use crate::error::{FdbError, FdbResult, RANKED_SET_EMPTY_KEY};
+use crate::range::{Range, RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{MutationType, ReadTransaction, Transaction};
+use crate::tuple::{key_util::key_after, Tuple};
+use crate::{Key, KeySelector};
+
+use super::decode_i64;
+
+// Number of levels in the skip list.
+const MAX_LEVELS: i64 = 6;
+
+// Each level has `1 / 2^LEVEL_FAN_POW` of the keys of the level
+// below.
+const LEVEL_FAN_POW: i64 = 2;
+
+/// A set of keys, ordered by their bytes, that supports rank queries.
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct RankedSet {
+    subspace: Subspace,
+}
+
+impl RankedSet {
+    /// Create a new [`RankedSet`] stored in [`Subspace`].
+    pub fn new(subspace: Subspace) -> RankedSet {
+        RankedSet { subspace }
+    }
+
+    /// Gets a reference to the [`Subspace`] of the [`RankedSet`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Returns `true` if `key` is in the [`RankedSet`].
+    pub async fn contains<T>(&self, tr: &T, key: Bytes) -> FdbResult<bool>
+    where
+        T: ReadTransaction,
+    {
+        if key.is_empty() {
+            return Ok(false);
+        }
+
+        Ok(tr.get(self.node_key(0, key)).await?.is_some())
+    }
+
+    /// Insert `key` into the [`RankedSet`].
+    ///
+    /// Inserting a key that is already in the [`RankedSet`] has no
+    /// effect. The empty key is reserved, and inserting it returns an
+    /// error with [`RANKED_SET_EMPTY_KEY`] code.
+    pub async fn insert<T>(&self, tr: &T, key: Bytes) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        if key.is_empty() {
+            return Err(FdbError::new(RANKED_SET_EMPTY_KEY));
+        }
+
+        self.setup_levels(tr).await?;
+
+        if self.contains(tr, key.clone()).await? {
+            return Ok(());
+        }
+
+        let key_hash = key_hash(&key);
+
+        for level in 0..MAX_LEVELS {
+            let prev_key = self.get_previous_node(tr, level, key.clone()).await?;
+
+            if key_hash & ((1 << (level * LEVEL_FAN_POW)) - 1) != 0 {
+                // `key` is not at this level, so it is counted by the
+                // previous key.
+                self.add_count(tr, level, prev_key, 1);
+            } else {
+                // Split the count of the previous key, by recounting
+                // the keys at the level below between the previous
+                // key and `key`.
+                let prev_count = self.get_count(tr, level, prev_key.clone()).await?;
+                let new_prev_count = self
+                    .slow_count(tr, level - 1, prev_key.clone(), key.clone())
+                    .await?;
+                let count = prev_count - new_prev_count + 1;
+
+                self.set_count(tr, level, prev_key, new_prev_count);
+                self.set_count(tr, level, key.clone(), count);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Delete `key` from the [`RankedSet`].
+    ///
+    /// Deleting a key that is not in the [`RankedSet`] has no effect.
+    pub async fn delete<T>(&self, tr: &T, key: Bytes) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        if !self.contains(tr, key.clone()).await? {
+            return Ok(());
+        }
+
+        for level in 0..MAX_LEVELS {
+            let node_key = self.node_key(level, key.clone());
+            let count = tr.get(node_key.clone()).await?;
+
+            if count.is_some() {
+                tr.clear(node_key);
+            }
+
+            if level == 0 {
+                continue;
+            }
+
+            // The keys counted by `key` are now counted by the
+            // previous key.
+            let prev_key = self.get_previous_node(tr, level, key.clone()).await?;
+            let count_change = count.map(|c| decode_i64(c.into())).unwrap_or(0) - 1;
+
+            self.add_count(tr, level, prev_key, count_change);
+        }
+
+        Ok(())
+    }
+
+    /// Get the rank of `key`, which is the number of keys in the
+    /// [`RankedSet`] that are less than `key`.
+    ///
+    /// Returns [`None`] if `key` is not in the [`RankedSet`].
+    pub async fn rank<T>(&self, tr: &T, key: Bytes) -> FdbResult<Option<i64>>
+    where
+        T: ReadTransaction,
+    {
+        if !self.contains(tr, key.clone()).await? {
+            return Ok(None);
+        }
+
+        let mut r = 0;
+        let mut rank_key = Bytes::new();
+
+        for level in (0..MAX_LEVELS).rev() {
+            let mut last_count = 0;
+
+            let mut range_stream = tr.get_range(
+                KeySelector::first_greater_or_equal(self.node_key(level, rank_key.clone())),
+                KeySelector::first_greater_than(self.node_key(level, key.clone())),
+                {
+                    let mut ro = RangeOptions::default();
+                    ro.set_mode(StreamingMode::WantAll);
+                    ro
+                },
+            );
+
+            while let Some(x) = range_stream.next().await {
+                let (k, c) = x?.into_parts();
+
+                rank_key = self.unpack_node_key(level, k)?;
+                last_count = decode_i64(c.into());
+                r += last_count;
+            }
+
+            // The last key at this level counts `key` and the keys
+            // after it.
+            r -= last_count;
+
+            if rank_key == key {
+                break;
+            }
+        }
+
+        Ok(Some(r))
+    }
+
+    /// Get the key with rank `rank`.
+    ///
+    /// Returns [`None`] if `rank` is negative, or not less than the
+    /// number of keys in the [`RankedSet`].
+    pub async fn get_nth<T>(&self, tr: &T, rank: i64) -> FdbResult<Option<Bytes>>
+    where
+        T: ReadTransaction,
+    {
+        if rank < 0 {
+            return Ok(None);
+        }
+
+        let mut rank = rank;
+        let mut key = Bytes::new();
+
+        for level in (0..MAX_LEVELS).rev() {
+            let (_, end) = self.level_subspace(level).range(&Tuple::new()).into_parts();
+
+            let mut range_stream = tr.get_range(
+                KeySelector::first_greater_or_equal(self.node_key(level, key.clone())),
+                KeySelector::first_greater_or_equal(end),
+                {
+                    let mut ro = RangeOptions::default();
+                    ro.set_mode(StreamingMode::Iterator);
+                    ro
+                },
+            );
+
+            let mut found = false;
+
+            while let Some(x) = range_stream.next().await {
+                let (k, c) = x?.into_parts();
+                let count = decode_i64(c.into());
+
+                if rank < count {
+                    key = self.unpack_node_key(level, k)?;
+                    found = true;
+                    break;
+                }
+
+                rank -= count;
+            }
+
+            if !found {
+                return Ok(None);
+            }
+        }
+
+        Ok(Some(key))
+    }
+
+    /// Get the keys with ranks from `begin` (inclusive) to `end`
+    /// (exclusive), in order.
+    pub async fn get_range_by_rank<T>(&self, tr: &T, begin: i64, end: i64) -> FdbResult<Vec<Bytes>>
+    where
+        T: ReadTransaction,
+    {
+        let begin = begin.max(0);
+
+        if begin >= end {
+            return Ok(Vec::new());
+        }
+
+        let first = match self.get_nth(tr, begin).await? {
+            Some(k) => k,
+            None => return Ok(Vec::new()),
+        };
+
+        let (_, level_end) = self.level_subspace(0).range(&Tuple::new()).into_parts();
+
+        let mut range_stream = tr.get_range(
+            KeySelector::first_greater_or_equal(self.node_key(0, first)),
+            KeySelector::first_greater_or_equal(level_end),
+            {
+                let mut ro = RangeOptions::default();
+                ro.set_limit((end - begin).try_into().unwrap_or(i32::MAX));
+                ro.set_mode(StreamingMode::WantAll);
+                ro
+            },
+        );
+
+        let mut res = Vec::new();
+
+        while let Some(x) = range_stream.next().await {
+            let (k, _) = x?.into_parts();
+            res.push(self.unpack_node_key(0, k)?);
+        }
+
+        Ok(res)
+    }
+
+    /// Get the number of keys in the [`RankedSet`].
+    pub async fn get_size<T>(&self, tr: &T) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        self.slow_count_range(tr, self.level_subspace(MAX_LEVELS - 1).range(&Tuple::new()))
+            .await
+    }
+
+    /// Remove all keys from the [`RankedSet`].
+    pub fn clear<T>(&self, tr: &T)
+    where
+        T: Transaction,
+    {
+        tr.clear_range(self.subspace.range(&Tuple::new()));
+    }
+
+    // Add the empty key to every level, if the `RankedSet` is new.
+    async fn setup_levels<T>(&self, tr: &T) -> FdbResult<()>
+    where
+        T: Transaction,
+    {
+        if tr.get(self.node_key(0, Bytes::new())).await?.is_none() {
+            for level in 0..MAX_LEVELS {
+                self.set_count(tr, level, Bytes::new(), 0);
+            }
+        }
+
+        Ok(())
+    }
+
+    // Find the last key at `level` that is less than `key`. This is
+    // where inserts and deletes would conflict, so we use a snapshot
+    // read and only add a read conflict on the keys from the previous
+    // key up to and including `key`.
+    async fn get_previous_node<T>(&self, tr: &T, level: i64, key: Bytes) -> FdbResult<Bytes>
+    where
+        T: Transaction,
+    {
+        let node_key = Bytes::from(self.node_key(level, key));
+
+        let prev_node_key = Bytes::from(
+            tr.snapshot()
+                .get_key(KeySelector::last_less_than(node_key.clone()))
+                .await?,
+        );
+
+        tr.add_read_conflict_range(Range::new(prev_node_key.clone(), key_after(node_key)))?;
+
+        self.unpack_node_key(level, prev_node_key)
+    }
+
+    async fn get_count<T>(&self, tr: &T, level: i64, key: Bytes) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        Ok(tr
+            .get(self.node_key(level, key))
+            .await?
+            .map(|c| decode_i64(c.into()))
+            .unwrap_or(0))
+    }
+
+    // Count the keys at level `0` between `begin_key` (inclusive) and
+    // `end_key` (exclusive), using the counts at `level`. Level `-1`
+    // has a count of `1` for every key other than the empty key.
+    async fn slow_count<T>(
+        &self,
+        tr: &T,
+        level: i64,
+        begin_key: Bytes,
+        end_key: Bytes,
+    ) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        if level == -1 {
+            return Ok(if begin_key.is_empty() { 0 } else { 1 });
+        }
+
+        self.slow_count_range(
+            tr,
+            Range::new(
+                self.node_key(level, begin_key),
+                self.node_key(level, end_key),
+            ),
+        )
+        .await
+    }
+
+    async fn slow_count_range<T>(&self, tr: &T, range: Range) -> FdbResult<i64>
+    where
+        T: ReadTransaction,
+    {
+        let mut range_stream = range.into_stream(tr, {
+            let mut ro = RangeOptions::default();
+            ro.set_mode(StreamingMode::WantAll);
+            ro
+        });
+
+        let mut count = 0;
+
+        while let Some(x) = range_stream.next().await {
+            let (_, c) = x?.into_parts();
+            count += decode_i64(c.into());
+        }
+
+        Ok(count)
+    }
+
+    fn add_count<T>(&self, tr: &T, level: i64, key: Bytes, delta: i64)
+    where
+        T: Transaction,
+    {
+        // Safety: `Add` is not `AppendIfFits`.
+        unsafe {
+            tr.mutate(
+                MutationType::Add,
+                self.node_key(level, key),
+                Bytes::copy_from_slice(&delta.to_le_bytes()),
+            );
+        }
+    }
+
+    fn set_count<T>(&self, tr: &T, level: i64, key: Bytes, count: i64)
+    where
+        T: Transaction,
+    {
+        tr.set(
+            self.node_key(level, key),
+            Bytes::copy_from_slice(&count.to_le_bytes()),
+        );
+    }
+
+    fn level_subspace(&self, level: i64) -> Subspace {
+        let mut t = Tuple::new();
+        t.add_i64(level);
+        self.subspace.subspace(&t)
+    }
+
+    fn node_key(&self, level: i64, key: Bytes) -> Key {
+        let mut t = Tuple::new();
+        t.add_bytes(key);
+        self.level_subspace(level).subspace(&t).pack().into()
+    }
+
+    fn unpack_node_key(&self, level: i64, node_key: impl Into<Bytes>) -> FdbResult<Bytes> {
+        Ok(self
+            .level_subspace(level)
+            .unpack(&node_key.into())?
+            .get_bytes_ref(0)?
+            .clone())
+    }
+}
+
+// Decides the levels that `key` is present at. The hash only needs to
+// be well distributed, not stable, as the levels a key is present at
+// are stored in the database.
+fn key_hash(key: &Bytes) -> i64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    (hasher.finish() >> 1) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::subspace::Subspace;
+
+    use super::{RankedSet, MAX_LEVELS};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    RankedSet:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn node_key() {
+        let ranked_set = RankedSet::new(Subspace::new(Bytes::from_static(b"ranked_set")));
+
+        // The empty key must sort first at each level, and levels
+        // must not overlap.
+        for level in 0..MAX_LEVELS {
+            let empty = Bytes::from(ranked_set.node_key(level, Bytes::new()));
+            let a = Bytes::from(ranked_set.node_key(level, Bytes::from_static(b"a")));
+            let next_level = Bytes::from(ranked_set.node_key(level + 1, Bytes::new()));
+
+            assert!(empty < a);
+            assert!(a < next_level);
+
+            assert_eq!(
+                ranked_set.unpack_node_key(level, a).unwrap(),
+                Bytes::from_static(b"a")
+            );
+        }
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction_concurrent_insert_delete() {
+        use crate::memory::MemoryDatabase;
+        use crate::transaction::Transaction;
+
+        let db = MemoryDatabase::new();
+        let ranked_set = RankedSet::new(Subspace::new(Bytes::from_static(b"ranked_set")));
+
+        // Even keys are in the `RankedSet` at the start.
+        let key = |i: usize| Bytes::from(format!("key_{:04}", i));
+        let mut keys = (0..400).step_by(2).map(key).collect::<Vec<_>>();
+
+        for k in &keys {
+            db.run(|tr| {
+                let (ranked_set, k) = (ranked_set.clone(), k.clone());
+                async move { ranked_set.insert(&tr, k).await }
+            })
+            .await
+            .unwrap();
+        }
+
+        // Concurrently insert `i + 1` and delete `i - 2`, which is its
+        // previous node at the levels where `i` is not, alternating
+        // which one commits first. The second commit might conflict,
+        // in which case it is retried.
+        for i in (4..400).step_by(4) {
+            let insert_tr = db.create_transaction().unwrap();
+            let delete_tr = db.create_transaction().unwrap();
+
+            ranked_set.insert(&insert_tr, key(i + 1)).await.unwrap();
+            ranked_set.delete(&delete_tr, key(i - 2)).await.unwrap();
+
+            let (insert_res, delete_res) = if i % 8 == 0 {
+                let insert_res = unsafe { insert_tr.commit() }.await;
+                (insert_res, unsafe { delete_tr.commit() }.await)
+            } else {
+                let delete_res = unsafe { delete_tr.commit() }.await;
+                (unsafe { insert_tr.commit() }.await, delete_res)
+            };
+
+            if insert_res.is_err() {
+                db.run(|tr| {
+                    let ranked_set = ranked_set.clone();
+                    async move { ranked_set.insert(&tr, key(i + 1)).await }
+                })
+                .await
+                .unwrap();
+            }
+
+            if delete_res.is_err() {
+                db.run(|tr| {
+                    let ranked_set = ranked_set.clone();
+                    async move { ranked_set.delete(&tr, key(i - 2)).await }
+                })
+                .await
+                .unwrap();
+            }
+
+            keys.retain(|k| *k != key(i - 2));
+            keys.push(key(i + 1));
+        }
+
+        keys.sort();
+
+        db.read(|tr| {
+            let (ranked_set, keys) = (ranked_set.clone(), keys.clone());
+            async move {
+                assert_eq!(ranked_set.get_size(&tr).await?, keys.len() as i64);
+
+                for (rank, k) in keys.into_iter().enumerate() {
+                    assert_eq!(ranked_set.rank(&tr, k.clone()).await?, Some(rank as i64));
+                    assert_eq!(ranked_set.get_nth(&tr, rank as i64).await?, Some(k));
+                }
+
+                Ok(())
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -0,0 +1,466 @@
+//! Provides [`WorkQueue`] type for queues with leased items.
+//!
+//! A worker claims an item from a [`WorkQueue`] for a lease period.
+//! While the item is leased, it is invisible to other workers. The
+//! worker then either acknowledges the item, which removes it from
+//! the [`WorkQueue`], or extends the lease. If the worker does
+//! neither before the lease expires, for example because the worker
+//! died, the item becomes visible again and can be claimed by another
+//! worker.
+//!
+//! Each item is stored under a key containing the time at which it
+//! becomes visible, so that claiming an item only needs to read the
+//! beginning of the [`WorkQueue`]. To avoid every worker trying to
+//! claim the same item, a worker claims a random item among the first
+//! `claim_window` visible items (see
+//! [`WorkQueue::new_with_claim_window`]). Times are in milliseconds since
+//! the UNIX epoch and are taken from the clock of the worker. Clocks
+//! of workers should be kept reasonably in sync.
+//!
+//! Workers that find the [`WorkQueue`] empty can wait for new items
+//! using [`WorkQueue::claim_wait`], which uses a [watch] instead of
+//! polling.
+//!
+//! The following keys are used within the [`Subspace`].
+//!
+//! - `("Q", visible_at, versionstamp)` - Item, which is visible at
+//!   `visible_at`. `versionstamp` identifies the item.
+//! - `("W",)` - Counter that is incremented on every push, and is
+//!   watched by idle workers.
+//!
+//! [watch]: crate::transaction::Transaction::watch
+
+use bytes::Bytes;
+
+use tokio_stream::StreamExt;
+
+use std::cmp;
+use std::convert::TryInto;
+use std::time::Duration;
+
+use crate::database::FdbDatabase;
+use crate::error::{FdbError, FdbResult, WORK_QUEUE_LEASE_LOST};
+use crate::range::{Range, RangeOptions, StreamingMode};
+use crate::subspace::Subspace;
+use crate::transaction::{MutationType, ReadTransaction, Transaction};
+use crate::tuple::{Tuple, Versionstamp};
+use crate::Key;
+
+use super::{duration_millis, now_millis, random_index};
+
+/// Number of visible items that claims are spread over, when the
+/// [`WorkQueue`] is created using [`WorkQueue::new`].
+pub const DEFAULT_CLAIM_WINDOW: usize = 16;
+
+/// An item claimed from a [`WorkQueue`].
+///
+/// A [`WorkItem`] is returned by [`WorkQueue::claim`] and is used to
+/// acknowledge the item or extend its lease.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WorkItem {
+    id: Versionstamp,
+    deadline: i64,
+    item: Bytes,
+}
+
+impl WorkItem {
+    /// Gets a reference to the [`Versionstamp`] that identifies the
+    /// item.
+    pub fn get_id_ref(&self) -> &Versionstamp {
+        &self.id
+    }
+
+    /// Get the time at which the lease expires, in milliseconds since
+    /// the UNIX epoch.
+    pub fn get_deadline(&self) -> i64 {
+        self.deadline
+    }
+
+    /// Gets a reference to the item.
+    pub fn get_item_ref(&self) -> &Bytes {
+        &self.item
+    }
+
+    /// Extract item from [`WorkItem`].
+    pub fn into_item(self) -> Bytes {
+        self.item
+    }
+
+    fn new(id: Versionstamp, deadline: i64, item: Bytes) -> WorkItem {
+        WorkItem { id, deadline, item }
+    }
+}
+
+/// A queue of items that are leased to workers, stored in a
+/// [`Subspace`] of a [`FdbDatabase`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: self
+#[derive(Clone, Debug)]
+pub struct WorkQueue {
+    fdb_database: FdbDatabase,
+    subspace: Subspace,
+    claim_window: usize,
+}
+
+impl WorkQueue {
+    /// Create a new [`WorkQueue`] stored in [`Subspace`] of
+    /// [`FdbDatabase`], with a claim window of
+    /// [`DEFAULT_CLAIM_WINDOW`].
+    pub fn new(fdb_database: FdbDatabase, subspace: Subspace) -> WorkQueue {
+        WorkQueue::new_with_claim_window(fdb_database, subspace, DEFAULT_CLAIM_WINDOW)
+    }
+
+    /// Create a new [`WorkQueue`] stored in [`Subspace`] of
+    /// [`FdbDatabase`], whose claims are spread over the first
+    /// `claim_window` visible items.
+    ///
+    /// A `claim_window` of `0` is treated as `1`.
+    pub fn new_with_claim_window(
+        fdb_database: FdbDatabase,
+        subspace: Subspace,
+        claim_window: usize,
+    ) -> WorkQueue {
+        WorkQueue {
+            fdb_database,
+            subspace,
+            claim_window: cmp::max(claim_window, 1),
+        }
+    }
+
+    /// Gets a reference to the [`Subspace`] of the [`WorkQueue`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Get the number of visible items that claims are spread over.
+    pub fn get_claim_window(&self) -> usize {
+        self.claim_window
+    }
+
+    /// Push `item` to the [`WorkQueue`]. The item is visible
+    /// immediately.
+    pub async fn push(&self, item: Bytes) -> FdbResult<()> {
+        self.push_delayed(item, Duration::from_secs(0)).await
+    }
+
+    /// Push `item` to the [`WorkQueue`]. The item becomes visible
+    /// after `delay`.
+    pub async fn push_delayed(&self, item: Bytes, delay: Duration) -> FdbResult<()> {
+        let visible_at = now_millis().saturating_add(duration_millis(delay));
+
+        self.fdb_database
+            .run(|tr| {
+                let item = item.clone();
+                async move {
+                    let mut t = Tuple::new();
+                    t.add_i64(visible_at);
+                    t.add_versionstamp(Versionstamp::incomplete(0));
+
+                    let key = self.item_subspace().pack_with_versionstamp(&t)?;
+
+                    // Safety: Neither `SetVersionstampedKey` nor
+                    // `Add` is `AppendIfFits`.
+                    unsafe {
+                        tr.mutate(MutationType::SetVersionstampedKey, key, item);
+                        tr.mutate(
+                            MutationType::Add,
+                            self.wakeup_key(),
+                            Bytes::copy_from_slice(&1_i64.to_le_bytes()),
+                        );
+                    }
+
+                    Ok(())
+                }
+            })
+            .await
+    }
+
+    /// Claim a visible item from the [`WorkQueue`] for `lease`.
+    ///
+    /// The item is chosen at random among the first `claim_window`
+    /// visible items. Returns [`None`] if there are no visible items.
+    pub async fn claim(&self, lease: Duration) -> FdbResult<Option<WorkItem>> {
+        self.fdb_database
+            .run(|tr| async move {
+                let now = now_millis();
+                self.claim_at(&tr, now, now.saturating_add(duration_millis(lease)))
+                    .await
+            })
+            .await
+    }
+
+    /// Claim a visible item from the [`WorkQueue`] for `lease`,
+    /// waiting until an item is available.
+    ///
+    /// While the [`WorkQueue`] has no visible items, a watch is used
+    /// to wake up when a new item is pushed. When there are leased
+    /// items, we also wake up when the earliest lease expires.
+    pub async fn claim_wait(&self, lease: Duration) -> FdbResult<WorkItem> {
+        loop {
+            let res = self
+                .fdb_database
+                .run(|tr| async move {
+                    let now = now_millis();
+
+                    if let Some(work_item) = self
+                        .claim_at(&tr, now, now.saturating_add(duration_millis(lease)))
+                        .await?
+                    {
+                        return Ok(Ok(work_item));
+                    }
+
+                    // Snapshot read, so that we do not conflict with
+                    // other workers.
+                    let next_visible_at = self
+                        .get_items(&tr.snapshot(), self.item_subspace().range(&Tuple::new()), 1)
+                        .await?
+                        .pop()
+                        .map(|(visible_at, _, _)| visible_at);
+
+                    let wait =
+                        next_visible_at.map(|v| Duration::from_millis(cmp::max(0, v - now) as u64));
+
+                    Ok(Err((tr.watch(self.wakeup_key()), wait)))
+                })
+                .await?;
+
+            match res {
+                Ok(work_item) => return Ok(work_item),
+                Err((watch, Some(wait))) => {
+                    // When the lease of an item expires before an
+                    // item is pushed, `timeout` returns an error,
+                    // which we ignore.
+                    if let Ok(res) = tokio::time::timeout(wait, watch).await {
+                        res?;
+                    }
+                }
+                Err((watch, None)) => watch.await?,
+            }
+        }
+    }
+
+    /// Acknowledge `work_item`, removing it from the [`WorkQueue`].
+    ///
+    /// If `work_item` is no longer leased, an error with
+    /// [`WORK_QUEUE_LEASE_LOST`] code is returned.
+    ///
+    /// **Note**: When the transaction fails with an unknown result and
+    /// is retried, an error with [`WORK_QUEUE_LEASE_LOST`] code can be
+    /// returned even though the acknowledgement was committed.
+    pub async fn ack(&self, work_item: &WorkItem) -> FdbResult<()> {
+        self.fdb_database
+            .run(|tr| async move {
+                let key = self.item_key(work_item.deadline, work_item.id.clone());
+
+                if tr.get(key.clone()).await?.is_none() {
+                    return Err(FdbError::new(WORK_QUEUE_LEASE_LOST));
+                }
+
+                tr.clear(key);
+
+                Ok(())
+            })
+            .await
+    }
+
+    /// Extend the lease of `work_item` to `lease` from now.
+    ///
+    /// Returns a new [`WorkItem`] with the updated deadline, which
+    /// must be used for subsequent calls. If `work_item` is no longer
+    /// leased, an error with [`WORK_QUEUE_LEASE_LOST`] code is
+    /// returned.
+    pub async fn extend(&self, work_item: &WorkItem, lease: Duration) -> FdbResult<WorkItem> {
+        let deadline = now_millis().saturating_add(duration_millis(lease));
+
+        let new_work_item = WorkItem::new(work_item.id.clone(), deadline, work_item.item.clone());
+
+        self.fdb_database
+            .run(|tr| {
+                let new_work_item = new_work_item.clone();
+                async move {
+                    let key = self.item_key(work_item.deadline, work_item.id.clone());
+                    let new_key = self.item_key(new_work_item.deadline, new_work_item.id.clone());
+
+                    if tr.get(key.clone()).await?.is_some() {
+                        tr.clear(key);
+                        tr.set(new_key, new_work_item.item.clone());
+                        Ok(new_work_item)
+                    } else if tr.get(new_key).await?.is_some() {
+                        // A previous attempt was committed, but we got
+                        // a `commit_unknown_result` error.
+                        Ok(new_work_item)
+                    } else {
+                        Err(FdbError::new(WORK_QUEUE_LEASE_LOST))
+                    }
+                }
+            })
+            .await
+    }
+
+    /// Remove all items from the [`WorkQueue`].
+    pub async fn clear(&self) -> FdbResult<()> {
+        self.fdb_database
+            .run(|tr| async move {
+                tr.clear_range(self.item_subspace().range(&Tuple::new()));
+                Ok(())
+            })
+            .await
+    }
+
+    async fn claim_at<T>(&self, tr: &T, now: i64, deadline: i64) -> FdbResult<Option<WorkItem>>
+    where
+        T: Transaction,
+    {
+        // Items with `visible_at` less than or equal to `now`.
+        let (begin, _) = self.item_subspace().range(&Tuple::new()).into_parts();
+        let end = Bytes::from(self.visible_at_key(now.saturating_add(1)));
+
+        // Snapshot read, so that we conflict with other workers only
+        // when we claim the same item.
+        let mut items = self
+            .get_items(&tr.snapshot(), Range::new(begin, end), self.claim_window)
+            .await?;
+
+        if items.is_empty() {
+            return Ok(None);
+        }
+
+        // Pick a random item, so that concurrent workers are likely to
+        // claim different items.
+        let (visible_at, id, item) = items.swap_remove(random_index(items.len()));
+
+        let key = self.item_key(visible_at, id.clone());
+
+        tr.add_read_conflict_key(key.clone())?;
+        tr.clear(key);
+        tr.set(self.item_key(deadline, id.clone()), item.clone());
+
+        Ok(Some(WorkItem::new(id, deadline, item)))
+    }
+
+    async fn get_items<T>(
+        &self,
+        tr: &T,
+        range: Range,
+        n: usize,
+    ) -> FdbResult<Vec<(i64, Versionstamp, Bytes)>>
+    where
+        T: ReadTransaction,
+    {
+        let mut range_stream = range.into_stream(tr, {
+            let mut ro = RangeOptions::default();
+            ro.set_limit(n.try_into().unwrap_or(i32::MAX));
+            ro.set_mode(StreamingMode::WantAll);
+            ro
+        });
+
+        let mut res = Vec::new();
+
+        while let Some(x) = range_stream.next().await {
+            let (key, value) = x?.into_parts();
+            let t = self.item_subspace().unpack(&key.into())?;
+
+            res.push((
+                t.get_i64(0)?,
+                t.get_versionstamp_ref(1)?.clone(),
+                value.into(),
+            ));
+        }
+
+        Ok(res)
+    }
+
+    fn item_subspace(&self) -> Subspace {
+        let mut t = Tuple::new();
+        t.add_string(String::from("Q"));
+        self.subspace.subspace(&t)
+    }
+
+    fn visible_at_key(&self, visible_at: i64) -> Key {
+        let mut t = Tuple::new();
+        t.add_i64(visible_at);
+        self.item_subspace().subspace(&t).pack().into()
+    }
+
+    fn item_key(&self, visible_at: i64, id: Versionstamp) -> Key {
+        let mut t = Tuple::new();
+        t.add_i64(visible_at);
+        t.add_versionstamp(id);
+        self.item_subspace().subspace(&t).pack().into()
+    }
+
+    fn wakeup_key(&self) -> Key {
+        let mut t = Tuple::new();
+        t.add_string(String::from("W"));
+        self.subspace.subspace(&t).pack().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::subspace::Subspace;
+    use crate::tuple::{Tuple, Versionstamp};
+
+    use super::{WorkItem, WorkQueue};
+
+    fn item_subspace() -> Subspace {
+        let mut t = Tuple::new();
+        t.add_string(String::from("Q"));
+        Subspace::new(Bytes::from_static(b"work_queue")).subspace(&t)
+    }
+
+    fn item_key(visible_at: i64, user_version: u16) -> Bytes {
+        let mut t = Tuple::new();
+        t.add_i64(visible_at);
+        t.add_versionstamp(Versionstamp::complete(
+            Bytes::from_static(b"\x00\x00\x00\x00\x00\x00\x00\x01\x00\x00"),
+            user_version,
+        ));
+        item_subspace().subspace(&t).pack()
+    }
+
+    fn visible_at_key(visible_at: i64) -> Bytes {
+        let mut t = Tuple::new();
+        t.add_i64(visible_at);
+        item_subspace().subspace(&t).pack()
+    }
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    WorkItem:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    WorkQueue:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn item_key_order() {
+        // Items must sort by the time at which they become visible,
+        // and then by their versionstamp.
+        assert!(item_key(-1, 0) < item_key(0, 0));
+        assert!(item_key(0, 0) < item_key(0, 1));
+        assert!(item_key(0, 1) < item_key(1_000, 0));
+        assert!(item_key(1_000, 0) < item_key(1_001, 0));
+
+        // Items visible at or before `1_000` must sort before the key
+        // used as the end of the range read by `claim_at`.
+        assert!(item_key(1_000, u16::MAX) < visible_at_key(1_001));
+        assert!(visible_at_key(1_001) < item_key(1_001, 0));
+    }
+}
@@ -51,6 +51,37 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! # Tracing
+//!
+//! When the `tracing` feature is enabled, [tracing] spans are opened
+//! for the following operations.
+//!
+//! - `run` and `read` on [`FdbDatabase`] and `FdbTenant`. Spans for
+//!   tenants have a `tenant` field.
+//!
+//! - `attempt` - Each time the closure passed to `run` or `read` is
+//!   called, with `attempt` and `read_version` fields.
+//!
+//! - `commit` - Commit of the transaction in `run`, with
+//!   `approximate_size` and `committed_version` fields.
+//!
+//! - `on_error` - Call to `on_error` after the closure or `commit`
+//!   fails, with a `code` field.
+//!
+//! - `range_batch` - Range read of a single batch by a range stream,
+//!   with `snapshot`, `reverse`, `count`, `more` and `error` fields.
+//!
+//! - `open_tenant`, `create_tenant` and `delete_tenant` (7.1 only),
+//!   with a `tenant` field.
+//!
+//! `range_batch` spans are at `TRACE` level. All other spans are at
+//! `DEBUG` level. `read_version` and `approximate_size` need a round
+//! trip to the cluster, and are only fetched when the span is
+//! enabled.
+//!
+//! [tracing]: https://docs.rs/tracing
+//! [`FdbDatabase`]: crate::database::FdbDatabase
 
 mod fdb;
 mod key_value;
@@ -62,17 +93,37 @@ mod mapped_key_value;
 #[cfg(feature = "fdb-7_1")]
 mod mapped_range;
 
+pub mod bulk_writer;
 pub mod database;
 pub mod error;
 pub mod future;
+pub mod layer;
 pub mod range;
 pub mod subspace;
 pub mod transaction;
 pub mod tuple;
 
+#[cfg(feature = "fdb-7_1")]
+pub mod management;
+
 #[cfg(feature = "fdb-7_1")]
 pub mod tenant;
 
+#[cfg(feature = "fault")]
+pub mod fault;
+
+#[cfg(feature = "memory")]
+pub mod memory;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "status")]
+pub mod status;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// Maximum API version supported by the client
 pub use fdb_sys::FDB_API_VERSION;
 
@@ -85,7 +136,7 @@ pub use crate::database::open_database::open_database;
 pub use crate::option::NetworkOption;
 
 #[cfg(feature = "fdb-7_1")]
-pub use crate::mapped_key_value::{MappedKeyValue, Mapper};
+pub use crate::mapped_key_value::{MappedKeyValue, Mapper, MapperBuilder};
 
 #[cfg(feature = "fdb-7_1")]
 pub use crate::tenant::tenant_inner::Tenant;
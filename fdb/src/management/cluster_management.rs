@@ -0,0 +1,552 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+use std::time::Duration;
+
+use crate::database::FdbDatabase;
+use crate::error::{FdbError, FdbResult, MANAGEMENT_INVALID_VALUE};
+use crate::layer::decode_i64;
+use crate::management::tag_throttle::{self, TAG_THROTTLE_END, TAG_THROTTLE_PREFIX};
+use crate::management::{TagThrottle, ThrottlePriority};
+use crate::range::{Range, RangeOptions};
+use crate::transaction::{
+    FdbTransaction, MutationType, ReadTransaction, Transaction, TransactionOption,
+};
+
+const EXCLUDED_PREFIX: &[u8; 23] = b"\xFF\xFF/management/excluded/";
+const EXCLUDED_END: &[u8; 23] = b"\xFF\xFF/management/excluded0";
+
+const FAILED_PREFIX: &[u8; 21] = b"\xFF\xFF/management/failed/";
+const FAILED_END: &[u8; 21] = b"\xFF\xFF/management/failed0";
+
+const IN_PROGRESS_EXCLUSION_PREFIX: &[u8; 36] = b"\xFF\xFF/management/in_progress_exclusion/";
+const IN_PROGRESS_EXCLUSION_END: &[u8; 36] = b"\xFF\xFF/management/in_progress_exclusion0";
+
+const DB_LOCKED_KEY: &[u8; 23] = b"\xFF\xFF/management/db_locked";
+
+const COORDINATORS_KEY: &[u8; 39] = b"\xFF\xFF/configuration/coordinators/processes";
+
+const PROCESS_CLASS_TYPE_PREFIX: &[u8; 36] = b"\xFF\xFF/configuration/process/class_type/";
+const PROCESS_CLASS_TYPE_END: &[u8; 36] = b"\xFF\xFF/configuration/process/class_type0";
+
+const TAG_THROTTLE_SIGNAL_KEY: &[u8; 23] = b"\xFF\x02/throttledTags/signal";
+const TAG_THROTTLE_LIMIT_KEY: &[u8; 36] = b"\xFF\x02/throttledTags/manualThrottleLimit";
+const TAG_THROTTLE_COUNT_KEY: &[u8; 36] = b"\xFF\x02/throttledTags/manualThrottleCount";
+
+/// The FDB API includes functions to manage the processes,
+/// coordinators, lock and tag throttles of a cluster.
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::management
+#[derive(Debug)]
+pub struct ClusterManagement;
+
+impl ClusterManagement {
+    /// Excludes processes from the cluster using a transaction
+    /// created on the specified [`FdbDatabase`].
+    pub async fn exclude_processes_db(db: &FdbDatabase, addresses: &[&str]) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::exclude_processes_tr(&tr, addresses) })
+            .await
+    }
+
+    /// Excludes processes from the cluster.
+    ///
+    /// Data is moved off the excluded processes, and they are not
+    /// assigned any roles. Use [`get_exclusions_in_progress_tr`] to
+    /// check if the excluded processes can be safely removed.
+    ///
+    /// [`get_exclusions_in_progress_tr`]: ClusterManagement::get_exclusions_in_progress_tr
+    pub fn exclude_processes_tr(tr: &FdbTransaction, addresses: &[&str]) -> FdbResult<()> {
+        tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+
+        for address in addresses {
+            tr.set(prefixed_key(EXCLUDED_PREFIX, address), Bytes::new());
+        }
+
+        Ok(())
+    }
+
+    /// Excludes failed processes from the cluster using a
+    /// transaction created on the specified [`FdbDatabase`].
+    pub async fn exclude_failed_processes_db(
+        db: &FdbDatabase,
+        addresses: &[&str],
+    ) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::exclude_failed_processes_tr(&tr, addresses) })
+            .await
+    }
+
+    /// Excludes failed processes from the cluster.
+    ///
+    /// Unlike [`exclude_processes_tr`], data is not moved off the
+    /// processes. The cluster assumes that the data on them is lost.
+    ///
+    /// [`exclude_processes_tr`]: ClusterManagement::exclude_processes_tr
+    pub fn exclude_failed_processes_tr(tr: &FdbTransaction, addresses: &[&str]) -> FdbResult<()> {
+        tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+
+        for address in addresses {
+            tr.set(prefixed_key(FAILED_PREFIX, address), Bytes::new());
+        }
+
+        Ok(())
+    }
+
+    /// Includes previously excluded processes back into the cluster
+    /// using a transaction created on the specified [`FdbDatabase`].
+    pub async fn include_processes_db(db: &FdbDatabase, addresses: &[&str]) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::include_processes_tr(&tr, addresses) })
+            .await
+    }
+
+    /// Includes previously excluded processes back into the cluster.
+    ///
+    /// Both the exclusions made using [`exclude_processes_tr`] and
+    /// [`exclude_failed_processes_tr`] are removed.
+    ///
+    /// [`exclude_processes_tr`]: ClusterManagement::exclude_processes_tr
+    /// [`exclude_failed_processes_tr`]: ClusterManagement::exclude_failed_processes_tr
+    pub fn include_processes_tr(tr: &FdbTransaction, addresses: &[&str]) -> FdbResult<()> {
+        tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+
+        for address in addresses {
+            tr.clear(prefixed_key(EXCLUDED_PREFIX, address));
+            tr.clear(prefixed_key(FAILED_PREFIX, address));
+        }
+
+        Ok(())
+    }
+
+    /// Gets the addresses of the excluded processes using a
+    /// transaction created on the specified [`FdbDatabase`].
+    pub async fn get_excluded_processes_db(db: &FdbDatabase) -> FdbResult<Vec<String>> {
+        db.read(|tr| async move { ClusterManagement::get_excluded_processes_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the addresses of the excluded processes.
+    pub async fn get_excluded_processes_tr<T>(tr: &T) -> FdbResult<Vec<String>>
+    where
+        T: ReadTransaction,
+    {
+        get_addresses(tr, EXCLUDED_PREFIX, EXCLUDED_END).await
+    }
+
+    /// Gets the addresses of the failed processes using a
+    /// transaction created on the specified [`FdbDatabase`].
+    pub async fn get_failed_processes_db(db: &FdbDatabase) -> FdbResult<Vec<String>> {
+        db.read(|tr| async move { ClusterManagement::get_failed_processes_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the addresses of the failed processes.
+    pub async fn get_failed_processes_tr<T>(tr: &T) -> FdbResult<Vec<String>>
+    where
+        T: ReadTransaction,
+    {
+        get_addresses(tr, FAILED_PREFIX, FAILED_END).await
+    }
+
+    /// Gets the addresses of the excluded processes that still have
+    /// data or roles, using a transaction created on the specified
+    /// [`FdbDatabase`].
+    pub async fn get_exclusions_in_progress_db(db: &FdbDatabase) -> FdbResult<Vec<String>> {
+        db.read(|tr| async move { ClusterManagement::get_exclusions_in_progress_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the addresses of the excluded processes that still have
+    /// data or roles.
+    ///
+    /// Excluded processes can be safely removed from the cluster
+    /// once they are not returned.
+    pub async fn get_exclusions_in_progress_tr<T>(tr: &T) -> FdbResult<Vec<String>>
+    where
+        T: ReadTransaction,
+    {
+        get_addresses(tr, IN_PROGRESS_EXCLUSION_PREFIX, IN_PROGRESS_EXCLUSION_END).await
+    }
+
+    /// Gets the addresses of the coordinators using a transaction
+    /// created on the specified [`FdbDatabase`].
+    pub async fn get_coordinators_db(db: &FdbDatabase) -> FdbResult<Vec<String>> {
+        db.read(|tr| async move { ClusterManagement::get_coordinators_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the addresses of the coordinators.
+    pub async fn get_coordinators_tr<T>(tr: &T) -> FdbResult<Vec<String>>
+    where
+        T: ReadTransaction,
+    {
+        let value = tr
+            .get(Bytes::from_static(COORDINATORS_KEY))
+            .await?
+            .ok_or_else(|| FdbError::new(MANAGEMENT_INVALID_VALUE))?;
+
+        let value = String::from_utf8(Bytes::from(value).to_vec())
+            .map_err(|_| FdbError::new(MANAGEMENT_INVALID_VALUE))?;
+
+        Ok(value
+            .split(',')
+            .filter(|address| !address.is_empty())
+            .map(String::from)
+            .collect())
+    }
+
+    /// Changes the coordinators using a transaction created on the
+    /// specified [`FdbDatabase`].
+    pub async fn set_coordinators_db(db: &FdbDatabase, addresses: &[&str]) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::set_coordinators_tr(&tr, addresses) })
+            .await
+    }
+
+    /// Changes the coordinators.
+    pub fn set_coordinators_tr(tr: &FdbTransaction, addresses: &[&str]) -> FdbResult<()> {
+        tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+
+        tr.set(
+            Bytes::from_static(COORDINATORS_KEY),
+            Bytes::from(addresses.join(",")),
+        );
+
+        Ok(())
+    }
+
+    /// Gets the address and class of the processes using a
+    /// transaction created on the specified [`FdbDatabase`].
+    pub async fn get_process_classes_db(db: &FdbDatabase) -> FdbResult<Vec<(String, String)>> {
+        db.read(|tr| async move { ClusterManagement::get_process_classes_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the address and class of the processes.
+    pub async fn get_process_classes_tr<T>(tr: &T) -> FdbResult<Vec<(String, String)>>
+    where
+        T: ReadTransaction,
+    {
+        get_special_key_range(tr, PROCESS_CLASS_TYPE_PREFIX, PROCESS_CLASS_TYPE_END)
+            .await?
+            .into_iter()
+            .map(|(address, class)| Ok((bytes_to_string(address)?, bytes_to_string(class)?)))
+            .collect()
+    }
+
+    /// Sets the class of a process using a transaction created on the
+    /// specified [`FdbDatabase`].
+    pub async fn set_process_class_db(
+        db: &FdbDatabase,
+        address: &str,
+        class: &str,
+    ) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::set_process_class_tr(&tr, address, class) })
+            .await
+    }
+
+    /// Sets the class of a process, such as `storage` or
+    /// `transaction`.
+    ///
+    /// Use `default` class to let the process use the class it was
+    /// started with.
+    pub fn set_process_class_tr(tr: &FdbTransaction, address: &str, class: &str) -> FdbResult<()> {
+        tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+
+        tr.set(
+            prefixed_key(PROCESS_CLASS_TYPE_PREFIX, address),
+            Bytes::copy_from_slice(class.as_bytes()),
+        );
+
+        Ok(())
+    }
+
+    /// Locks the database with `uid` using a transaction created on
+    /// the specified [`FdbDatabase`].
+    pub async fn lock_database_db(db: &FdbDatabase, uid: Uuid) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::lock_database_tr(&tr, uid) })
+            .await
+    }
+
+    /// Locks the database with `uid`.
+    ///
+    /// Once the database is locked, only transactions with
+    /// [`LockAware`] or [`ReadLockAware`] option can access it. Commit
+    /// fails with `database_locked` error if the database is already
+    /// locked with a different `uid`.
+    ///
+    /// [`LockAware`]: TransactionOption::LockAware
+    /// [`ReadLockAware`]: TransactionOption::ReadLockAware
+    pub fn lock_database_tr(tr: &FdbTransaction, uid: Uuid) -> FdbResult<()> {
+        tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+        tr.set_option(TransactionOption::LockAware)?;
+
+        tr.set(
+            Bytes::from_static(DB_LOCKED_KEY),
+            Bytes::from(uid.to_simple().to_string()),
+        );
+
+        Ok(())
+    }
+
+    /// Unlocks the database locked with `uid` using a transaction
+    /// created on the specified [`FdbDatabase`].
+    pub async fn unlock_database_db(db: &FdbDatabase, uid: Uuid) -> FdbResult<()> {
+        db.run(|tr| async move { ClusterManagement::unlock_database_tr(&tr, uid).await })
+            .await
+    }
+
+    /// Unlocks the database locked with `uid`.
+    ///
+    /// Returns `database_locked` error if the database is locked with
+    /// a different `uid`. Unlocking a database that is not locked
+    /// does nothing.
+    pub async fn unlock_database_tr(tr: &FdbTransaction, uid: Uuid) -> FdbResult<()> {
+        match ClusterManagement::get_database_lock_tr(tr).await? {
+            None => Ok(()),
+            Some(lock_uid) if lock_uid == uid => {
+                tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
+                tr.clear(Bytes::from_static(DB_LOCKED_KEY));
+                Ok(())
+            }
+            Some(_) => {
+                // `database_locked` error
+                Err(FdbError::new(1038))
+            }
+        }
+    }
+
+    /// Gets the `uid` that the database is locked with, using a
+    /// transaction created on the specified [`FdbDatabase`].
+    pub async fn get_database_lock_db(db: &FdbDatabase) -> FdbResult<Option<Uuid>> {
+        db.read(|tr| async move { ClusterManagement::get_database_lock_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the `uid` that the database is locked with, or `None` if
+    /// the database is not locked.
+    ///
+    /// This sets [`LockAware`] option on the transaction, so that it
+    /// can read a locked database.
+    ///
+    /// [`LockAware`]: TransactionOption::LockAware
+    pub async fn get_database_lock_tr<T>(tr: &T) -> FdbResult<Option<Uuid>>
+    where
+        T: ReadTransaction,
+    {
+        tr.set_option(TransactionOption::LockAware)?;
+
+        tr.get(Bytes::from_static(DB_LOCKED_KEY))
+            .await?
+            .map(|uid| {
+                Uuid::parse_str(&bytes_to_string(uid.into())?)
+                    .map_err(|_| FdbError::new(MANAGEMENT_INVALID_VALUE))
+            })
+            .transpose()
+    }
+
+    /// Gets the tag throttles using a transaction created on the
+    /// specified [`FdbDatabase`].
+    pub async fn get_throttled_tags_db(db: &FdbDatabase) -> FdbResult<Vec<TagThrottle>> {
+        db.read(|tr| async move { ClusterManagement::get_throttled_tags_tr(&tr).await })
+            .await
+    }
+
+    /// Gets the tag throttles, both manual and automatic.
+    pub async fn get_throttled_tags_tr<T>(tr: &T) -> FdbResult<Vec<TagThrottle>>
+    where
+        T: ReadTransaction,
+    {
+        tr.set_option(TransactionOption::ReadSystemKeys)?;
+
+        let mut range_stream = Range::new(
+            Bytes::from_static(TAG_THROTTLE_PREFIX),
+            Bytes::from_static(TAG_THROTTLE_END),
+        )
+        .into_stream(tr, RangeOptions::default());
+
+        let mut res = Vec::new();
+
+        while let Some(x) = range_stream.next().await {
+            let (key, value) = x?.into_parts();
+            res.push(tag_throttle::tag_throttle_from_key_value(
+                key.into(),
+                value.into(),
+            )?);
+        }
+
+        Ok(res)
+    }
+
+    /// Throttles transactions with `tag` using a transaction created
+    /// on the specified [`FdbDatabase`].
+    pub async fn throttle_tag_db(
+        db: &FdbDatabase,
+        tag: &str,
+        tps_rate: f64,
+        duration: Duration,
+        priority: ThrottlePriority,
+    ) -> FdbResult<()> {
+        db.run(|tr| async move {
+            ClusterManagement::throttle_tag_tr(&tr, tag, tps_rate, duration, priority).await
+        })
+        .await
+    }
+
+    /// Throttles transactions with `tag` and `priority` to `tps_rate`
+    /// transactions per second, for `duration`.
+    ///
+    /// Transactions with a higher priority are also throttled. An
+    /// existing manual throttle on the same tag and priority is
+    /// replaced. Returns `too_many_tag_throttles` error if the limit
+    /// on manual throttles is reached.
+    ///
+    /// **Note:** Tag throttles are stored in the system keys, and not
+    /// in the special key space. This uses the same format as
+    /// `throttle` command in `fdbcli`.
+    pub async fn throttle_tag_tr(
+        tr: &FdbTransaction,
+        tag: &str,
+        tps_rate: f64,
+        duration: Duration,
+        priority: ThrottlePriority,
+    ) -> FdbResult<()> {
+        tr.set_option(TransactionOption::AccessSystemKeys)?;
+
+        let key = tag_throttle::tag_throttle_key(tag, priority)?;
+
+        if tr.get(key.clone()).await?.is_none() {
+            update_throttle_count(tr, 1).await?;
+        }
+
+        tr.set(key, tag_throttle::tag_throttle_value(tps_rate, duration));
+
+        signal_throttle_change(tr);
+
+        Ok(())
+    }
+
+    /// Removes manual throttles on `tag` using a transaction created
+    /// on the specified [`FdbDatabase`].
+    pub async fn unthrottle_tag_db(db: &FdbDatabase, tag: &str) -> FdbResult<bool> {
+        db.run(|tr| async move { ClusterManagement::unthrottle_tag_tr(&tr, tag).await })
+            .await
+    }
+
+    /// Removes manual throttles on `tag`, at all priorities.
+    ///
+    /// Returns `true` if a throttle was removed.
+    pub async fn unthrottle_tag_tr(tr: &FdbTransaction, tag: &str) -> FdbResult<bool> {
+        tr.set_option(TransactionOption::AccessSystemKeys)?;
+
+        let mut removed = 0;
+
+        for priority in ThrottlePriority::ALL.iter() {
+            let key = tag_throttle::tag_throttle_key(tag, *priority)?;
+
+            if tr.get(key.clone()).await?.is_some() {
+                tr.clear(key);
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            update_throttle_count(tr, -removed).await?;
+            signal_throttle_change(tr);
+        }
+
+        Ok(removed > 0)
+    }
+}
+
+fn prefixed_key(prefix: &'static [u8], suffix: &str) -> Bytes {
+    let mut b = BytesMut::new();
+    b.put(prefix);
+    b.put(suffix.as_bytes());
+    b.into()
+}
+
+fn bytes_to_string(b: Bytes) -> FdbResult<String> {
+    String::from_utf8(b.to_vec()).map_err(|_| FdbError::new(MANAGEMENT_INVALID_VALUE))
+}
+
+// Returns key (without `prefix`) and value of the keys in a special
+// key range.
+async fn get_special_key_range<T>(
+    tr: &T,
+    prefix: &'static [u8],
+    end: &'static [u8],
+) -> FdbResult<Vec<(Bytes, Bytes)>>
+where
+    T: ReadTransaction,
+{
+    let mut range_stream = Range::new(Bytes::from_static(prefix), Bytes::from_static(end))
+        .into_stream(tr, RangeOptions::default());
+
+    let mut res = Vec::new();
+
+    while let Some(x) = range_stream.next().await {
+        let (key, value) = x?.into_parts();
+        res.push((Bytes::from(key).slice(prefix.len()..), value.into()));
+    }
+
+    Ok(res)
+}
+
+async fn get_addresses<T>(
+    tr: &T,
+    prefix: &'static [u8],
+    end: &'static [u8],
+) -> FdbResult<Vec<String>>
+where
+    T: ReadTransaction,
+{
+    get_special_key_range(tr, prefix, end)
+        .await?
+        .into_iter()
+        .map(|(address, _)| bytes_to_string(address))
+        .collect()
+}
+
+// Number of manual throttles is kept in a counter, which is checked
+// against the limit set by the cluster.
+async fn update_throttle_count(tr: &FdbTransaction, delta: i64) -> FdbResult<()> {
+    let count = tr
+        .get(Bytes::from_static(TAG_THROTTLE_COUNT_KEY))
+        .await?
+        .map(|v| decode_i64(v.into()))
+        .unwrap_or(0);
+
+    let limit = tr
+        .get(Bytes::from_static(TAG_THROTTLE_LIMIT_KEY))
+        .await?
+        .map(|v| decode_i64(v.into()))
+        .unwrap_or(0);
+
+    let count = count + delta;
+
+    if count > limit {
+        // `too_many_tag_throttles` error
+        return Err(FdbError::new(2111));
+    }
+
+    tr.set(
+        Bytes::from_static(TAG_THROTTLE_COUNT_KEY),
+        Bytes::copy_from_slice(&count.to_le_bytes()),
+    );
+
+    Ok(())
+}
+
+// Notifies the cluster that manual throttles have changed.
+fn signal_throttle_change(tr: &FdbTransaction) {
+    // Safety: `SetVersionstampedValue` is not `AppendIfFits`.
+    unsafe {
+        tr.mutate(
+            MutationType::SetVersionstampedValue,
+            Bytes::from_static(TAG_THROTTLE_SIGNAL_KEY),
+            Bytes::from_static(b"XXXXXXXXXX\x00\x00\x00\x00"),
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! Provides [`ClusterManagement`] type, for managing a FDB cluster.
+//!
+//! Like [`TenantManagement`], [`ClusterManagement`] provides
+//! functions that take a [`FdbDatabase`] (with `_db` suffix), and
+//! functions that take a transaction (with `_tr` suffix). Changes made
+//! using a transaction take effect when it is committed.
+//!
+//! - Exclusion - [`exclude_processes_tr`],
+//!   [`exclude_failed_processes_tr`] and [`include_processes_tr`]
+//!   use `\xff\xff/management/excluded/` and
+//!   `\xff\xff/management/failed/` special keys.
+//!
+//! - Coordinators and process classes - [`set_coordinators_tr`] and
+//!   [`set_process_class_tr`] use `\xff\xff/configuration/` special
+//!   keys.
+//!
+//! - Lock - [`lock_database_tr`] and [`unlock_database_tr`] use
+//!   `\xff\xff/management/db_locked` special key.
+//!
+//! - Tag throttling - [`throttle_tag_tr`] and [`unthrottle_tag_tr`]
+//!   use `\xff\x02/throttledTags/` system keys, as FDB does not
+//!   provide special keys for tag throttling.
+//!
+//! Changing the database configuration, such as the redundancy mode
+//! or the storage engine, is not supported, as FDB does not provide
+//! special keys for it. Use `configure` command in `fdbcli` instead.
+//!
+//! When the cluster rejects a change to special keys, commit returns
+//! `special_keys_api_failure` error. The reason is available in
+//! `\xff\xff/error_message` special key of the transaction.
+//!
+//! [`TenantManagement`]: crate::tenant::TenantManagement
+//! [`FdbDatabase`]: crate::database::FdbDatabase
+//! [`exclude_processes_tr`]: ClusterManagement::exclude_processes_tr
+//! [`exclude_failed_processes_tr`]: ClusterManagement::exclude_failed_processes_tr
+//! [`include_processes_tr`]: ClusterManagement::include_processes_tr
+//! [`set_coordinators_tr`]: ClusterManagement::set_coordinators_tr
+//! [`set_process_class_tr`]: ClusterManagement::set_process_class_tr
+//! [`lock_database_tr`]: ClusterManagement::lock_database_tr
+//! [`unlock_database_tr`]: ClusterManagement::unlock_database_tr
+//! [`throttle_tag_tr`]: ClusterManagement::throttle_tag_tr
+//! [`unthrottle_tag_tr`]: ClusterManagement::unthrottle_tag_tr
+
+mod cluster_management;
+mod tag_throttle;
+
+pub use cluster_management::ClusterManagement;
+pub use tag_throttle::{TagThrottle, ThrottlePriority};
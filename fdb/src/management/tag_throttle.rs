@@ -0,0 +1,298 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use std::convert::TryInto;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::error::{FdbError, FdbResult, MANAGEMENT_INVALID_VALUE};
+
+pub(super) const TAG_THROTTLE_PREFIX: &[u8; 21] = b"\xFF\x02/throttledTags/tag/";
+
+pub(super) const TAG_THROTTLE_END: &[u8; 21] = b"\xFF\x02/throttledTags/tag0";
+
+// `tag_too_long` error, when a tag is longer than
+// `MAX_TRANSACTION_TAG_LENGTH` client knob.
+const MAX_TAG_LENGTH: usize = 16;
+
+// Protocol version that added the throttle reason to the value.
+const PROTOCOL_VERSION_TAG_THROTTLE_VALUE_REASON: u64 = 0x0FDB_00B0_7001_0001;
+
+// `TagThrottledReason::MANUAL`
+const REASON_MANUAL: u8 = 1;
+
+/// Priority of the transactions that are throttled by a
+/// [`TagThrottle`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ThrottlePriority {
+    /// Transactions with `PriorityBatch` option.
+    Batch,
+    /// Transactions without a priority option.
+    Default,
+    /// Transactions with `PrioritySystemImmediate` option.
+    Immediate,
+}
+
+impl ThrottlePriority {
+    pub(super) const ALL: [ThrottlePriority; 3] = [
+        ThrottlePriority::Batch,
+        ThrottlePriority::Default,
+        ThrottlePriority::Immediate,
+    ];
+
+    fn code(self) -> u8 {
+        match self {
+            ThrottlePriority::Batch => 0,
+            ThrottlePriority::Default => 1,
+            ThrottlePriority::Immediate => 2,
+        }
+    }
+
+    fn from_code(code: u8) -> FdbResult<ThrottlePriority> {
+        match code {
+            0 => Ok(ThrottlePriority::Batch),
+            1 => Ok(ThrottlePriority::Default),
+            2 => Ok(ThrottlePriority::Immediate),
+            _ => Err(FdbError::new(MANAGEMENT_INVALID_VALUE)),
+        }
+    }
+}
+
+/// A throttle on the transactions with a transaction tag.
+///
+/// Throttles are either set manually using [`throttle_tag_tr`], or
+/// automatically by the cluster when a tag is busy.
+///
+/// [`throttle_tag_tr`]: crate::management::ClusterManagement::throttle_tag_tr
+#[derive(Clone, Debug, PartialEq)]
+pub struct TagThrottle {
+    tag: String,
+    priority: ThrottlePriority,
+    auto_throttled: bool,
+    tps_rate: f64,
+    expiration_time: f64,
+    duration: f64,
+}
+
+impl TagThrottle {
+    /// Gets the throttled transaction tag.
+    pub fn get_tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Gets the priority of the throttled transactions.
+    pub fn get_priority(&self) -> ThrottlePriority {
+        self.priority
+    }
+
+    /// Returns `true` if the throttle was set automatically by the
+    /// cluster.
+    pub fn is_auto_throttled(&self) -> bool {
+        self.auto_throttled
+    }
+
+    /// Gets the maximum rate of transactions per second.
+    pub fn get_tps_rate(&self) -> f64 {
+        self.tps_rate
+    }
+
+    /// Gets the time when the throttle expires.
+    ///
+    /// This is `None` when the cluster has not yet started the
+    /// throttle.
+    pub fn get_expiration_time(&self) -> Option<SystemTime> {
+        if self.expiration_time > 0.0 {
+            Some(UNIX_EPOCH + Duration::from_secs_f64(self.expiration_time))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the duration of the throttle.
+    pub fn get_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration)
+    }
+}
+
+// Key is the prefix, followed by a byte that is `1` for automatic
+// throttles, the priority, and the tag prefixed by its length.
+pub(super) fn tag_throttle_key(tag: &str, priority: ThrottlePriority) -> FdbResult<Bytes> {
+    if tag.len() > MAX_TAG_LENGTH {
+        // `tag_too_long` error
+        return Err(FdbError::new(2110));
+    }
+
+    let mut b = BytesMut::new();
+    b.put(TAG_THROTTLE_PREFIX.as_ref());
+    b.put_u8(0);
+    b.put_u8(priority.code());
+    b.put_u8(tag.len() as u8);
+    b.put(tag.as_bytes());
+    Ok(b.into())
+}
+
+// Value is the protocol version, followed by the rate, expiration
+// time and duration as `f64` and the reason, all little endian. The
+// cluster sets the expiration time when it starts the throttle.
+pub(super) fn tag_throttle_value(tps_rate: f64, duration: Duration) -> Bytes {
+    let mut b = BytesMut::new();
+    b.put_u64_le(PROTOCOL_VERSION_TAG_THROTTLE_VALUE_REASON);
+    b.put_f64_le(tps_rate);
+    b.put_f64_le(0.0);
+    b.put_f64_le(duration.as_secs_f64());
+    b.put_u8(REASON_MANUAL);
+    b.into()
+}
+
+pub(super) fn tag_throttle_from_key_value(key: Bytes, value: Bytes) -> FdbResult<TagThrottle> {
+    let key = key
+        .get(TAG_THROTTLE_PREFIX.len()..)
+        .ok_or_else(|| FdbError::new(MANAGEMENT_INVALID_VALUE))?;
+
+    let (auto_throttled, priority, tag) = match key {
+        [auto_throttled, priority, len, tag @ ..] if tag.len() == *len as usize => (
+            *auto_throttled != 0,
+            ThrottlePriority::from_code(*priority)?,
+            String::from_utf8_lossy(tag).into_owned(),
+        ),
+        _ => return Err(FdbError::new(MANAGEMENT_INVALID_VALUE)),
+    };
+
+    // Skip the protocol version. Values written by older versions do
+    // not have a reason.
+    let f64_at = |i: usize| -> FdbResult<f64> {
+        let x = value
+            .get(8 + (i * 8)..16 + (i * 8))
+            .and_then(|b| b.try_into().ok())
+            .map(f64::from_le_bytes)
+            .ok_or_else(|| FdbError::new(MANAGEMENT_INVALID_VALUE))?;
+
+        if x.is_finite() && x >= 0.0 {
+            Ok(x)
+        } else {
+            Err(FdbError::new(MANAGEMENT_INVALID_VALUE))
+        }
+    };
+
+    Ok(TagThrottle {
+        tag,
+        priority,
+        auto_throttled,
+        tps_rate: f64_at(0)?,
+        expiration_time: f64_at(1)?,
+        duration: f64_at(2)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+    use impls::impls;
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::error::{FdbError, MANAGEMENT_INVALID_VALUE};
+
+    use super::{
+        tag_throttle_from_key_value, tag_throttle_key, tag_throttle_value, TagThrottle,
+        ThrottlePriority,
+    };
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    TagThrottle:
+	        Send &
+		Sync &
+		Clone &
+		PartialEq &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    ThrottlePriority:
+	        Send &
+		Sync &
+		Clone &
+		Copy &
+		PartialEq &
+		Eq));
+    }
+
+    #[test]
+    fn test_tag_throttle_key() {
+        assert_eq!(
+            tag_throttle_key("foo", ThrottlePriority::Default),
+            Ok(Bytes::from_static(
+                b"\xFF\x02/throttledTags/tag/\x00\x01\x03foo"
+            ))
+        );
+        assert_eq!(
+            tag_throttle_key("foo", ThrottlePriority::Batch),
+            Ok(Bytes::from_static(
+                b"\xFF\x02/throttledTags/tag/\x00\x00\x03foo"
+            ))
+        );
+        assert_eq!(
+            tag_throttle_key("abcdefghijklmnopq", ThrottlePriority::Default),
+            Err(FdbError::new(2110))
+        );
+    }
+
+    #[test]
+    fn test_tag_throttle_from_key_value() {
+        let key = tag_throttle_key("foo", ThrottlePriority::Immediate).unwrap();
+        let value = tag_throttle_value(10.0, Duration::from_secs(3600));
+
+        let tag_throttle = tag_throttle_from_key_value(key, value).unwrap();
+
+        assert_eq!(tag_throttle.get_tag(), "foo");
+        assert_eq!(tag_throttle.get_priority(), ThrottlePriority::Immediate);
+        assert!(!tag_throttle.is_auto_throttled());
+        assert_eq!(tag_throttle.get_tps_rate(), 10.0);
+        assert_eq!(tag_throttle.get_expiration_time(), None);
+        assert_eq!(tag_throttle.get_duration(), Duration::from_secs(3600));
+
+        // Automatic throttle, started by the cluster and written
+        // without a reason.
+        let key = Bytes::from_static(b"\xFF\x02/throttledTags/tag/\x01\x00\x03bar");
+        let value = {
+            let mut b = BytesMut::new();
+            b.put_u64_le(0x0FDB_00B0_6301_0000);
+            b.put_f64_le(5.0);
+            b.put_f64_le(1_600_000_000.0);
+            b.put_f64_le(60.0);
+            Bytes::from(b)
+        };
+
+        let tag_throttle = tag_throttle_from_key_value(key, value).unwrap();
+
+        assert_eq!(tag_throttle.get_tag(), "bar");
+        assert_eq!(tag_throttle.get_priority(), ThrottlePriority::Batch);
+        assert!(tag_throttle.is_auto_throttled());
+        assert_eq!(
+            tag_throttle.get_expiration_time(),
+            Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        );
+
+        // Invalid tag length, priority and value.
+        for (key, value) in vec![
+            (
+                Bytes::from_static(b"\xFF\x02/throttledTags/tag/\x00\x01\x04foo"),
+                tag_throttle_value(10.0, Duration::from_secs(1)),
+            ),
+            (
+                Bytes::from_static(b"\xFF\x02/throttledTags/tag/\x00\x03\x03foo"),
+                tag_throttle_value(10.0, Duration::from_secs(1)),
+            ),
+            (
+                Bytes::from_static(b"\xFF\x02/throttledTags/tag/\x00\x01\x03foo"),
+                Bytes::from_static(b"\x01\x00\x00\x00"),
+            ),
+        ] {
+            assert_eq!(
+                tag_throttle_from_key_value(key, value),
+                Err(FdbError::new(MANAGEMENT_INVALID_VALUE))
+            );
+        }
+    }
+}
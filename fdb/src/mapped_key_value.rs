@@ -1,12 +1,15 @@
 //! Mapper and mapped key-value types
 
+use crate::error::{FdbError, FdbResult, MAPPER_INVALID_LITERAL};
 use crate::range::Range;
 use crate::tuple::Tuple;
 use crate::KeyValue;
 
 /// [`Mapper`] represents the behaviour of a mapped range read.
 ///
-/// [`Mapper`] can be converted from and into [`Tuple`].
+/// [`Mapper`] can be converted from and into [`Tuple`]. Use
+/// [`MapperBuilder`] to build a [`Mapper`] without writing the
+/// element references by hand.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Mapper(Tuple);
 
@@ -22,6 +25,146 @@ impl From<Mapper> for Tuple {
     }
 }
 
+#[derive(Clone, Debug)]
+enum MapperElement {
+    Key(usize),
+    Value(usize),
+    Literal(Tuple),
+}
+
+/// A builder for [`Mapper`].
+///
+/// For each key-value read by a mapped range read, FDB builds a
+/// *mapped key* by replacing the element references in the
+/// [`Mapper`] with elements of the key or the value, which must be
+/// tuple encoded.
+///
+/// - [`add_key_element`] adds the element at the given index of the
+///   key tuple (`"{K[i]}"`).
+/// - [`add_value_element`] adds the element at the given index of the
+///   value tuple (`"{V[i]}"`).
+/// - [`add_literal`] and [`add_literal_string`] add elements that are
+///   copied as is into the mapped key. In a literal string or byte
+///   string, `{` and `}` must be escaped as `{{` and `}}`.
+/// - [`set_range`] adds the range suffix (`"{...}"`) as the last
+///   element.
+///
+/// The shape of each [`MappedKeyValue`] depends on the range suffix.
+///
+/// - With the range suffix, the packed mapped key is used as a
+///   prefix. [`get_range_ref`] returns the range of keys starting with
+///   that prefix, and [`get_range_result_ref`] returns all the
+///   key-values within that range.
+/// - Without the range suffix, the packed mapped key is read as a
+///   single key. [`get_range_result_ref`] returns at most one
+///   key-value.
+///
+/// In both cases, [`get_key_value_ref`] returns the key-value that
+/// was used to build the mapped key.
+///
+/// [`add_key_element`]: MapperBuilder::add_key_element
+/// [`add_value_element`]: MapperBuilder::add_value_element
+/// [`add_literal`]: MapperBuilder::add_literal
+/// [`add_literal_string`]: MapperBuilder::add_literal_string
+/// [`set_range`]: MapperBuilder::set_range
+/// [`get_range_ref`]: MappedKeyValue::get_range_ref
+/// [`get_range_result_ref`]: MappedKeyValue::get_range_result_ref
+/// [`get_key_value_ref`]: MappedKeyValue::get_key_value_ref
+#[derive(Clone, Debug, Default)]
+pub struct MapperBuilder {
+    elements: Vec<MapperElement>,
+    range: bool,
+}
+
+impl MapperBuilder {
+    /// Create a new [`MapperBuilder`] with no elements and without the
+    /// range suffix.
+    pub fn new() -> MapperBuilder {
+        MapperBuilder::default()
+    }
+
+    /// Add a reference to the element at `index` of the key tuple.
+    pub fn add_key_element(&mut self, index: usize) {
+        self.elements.push(MapperElement::Key(index));
+    }
+
+    /// Add a reference to the element at `index` of the value tuple.
+    pub fn add_value_element(&mut self, index: usize) {
+        self.elements.push(MapperElement::Value(index));
+    }
+
+    /// Add the elements of `t` as literal elements.
+    ///
+    /// Any `{` or `}` in a string or byte string element of `t` must
+    /// already be escaped as `{{` or `}}`, otherwise [`build`] returns
+    /// an error. FDB does not interpret the other elements, including
+    /// nested tuples, so they are not checked.
+    ///
+    /// [`build`]: MapperBuilder::build
+    pub fn add_literal(&mut self, t: Tuple) {
+        self.elements.push(MapperElement::Literal(t));
+    }
+
+    /// Add `s` as a literal string element, escaping any `{` or `}`.
+    pub fn add_literal_string(&mut self, s: String) {
+        let mut t = Tuple::new();
+        t.add_string(s.replace('{', "{{").replace('}', "}}"));
+        self.elements.push(MapperElement::Literal(t));
+    }
+
+    /// If `range` is `true`, the range suffix is added as the last
+    /// element of the [`Mapper`].
+    pub fn set_range(&mut self, range: bool) {
+        self.range = range;
+    }
+
+    /// Build the [`Mapper`].
+    pub fn build(self) -> FdbResult<Mapper> {
+        let mut t = Tuple::new();
+
+        for element in self.elements {
+            match element {
+                MapperElement::Key(i) => t.add_string(format!("{{K[{}]}}", i)),
+                MapperElement::Value(i) => t.add_string(format!("{{V[{}]}}", i)),
+                MapperElement::Literal(literal) => {
+                    for i in 0..literal.size() {
+                        let escaped = match (literal.get_string_ref(i), literal.get_bytes_ref(i)) {
+                            (Ok(s), _) => is_escaped(s.as_bytes()),
+                            (_, Ok(b)) => is_escaped(b),
+                            _ => true,
+                        };
+                        if !escaped {
+                            return Err(FdbError::new(MAPPER_INVALID_LITERAL));
+                        }
+                    }
+                    t.append(literal);
+                }
+            }
+        }
+
+        if self.range {
+            t.add_string(String::from("{...}"));
+        }
+
+        Ok(Mapper(t))
+    }
+}
+
+// Check that every `{` and `}` in `s` is part of an escaped `{{` or
+// `}}`. FDB treats string and byte string elements the same way, so
+// this works on bytes.
+fn is_escaped(s: &[u8]) -> bool {
+    let mut bytes = s.iter();
+
+    while let Some(b) = bytes.next() {
+        if (*b == b'{' || *b == b'}') && bytes.next() != Some(b) {
+            return false;
+        }
+    }
+
+    true
+}
+
 /// A mapped key/value pair.
 ///
 /// Mapped range read operations on FDB return [`MappedKeyValue`].
@@ -81,3 +224,91 @@ impl MappedKeyValue {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::error::{FdbError, MAPPER_INVALID_LITERAL};
+    use crate::tuple::Tuple;
+
+    use super::{is_escaped, MapperBuilder};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    MapperBuilder:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn mapper_builder() {
+        let mut literal = Tuple::new();
+        literal.add_string(String::from("prefix"));
+        literal.add_i64(1);
+
+        let mut builder = MapperBuilder::new();
+        builder.add_literal(literal);
+        builder.add_key_element(2);
+        builder.add_value_element(0);
+        builder.add_literal_string(String::from("{literal}"));
+        builder.set_range(true);
+
+        let mut t = Tuple::new();
+        t.add_string(String::from("prefix"));
+        t.add_i64(1);
+        t.add_string(String::from("{K[2]}"));
+        t.add_string(String::from("{V[0]}"));
+        t.add_string(String::from("{{literal}}"));
+        t.add_string(String::from("{...}"));
+
+        assert_eq!(Tuple::from(builder.build().unwrap()), t);
+
+        let mut literal = Tuple::new();
+        literal.add_string(String::from("{K[2]}"));
+
+        let mut builder = MapperBuilder::new();
+        builder.add_literal(literal);
+
+        assert_eq!(builder.build(), Err(FdbError::new(MAPPER_INVALID_LITERAL)));
+
+        let mut literal = Tuple::new();
+        literal.add_bytes(Bytes::from_static(b"{V[0]}"));
+
+        let mut builder = MapperBuilder::new();
+        builder.add_literal(literal);
+
+        assert_eq!(builder.build(), Err(FdbError::new(MAPPER_INVALID_LITERAL)));
+
+        // Nested tuples are not interpreted by FDB.
+        let mut nested = Tuple::new();
+        nested.add_string(String::from("{K[2]}"));
+
+        let mut literal = Tuple::new();
+        literal.add_bytes(Bytes::from_static(b"{{bytes}}"));
+        literal.add_tuple(nested);
+
+        let mut builder = MapperBuilder::new();
+        builder.add_literal(literal.clone());
+
+        assert_eq!(Tuple::from(builder.build().unwrap()), literal);
+    }
+
+    #[test]
+    fn escaped() {
+        assert!(is_escaped(b""));
+        assert!(is_escaped(b"abc"));
+        assert!(is_escaped(b"{{abc}}"));
+        assert!(is_escaped(b"}}{{"));
+        assert!(!is_escaped(b"{"));
+        assert!(!is_escaped(b"}"));
+        assert!(!is_escaped(b"{...}"));
+        assert!(!is_escaped(b"{{{"));
+        assert!(!is_escaped(b"{}"));
+    }
+}
@@ -8,11 +8,14 @@ use std::task::{Context, Poll};
 
 use crate::error::{FdbError, FdbResult};
 use crate::future::{FdbFuture, FdbFutureMappedKeyValueArray};
-use crate::range::{RangeOptions, StreamingMode};
+use crate::range::{fetch_ok_transition, has_next_batch, RangeOptions, StreamingMode};
 use crate::transaction::FdbTransaction;
 use crate::tuple::Tuple;
 use crate::{KeySelector, MappedKeyValue, Mapper};
 
+#[cfg(feature = "tracing")]
+use crate::range::batch_span;
+
 // Java API refers to this type `MappedRangeResult`. It is also very
 // similar to `KeyValueArray`. We could potentially in future make
 // this type and `KeyValueArray` generic over `T`, where `T` can be
@@ -41,6 +44,7 @@ impl MappedKeyValueArray {
 enum MappedRangeResultStateMachineState {
     Fetching,
     MappedKeyValueArrayAvailable,
+    MappedKeyValueArrayPrefetching,
     Error,
     Done,
 }
@@ -56,6 +60,13 @@ enum MappedRangeResultStateMachineData {
         count: i32,
         more: bool,
     },
+    MappedKeyValueArrayPrefetching {
+        mkvs: Vec<MappedKeyValue>,
+        index: i32,
+        count: i32,
+        more: bool,
+        fdb_future_mapped_key_value_array: FdbFutureMappedKeyValueArray,
+    },
     Error {
         fdb_error: FdbError,
     },
@@ -94,6 +105,11 @@ pub(crate) struct MappedRangeResultStateMachine {
     reverse: bool,
     mapper: Mapper,
 
+    // When `prefetch` is `true`, the next batch is requested as soon
+    // as the current batch is received, instead of waiting for the
+    // current batch to be consumed.
+    prefetch: bool,
+
     // This is *only* used in case of `StreamingMode::Iterator`. In
     // other cases, we set it to `None`.
     iteration: Option<i32>,
@@ -108,11 +124,21 @@ pub(crate) struct MappedRangeResultStateMachine {
     // specified. However, we don't check for this as binding tester
     // checks for `2210` errors.
     limit: Option<i32>,
+
+    // Soft cap on the number of bytes returned in each batch. `0`
+    // means no limit.
+    target_bytes: i32,
     begin_sel: KeySelector,
     end_sel: KeySelector,
 
     mapped_range_result_state_machine_state: MappedRangeResultStateMachineState,
     mapped_range_result_state_machine_data: MappedRangeResultStateMachineData,
+
+    // Span for the mapped range read of the current batch. It is
+    // created when the batch is first polled, and closed when the
+    // batch is received.
+    #[cfg(feature = "tracing")]
+    batch_span: Option<tracing::Span>,
 }
 
 impl MappedRangeResultStateMachine {
@@ -129,6 +155,8 @@ impl MappedRangeResultStateMachine {
         iteration: Option<i32>,
         reverse: bool,
         limit: Option<i32>,
+        target_bytes: i32,
+        prefetch: bool,
         snapshot: bool,
         fdb_future_mapped_key_value_array: FdbFutureMappedKeyValueArray,
     ) -> MappedRangeResultStateMachine {
@@ -138,14 +166,18 @@ impl MappedRangeResultStateMachine {
             mode,
             reverse,
             mapper,
+            prefetch,
             iteration,
             limit,
+            target_bytes,
             begin_sel,
             end_sel,
             mapped_range_result_state_machine_state: MappedRangeResultStateMachineState::Fetching,
             mapped_range_result_state_machine_data: MappedRangeResultStateMachineData::Fetching {
                 fdb_future_mapped_key_value_array,
             },
+            #[cfg(feature = "tracing")]
+            batch_span: None,
         }
     }
 
@@ -156,131 +188,116 @@ impl MappedRangeResultStateMachine {
         loop {
             match self.mapped_range_result_state_machine_state {
                 MappedRangeResultStateMachineState::Fetching => {
+                    #[cfg(feature = "tracing")]
+                    let span = {
+                        let (snapshot, reverse) = (self.snapshot, self.reverse);
+                        self.batch_span
+                            .get_or_insert_with(|| batch_span(snapshot, reverse))
+                            .clone()
+                    };
+
+                    #[cfg(feature = "tracing")]
+                    let _enter = span.enter();
+
                     if let MappedRangeResultStateMachineData::Fetching {
                         ref mut fdb_future_mapped_key_value_array,
                     } = self.mapped_range_result_state_machine_data
                     {
-                        match Pin::new(fdb_future_mapped_key_value_array).poll(cx) {
-                            Poll::Ready(res) => match res {
-                                Ok(mapped_key_value_array) => {
-                                    let MappedKeyValueArray {
-                                        mkvs,
-                                        index,
-                                        count,
-                                        more,
-                                    } = mapped_key_value_array;
-                                    if count == 0 {
-                                        // In case count is zero, we are done.
-                                        self.step_once_with_event(
-                                            MappedRangeResultStateMachineEvent::FetchDone,
-                                        );
-                                    } else {
-                                        self.step_once_with_event(
-                                            MappedRangeResultStateMachineEvent::FetchOk {
-                                                mkvs,
-                                                index,
-                                                count,
-                                                more,
-                                            },
-                                        );
-                                    }
+                        let res = match Pin::new(fdb_future_mapped_key_value_array).poll(cx) {
+                            Poll::Ready(res) => res,
+                            Poll::Pending => return Poll::Pending,
+                        };
+
+                        #[cfg(feature = "tracing")]
+                        {
+                            match res {
+                                Ok(ref mapped_key_value_array) => {
+                                    span.record("count", mapped_key_value_array.count);
+                                    span.record("more", mapped_key_value_array.more);
                                 }
-                                Err(fdb_error) => {
-                                    self.step_once_with_event(
-                                        MappedRangeResultStateMachineEvent::FetchError {
-                                            fdb_error,
-                                        },
-                                    );
+                                Err(ref fdb_error) => {
+                                    span.record("error", fdb_error.code());
                                 }
-                            },
-                            Poll::Pending => return Poll::Pending,
+                            }
+                            self.batch_span = None;
                         }
-                    } else {
-                        panic!("invalid mapped_range_result_state_machine_data");
-                    }
-                }
-                MappedRangeResultStateMachineState::MappedKeyValueArrayAvailable => {
-                    if let MappedRangeResultStateMachineData::MappedKeyValueArrayAvailable {
-                        ref mkvs,
-                        ref mut index,
-                        count,
-                        more,
-                    } = self.mapped_range_result_state_machine_data
-                    {
-                        // Unlike in Python, where the `index ==
-                        // count` check is done when returning the
-                        // last element, in our case the last element
-                        // gets returned and in the next call to
-                        // `poll_next`, we do our check.
-                        if *index == count {
-                            // Should we get more?
-                            if more {
-                                if let Some(0) = self.limit {
+
+                        match res {
+                            Ok(mapped_key_value_array) => {
+                                let MappedKeyValueArray {
+                                    mkvs,
+                                    index,
+                                    count,
+                                    more,
+                                } = mapped_key_value_array;
+                                if count == 0 {
+                                    // In case count is zero, we are done.
                                     self.step_once_with_event(
                                         MappedRangeResultStateMachineEvent::FetchDone,
                                     );
                                 } else {
-                                    // `limit` is either `None` or
-                                    // non-zero.
-
-                                    // iteration, limit, begin_sel and
-                                    // end_sel have already been updated
-                                    // in the transition action.
-                                    let options = match self.limit {
-                                        Some(limit) => RangeOptions {
-                                            limit,
-                                            mode: self.mode,
-                                            reverse: self.reverse,
-                                        },
-                                        None => RangeOptions {
-                                            limit: 0,
-                                            mode: self.mode,
-                                            reverse: self.reverse,
-                                        },
-                                    };
-
-                                    let fdb_future_mapped_key_value_array =
-                                        fdb_transaction_get_mapped_range(
-                                            self.transaction.get_c_api_ptr(),
-                                            self.begin_sel.clone(),
-                                            self.end_sel.clone(),
-                                            self.mapper.clone(),
-                                            options,
-                                            self.iteration.unwrap_or(0),
-                                            self.snapshot,
-                                        );
-
                                     self.step_once_with_event(
-                                        MappedRangeResultStateMachineEvent::FetchNextBatch {
-                                            fdb_future_mapped_key_value_array,
+                                        MappedRangeResultStateMachineEvent::FetchOk {
+                                            mkvs,
+                                            index,
+                                            count,
+                                            more,
                                         },
                                     );
                                 }
-                            } else {
+                            }
+                            Err(fdb_error) => {
                                 self.step_once_with_event(
-                                    MappedRangeResultStateMachineEvent::FetchDone,
+                                    MappedRangeResultStateMachineEvent::FetchError { fdb_error },
                                 );
                             }
-                        } else {
-                            // We need to remove elements from the
-                            // beginning. If we used `Vec::remove`
-                            // that would keep shifting elements to
-                            // the left. Instead of modifying `mkvs`,
-                            // we just clone the element that we need.
-                            //
-                            // Safety: `index` starts with `0` (set in
-                            //          `MappedKeyValueArray::new`)
-                            //          and is incremented till it
-                            //          reaches `count`.
-                            let result = mkvs[TryInto::<usize>::try_into(*index).unwrap()].clone();
-                            *index += 1;
-
-                            return Poll::Ready(Some(Ok(result)));
                         }
                     } else {
                         panic!("invalid mapped_range_result_state_machine_data");
                     }
                 }
+                MappedRangeResultStateMachineState::MappedKeyValueArrayAvailable
+                | MappedRangeResultStateMachineState::MappedKeyValueArrayPrefetching => {
+                    let (mkvs, index, count) = match self.mapped_range_result_state_machine_data {
+                        MappedRangeResultStateMachineData::MappedKeyValueArrayAvailable {
+                            ref mkvs,
+                            ref mut index,
+                            count,
+                            ..
+                        }
+                        | MappedRangeResultStateMachineData::MappedKeyValueArrayPrefetching {
+                            ref mkvs,
+                            ref mut index,
+                            count,
+                            ..
+                        } => (mkvs, index, count),
+                        _ => panic!("invalid mapped_range_result_state_machine_data"),
+                    };
+
+                    // Unlike in Python, where the `index == count`
+                    // check is done when returning the last element,
+                    // in our case the last element gets returned and
+                    // in the next call to `poll_next`, we do our
+                    // check.
+                    if *index == count {
+                        self.fetch_next_batch_or_done();
+                    } else {
+                        // We need to remove elements from the
+                        // beginning. If we used `Vec::remove` that
+                        // would keep shifting elements to the
+                        // left. Instead of modifying `mkvs`, we just
+                        // clone the element that we need.
+                        //
+                        // Safety: `index` starts with `0` (set in
+                        //          `MappedKeyValueArray::new`) and is
+                        //          incremented till it reaches
+                        //          `count`.
+                        let result = mkvs[TryInto::<usize>::try_into(*index).unwrap()].clone();
+                        *index += 1;
+
+                        return Poll::Ready(Some(Ok(result)));
+                    }
+                }
                 MappedRangeResultStateMachineState::Error => {
                     if let MappedRangeResultStateMachineData::Error { fdb_error } =
                         self.mapped_range_result_state_machine_data
@@ -295,6 +312,69 @@ impl MappedRangeResultStateMachine {
         }
     }
 
+    // Called in `MappedKeyValueArrayAvailable` and
+    // `MappedKeyValueArrayPrefetching` states once all the
+    // mapped key-value pairs in the current `MappedKeyValueArray`
+    // have been returned.
+    fn fetch_next_batch_or_done(&mut self) {
+        match std::mem::replace(
+            &mut self.mapped_range_result_state_machine_data,
+            MappedRangeResultStateMachineData::Done,
+        ) {
+            MappedRangeResultStateMachineData::MappedKeyValueArrayAvailable { more, .. } => {
+                // Should we get more?
+                if has_next_batch(more, self.limit) {
+                    // iteration, limit, begin_sel and end_sel have
+                    // already been updated in the transition action.
+                    let fdb_future_mapped_key_value_array = self.get_next_batch();
+
+                    self.step_once_with_event(MappedRangeResultStateMachineEvent::FetchNextBatch {
+                        fdb_future_mapped_key_value_array,
+                    });
+                } else {
+                    self.step_once_with_event(MappedRangeResultStateMachineEvent::FetchDone);
+                }
+            }
+            MappedRangeResultStateMachineData::MappedKeyValueArrayPrefetching {
+                more,
+                fdb_future_mapped_key_value_array,
+                ..
+            } => {
+                // The next batch has already been requested in the
+                // transition action.
+                debug_assert!(has_next_batch(more, self.limit));
+
+                self.step_once_with_event(MappedRangeResultStateMachineEvent::FetchNextBatch {
+                    fdb_future_mapped_key_value_array,
+                });
+            }
+            _ => panic!("invalid mapped_range_result_state_machine_data"),
+        }
+    }
+
+    // Issue a mapped range read for the next batch, using the updated
+    // iteration, limit, begin_sel and end_sel.
+    fn get_next_batch(&self) -> FdbFutureMappedKeyValueArray {
+        let options = RangeOptions {
+            limit: self.limit.unwrap_or(0),
+            target_bytes: self.target_bytes,
+            mode: self.mode,
+            reverse: self.reverse,
+            prefetch: self.prefetch,
+            iteration: self.iteration.unwrap_or(0),
+        };
+
+        fdb_transaction_get_mapped_range(
+            self.transaction.get_c_api_ptr(),
+            self.begin_sel.clone(),
+            self.end_sel.clone(),
+            self.mapper.clone(),
+            options,
+            self.iteration.unwrap_or(0),
+            self.snapshot,
+        )
+    }
+
     fn step_once_with_event(&mut self, event: MappedRangeResultStateMachineEvent) {
         self.mapped_range_result_state_machine_state =
             match self.mapped_range_result_state_machine_state {
@@ -314,17 +394,15 @@ impl MappedRangeResultStateMachine {
                         // case it is needed. This would be used by
                         // `FetchNextBatch` event.
 
-                        if more {
-                            // This assumes that we have mode to be
-                            // `StreamingMode::Iterator`.
-                            if let Some(iteration) = self.iteration.as_mut() {
-                                *iteration += 1;
-                            }
-
-                            if let Some(limit) = self.limit.as_mut() {
-                                *limit -= count;
-                            }
+                        let prefetching = fetch_ok_transition(
+                            more,
+                            count,
+                            self.prefetch,
+                            &mut self.limit,
+                            &mut self.iteration,
+                        );
 
+                        if more {
                             // Safety: We only generate the `FetchOk` event
                             // when count > 0, otherwise we go to `FetchDone`.
                             let last_index = TryInto::<usize>::try_into(count - 1).unwrap();
@@ -340,14 +418,31 @@ impl MappedRangeResultStateMachine {
                             }
                         }
 
-                        self.mapped_range_result_state_machine_data =
-                            MappedRangeResultStateMachineData::MappedKeyValueArrayAvailable {
-                                mkvs,
-                                index,
-                                count,
-                                more,
-                            };
-                        MappedRangeResultStateMachineState::MappedKeyValueArrayAvailable
+                        if prefetching {
+                            // Request the next batch right away, so
+                            // that it is fetched while `mkvs` is
+                            // being consumed.
+                            let fdb_future_mapped_key_value_array = self.get_next_batch();
+
+                            self.mapped_range_result_state_machine_data =
+                                MappedRangeResultStateMachineData::MappedKeyValueArrayPrefetching {
+                                    mkvs,
+                                    index,
+                                    count,
+                                    more,
+                                    fdb_future_mapped_key_value_array,
+                                };
+                            MappedRangeResultStateMachineState::MappedKeyValueArrayPrefetching
+                        } else {
+                            self.mapped_range_result_state_machine_data =
+                                MappedRangeResultStateMachineData::MappedKeyValueArrayAvailable {
+                                    mkvs,
+                                    index,
+                                    count,
+                                    more,
+                                };
+                            MappedRangeResultStateMachineState::MappedKeyValueArrayAvailable
+                        }
                     }
                     MappedRangeResultStateMachineEvent::FetchDone => {
                         self.mapped_range_result_state_machine_data =
@@ -378,6 +473,18 @@ impl MappedRangeResultStateMachine {
                     }
                     _ => panic!("Invalid event!"),
                 },
+                MappedRangeResultStateMachineState::MappedKeyValueArrayPrefetching => match event {
+                    MappedRangeResultStateMachineEvent::FetchNextBatch {
+                        fdb_future_mapped_key_value_array,
+                    } => {
+                        self.mapped_range_result_state_machine_data =
+                            MappedRangeResultStateMachineData::Fetching {
+                                fdb_future_mapped_key_value_array,
+                            };
+                        MappedRangeResultStateMachineState::Fetching
+                    }
+                    _ => panic!("Invalid event!"),
+                },
                 MappedRangeResultStateMachineState::Error
                 | MappedRangeResultStateMachineState::Done => {
                     panic!("Invalid event!");
@@ -411,9 +518,7 @@ pub(crate) fn fdb_transaction_get_mapped_range(
     let mapper_name = mapper.as_ref().as_ptr();
     let mapper_name_length = mapper.as_ref().len().try_into().unwrap();
 
-    // This is similar to Java, where calls to
-    // `tr.getMappedRange_internal` sets the `target_bytes` to `0`.
-    let target_bytes = 0;
+    let target_bytes = options.get_target_bytes();
 
     let limit = options.get_limit();
     let mode = options.get_mode().code();
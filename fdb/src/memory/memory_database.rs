@@ -0,0 +1,189 @@
+use parking_lot::Mutex;
+
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::error::FdbResult;
+use crate::memory::{MemoryReadTransaction, MemoryTransaction};
+use crate::transaction::retry_loop;
+
+use super::store::Store;
+
+/// An in-memory database.
+///
+/// Cloning a [`MemoryDatabase`] returns a handle to the same
+/// database.
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::memory
+#[derive(Clone, Debug, Default)]
+pub struct MemoryDatabase {
+    store: Arc<Mutex<Store>>,
+}
+
+impl MemoryDatabase {
+    /// Create a new empty [`MemoryDatabase`].
+    pub fn new() -> MemoryDatabase {
+        MemoryDatabase::default()
+    }
+
+    /// Creates a [`MemoryTransaction`] that operates on this
+    /// [`MemoryDatabase`].
+    pub fn create_transaction(&self) -> FdbResult<MemoryTransaction> {
+        Ok(MemoryTransaction::new(self.store.clone()))
+    }
+
+    /// Runs a closure in the context that takes a
+    /// [`MemoryTransaction`].
+    ///
+    /// # Note
+    ///
+    /// Like [`FdbDatabase::run`], the closure will run multiple times
+    /// (retry) when certain errors are encountered.
+    ///
+    /// [`FdbDatabase::run`]: crate::database::FdbDatabase::run
+    pub async fn run<T, F, Fut>(&self, f: F) -> FdbResult<T>
+    where
+        F: FnMut(MemoryTransaction) -> Fut,
+        Fut: Future<Output = FdbResult<T>>,
+    {
+        let t = self.create_transaction()?;
+
+        retry_loop::run(t, f).await
+    }
+
+    /// Runs a closure in the context that takes a
+    /// [`MemoryReadTransaction`].
+    ///
+    /// # Note
+    ///
+    /// Like [`FdbDatabase::read`], the closure will run multiple
+    /// times (retry) when certain errors are encountered.
+    ///
+    /// [`FdbDatabase::read`]: crate::database::FdbDatabase::read
+    pub async fn read<T, F, Fut>(&self, f: F) -> FdbResult<T>
+    where
+        F: FnMut(MemoryReadTransaction) -> Fut,
+        Fut: Future<Output = FdbResult<T>>,
+    {
+        let t = self.create_transaction()?.snapshot();
+
+        retry_loop::read(t, f).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::error::FdbError;
+    use crate::transaction::{MutationType, ReadTransaction, Transaction};
+    use crate::tuple::{Tuple, Versionstamp};
+    use crate::Value;
+
+    use super::MemoryDatabase;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    MemoryDatabase:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    #[tokio::test]
+    async fn run_retries_on_conflict() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        tr.set(Bytes::from_static(b"key"), Bytes::from_static(b"a"));
+        unsafe { tr.commit() }.await.unwrap();
+
+        let mut attempts = 0;
+
+        let res = db
+            .run(|tr| {
+                attempts += 1;
+                let db = db.clone();
+                let first_attempt = attempts == 1;
+                async move {
+                    let value = tr.get(Bytes::from_static(b"key")).await?;
+
+                    if first_attempt {
+                        // Concurrent write to the key we just read.
+                        let other = db.create_transaction()?;
+                        other.set(Bytes::from_static(b"key"), Bytes::from_static(b"b"));
+                        unsafe { other.commit() }.await?;
+                    }
+
+                    tr.set(Bytes::from_static(b"other"), Bytes::from_static(b"c"));
+
+                    Ok(value)
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(res, Some(Value::from(Bytes::from_static(b"b"))));
+
+        let res = db
+            .read(|tr| async move { tr.get(Bytes::from_static(b"other")).await })
+            .await
+            .unwrap();
+        assert_eq!(res, Some(Value::from(Bytes::from_static(b"c"))));
+
+        let res = db
+            .run(|_tr| async move { Result::<(), FdbError>::Err(FdbError::new(2000)) })
+            .await;
+        assert_eq!(res, Err(FdbError::new(2000)));
+    }
+
+    #[tokio::test]
+    async fn run_retries_on_versionstamped_value_conflict() {
+        let db = MemoryDatabase::new();
+
+        let mut attempts = 0;
+
+        let res = db
+            .run(|tr| {
+                attempts += 1;
+                let db = db.clone();
+                let first_attempt = attempts == 1;
+                async move {
+                    let value = tr.get(Bytes::from_static(b"key")).await?;
+
+                    if first_attempt {
+                        // Concurrent versionstamped write to the key
+                        // we just read.
+                        let mut t = Tuple::new();
+                        t.add_versionstamp(Versionstamp::incomplete(0));
+
+                        let other = db.create_transaction()?;
+                        unsafe {
+                            other.mutate(
+                                MutationType::SetVersionstampedValue,
+                                Bytes::from_static(b"key"),
+                                t.pack_with_versionstamp(Bytes::new())?,
+                            )
+                        };
+                        unsafe { other.commit() }.await?;
+                    }
+
+                    tr.set(Bytes::from_static(b"other"), Bytes::from_static(b"c"));
+
+                    Ok(value.is_some())
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert!(res);
+    }
+}
@@ -0,0 +1,1403 @@
+use bytes::Bytes;
+
+use futures::future::{self, FutureExt};
+use futures::stream::{self, StreamExt};
+
+use parking_lot::{Mutex, MutexGuard};
+
+use tokio::sync::oneshot;
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::ops::Bound;
+use std::sync::Arc;
+
+use crate::error::{FdbError, FdbResult};
+use crate::memory::{MemoryFuture, MemoryStream};
+use crate::range::{Range, RangeOptions};
+use crate::transaction::{MutationType, ReadTransaction, Transaction, TransactionOption};
+use crate::{Key, KeySelector, KeyValue, Value};
+
+#[cfg(feature = "fdb-7_1")]
+use crate::tuple::Tuple;
+
+#[cfg(feature = "fdb-7_1")]
+use crate::{MappedKeyValue, Mapper};
+
+use super::store::{apply_mutation, fill_versionstamp, key_after, Store};
+
+// FDB error codes returned by `MemoryTransaction`.
+const NOT_COMMITTED: i32 = 1020;
+const TRANSACTION_CANCELLED: i32 = 1025;
+const CLIENT_INVALID_OPERATION: i32 = 2000;
+const NO_COMMIT_VERSION: i32 = 2021;
+const KEY_TOO_LARGE: i32 = 2102;
+const VALUE_TOO_LARGE: i32 = 2103;
+
+#[cfg(feature = "fdb-7_1")]
+const MAPPER_BAD_INDEX: i32 = 2220;
+
+#[cfg(feature = "fdb-7_1")]
+const MAPPER_BAD_RANGE_DESCRIPTOR: i32 = 2222;
+
+// Errors for which `on_error` resets the transaction so that it can
+// be retried.
+const RETRYABLE_ERRORS: [i32; 7] = [1007, 1009, 1020, 1021, 1037, 1051, 1213];
+
+const KEY_SIZE_LIMIT: usize = 10000;
+const VALUE_SIZE_LIMIT: usize = 100000;
+
+// A write to a key within a transaction.
+//
+// `Mutations` is used when atomic mutations are done on a key whose
+// value is not known to the transaction. The mutations are applied to
+// the committed value when reading and on commit.
+#[derive(Debug)]
+enum Write {
+    Value(Option<Bytes>),
+    Mutations(Vec<(MutationType, Bytes)>),
+}
+
+#[derive(Debug, Default)]
+struct TransactionState {
+    read_version: Option<i64>,
+    writes: BTreeMap<Bytes, Write>,
+    cleared: Vec<(Bytes, Bytes)>,
+    versionstamped: Vec<(MutationType, Bytes, Bytes)>,
+    read_conflicts: Vec<(Bytes, Bytes)>,
+    write_conflicts: Vec<(Bytes, Bytes)>,
+    watches: Vec<(Bytes, Option<Bytes>, oneshot::Sender<FdbResult<()>>)>,
+    versionstamps: Vec<oneshot::Sender<FdbResult<Bytes>>>,
+    committed_version: Option<i64>,
+    cancelled: bool,
+    error: Option<FdbError>,
+}
+
+impl TransactionState {
+    fn check(&self) -> FdbResult<()> {
+        if self.cancelled {
+            Err(FdbError::new(TRANSACTION_CANCELLED))
+        } else if let Some(e) = self.error {
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn get_read_version(&mut self, store: &Store) -> i64 {
+        *self.read_version.get_or_insert_with(|| store.get_version())
+    }
+
+    fn is_cleared(&self, key: &Bytes) -> bool {
+        self.cleared.iter().any(|(b, e)| b <= key && key < e)
+    }
+
+    // Value of `key` including the writes of this transaction.
+    fn read(&self, store: &Store, read_version: i64, key: &Bytes) -> Option<Bytes> {
+        let committed = || {
+            if self.is_cleared(key) {
+                None
+            } else {
+                store.get(key, read_version)
+            }
+        };
+
+        match self.writes.get(key) {
+            Some(Write::Value(v)) => v.clone(),
+            Some(Write::Mutations(ops)) => ops.iter().fold(committed(), |acc, (optype, param)| {
+                apply_mutation(*optype, acc, param)
+            }),
+            None => committed(),
+        }
+    }
+
+    // Key-values in `[begin, end)` including the writes of this
+    // transaction. `end` of `None` means no upper bound.
+    fn read_range(
+        &self,
+        store: &Store,
+        read_version: i64,
+        begin: Bytes,
+        end: Option<Bytes>,
+    ) -> BTreeMap<Bytes, Bytes> {
+        let end = match end {
+            Some(e) if begin >= e => return BTreeMap::new(),
+            Some(e) => Bound::Excluded(e),
+            None => Bound::Unbounded,
+        };
+        let begin = Bound::Included(begin);
+
+        let mut res = store
+            .range(begin.clone(), end.clone(), read_version)
+            .into_iter()
+            .filter(|(k, _)| !self.is_cleared(k))
+            .collect::<BTreeMap<Bytes, Bytes>>();
+
+        for key in self.writes.range((begin, end)).map(|(k, _)| k) {
+            match self.read(store, read_version, key) {
+                Some(v) => res.insert(key.clone(), v),
+                None => res.remove(key),
+            };
+        }
+
+        res
+    }
+
+    fn check_key(&mut self, key: &Bytes) {
+        if key.len() > KEY_SIZE_LIMIT {
+            self.error.get_or_insert(FdbError::new(KEY_TOO_LARGE));
+        }
+    }
+
+    fn check_value(&mut self, value: &Bytes) {
+        if value.len() > VALUE_SIZE_LIMIT {
+            self.error.get_or_insert(FdbError::new(VALUE_TOO_LARGE));
+        }
+    }
+
+    fn mutate(&mut self, optype: MutationType, key: Bytes, param: Bytes) {
+        match optype {
+            MutationType::SetVersionstampedKey | MutationType::SetVersionstampedValue => {
+                let b = if let MutationType::SetVersionstampedKey = optype {
+                    &key
+                } else {
+                    &param
+                };
+                if fill_versionstamp(b, &[0; 10]).is_none() {
+                    self.error
+                        .get_or_insert(FdbError::new(CLIENT_INVALID_OPERATION));
+                }
+                self.versionstamped.push((optype, key, param));
+            }
+            _ => {
+                let write = match self.writes.remove(&key) {
+                    Some(Write::Value(v)) => Write::Value(apply_mutation(optype, v, &param)),
+                    Some(Write::Mutations(mut ops)) => {
+                        ops.push((optype, param));
+                        Write::Mutations(ops)
+                    }
+                    None if self.is_cleared(&key) => {
+                        Write::Value(apply_mutation(optype, None, &param))
+                    }
+                    None => Write::Mutations(vec![(optype, param)]),
+                };
+                self.writes.insert(key, write);
+            }
+        }
+    }
+
+    fn clear_range(&mut self, begin: Bytes, end: Bytes) {
+        if begin < end {
+            let keys = self
+                .writes
+                .range(begin.clone()..end.clone())
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<Bytes>>();
+            for key in keys {
+                self.writes.remove(&key);
+            }
+            self.cleared.push((begin, end));
+        }
+    }
+
+    fn get_approximate_size(&self) -> i64 {
+        let writes = self
+            .writes
+            .iter()
+            .map(|(k, w)| {
+                k.len()
+                    + match w {
+                        Write::Value(v) => v.as_ref().map_or(0, |v| v.len()),
+                        Write::Mutations(ops) => ops.iter().map(|(_, p)| p.len()).sum(),
+                    }
+            })
+            .sum::<usize>();
+
+        let versionstamped = self
+            .versionstamped
+            .iter()
+            .map(|(_, k, p)| k.len() + p.len())
+            .sum::<usize>();
+
+        let ranges = self
+            .cleared
+            .iter()
+            .chain(self.read_conflicts.iter())
+            .chain(self.write_conflicts.iter())
+            .map(|(b, e)| b.len() + e.len())
+            .sum::<usize>();
+
+        (writes + versionstamped + ranges) as i64
+    }
+
+    fn commit(&mut self, store: &mut Store) -> FdbResult<()> {
+        self.check()?;
+
+        let read_version = self.get_read_version(store);
+
+        let mut write_ranges = self
+            .writes
+            .keys()
+            .map(|k| (k.clone(), key_after(k)))
+            .chain(self.cleared.iter().cloned())
+            .chain(self.write_conflicts.iter().cloned())
+            .collect::<Vec<(Bytes, Bytes)>>();
+
+        if write_ranges.is_empty() && self.versionstamped.is_empty() {
+            // Read-only transaction.
+            self.committed_version = Some(-1);
+            for sender in self.versionstamps.drain(..) {
+                let _ = sender.send(Err(FdbError::new(NO_COMMIT_VERSION)));
+            }
+            self.register_watches(store);
+            return Ok(());
+        }
+
+        if store.conflicts(read_version, &self.read_conflicts) {
+            return Err(FdbError::new(NOT_COMMITTED));
+        }
+
+        let version = store.get_version() + 1;
+
+        for (begin, end) in self.cleared.iter() {
+            store.clear_range(begin.clone(), end.clone(), version);
+        }
+
+        for (key, write) in std::mem::take(&mut self.writes) {
+            let value = match write {
+                Write::Value(v) => v,
+                Write::Mutations(ops) => ops
+                    .iter()
+                    .fold(store.get_latest(&key), |acc, (optype, param)| {
+                        apply_mutation(*optype, acc, param)
+                    }),
+            };
+            store.set(key, value, version);
+        }
+
+        // 8-byte big-endian commit version followed by 2-byte
+        // big-endian batch number.
+        let mut versionstamp = version.to_be_bytes().to_vec();
+        versionstamp.extend_from_slice(&[0x00, 0x00]);
+
+        for (optype, key, param) in std::mem::take(&mut self.versionstamped) {
+            // Offsets were checked in `mutate`, so `unwrap` does not
+            // panic.
+            if let MutationType::SetVersionstampedKey = optype {
+                let key = fill_versionstamp(&key, &versionstamp).unwrap();
+                write_ranges.push((key.clone(), key_after(&key)));
+                store.set(key, Some(param), version);
+            } else {
+                let value = fill_versionstamp(&param, &versionstamp).unwrap();
+                write_ranges.push((key.clone(), key_after(&key)));
+                store.set(key, Some(value), version);
+            }
+        }
+
+        store.finish_commit(version, write_ranges);
+
+        self.committed_version = Some(version);
+
+        for sender in self.versionstamps.drain(..) {
+            let _ = sender.send(Ok(Bytes::from(versionstamp.clone())));
+        }
+
+        self.register_watches(store);
+
+        Ok(())
+    }
+
+    fn register_watches(&mut self, store: &mut Store) {
+        for (key, value, sender) in self.watches.drain(..) {
+            store.add_watch(key, value, sender);
+        }
+    }
+}
+
+/// A transaction on a [`MemoryDatabase`].
+///
+/// [`MemoryTransaction`] implements [`ReadTransaction`] and
+/// [`Transaction`] traits, so it can be used in place of
+/// [`FdbTransaction`] by code that is generic over these traits.
+///
+/// See [module] documentation for details.
+///
+/// [`MemoryDatabase`]: crate::memory::MemoryDatabase
+/// [`FdbTransaction`]: crate::transaction::FdbTransaction
+/// [module]: crate::memory
+#[derive(Clone, Debug)]
+pub struct MemoryTransaction {
+    store: Arc<Mutex<Store>>,
+    state: Arc<Mutex<TransactionState>>,
+}
+
+impl MemoryTransaction {
+    /// Return a [`MemoryReadTransaction`] that does snapshot reads.
+    pub fn snapshot(&self) -> MemoryReadTransaction {
+        MemoryReadTransaction {
+            inner: self.clone(),
+        }
+    }
+
+    pub(super) fn new(store: Arc<Mutex<Store>>) -> MemoryTransaction {
+        MemoryTransaction {
+            store,
+            state: Arc::new(Mutex::new(TransactionState::default())),
+        }
+    }
+
+    // `Store` is always locked before `TransactionState`.
+    fn lock(&self) -> (MutexGuard<'_, Store>, MutexGuard<'_, TransactionState>) {
+        let store = self.store.lock();
+        let state = self.state.lock();
+        (store, state)
+    }
+
+    fn read_get(&self, key: Bytes, snapshot: bool) -> FdbResult<Option<Value>> {
+        let (store, mut state) = self.lock();
+        state.check()?;
+
+        let read_version = state.get_read_version(&store);
+
+        if !snapshot {
+            let end = key_after(&key);
+            state.read_conflicts.push((key.clone(), end));
+        }
+
+        Ok(state.read(&store, read_version, &key).map(Value::from))
+    }
+
+    fn read_key(&self, selector: KeySelector, snapshot: bool) -> FdbResult<Key> {
+        let (store, mut state) = self.lock();
+        state.check()?;
+
+        let read_version = state.get_read_version(&store);
+        let view = state.read_range(&store, read_version, Bytes::new(), None);
+
+        let (anchor, or_equal, offset) = selector.deconstruct();
+        let anchor = Bytes::from(anchor);
+        let key = resolve(&view, &anchor, or_equal, offset);
+
+        if !snapshot {
+            let (begin, end) = if key < anchor {
+                (key.clone(), anchor)
+            } else {
+                (anchor, key.clone())
+            };
+            let end = key_after(&end);
+            state.read_conflicts.push((begin, end));
+        }
+
+        Ok(key.into())
+    }
+
+    fn read_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+        snapshot: bool,
+    ) -> FdbResult<Vec<KeyValue>> {
+        let (store, mut state) = self.lock();
+        state.check()?;
+
+        let read_version = state.get_read_version(&store);
+        let view = state.read_range(&store, read_version, Bytes::new(), None);
+
+        let (begin_key, begin_or_equal, begin_offset) = begin.deconstruct();
+        let (end_key, end_or_equal, end_offset) = end.deconstruct();
+        let begin = resolve(&view, &begin_key.into(), begin_or_equal, begin_offset);
+        let end = resolve(&view, &end_key.into(), end_or_equal, end_offset);
+
+        if begin >= end {
+            return Ok(Vec::new());
+        }
+
+        let mut kvs = view
+            .range(begin.clone()..end.clone())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect::<Vec<(Bytes, Bytes)>>();
+
+        let reverse = options.get_reverse();
+        if reverse {
+            kvs.reverse();
+        }
+
+        let limit = options.get_limit();
+        let limited = limit > 0 && kvs.len() > limit as usize;
+        if limited {
+            kvs.truncate(limit as usize);
+        }
+
+        if !snapshot {
+            // When the limit is reached, only the keys that were
+            // returned are added to the read conflict range.
+            let conflict_range = match kvs.last() {
+                Some((k, _)) if limited && reverse => (k.clone(), end),
+                Some((k, _)) if limited => (begin, key_after(k)),
+                _ => (begin, end),
+            };
+            state.read_conflicts.push(conflict_range);
+        }
+
+        Ok(kvs
+            .into_iter()
+            .map(|(k, v)| KeyValue::new(k.into(), v.into()))
+            .collect())
+    }
+
+    fn read_estimated_range_size_bytes(&self, range: Range) -> FdbResult<i64> {
+        let (store, mut state) = self.lock();
+        state.check()?;
+
+        let read_version = state.get_read_version(&store);
+        let (begin, end) = range.into_parts();
+
+        Ok(state
+            .read_range(&store, read_version, begin.into(), Some(end.into()))
+            .iter()
+            .map(|(k, v)| (k.len() + v.len()) as i64)
+            .sum())
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn read_mapped_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        mapper: Mapper,
+        options: RangeOptions,
+        snapshot: bool,
+    ) -> FdbResult<Vec<MappedKeyValue>> {
+        let mapper = Tuple::from(mapper);
+        let mut res = Vec::new();
+
+        for kv in self.read_range(begin, end, options, snapshot)? {
+            let (key, value) = kv.into_parts();
+            let (mapped_key, is_range) =
+                map_key(&mapper, &key.clone().into(), &value.clone().into())?;
+
+            let (range, range_result) = if is_range {
+                let range = Range::starts_with(mapped_key);
+                let range_result = self.read_range(
+                    KeySelector::first_greater_or_equal(range.begin_key_ref().clone()),
+                    KeySelector::first_greater_or_equal(range.end_key_ref().clone()),
+                    RangeOptions::default(),
+                    snapshot,
+                )?;
+                (range, range_result)
+            } else {
+                let range = Range::new(mapped_key.clone(), key_after(&mapped_key));
+                let range_result = self
+                    .read_get(mapped_key.clone(), snapshot)?
+                    .map(|v| vec![KeyValue::new(mapped_key.into(), v)])
+                    .unwrap_or_default();
+                (range, range_result)
+            };
+
+            res.push(MappedKeyValue::new(
+                KeyValue::new(key, value),
+                range,
+                range_result,
+            ));
+        }
+
+        Ok(res)
+    }
+}
+
+// Errors returned by methods that do not return a future (such as
+// `set`) are reported on the next read or on commit.
+impl ReadTransaction for MemoryTransaction {
+    type FutureUnit = MemoryFuture<()>;
+    type FutureMaybeValue = MemoryFuture<Option<Value>>;
+    type FutureCStringArray = MemoryFuture<Vec<CString>>;
+    type FutureI64 = MemoryFuture<i64>;
+    type FutureKey = MemoryFuture<Key>;
+    #[cfg(feature = "fdb-7_1")]
+    type StreamMappedKeyValue = MemoryStream<MappedKeyValue>;
+    type StreamKeyValue = MemoryStream<KeyValue>;
+    #[cfg(feature = "fdb-7_1")]
+    type FutureKeyArray = MemoryFuture<Vec<Key>>;
+
+    unsafe fn on_error(&self, e: FdbError) -> MemoryFuture<()> {
+        let res = if RETRYABLE_ERRORS.contains(&e.code()) {
+            *self.state.lock() = TransactionState::default();
+            Ok(())
+        } else {
+            Err(e)
+        };
+
+        future::ready(res).boxed()
+    }
+
+    fn get(&self, key: impl Into<Key>) -> MemoryFuture<Option<Value>> {
+        future::ready(self.read_get(key.into().into(), false)).boxed()
+    }
+
+    // A `MemoryDatabase` has no storage servers.
+    fn get_addresses_for_key(&self, _key: impl Into<Key>) -> MemoryFuture<Vec<CString>> {
+        future::ready(Ok(Vec::new())).boxed()
+    }
+
+    fn get_estimated_range_size_bytes(&self, range: Range) -> MemoryFuture<i64> {
+        future::ready(self.read_estimated_range_size_bytes(range)).boxed()
+    }
+
+    fn get_key(&self, selector: KeySelector) -> MemoryFuture<Key> {
+        future::ready(self.read_key(selector, false)).boxed()
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_mapped_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        mapper: impl Into<Mapper>,
+        options: RangeOptions,
+    ) -> MemoryStream<MappedKeyValue> {
+        into_flat_stream(self.read_mapped_range(begin, end, mapper.into(), options, false))
+    }
+
+    fn get_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+    ) -> MemoryStream<KeyValue> {
+        into_flat_stream(self.read_range(begin, end, options, false))
+    }
+
+    // A `MemoryDatabase` does not split ranges, so `begin` and `end`
+    // are returned.
+    #[cfg(feature = "fdb-7_1")]
+    fn get_range_split_points(
+        &self,
+        begin: impl Into<Key>,
+        end: impl Into<Key>,
+        _chunk_size: i64,
+    ) -> MemoryFuture<Vec<Key>> {
+        future::ready(Ok(vec![begin.into(), end.into()])).boxed()
+    }
+
+    unsafe fn get_read_version(&self) -> MemoryFuture<i64> {
+        let (store, mut state) = self.lock();
+        let res = state.check().map(|_| state.get_read_version(&store));
+        future::ready(res).boxed()
+    }
+
+    // Options are ignored.
+    fn set_option(&self, _option: TransactionOption) -> FdbResult<()> {
+        Ok(())
+    }
+
+    unsafe fn set_read_version(&self, version: i64) {
+        self.state.lock().read_version = Some(version);
+    }
+}
+
+impl Transaction for MemoryTransaction {
+    type CommittedVersion = FdbResult<i64>;
+    type TransactionVersionstamp = MemoryFuture<Bytes>;
+    type Snapshot = MemoryReadTransaction;
+
+    fn add_read_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
+        let key = Bytes::from(key.into());
+        let end = key_after(&key);
+        self.state.lock().read_conflicts.push((key, end));
+        Ok(())
+    }
+
+    fn add_read_conflict_range(&self, range: Range) -> FdbResult<()> {
+        let (begin, end) = range.into_parts();
+        self.state
+            .lock()
+            .read_conflicts
+            .push((begin.into(), end.into()));
+        Ok(())
+    }
+
+    fn add_write_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
+        let key = Bytes::from(key.into());
+        let end = key_after(&key);
+        self.state.lock().write_conflicts.push((key, end));
+        Ok(())
+    }
+
+    fn add_write_conflict_range(&self, range: Range) -> FdbResult<()> {
+        let (begin, end) = range.into_parts();
+        self.state
+            .lock()
+            .write_conflicts
+            .push((begin.into(), end.into()));
+        Ok(())
+    }
+
+    // Subsequent reads and commit fail with `transaction_cancelled`
+    // (`1025`) error.
+    unsafe fn cancel(&self) {
+        self.state.lock().cancelled = true;
+    }
+
+    fn clear(&self, key: impl Into<Key>) {
+        let key = Bytes::from(key.into());
+        let mut state = self.state.lock();
+        state.check_key(&key);
+        state.writes.insert(key, Write::Value(None));
+    }
+
+    fn clear_range(&self, range: Range) {
+        let (begin, end) = range.into_parts();
+        self.state.lock().clear_range(begin.into(), end.into());
+    }
+
+    unsafe fn commit(&self) -> MemoryFuture<()> {
+        let mut store = self.store.lock();
+        let res = self.state.lock().commit(&mut store);
+        future::ready(res).boxed()
+    }
+
+    fn get_approximate_size(&self) -> MemoryFuture<i64> {
+        future::ready(Ok(self.state.lock().get_approximate_size())).boxed()
+    }
+
+    // This is `-1` for read-only transactions and transactions that
+    // have not been committed.
+    unsafe fn get_committed_version(&self) -> FdbResult<i64> {
+        Ok(self.state.lock().committed_version.unwrap_or(-1))
+    }
+
+    unsafe fn get_versionstamp(&self) -> MemoryFuture<Bytes> {
+        let (sender, receiver) = oneshot::channel();
+        self.state.lock().versionstamps.push(sender);
+        receive(receiver)
+    }
+
+    unsafe fn mutate(&self, optype: MutationType, key: impl Into<Key>, param: Bytes) {
+        let key = Bytes::from(key.into());
+        let mut state = self.state.lock();
+        state.check_key(&key);
+        state.check_value(&param);
+        state.mutate(optype, key, param);
+    }
+
+    unsafe fn reset(&self) {
+        *self.state.lock() = TransactionState::default();
+    }
+
+    fn set(&self, key: impl Into<Key>, value: impl Into<Value>) {
+        let key = Bytes::from(key.into());
+        let value = Bytes::from(value.into());
+        let mut state = self.state.lock();
+        state.check_key(&key);
+        state.check_value(&value);
+        state.writes.insert(key, Write::Value(Some(value)));
+    }
+
+    fn snapshot(&self) -> MemoryReadTransaction {
+        MemoryTransaction::snapshot(self)
+    }
+
+    // The watch becomes ready when the value of the key changes,
+    // after this transaction is committed.
+    fn watch(&self, key: impl Into<Key>) -> MemoryFuture<()> {
+        let key = Bytes::from(key.into());
+        let (sender, receiver) = oneshot::channel();
+
+        let (store, mut state) = self.lock();
+        let read_version = state.get_read_version(&store);
+        let value = state.read(&store, read_version, &key);
+        state.watches.push((key, value, sender));
+
+        receive(receiver)
+    }
+}
+
+/// A read-only view of a [`MemoryTransaction`] that does snapshot
+/// reads.
+///
+/// [`MemoryReadTransaction`] implements [`ReadTransaction`] trait,
+/// so it can be used in place of [`FdbReadTransaction`].
+///
+/// [`FdbReadTransaction`]: crate::transaction::FdbReadTransaction
+#[derive(Clone, Debug)]
+pub struct MemoryReadTransaction {
+    inner: MemoryTransaction,
+}
+
+// `snapshot` is `true` below because any reads that we do on
+// `MemoryReadTransaction` is a `snapshot` read.
+impl ReadTransaction for MemoryReadTransaction {
+    type FutureUnit = MemoryFuture<()>;
+    type FutureMaybeValue = MemoryFuture<Option<Value>>;
+    type FutureCStringArray = MemoryFuture<Vec<CString>>;
+    type FutureI64 = MemoryFuture<i64>;
+    type FutureKey = MemoryFuture<Key>;
+    #[cfg(feature = "fdb-7_1")]
+    type StreamMappedKeyValue = MemoryStream<MappedKeyValue>;
+    type StreamKeyValue = MemoryStream<KeyValue>;
+    #[cfg(feature = "fdb-7_1")]
+    type FutureKeyArray = MemoryFuture<Vec<Key>>;
+
+    unsafe fn on_error(&self, e: FdbError) -> MemoryFuture<()> {
+        self.inner.on_error(e)
+    }
+
+    fn get(&self, key: impl Into<Key>) -> MemoryFuture<Option<Value>> {
+        future::ready(self.inner.read_get(key.into().into(), true)).boxed()
+    }
+
+    fn get_addresses_for_key(&self, key: impl Into<Key>) -> MemoryFuture<Vec<CString>> {
+        self.inner.get_addresses_for_key(key)
+    }
+
+    fn get_estimated_range_size_bytes(&self, range: Range) -> MemoryFuture<i64> {
+        self.inner.get_estimated_range_size_bytes(range)
+    }
+
+    fn get_key(&self, selector: KeySelector) -> MemoryFuture<Key> {
+        future::ready(self.inner.read_key(selector, true)).boxed()
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_mapped_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        mapper: impl Into<Mapper>,
+        options: RangeOptions,
+    ) -> MemoryStream<MappedKeyValue> {
+        into_flat_stream(
+            self.inner
+                .read_mapped_range(begin, end, mapper.into(), options, true),
+        )
+    }
+
+    fn get_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+    ) -> MemoryStream<KeyValue> {
+        into_flat_stream(self.inner.read_range(begin, end, options, true))
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_range_split_points(
+        &self,
+        begin: impl Into<Key>,
+        end: impl Into<Key>,
+        chunk_size: i64,
+    ) -> MemoryFuture<Vec<Key>> {
+        self.inner.get_range_split_points(begin, end, chunk_size)
+    }
+
+    unsafe fn get_read_version(&self) -> MemoryFuture<i64> {
+        self.inner.get_read_version()
+    }
+
+    fn set_option(&self, option: TransactionOption) -> FdbResult<()> {
+        self.inner.set_option(option)
+    }
+
+    unsafe fn set_read_version(&self, version: i64) {
+        self.inner.set_read_version(version)
+    }
+}
+
+// Resolves a key selector against the keys in `view`.
+fn resolve(view: &BTreeMap<Bytes, Bytes>, anchor: &Bytes, or_equal: bool, offset: i32) -> Bytes {
+    if offset > 0 {
+        let after = if or_equal {
+            Bound::Excluded(anchor.clone())
+        } else {
+            Bound::Included(anchor.clone())
+        };
+
+        view.range((after, Bound::Unbounded))
+            .nth((offset - 1) as usize)
+            .map(|(k, _)| k.clone())
+            .unwrap_or_else(|| Bytes::from_static(b"\xff"))
+    } else {
+        let upto = if or_equal {
+            Bound::Included(anchor.clone())
+        } else {
+            Bound::Excluded(anchor.clone())
+        };
+
+        view.range((Bound::Unbounded, upto))
+            .rev()
+            .nth((-i64::from(offset)) as usize)
+            .map(|(k, _)| k.clone())
+            .unwrap_or_default()
+    }
+}
+
+// Builds the mapped key for a key-value. Returns the mapped key, and
+// `true` if the mapper ends with the range suffix.
+#[cfg(feature = "fdb-7_1")]
+fn map_key(mapper: &Tuple, key: &Bytes, value: &Bytes) -> FdbResult<(Bytes, bool)> {
+    let key_tuple = Tuple::from_bytes(key.clone());
+    let value_tuple = Tuple::from_bytes(value.clone());
+
+    let mut t = Tuple::new();
+    let mut is_range = false;
+
+    for i in 0..mapper.size() {
+        if is_range {
+            return Err(FdbError::new(MAPPER_BAD_RANGE_DESCRIPTOR));
+        }
+
+        match mapper.get_string_ref(i) {
+            Ok(s) if s == "{...}" => is_range = true,
+            Ok(s) if s.starts_with("{K[") || s.starts_with("{V[") => {
+                let index = s[3..]
+                    .strip_suffix("]}")
+                    .and_then(|x| x.parse::<usize>().ok())
+                    .ok_or_else(|| FdbError::new(MAPPER_BAD_INDEX))?;
+                let source = if s.starts_with("{K[") {
+                    &key_tuple
+                } else {
+                    &value_tuple
+                };
+                source
+                    .as_ref()
+                    .map_err(|_| FdbError::new(MAPPER_BAD_INDEX))
+                    .and_then(|source| {
+                        t.add_element_from(source, index)
+                            .map_err(|_| FdbError::new(MAPPER_BAD_INDEX))
+                    })?;
+            }
+            Ok(s) => t.add_string(s.replace("{{", "{").replace("}}", "}")),
+            Err(_) => t.add_element_from(mapper, i)?,
+        }
+    }
+
+    Ok((t.pack(), is_range))
+}
+
+fn into_stream<T>(items: Vec<FdbResult<T>>) -> MemoryStream<T>
+where
+    T: Send + 'static,
+{
+    // Like `FdbStreamMaybeValue`, the stream ends after the first
+    // error.
+    let mut items = items;
+    if let Some(i) = items.iter().position(|x| x.is_err()) {
+        items.truncate(i + 1);
+    }
+    stream::iter(items).boxed()
+}
+
+fn into_flat_stream<T>(res: FdbResult<Vec<T>>) -> MemoryStream<T>
+where
+    T: Send + 'static,
+{
+    match res {
+        Ok(items) => into_stream(items.into_iter().map(Ok).collect()),
+        Err(e) => into_stream(vec![Err(e)]),
+    }
+}
+
+fn receive<T>(receiver: oneshot::Receiver<FdbResult<T>>) -> MemoryFuture<T>
+where
+    T: Send + 'static,
+{
+    receiver
+        .map(|res| res.unwrap_or_else(|_| Err(FdbError::new(TRANSACTION_CANCELLED))))
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use tokio_stream::StreamExt;
+
+    use crate::error::FdbError;
+    use crate::memory::MemoryDatabase;
+    use crate::range::{Range, RangeCursor, RangeOptions};
+    use crate::transaction::{MutationType, ReadTransaction, Transaction};
+    use crate::tuple::{Tuple, Versionstamp};
+    use crate::{Key, KeySelector, Value};
+
+    use super::{MemoryReadTransaction, MemoryTransaction};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    MemoryTransaction:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    MemoryReadTransaction:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+    }
+
+    fn value(v: &'static [u8]) -> Option<Value> {
+        Some(Value::from(Bytes::from_static(v)))
+    }
+
+    async fn keys(
+        tr: &MemoryTransaction,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+    ) -> Vec<Bytes> {
+        tr.get_range(begin, end, options)
+            .map(|kv| Bytes::from(kv.unwrap().into_key()))
+            .collect::<Vec<Bytes>>()
+            .await
+    }
+
+    #[tokio::test]
+    async fn read_your_writes() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        tr.set(Bytes::from_static(b"a"), Bytes::from_static(b"1"));
+        tr.set(Bytes::from_static(b"b"), Bytes::from_static(b"2"));
+        tr.set(Bytes::from_static(b"c"), Bytes::from_static(b"3"));
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        tr.clear_range(Range::new(
+            Bytes::from_static(b"a"),
+            Bytes::from_static(b"c"),
+        ));
+        tr.set(Bytes::from_static(b"b"), Bytes::from_static(b"4"));
+
+        assert_eq!(tr.get(Bytes::from_static(b"a")).await.unwrap(), None);
+        assert_eq!(tr.get(Bytes::from_static(b"b")).await.unwrap(), value(b"4"));
+        assert_eq!(tr.get(Bytes::from_static(b"c")).await.unwrap(), value(b"3"));
+
+        let res = keys(
+            &tr,
+            KeySelector::first_greater_or_equal(Bytes::new()),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"\xff")),
+            RangeOptions::default(),
+        )
+        .await;
+        assert_eq!(
+            res,
+            vec![Bytes::from_static(b"b"), Bytes::from_static(b"c")]
+        );
+
+        // Other transactions do not see uncommitted writes.
+        let other = db.create_transaction().unwrap();
+        assert_eq!(
+            other.get(Bytes::from_static(b"a")).await.unwrap(),
+            value(b"1")
+        );
+
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        assert_eq!(tr.get(Bytes::from_static(b"a")).await.unwrap(), None);
+        assert_eq!(tr.get(Bytes::from_static(b"b")).await.unwrap(), value(b"4"));
+    }
+
+    #[tokio::test]
+    async fn conflicts() {
+        let db = MemoryDatabase::new();
+
+        let tr1 = db.create_transaction().unwrap();
+        let tr2 = db.create_transaction().unwrap();
+        let tr3 = db.create_transaction().unwrap();
+
+        tr1.get(Bytes::from_static(b"key")).await.unwrap();
+        tr2.snapshot()
+            .get(Bytes::from_static(b"key"))
+            .await
+            .unwrap();
+        tr3.get(Bytes::from_static(b"other")).await.unwrap();
+
+        let writer = db.create_transaction().unwrap();
+        writer.set(Bytes::from_static(b"key"), Bytes::from_static(b"v"));
+        unsafe { writer.commit() }.await.unwrap();
+
+        for tr in &[&tr1, &tr2, &tr3] {
+            tr.set(Bytes::from_static(b"x"), Bytes::from_static(b"v"));
+        }
+
+        // Serializable read of a key written after the read version.
+        assert_eq!(unsafe { tr1.commit() }.await, Err(FdbError::new(1020)));
+        // Snapshot read does not conflict.
+        unsafe { tr2.commit() }.await.unwrap();
+        // Read of a different key does not conflict.
+        unsafe { tr3.commit() }.await.unwrap();
+
+        // `on_error` resets the transaction, so the retry succeeds.
+        unsafe { tr1.on_error(FdbError::new(1020)) }.await.unwrap();
+        tr1.get(Bytes::from_static(b"key")).await.unwrap();
+        unsafe { tr1.commit() }.await.unwrap();
+
+        assert_eq!(
+            unsafe { tr1.on_error(FdbError::new(2000)) }.await,
+            Err(FdbError::new(2000))
+        );
+    }
+
+    #[tokio::test]
+    async fn range_limit_conflict() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        for k in &[b"a", b"b", b"c"] {
+            tr.set(Bytes::from_static(*k), Bytes::new());
+        }
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        let mut options = RangeOptions::default();
+        options.set_limit(1);
+        let res = keys(
+            &tr,
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"a")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"z")),
+            options,
+        )
+        .await;
+        assert_eq!(res, vec![Bytes::from_static(b"a")]);
+        tr.set(Bytes::from_static(b"x"), Bytes::new());
+
+        // Write outside of the keys returned does not conflict.
+        let writer = db.create_transaction().unwrap();
+        writer.clear(Bytes::from_static(b"c"));
+        unsafe { writer.commit() }.await.unwrap();
+
+        unsafe { tr.commit() }.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn get_many() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        for k in &[b"a", b"b", b"c"] {
+            tr.set(Bytes::from_static(*k), Bytes::from_static(*k));
+        }
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        tr.clear(Bytes::from_static(b"b"));
+
+        // Values are returned in the order of the keys, including
+        // the writes of the transaction.
+        let res = tr
+            .get_many(
+                vec![
+                    Bytes::from_static(b"c"),
+                    Bytes::from_static(b"x"),
+                    Bytes::from_static(b"b"),
+                    Bytes::from_static(b"a"),
+                ],
+                2,
+            )
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            res,
+            vec![Ok(value(b"c")), Ok(None), Ok(None), Ok(value(b"a"))]
+        );
+
+        let res = tr
+            .get_key_many(
+                vec![
+                    KeySelector::first_greater_than(Bytes::from_static(b"a")),
+                    KeySelector::last_less_than(Bytes::from_static(b"z")),
+                ],
+                0,
+            )
+            .map(|k| Bytes::from(k.unwrap()))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(
+            res,
+            vec![Bytes::from_static(b"c"), Bytes::from_static(b"c")]
+        );
+    }
+
+    #[tokio::test]
+    async fn key_selectors() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        for k in &[b"b", b"d", b"f"] {
+            tr.set(Bytes::from_static(*k), Bytes::new());
+        }
+
+        let get_key = |selector| {
+            let tr = tr.clone();
+            async move { Bytes::from(tr.get_key(selector).await.unwrap()) }
+        };
+
+        assert_eq!(
+            get_key(KeySelector::first_greater_or_equal(Bytes::from_static(
+                b"d"
+            )))
+            .await,
+            Bytes::from_static(b"d")
+        );
+        assert_eq!(
+            get_key(KeySelector::first_greater_than(Bytes::from_static(b"d"))).await,
+            Bytes::from_static(b"f")
+        );
+        assert_eq!(
+            get_key(KeySelector::last_less_than(Bytes::from_static(b"d"))).await,
+            Bytes::from_static(b"b")
+        );
+        assert_eq!(
+            get_key(KeySelector::last_less_or_equal(Bytes::from_static(b"d"))).await,
+            Bytes::from_static(b"d")
+        );
+        assert_eq!(
+            get_key(KeySelector::first_greater_or_equal(Bytes::from_static(b"c")).add(1)).await,
+            Bytes::from_static(b"f")
+        );
+        assert_eq!(
+            get_key(KeySelector::last_less_than(Bytes::from_static(b"b"))).await,
+            Bytes::new()
+        );
+        assert_eq!(
+            get_key(KeySelector::first_greater_than(Bytes::from_static(b"f"))).await,
+            Bytes::from_static(b"\xff")
+        );
+
+        let mut options = RangeOptions::default();
+        options.set_reverse(true);
+        let res = keys(
+            &tr,
+            KeySelector::first_greater_than(Bytes::from_static(b"b")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"\xff")),
+            options,
+        )
+        .await;
+        assert_eq!(
+            res,
+            vec![Bytes::from_static(b"f"), Bytes::from_static(b"d")]
+        );
+    }
+
+    #[tokio::test]
+    async fn range_cursor() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        for k in &[b"a", b"b", b"c"] {
+            tr.set(Bytes::from_static(*k), Bytes::new());
+        }
+        unsafe { tr.commit() }.await.unwrap();
+
+        let begin = KeySelector::first_greater_or_equal(Bytes::from_static(b"a"));
+        let end = KeySelector::first_greater_or_equal(Bytes::from_static(b"z"));
+
+        let tr = db.create_transaction().unwrap();
+        let mut cursor = RangeCursor::new(&tr, begin, end, RangeOptions::default());
+        let kv = cursor.next().await.unwrap().unwrap();
+        assert_eq!(Bytes::from(kv.into_key()), Bytes::from_static(b"a"));
+
+        // Resume on a new transaction.
+        let continuation = cursor.get_continuation();
+        let tr = db.create_transaction().unwrap();
+        let res = RangeCursor::from_continuation(&tr, continuation)
+            .map(|kv| Bytes::from(kv.unwrap().into_key()))
+            .collect::<Vec<Bytes>>()
+            .await;
+        assert_eq!(
+            res,
+            vec![Bytes::from_static(b"b"), Bytes::from_static(b"c")]
+        );
+    }
+
+    #[tokio::test]
+    async fn atomic_mutations() {
+        let db = MemoryDatabase::new();
+        let key = || Bytes::from_static(b"counter");
+        let one = || Bytes::copy_from_slice(&1_i64.to_le_bytes());
+
+        let tr = db.create_transaction().unwrap();
+        unsafe { tr.mutate(MutationType::Add, key(), one()) };
+        unsafe { tr.commit() }.await.unwrap();
+
+        let tr1 = db.create_transaction().unwrap();
+        let tr2 = db.create_transaction().unwrap();
+        unsafe { tr1.get_read_version() }.await.unwrap();
+        unsafe { tr2.get_read_version() }.await.unwrap();
+
+        unsafe { tr1.mutate(MutationType::Add, key(), one()) };
+        unsafe { tr2.mutate(MutationType::Add, key(), one()) };
+        assert_eq!(
+            tr1.get(key()).await.unwrap(),
+            Some(Value::from(Bytes::copy_from_slice(&2_i64.to_le_bytes())))
+        );
+
+        // Atomic mutations do not conflict with each other.
+        unsafe { tr1.commit() }.await.unwrap();
+        unsafe { tr2.commit() }.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        assert_eq!(
+            tr.get(key()).await.unwrap(),
+            Some(Value::from(Bytes::copy_from_slice(&3_i64.to_le_bytes())))
+        );
+    }
+
+    #[tokio::test]
+    async fn versionstamps() {
+        let db = MemoryDatabase::new();
+
+        let mut t = Tuple::new();
+        t.add_versionstamp(Versionstamp::incomplete(7));
+        let key = t.pack_with_versionstamp(Bytes::from_static(b"p")).unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        unsafe {
+            tr.mutate(
+                MutationType::SetVersionstampedKey,
+                key,
+                Bytes::from_static(b"v"),
+            )
+        };
+        let versionstamp = unsafe { tr.get_versionstamp() };
+        unsafe { tr.commit() }.await.unwrap();
+        let versionstamp = versionstamp.await.unwrap();
+
+        let tr = db.create_transaction().unwrap();
+        let kvs = tr
+            .get_range(
+                KeySelector::first_greater_or_equal(Bytes::from_static(b"p")),
+                KeySelector::first_greater_or_equal(Bytes::from_static(b"q")),
+                RangeOptions::default(),
+            )
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(kvs.len(), 1);
+
+        let key = Bytes::from(kvs[0].as_ref().unwrap().get_key_ref().clone());
+        let t = Tuple::from_bytes(key.slice(1..)).unwrap();
+        let v = t.get_versionstamp_ref(0).unwrap();
+        assert_eq!(v.get_transaction_version(), versionstamp);
+        assert_eq!(v.get_user_version(), 7);
+
+        // Read-only transaction does not have a versionstamp.
+        let tr = db.create_transaction().unwrap();
+        let versionstamp = unsafe { tr.get_versionstamp() };
+        unsafe { tr.commit() }.await.unwrap();
+        assert_eq!(versionstamp.await, Err(FdbError::new(2021)));
+    }
+
+    #[tokio::test]
+    async fn watches() {
+        let db = MemoryDatabase::new();
+        let key = || Bytes::from_static(b"key");
+
+        let tr = db.create_transaction().unwrap();
+        let watch = tr.watch(key());
+        unsafe { tr.commit() }.await.unwrap();
+
+        // Write that does not change the value does not trigger the
+        // watch.
+        let tr = db.create_transaction().unwrap();
+        tr.clear(key());
+        unsafe { tr.commit() }.await.unwrap();
+
+        let handle = tokio::spawn(watch);
+
+        let tr = db.create_transaction().unwrap();
+        tr.set(key(), Bytes::from_static(b"v"));
+        unsafe { tr.commit() }.await.unwrap();
+
+        handle.await.unwrap().unwrap();
+
+        // Watch of a transaction that is reset is cancelled.
+        let tr = db.create_transaction().unwrap();
+        let watch = tr.watch(key());
+        unsafe { tr.reset() };
+        assert_eq!(watch.await, Err(FdbError::new(1025)));
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    #[tokio::test]
+    async fn mapped_range() {
+        use crate::MapperBuilder;
+
+        let db = MemoryDatabase::new();
+
+        let pack = |prefix: &str, id: i64| {
+            let mut t = Tuple::new();
+            t.add_string(prefix.to_string());
+            t.add_i64(id);
+            t.pack()
+        };
+
+        let tr = db.create_transaction().unwrap();
+        for id in 0..3 {
+            tr.set(pack("index", id), Bytes::new());
+            tr.set(pack("record", id), Bytes::from(format!("record {}", id)));
+        }
+
+        let mut builder = MapperBuilder::new();
+        builder.add_literal_string(String::from("record"));
+        builder.add_key_element(1);
+        builder.set_range(true);
+        let mapper = builder.build().unwrap();
+
+        let mkvs = tr
+            .get_mapped_range(
+                KeySelector::first_greater_or_equal(pack("index", 1)),
+                KeySelector::first_greater_or_equal(pack("index", 3)),
+                mapper,
+                RangeOptions::default(),
+            )
+            .collect::<Vec<_>>()
+            .await;
+
+        assert_eq!(mkvs.len(), 2);
+        for (id, mkv) in (1..3).zip(mkvs) {
+            let range_result = mkv.unwrap().into_range_result();
+            assert_eq!(range_result.len(), 1);
+            assert_eq!(
+                Bytes::from(range_result[0].get_value_ref().clone()),
+                Bytes::from(format!("record {}", id))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel() {
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        unsafe { tr.cancel() };
+        assert_eq!(
+            tr.get(Key::from(Bytes::from_static(b"key"))).await,
+            Err(FdbError::new(1025))
+        );
+        assert_eq!(unsafe { tr.commit() }.await, Err(FdbError::new(1025)));
+    }
+
+    #[tokio::test]
+    async fn deferred_errors() {
+        let db = MemoryDatabase::new();
+
+        // `key_too_large` from `set` is reported on the next read and
+        // on commit.
+        let tr = db.create_transaction().unwrap();
+        tr.set(Bytes::from(vec![0; 10_001]), Bytes::from_static(b"v"));
+        assert_eq!(
+            tr.get(Key::from(Bytes::from_static(b"key"))).await,
+            Err(FdbError::new(2102))
+        );
+        assert_eq!(unsafe { tr.commit() }.await, Err(FdbError::new(2102)));
+    }
+}
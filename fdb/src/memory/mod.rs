@@ -0,0 +1,59 @@
+//! Provides [`MemoryDatabase`] type, an in-memory implementation of
+//! the database and transaction API for unit tests.
+//!
+//! [`MemoryDatabase`] keeps an ordered map of keys and values in
+//! memory, so it does not need a running FDB cluster. This module is
+//! available when the `memory` feature is enabled.
+//!
+//! [`MemoryTransaction`] implements [`ReadTransaction`] and
+//! [`Transaction`] traits, and [`MemoryReadTransaction`] implements
+//! [`ReadTransaction`] trait. Code that is generic over these traits,
+//! such as the [layers], can be tested using [`MemoryDatabase`].
+//!
+//! [`MemoryTransaction`] models the following FDB behaviour.
+//!
+//! - Reads see the writes previously done in the same transaction
+//!   (read-your-writes).
+//!
+//! - Reads happen at the read version of the transaction. On commit,
+//!   if any key read by a serializable read was written by a
+//!   transaction that committed after the read version, commit fails
+//!   with `not_committed` (`1020`) error. Snapshot reads, done using
+//!   [`MemoryReadTransaction`], do not cause conflicts.
+//!
+//! - Atomic mutations, including versionstamped keys and values, are
+//!   applied on commit.
+//!
+//! - Watches become ready when the value of the key changes after
+//!   the transaction that created the watch commits.
+//!
+//! - [`KeySelector`]s are resolved against the keys visible to the
+//!   transaction.
+//!
+//! Transaction options are accepted and ignored. Each commit
+//! increments the database version by `1`.
+//!
+//! [`ReadTransaction`]: crate::transaction::ReadTransaction
+//! [`Transaction`]: crate::transaction::Transaction
+//! [layers]: crate::layer
+//! [`KeySelector`]: crate::KeySelector
+
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+
+use crate::error::FdbResult;
+
+mod memory_database;
+mod memory_transaction;
+mod store;
+
+pub use memory_database::MemoryDatabase;
+pub use memory_transaction::{MemoryReadTransaction, MemoryTransaction};
+
+/// Future returned by [`MemoryTransaction`] and
+/// [`MemoryReadTransaction`] methods.
+pub type MemoryFuture<T> = BoxFuture<'static, FdbResult<T>>;
+
+/// Stream returned by [`MemoryTransaction`] and
+/// [`MemoryReadTransaction`] methods.
+pub type MemoryStream<T> = BoxStream<'static, FdbResult<T>>;
@@ -0,0 +1,366 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use tokio::sync::oneshot;
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::ops::Bound;
+
+use crate::error::FdbResult;
+use crate::transaction::MutationType;
+
+// Maximum size of a value after `AppendIfFits`.
+const VALUE_SIZE_LIMIT: usize = 100000;
+
+// Values of a key in increasing order of version. `None` means that
+// the key was cleared at that version.
+type History = Vec<(i64, Option<Bytes>)>;
+
+#[derive(Debug)]
+struct Watch {
+    key: Bytes,
+    value: Option<Bytes>,
+    sender: oneshot::Sender<FdbResult<()>>,
+}
+
+// Committed state of a `MemoryDatabase`.
+//
+// Every committed version of every key is kept, so that transactions
+// can read at their read version. `commits` holds the write conflict
+// ranges of each commit, which are checked against the read conflict
+// ranges of later commits.
+#[derive(Debug, Default)]
+pub(super) struct Store {
+    version: i64,
+    data: BTreeMap<Bytes, History>,
+    commits: Vec<(i64, Vec<(Bytes, Bytes)>)>,
+    watches: Vec<Watch>,
+}
+
+impl Store {
+    pub(super) fn get_version(&self) -> i64 {
+        self.version
+    }
+
+    pub(super) fn get(&self, key: &Bytes, version: i64) -> Option<Bytes> {
+        self.data.get(key).and_then(|h| value_at(h, version))
+    }
+
+    pub(super) fn get_latest(&self, key: &Bytes) -> Option<Bytes> {
+        self.get(key, self.version)
+    }
+
+    pub(super) fn range(
+        &self,
+        begin: Bound<Bytes>,
+        end: Bound<Bytes>,
+        version: i64,
+    ) -> BTreeMap<Bytes, Bytes> {
+        self.data
+            .range((begin, end))
+            .filter_map(|(k, h)| value_at(h, version).map(|v| (k.clone(), v)))
+            .collect()
+    }
+
+    // Returns `true` if a commit after `read_version` wrote to any of
+    // `read_ranges`.
+    pub(super) fn conflicts(&self, read_version: i64, read_ranges: &[(Bytes, Bytes)]) -> bool {
+        self.commits
+            .iter()
+            .rev()
+            .take_while(|(version, _)| *version > read_version)
+            .any(|(_, write_ranges)| {
+                write_ranges
+                    .iter()
+                    .any(|w| read_ranges.iter().any(|r| intersects(r, w)))
+            })
+    }
+
+    pub(super) fn set(&mut self, key: Bytes, value: Option<Bytes>, version: i64) {
+        self.data.entry(key).or_default().push((version, value));
+    }
+
+    pub(super) fn clear_range(&mut self, begin: Bytes, end: Bytes, version: i64) {
+        if begin < end {
+            for (_, h) in self.data.range_mut(begin..end) {
+                if value_at(h, version).is_some() {
+                    h.push((version, None));
+                }
+            }
+        }
+    }
+
+    pub(super) fn finish_commit(&mut self, version: i64, write_ranges: Vec<(Bytes, Bytes)>) {
+        self.version = version;
+        self.commits.push((version, write_ranges));
+        self.fire_watches();
+    }
+
+    pub(super) fn add_watch(
+        &mut self,
+        key: Bytes,
+        value: Option<Bytes>,
+        sender: oneshot::Sender<FdbResult<()>>,
+    ) {
+        self.watches.push(Watch { key, value, sender });
+        self.fire_watches();
+    }
+
+    fn fire_watches(&mut self) {
+        let watches = std::mem::take(&mut self.watches);
+
+        for watch in watches {
+            if self.get_latest(&watch.key) != watch.value {
+                let _ = watch.sender.send(Ok(()));
+            } else if !watch.sender.is_closed() {
+                self.watches.push(watch);
+            }
+        }
+    }
+}
+
+fn value_at(h: &[(i64, Option<Bytes>)], version: i64) -> Option<Bytes> {
+    h.iter()
+        .rev()
+        .find(|(v, _)| *v <= version)
+        .and_then(|(_, value)| value.clone())
+}
+
+pub(super) fn intersects(a: &(Bytes, Bytes), b: &(Bytes, Bytes)) -> bool {
+    a.0 < b.1 && b.0 < a.1
+}
+
+// Returns the key immediately following `key`.
+pub(super) fn key_after(key: &Bytes) -> Bytes {
+    let mut b = BytesMut::with_capacity(key.len() + 1);
+    b.put(key.clone());
+    b.put_u8(0x00);
+    b.into()
+}
+
+// Applies an atomic mutation to `existing`, returning the new value.
+//
+// Versionstamp mutations are applied at commit time and are not
+// handled here.
+pub(super) fn apply_mutation(
+    optype: MutationType,
+    existing: Option<Bytes>,
+    param: &Bytes,
+) -> Option<Bytes> {
+    match optype {
+        MutationType::Add => {
+            let e = resize(existing.as_deref().unwrap_or(&[]), param.len());
+            let mut carry = 0_u16;
+            let res = e
+                .iter()
+                .zip(param.iter())
+                .map(|(a, b)| {
+                    let s = u16::from(*a) + u16::from(*b) + carry;
+                    carry = s >> 8;
+                    s as u8
+                })
+                .collect::<Vec<u8>>();
+            Some(res.into())
+        }
+        MutationType::And | MutationType::BitAnd => Some(match existing {
+            Some(e) => bitwise(&e, param, |a, b| a & b),
+            None => param.clone(),
+        }),
+        MutationType::Or | MutationType::BitOr => Some(match existing {
+            Some(e) => bitwise(&e, param, |a, b| a | b),
+            None => param.clone(),
+        }),
+        MutationType::Xor | MutationType::BitXor => Some(match existing {
+            Some(e) => bitwise(&e, param, |a, b| a ^ b),
+            None => param.clone(),
+        }),
+        MutationType::AppendIfFits => Some(match existing {
+            Some(e) if e.len() + param.len() > VALUE_SIZE_LIMIT => e,
+            Some(e) => {
+                let mut b = BytesMut::with_capacity(e.len() + param.len());
+                b.put(e);
+                b.put(param.clone());
+                b.into()
+            }
+            None => param.clone(),
+        }),
+        MutationType::Max | MutationType::Min => Some(match existing {
+            Some(e) => {
+                let e = resize(&e, param.len());
+                // Compare as little-endian unsigned integers.
+                let e_greater = e.iter().rev().cmp(param.iter().rev()) == Ordering::Greater;
+                if e_greater == matches!(optype, MutationType::Max) {
+                    e.into()
+                } else {
+                    param.clone()
+                }
+            }
+            None => param.clone(),
+        }),
+        MutationType::ByteMin => Some(match existing {
+            Some(e) if e < *param => e,
+            _ => param.clone(),
+        }),
+        MutationType::ByteMax => Some(match existing {
+            Some(e) if e > *param => e,
+            _ => param.clone(),
+        }),
+        MutationType::CompareAndClear => existing.filter(|e| e != param),
+        MutationType::SetVersionstampedKey | MutationType::SetVersionstampedValue => existing,
+    }
+}
+
+// Replaces the 10 bytes at the offset given by the last 4 bytes of
+// `b` with `versionstamp`, and removes the offset. Returns `None` if
+// the offset is invalid.
+pub(super) fn fill_versionstamp(b: &Bytes, versionstamp: &[u8]) -> Option<Bytes> {
+    if b.len() < 4 {
+        return None;
+    }
+
+    let (body, offset) = b.split_at(b.len() - 4);
+    let mut offset_bytes = [0_u8; 4];
+    offset_bytes.copy_from_slice(offset);
+    let offset = u32::from_le_bytes(offset_bytes) as usize;
+
+    if offset + versionstamp.len() > body.len() {
+        return None;
+    }
+
+    let mut res = body.to_vec();
+    res[offset..offset + versionstamp.len()].copy_from_slice(versionstamp);
+    Some(res.into())
+}
+
+fn resize(b: &[u8], len: usize) -> Vec<u8> {
+    let mut res = b.to_vec();
+    res.resize(len, 0x00);
+    res
+}
+
+fn bitwise(existing: &[u8], param: &Bytes, f: impl Fn(u8, u8) -> u8) -> Bytes {
+    resize(existing, param.len())
+        .iter()
+        .zip(param.iter())
+        .map(|(a, b)| f(*a, *b))
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::transaction::MutationType;
+
+    use super::{apply_mutation, fill_versionstamp, Store};
+
+    #[test]
+    fn mutations() {
+        let add = |e: Option<&'static [u8]>, p: &'static [u8]| {
+            apply_mutation(
+                MutationType::Add,
+                e.map(Bytes::from_static),
+                &Bytes::from_static(p),
+            )
+        };
+
+        assert_eq!(add(None, &[1, 0]), Some(Bytes::from_static(&[1, 0])));
+        assert_eq!(
+            add(Some(&[0xff, 0x00, 0x05]), &[1, 0]),
+            Some(Bytes::from_static(&[0, 1]))
+        );
+        assert_eq!(
+            add(Some(&[0xff]), &[0xff, 0xff]),
+            Some(Bytes::from_static(&[0xfe, 0x00]))
+        );
+
+        let max = apply_mutation(
+            MutationType::Max,
+            Some(Bytes::from_static(&[0x00, 0x01])),
+            &Bytes::from_static(&[0xff, 0x00]),
+        );
+        assert_eq!(max, Some(Bytes::from_static(&[0x00, 0x01])));
+
+        let min = apply_mutation(
+            MutationType::Min,
+            Some(Bytes::from_static(&[0x00, 0x01])),
+            &Bytes::from_static(&[0xff, 0x00]),
+        );
+        assert_eq!(min, Some(Bytes::from_static(&[0xff, 0x00])));
+
+        let byte_min = apply_mutation(
+            MutationType::ByteMin,
+            Some(Bytes::from_static(b"abc")),
+            &Bytes::from_static(b"ab"),
+        );
+        assert_eq!(byte_min, Some(Bytes::from_static(b"ab")));
+
+        let bit_and = apply_mutation(
+            MutationType::BitAnd,
+            Some(Bytes::from_static(&[0x0f])),
+            &Bytes::from_static(&[0xff, 0xff]),
+        );
+        assert_eq!(bit_and, Some(Bytes::from_static(&[0x0f, 0x00])));
+
+        let append = apply_mutation(
+            MutationType::AppendIfFits,
+            Some(Bytes::from_static(b"ab")),
+            &Bytes::from_static(b"cd"),
+        );
+        assert_eq!(append, Some(Bytes::from_static(b"abcd")));
+
+        assert_eq!(
+            apply_mutation(
+                MutationType::CompareAndClear,
+                Some(Bytes::from_static(b"ab")),
+                &Bytes::from_static(b"ab"),
+            ),
+            None
+        );
+        assert_eq!(
+            apply_mutation(
+                MutationType::CompareAndClear,
+                Some(Bytes::from_static(b"ab")),
+                &Bytes::from_static(b"cd"),
+            ),
+            Some(Bytes::from_static(b"ab"))
+        );
+    }
+
+    #[test]
+    fn versionstamp() {
+        let b = Bytes::from_static(&[b'a', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, b'z', 1, 0, 0, 0]);
+        assert_eq!(
+            fill_versionstamp(&b, &[1, 2, 3, 4, 5, 6, 7, 8, 9, 10]),
+            Some(Bytes::from_static(&[
+                b'a', 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, b'z'
+            ]))
+        );
+
+        let b = Bytes::from_static(&[b'a', 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, b'z', 3, 0, 0, 0]);
+        assert_eq!(fill_versionstamp(&b, &[0; 10]), None);
+    }
+
+    #[test]
+    fn store_versions() {
+        let mut store = Store::default();
+        let key = Bytes::from_static(b"key");
+
+        store.set(key.clone(), Some(Bytes::from_static(b"v1")), 1);
+        store.finish_commit(1, vec![(key.clone(), Bytes::from_static(b"key\x00"))]);
+
+        store.clear_range(Bytes::from_static(b"a"), Bytes::from_static(b"z"), 2);
+        store.finish_commit(
+            2,
+            vec![(Bytes::from_static(b"a"), Bytes::from_static(b"z"))],
+        );
+
+        assert_eq!(store.get(&key, 0), None);
+        assert_eq!(store.get(&key, 1), Some(Bytes::from_static(b"v1")));
+        assert_eq!(store.get_latest(&key), None);
+
+        assert!(store.conflicts(1, &[(key.clone(), Bytes::from_static(b"key\x00"))]));
+        assert!(!store.conflicts(2, &[(key, Bytes::from_static(b"key\x00"))]));
+    }
+}
@@ -0,0 +1,147 @@
+use parking_lot::{const_mutex, Mutex};
+
+use std::convert::TryInto;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::error::FdbError;
+use crate::metrics::histogram::Histogram;
+use crate::metrics::{MetricsSnapshot, Operation};
+
+// Metrics for all the transactions in the process.
+pub(super) static CLIENT_METRICS: ClientMetrics = ClientMetrics::new();
+
+// `errors` is a list of error code and count, ordered by error
+// code. There are only a few distinct error codes that are passed to
+// `on_error`, so we use a `Vec` instead of a map.
+#[derive(Debug)]
+pub(super) struct ClientMetrics {
+    get_latency: Histogram,
+    get_range_latency: Histogram,
+    commit_latency: Histogram,
+    get_read_version_latency: Histogram,
+    retries: AtomicU64,
+    errors: Mutex<Vec<(i32, u64)>>,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    active_transactions: AtomicU64,
+}
+
+impl ClientMetrics {
+    pub(super) const fn new() -> ClientMetrics {
+        ClientMetrics {
+            get_latency: Histogram::new(),
+            get_range_latency: Histogram::new(),
+            commit_latency: Histogram::new(),
+            get_read_version_latency: Histogram::new(),
+            retries: AtomicU64::new(0),
+            errors: const_mutex(Vec::new()),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            active_transactions: AtomicU64::new(0),
+        }
+    }
+
+    pub(super) fn record_latency(&self, operation: Operation, latency: Duration) {
+        match operation {
+            Operation::Get => &self.get_latency,
+            Operation::GetRange => &self.get_range_latency,
+            Operation::Commit => &self.commit_latency,
+            Operation::GetReadVersion => &self.get_read_version_latency,
+        }
+        .record(latency);
+    }
+
+    pub(super) fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn record_error(&self, e: FdbError) {
+        let mut errors = self.errors.lock();
+
+        match errors.binary_search_by_key(&e.code(), |(code, _)| *code) {
+            Ok(i) => errors[i].1 += 1,
+            Err(i) => errors.insert(i, (e.code(), 1)),
+        }
+    }
+
+    pub(super) fn record_bytes_read(&self, bytes: usize) {
+        self.bytes_read
+            .fetch_add(bytes.try_into().unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    pub(super) fn record_bytes_written(&self, bytes: usize) {
+        self.bytes_written
+            .fetch_add(bytes.try_into().unwrap_or(u64::MAX), Ordering::Relaxed);
+    }
+
+    pub(super) fn transaction_created(&self) {
+        self.active_transactions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn transaction_destroyed(&self) {
+        self.active_transactions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(super) fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            get_latency: self.get_latency.snapshot(),
+            get_range_latency: self.get_range_latency.snapshot(),
+            commit_latency: self.commit_latency.snapshot(),
+            get_read_version_latency: self.get_read_version_latency.snapshot(),
+            retries: self.retries.load(Ordering::Relaxed),
+            errors: self
+                .errors
+                .lock()
+                .iter()
+                .map(|(code, count)| (FdbError::new(*code), *count))
+                .collect(),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            active_transactions: self.active_transactions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::error::FdbError;
+    use crate::metrics::Operation;
+
+    use super::ClientMetrics;
+
+    // Tests use their own `ClientMetrics` instead of
+    // `CLIENT_METRICS`, as tests run concurrently.
+    #[test]
+    fn record() {
+        let client_metrics = ClientMetrics::new();
+
+        client_metrics.record_latency(Operation::Commit, Duration::from_millis(3));
+        client_metrics.record_retry();
+        client_metrics.record_retry();
+        client_metrics.record_error(FdbError::new(1020));
+        client_metrics.record_error(FdbError::new(1007));
+        client_metrics.record_error(FdbError::new(1020));
+        client_metrics.record_bytes_read(10);
+        client_metrics.record_bytes_written(20);
+        client_metrics.record_bytes_written(5);
+        client_metrics.transaction_created();
+        client_metrics.transaction_created();
+        client_metrics.transaction_destroyed();
+
+        let snapshot = client_metrics.snapshot();
+
+        assert_eq!(snapshot.get_latency(Operation::Commit).get_count(), 1);
+        assert_eq!(snapshot.get_latency(Operation::Get).get_count(), 0);
+        assert_eq!(snapshot.get_retries(), 2);
+        assert_eq!(
+            snapshot.get_errors(),
+            &[(FdbError::new(1007), 1), (FdbError::new(1020), 2)][..]
+        );
+        assert_eq!(snapshot.get_bytes_read(), 10);
+        assert_eq!(snapshot.get_bytes_written(), 25);
+        assert_eq!(snapshot.get_active_transactions(), 1);
+    }
+}
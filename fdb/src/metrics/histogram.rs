@@ -0,0 +1,167 @@
+use std::convert::TryInto;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// Upper bounds of the histogram buckets. Latencies larger than the
+// last bound are only included in the count and sum.
+const BUCKET_BOUNDS: [Duration; 15] = [
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_micros(2500),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(25),
+    Duration::from_millis(50),
+    Duration::from_millis(100),
+    Duration::from_millis(250),
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_millis(2500),
+    Duration::from_secs(5),
+];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+// A latency histogram with fixed buckets. Atomics are used so that
+// recording a latency does not need a lock.
+//
+// `buckets[i]` is the number of latencies that are less than or equal
+// to `BUCKET_BOUNDS[i]`, and larger than `BUCKET_BOUNDS[i - 1]`.
+#[derive(Debug)]
+pub(super) struct Histogram {
+    buckets: [AtomicU64; 15],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub(super) const fn new() -> Histogram {
+        Histogram {
+            buckets: [ZERO; 15],
+            count: ZERO,
+            sum_micros: ZERO,
+        }
+    }
+
+    pub(super) fn record(&self, latency: Duration) {
+        if let Some(i) = BUCKET_BOUNDS.iter().position(|bound| latency <= *bound) {
+            self.buckets[i].fetch_add(1, Ordering::Relaxed);
+        }
+
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros.fetch_add(
+            latency.as_micros().try_into().unwrap_or(u64::MAX),
+            Ordering::Relaxed,
+        );
+    }
+
+    pub(super) fn snapshot(&self) -> HistogramSnapshot {
+        let mut cumulative = 0;
+
+        let buckets = BUCKET_BOUNDS
+            .iter()
+            .zip(self.buckets.iter())
+            .map(|(bound, bucket)| {
+                cumulative += bucket.load(Ordering::Relaxed);
+                (*bound, cumulative)
+            })
+            .collect();
+
+        HistogramSnapshot {
+            buckets,
+            count: self.count.load(Ordering::Relaxed),
+            sum: Duration::from_micros(self.sum_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// A snapshot of a latency histogram.
+///
+/// Each bucket has an upper bound and the number of latencies that
+/// are less than or equal to the upper bound. Bucket counts are
+/// cumulative, which matches the [Prometheus] histogram type. The
+/// total count includes latencies that are larger than the last upper
+/// bound.
+///
+/// See [module] documentation for details.
+///
+/// [Prometheus]: https://prometheus.io/docs/concepts/metric_types/#histogram
+/// [module]: crate::metrics
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramSnapshot {
+    buckets: Vec<(Duration, u64)>,
+    count: u64,
+    sum: Duration,
+}
+
+impl HistogramSnapshot {
+    /// Gets the buckets, as pairs of upper bound and cumulative
+    /// count.
+    pub fn get_buckets(&self) -> &[(Duration, u64)] {
+        &self.buckets
+    }
+
+    /// Gets the number of latencies recorded.
+    pub fn get_count(&self) -> u64 {
+        self.count
+    }
+
+    /// Gets the sum of latencies recorded, with microsecond
+    /// precision.
+    pub fn get_sum(&self) -> Duration {
+        self.sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use std::time::Duration;
+
+    use super::{Histogram, HistogramSnapshot};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    HistogramSnapshot:
+	        Send &
+		Sync &
+		Clone &
+		PartialEq &
+		!Copy));
+    }
+
+    #[test]
+    fn record() {
+        let histogram = Histogram::new();
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.get_buckets().len(), 15);
+        assert!(snapshot.get_buckets().iter().all(|(_, count)| *count == 0));
+        assert_eq!(snapshot.get_count(), 0);
+        assert_eq!(snapshot.get_sum(), Duration::from_micros(0));
+
+        histogram.record(Duration::from_micros(50));
+        histogram.record(Duration::from_micros(100));
+        histogram.record(Duration::from_millis(3));
+        histogram.record(Duration::from_secs(10));
+
+        let snapshot = histogram.snapshot();
+        let buckets = snapshot.get_buckets();
+
+        assert_eq!(buckets[0], (Duration::from_micros(100), 2));
+        assert_eq!(buckets[4], (Duration::from_micros(2500), 2));
+        assert_eq!(buckets[5], (Duration::from_millis(5), 3));
+        assert_eq!(buckets[14], (Duration::from_secs(5), 3));
+        assert_eq!(snapshot.get_count(), 4);
+        assert_eq!(
+            snapshot.get_sum(),
+            Duration::from_micros(50 + 100 + 3_000 + 10_000_000)
+        );
+    }
+}
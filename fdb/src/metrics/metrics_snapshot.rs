@@ -0,0 +1,85 @@
+use crate::error::FdbError;
+use crate::metrics::{HistogramSnapshot, Operation};
+
+/// A snapshot of the client metrics.
+///
+/// A [`MetricsSnapshot`] can be obtained using [`snapshot`]
+/// function.
+///
+/// See [module] documentation for details.
+///
+/// [`snapshot`]: crate::metrics::snapshot
+/// [module]: crate::metrics
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricsSnapshot {
+    pub(super) get_latency: HistogramSnapshot,
+    pub(super) get_range_latency: HistogramSnapshot,
+    pub(super) commit_latency: HistogramSnapshot,
+    pub(super) get_read_version_latency: HistogramSnapshot,
+    pub(super) retries: u64,
+    pub(super) errors: Vec<(FdbError, u64)>,
+    pub(super) bytes_read: u64,
+    pub(super) bytes_written: u64,
+    pub(super) active_transactions: u64,
+}
+
+impl MetricsSnapshot {
+    /// Gets the latency histogram for `operation`.
+    pub fn get_latency(&self, operation: Operation) -> &HistogramSnapshot {
+        match operation {
+            Operation::Get => &self.get_latency,
+            Operation::GetRange => &self.get_range_latency,
+            Operation::Commit => &self.commit_latency,
+            Operation::GetReadVersion => &self.get_read_version_latency,
+        }
+    }
+
+    /// Gets the number of times the closure passed to `run` or
+    /// `read` was retried.
+    pub fn get_retries(&self) -> u64 {
+        self.retries
+    }
+
+    /// Gets the number of errors passed to `on_error`, for each
+    /// error, ordered by error code.
+    pub fn get_errors(&self) -> &[(FdbError, u64)] {
+        &self.errors
+    }
+
+    /// Gets the number of bytes of keys and values read by `get`
+    /// and range reads.
+    pub fn get_bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Gets the number of bytes of keys, values and parameters passed
+    /// to `set`, `mutate`, `clear` and `clear_range`.
+    pub fn get_bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+
+    /// Gets the number of transactions that have been created and
+    /// not yet destroyed.
+    pub fn get_active_transactions(&self) -> u64 {
+        self.active_transactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use super::MetricsSnapshot;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    MetricsSnapshot:
+	        Send &
+		Sync &
+		Clone &
+		PartialEq &
+		!Copy));
+    }
+}
@@ -0,0 +1,104 @@
+//! Provides client metrics for FDB operations.
+//!
+//! When `metrics` feature is enabled, the following metrics are
+//! recorded for all the transactions in the process.
+//!
+//! - Latency histograms for `get`, each batch of a range read,
+//!   `commit` and `get_read_version`. See [`Operation`].
+//!
+//! - Number of retries of the closure passed to `run` and `read`.
+//!
+//! - Number of errors passed to `on_error`, by error code.
+//!
+//! - Number of bytes read and written.
+//!
+//! - Number of active transactions.
+//!
+//! The [`snapshot`] function returns the current value of the
+//! metrics as a [`MetricsSnapshot`], which can be exported to a
+//! monitoring system such as Prometheus. Counters only increase, so
+//! rates can be computed from successive snapshots.
+//!
+//! **Note:** Latency of a FDB future is measured from when it is
+//! created, till it is ready *and* polled. Values in a
+//! [`MetricsSnapshot`] are read one at a time, and might not be
+//! consistent with each other when there are concurrent transactions.
+
+mod client_metrics;
+mod histogram;
+mod metrics_snapshot;
+
+use std::time::Duration;
+
+use crate::error::FdbError;
+
+use client_metrics::CLIENT_METRICS;
+
+pub use histogram::HistogramSnapshot;
+pub use metrics_snapshot::MetricsSnapshot;
+
+/// Operations for which latency is recorded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Operation {
+    /// `get` on a transaction.
+    Get,
+    /// A single batch of a range read.
+    GetRange,
+    /// `commit` on a transaction.
+    Commit,
+    /// `get_read_version` on a transaction.
+    GetReadVersion,
+}
+
+/// Returns a snapshot of the client metrics.
+pub fn snapshot() -> MetricsSnapshot {
+    CLIENT_METRICS.snapshot()
+}
+
+pub(crate) fn record_latency(operation: Operation, latency: Duration) {
+    CLIENT_METRICS.record_latency(operation, latency);
+}
+
+pub(crate) fn record_retry() {
+    CLIENT_METRICS.record_retry();
+}
+
+pub(crate) fn record_error(e: FdbError) {
+    CLIENT_METRICS.record_error(e);
+}
+
+pub(crate) fn record_bytes_read(bytes: usize) {
+    CLIENT_METRICS.record_bytes_read(bytes);
+}
+
+pub(crate) fn record_bytes_written(bytes: usize) {
+    CLIENT_METRICS.record_bytes_written(bytes);
+}
+
+pub(crate) fn transaction_created() {
+    CLIENT_METRICS.transaction_created();
+}
+
+pub(crate) fn transaction_destroyed() {
+    CLIENT_METRICS.transaction_destroyed();
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use super::Operation;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    Operation:
+	        Send &
+		Sync &
+		Clone &
+		Copy &
+		PartialEq &
+		Eq));
+    }
+}
@@ -2,22 +2,24 @@
 
 use bytes::Bytes;
 
+use futures::Stream;
+
 use std::convert::TryInto;
 use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use crate::error::{FdbError, FdbResult};
+use crate::error::{FdbError, FdbResult, RANGE_CONTINUATION_INVALID};
 use crate::future::{FdbFuture, FdbFutureKeyValueArray, FdbStreamKeyValue};
 use crate::transaction::{FdbTransaction, ReadTransaction};
-use crate::tuple::key_util;
+use crate::tuple::{key_util, Tuple};
 use crate::{Key, KeySelector, KeyValue};
 
 #[cfg(feature = "fdb-7_1")]
 use crate::Mapper;
 
-#[cfg(feature = "fdb-7_1")]
-use crate::future::FdbStreamMappedKeyValue;
+#[cfg(feature = "metrics")]
+use crate::metrics::Operation;
 
 pub use crate::option::StreamingMode;
 
@@ -86,11 +88,11 @@ impl Range {
 
     /// Gets an ordered range of keys and values from the database.
     ///
-    /// The returned [`FdbStreamKeyValue`] implements [`Stream`] trait
-    /// that yields a [`KeyValue`] item.
+    /// The returned stream implements [`Stream`] trait that yields a
+    /// [`KeyValue`] item.
     ///
     /// [`Stream`]: futures::Stream
-    pub fn into_stream<T>(self, rt: &T, options: RangeOptions) -> FdbStreamKeyValue
+    pub fn into_stream<T>(self, rt: &T, options: RangeOptions) -> T::StreamKeyValue
     where
         T: ReadTransaction,
     {
@@ -106,8 +108,8 @@ impl Range {
     /// Gets an ordered range of mapped keys and values from the
     /// database.
     ///
-    /// The returned [`FdbStreamMappedKeyValue`] implements [`Stream`] trait
-    /// that yields a [`MappedKeyValue`] item.
+    /// The returned stream implements [`Stream`] trait that yields a
+    /// [`MappedKeyValue`] item.
     ///
     /// [`Stream`]: futures::Stream
     /// [`MappedKeyValue`]: crate::MappedKeyValue
@@ -116,7 +118,7 @@ impl Range {
         rt: &T,
         mapper: Mapper,
         options: RangeOptions,
-    ) -> FdbStreamMappedKeyValue
+    ) -> T::StreamMappedKeyValue
     where
         T: ReadTransaction,
     {
@@ -131,15 +133,19 @@ impl Range {
 
 /// [`RangeOptions`] specify how a database range operation is carried out.
 ///
-/// There are three parameters for which accessors methods are provided.
+/// There are six parameters for which accessors methods are provided.
 ///
 /// 1. Limit restricts the number of key-value pairs returned as part
 ///    of a range read. A value of zero indicates no limit.
 ///
-/// 2. Mode sets the [streaming mode] of the range read, allowing
+/// 2. Target bytes is a (soft) cap on the combined number of bytes of
+///    keys and values returned in each batch fetched from the
+///    database. A value of zero indicates no limit.
+///
+/// 3. Mode sets the [streaming mode] of the range read, allowing
 ///    database to balance latency and bandwidth for this read.
 ///
-/// 3. Reverse indicates that the read should be performed
+/// 4. Reverse indicates that the read should be performed
 ///    lexicographic order (when false) or reverse lexicographic (when
 ///    true).
 ///
@@ -148,17 +154,33 @@ impl Range {
 ///    supported natively by the database should have minimal extra
 ///    cost.
 ///
+/// 5. Prefetch indicates that the next batch should be requested from
+///    the database while the current batch is still being consumed,
+///    instead of after it has been consumed. This saves a round trip
+///    per batch, at the cost of reading a batch that might not be
+///    needed.
+///
+/// 6. Iteration is the iteration number of the first batch, when
+///    [iterator streaming mode] is used. The database returns larger
+///    batches as the iteration number increases. It is incremented
+///    for each subsequent batch, and must be at least one. It is
+///    ignored in other streaming modes.
+///
 /// To create a value of [`RangeOptions`] type, use
 /// [`Default::default`] method. The default value represents - no
-/// limit, [iterator streaming mode] and lexicographic order.
+/// limit, no target bytes, [iterator streaming mode], lexicographic
+/// order, no prefetch and an iteration of one.
 ///
 /// [streaming mode]: StreamingMode
 /// [iterator streaming mode]: StreamingMode::Iterator
 #[derive(Clone, Debug)]
 pub struct RangeOptions {
     pub(crate) limit: i32,
+    pub(crate) target_bytes: i32,
     pub(crate) mode: StreamingMode,
     pub(crate) reverse: bool,
+    pub(crate) prefetch: bool,
+    pub(crate) iteration: i32,
 }
 
 impl RangeOptions {
@@ -172,6 +194,16 @@ impl RangeOptions {
         self.limit
     }
 
+    /// Set target bytes
+    pub fn set_target_bytes(&mut self, target_bytes: i32) {
+        self.target_bytes = target_bytes;
+    }
+
+    /// Get target bytes
+    pub fn get_target_bytes(&self) -> i32 {
+        self.target_bytes
+    }
+
     /// Set streaming mode
     pub fn set_mode(&mut self, mode: StreamingMode) {
         self.mode = mode;
@@ -192,11 +224,39 @@ impl RangeOptions {
         self.reverse
     }
 
-    pub(crate) fn new(limit: i32, mode: StreamingMode, reverse: bool) -> RangeOptions {
+    /// Set prefetch
+    pub fn set_prefetch(&mut self, prefetch: bool) {
+        self.prefetch = prefetch;
+    }
+
+    /// Get prefetch
+    pub fn get_prefetch(&self) -> bool {
+        self.prefetch
+    }
+
+    /// Set iteration
+    pub fn set_iteration(&mut self, iteration: i32) {
+        self.iteration = iteration;
+    }
+
+    /// Get iteration
+    pub fn get_iteration(&self) -> i32 {
+        self.iteration
+    }
+
+    pub(crate) fn new(
+        limit: i32,
+        target_bytes: i32,
+        mode: StreamingMode,
+        reverse: bool,
+    ) -> RangeOptions {
         RangeOptions {
             limit,
+            target_bytes,
             mode,
             reverse,
+            prefetch: false,
+            iteration: 1,
         }
     }
 }
@@ -205,10 +265,378 @@ impl Default for RangeOptions {
     fn default() -> RangeOptions {
         RangeOptions {
             limit: 0,
+            target_bytes: 0,
             mode: StreamingMode::Iterator,
             reverse: false,
+            prefetch: false,
+            iteration: 1,
+        }
+    }
+}
+
+/// [`RangeCursor`] is a range read that can be stopped and later
+/// resumed from where it left off, possibly in a different
+/// transaction.
+///
+/// [`RangeCursor`] wraps the key-value stream returned by
+/// [`ReadTransaction::get_range`]. It defaults to [`FdbStreamKeyValue`],
+/// the stream returned by [`FdbTransaction`] and
+/// [`FdbReadTransaction`].
+///
+/// [`RangeCursor`] implements [`Stream`] trait that yields a
+/// [`KeyValue`] item. At any point, [`get_continuation`] can be used
+/// to obtain a [`RangeContinuation`], which records the last key that
+/// was returned, the read direction, the remaining limit and the
+/// [`RangeOptions`]. A [`RangeContinuation`] can be converted into
+/// [`Bytes`] and sent to a client (for example as a pagination token
+/// in a HTTP API). Later [`from_continuation`] can be used to
+/// reconstruct the [`RangeCursor`] on a new transaction.
+///
+/// Both forward and reverse range reads are supported.
+///
+/// **Note:** Key-values read using a resumed [`RangeCursor`] are
+/// read at the read version of the new transaction. The pages
+/// returned across transactions are therefore not a consistent
+/// snapshot of the range.
+///
+/// [`Stream`]: futures::Stream
+/// [`get_continuation`]: RangeCursor::get_continuation
+/// [`from_continuation`]: RangeCursor::from_continuation
+/// [`FdbReadTransaction`]: crate::transaction::FdbReadTransaction
+//
+// `RangeCursor` is built on top of a key-value stream such as
+// `FdbStreamKeyValue` (which internally uses
+// `RangeResultStateMachine`). We only keep track of the last key that
+// was returned from the stream and the number of key-values that were
+// returned. The `RangeResultStateMachine` maintains similar
+// information, but it is updated when a batch is fetched rather than
+// when a key-value is returned.
+#[derive(Debug)]
+pub struct RangeCursor<S = FdbStreamKeyValue> {
+    stream_key_value: S,
+    continuation: RangeContinuation,
+}
+
+impl<S> RangeCursor<S> {
+    /// Create a new [`RangeCursor`] over the range specified by
+    /// `begin` and `end` [`KeySelector`]s.
+    pub fn new<T>(
+        rt: &T,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+    ) -> RangeCursor<S>
+    where
+        T: ReadTransaction<StreamKeyValue = S>,
+    {
+        RangeCursor::from_continuation(rt, RangeContinuation::new(begin, end, options))
+    }
+
+    /// Reconstruct a [`RangeCursor`] from a [`RangeContinuation`].
+    ///
+    /// If the [`RangeContinuation`] has reached its end (see
+    /// [`is_end`]), then the returned [`RangeCursor`] will not yield
+    /// any more key-values.
+    ///
+    /// [`is_end`]: RangeContinuation::is_end
+    pub fn from_continuation<T>(rt: &T, continuation: RangeContinuation) -> RangeCursor<S>
+    where
+        T: ReadTransaction<StreamKeyValue = S>,
+    {
+        let (begin, end, options) = continuation.get_resume_parameters();
+
+        let stream_key_value = rt.get_range(begin, end, options);
+
+        RangeCursor {
+            stream_key_value,
+            continuation,
+        }
+    }
+
+    /// Returns a [`RangeContinuation`] that can be used to resume
+    /// reading the range after the last key-value that was returned
+    /// by this [`RangeCursor`].
+    pub fn get_continuation(&self) -> RangeContinuation {
+        self.continuation.clone()
+    }
+}
+
+impl<S> Stream for RangeCursor<S>
+where
+    S: Stream<Item = FdbResult<KeyValue>> + Unpin,
+{
+    type Item = FdbResult<KeyValue>;
+
+    fn poll_next(
+        mut self: Pin<&mut RangeCursor<S>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<KeyValue>>> {
+        // There is nothing more to read. We should not issue a range
+        // read with a limit of `0`, as it would mean *no limit*.
+        if self.continuation.is_end() {
+            return Poll::Ready(None);
+        }
+
+        match Pin::new(&mut self.stream_key_value).poll_next(cx) {
+            Poll::Ready(Some(Ok(kv))) => {
+                self.continuation.advance(kv.get_key_ref().clone());
+                Poll::Ready(Some(Ok(kv)))
+            }
+            Poll::Ready(Some(Err(fdb_error))) => Poll::Ready(Some(Err(fdb_error))),
+            Poll::Ready(None) => {
+                self.continuation.done = true;
+                Poll::Ready(None)
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// An opaque, serializable position within a range read done using
+/// [`RangeCursor`].
+///
+/// A [`RangeContinuation`] can be converted to [`Bytes`] using
+/// [`to_bytes`] and back using [`from_bytes`].
+///
+/// **Note:** The iteration of [`RangeOptions`] is not encoded. A
+/// range read resumed from a decoded [`RangeContinuation`] starts at
+/// iteration `1`.
+///
+/// [`to_bytes`]: RangeContinuation::to_bytes
+/// [`from_bytes`]: RangeContinuation::from_bytes
+//
+// `begin` and `end` are the key selectors of the original range
+// read. `options` are the original `RangeOptions`, except that
+// `limit` is the *remaining* limit. When `last_key` is `Some(...)`,
+// then `begin` (forward scan) or `end` (reverse scan) is adjusted
+// when the range read is resumed.
+//
+// `RangeCursor` only sees key-values and not batch boundaries, so it
+// cannot track the iteration. That is why `iteration` is not encoded.
+#[derive(Clone, Debug)]
+pub struct RangeContinuation {
+    begin: KeySelector,
+    end: KeySelector,
+    last_key: Option<Key>,
+    options: RangeOptions,
+    done: bool,
+}
+
+impl RangeContinuation {
+    /// Returns `true` if the range read has been completed and there
+    /// are no more key-values to be read.
+    pub fn is_end(&self) -> bool {
+        self.done
+    }
+
+    /// Gets a reference to the last [`Key`] that was returned, if
+    /// any.
+    pub fn get_last_key_ref(&self) -> Option<&Key> {
+        self.last_key.as_ref()
+    }
+
+    /// Gets the remaining limit. A value of zero indicates no limit.
+    pub fn get_limit(&self) -> i32 {
+        self.options.get_limit()
+    }
+
+    /// Get the read order (lexicographic or non-lexicographic)
+    pub fn get_reverse(&self) -> bool {
+        self.options.get_reverse()
+    }
+
+    /// Get an encoded representation of this [`RangeContinuation`].
+    pub fn to_bytes(&self) -> Bytes {
+        let mut t = Tuple::new();
+
+        t.add_i8(RANGE_CONTINUATION_FORMAT_VERSION);
+
+        let (key, or_equal, offset) = self.begin.clone().deconstruct();
+        t.add_bytes(key.into());
+        t.add_bool(or_equal);
+        t.add_i32(offset);
+
+        let (key, or_equal, offset) = self.end.clone().deconstruct();
+        t.add_bytes(key.into());
+        t.add_bool(or_equal);
+        t.add_i32(offset);
+
+        match self.last_key.clone() {
+            Some(k) => t.add_bytes(k.into()),
+            None => t.add_null(),
+        }
+
+        t.add_i32(self.options.get_limit());
+        t.add_i32(self.options.get_target_bytes());
+        t.add_i8(streaming_mode_to_i8(self.options.get_mode()));
+        t.add_bool(self.options.get_reverse());
+        t.add_bool(self.options.get_prefetch());
+        t.add_bool(self.done);
+
+        t.pack()
+    }
+
+    /// Decode a [`RangeContinuation`] from [`Bytes`] that was
+    /// previously created using [`to_bytes`].
+    ///
+    /// [`to_bytes`]: RangeContinuation::to_bytes
+    pub fn from_bytes(b: impl Into<Bytes>) -> FdbResult<RangeContinuation> {
+        let t = Tuple::from_bytes(b).map_err(|_| FdbError::new(RANGE_CONTINUATION_INVALID))?;
+
+        RangeContinuation::from_tuple(&t).map_err(|_| FdbError::new(RANGE_CONTINUATION_INVALID))
+    }
+
+    fn from_tuple(t: &Tuple) -> FdbResult<RangeContinuation> {
+        if t.size() != 14 || t.get_i8(0)? != RANGE_CONTINUATION_FORMAT_VERSION {
+            return Err(FdbError::new(RANGE_CONTINUATION_INVALID));
+        }
+
+        let begin = KeySelector::new(t.get_bytes_ref(1)?.clone(), t.get_bool(2)?, t.get_i32(3)?);
+        let end = KeySelector::new(t.get_bytes_ref(4)?.clone(), t.get_bool(5)?, t.get_i32(6)?);
+
+        let last_key = match t.get_null(7) {
+            Ok(()) => None,
+            Err(_) => Some(t.get_bytes_ref(7)?.clone().into()),
+        };
+
+        let limit = t.get_i32(8)?;
+        let target_bytes = t.get_i32(9)?;
+        let mode = streaming_mode_from_i8(t.get_i8(10)?)?;
+        let reverse = t.get_bool(11)?;
+        let prefetch = t.get_bool(12)?;
+        let done = t.get_bool(13)?;
+
+        let mut options = RangeOptions::new(limit, target_bytes, mode, reverse);
+        options.set_prefetch(prefetch);
+
+        Ok(RangeContinuation {
+            begin,
+            end,
+            last_key,
+            options,
+            done,
+        })
+    }
+
+    fn new(begin: KeySelector, end: KeySelector, options: RangeOptions) -> RangeContinuation {
+        RangeContinuation {
+            begin,
+            end,
+            last_key: None,
+            options,
+            done: false,
+        }
+    }
+
+    // Called by `RangeCursor` when a key-value is returned to the
+    // caller.
+    fn advance(&mut self, key: Key) {
+        self.last_key = Some(key);
+
+        // When limit is `0`, there is no limit.
+        if self.options.limit != 0 {
+            self.options.limit -= 1;
+
+            if self.options.limit == 0 {
+                self.done = true;
+            }
         }
     }
+
+    // Returns the key selectors and range options needed to resume
+    // the range read.
+    fn get_resume_parameters(&self) -> (KeySelector, KeySelector, RangeOptions) {
+        match self.last_key.clone() {
+            None => (self.begin.clone(), self.end.clone(), self.options.clone()),
+            Some(last_key) => {
+                if self.options.get_reverse() {
+                    (
+                        self.begin.clone(),
+                        KeySelector::first_greater_or_equal(last_key),
+                        self.options.clone(),
+                    )
+                } else {
+                    (
+                        KeySelector::first_greater_than(last_key),
+                        self.end.clone(),
+                        self.options.clone(),
+                    )
+                }
+            }
+        }
+    }
+}
+
+// Used to version the encoded representation of
+// `RangeContinuation`. It *must* be incremented whenever the fields
+// in the encoded representation change.
+const RANGE_CONTINUATION_FORMAT_VERSION: i8 = 0;
+
+// `StreamingMode::code()` is C API dependent. So, we use our own
+// encoding for `RangeContinuation`.
+fn streaming_mode_to_i8(mode: StreamingMode) -> i8 {
+    match mode {
+        StreamingMode::WantAll => 0,
+        StreamingMode::Iterator => 1,
+        StreamingMode::Exact => 2,
+        StreamingMode::Small => 3,
+        StreamingMode::Medium => 4,
+        StreamingMode::Large => 5,
+        StreamingMode::Serial => 6,
+    }
+}
+
+fn streaming_mode_from_i8(mode: i8) -> FdbResult<StreamingMode> {
+    match mode {
+        0 => Ok(StreamingMode::WantAll),
+        1 => Ok(StreamingMode::Iterator),
+        2 => Ok(StreamingMode::Exact),
+        3 => Ok(StreamingMode::Small),
+        4 => Ok(StreamingMode::Medium),
+        5 => Ok(StreamingMode::Large),
+        6 => Ok(StreamingMode::Serial),
+        _ => Err(FdbError::new(RANGE_CONTINUATION_INVALID)),
+    }
+}
+
+/// A batch of [`KeyValue`]s returned by [`FdbStreamKeyValueBatch`].
+///
+/// Each batch corresponds to a single range read issued to the
+/// database. The size of the batch can be controlled using the
+/// limit, target bytes and mode parameters of [`RangeOptions`].
+///
+/// [`FdbStreamKeyValueBatch`]: crate::future::FdbStreamKeyValueBatch
+#[derive(Clone, Debug)]
+pub struct KeyValueBatch {
+    kvs: Vec<KeyValue>,
+    more: bool,
+}
+
+impl KeyValueBatch {
+    /// Gets a reference to the [`KeyValue`]s in the batch.
+    pub fn get_key_values_ref(&self) -> &Vec<KeyValue> {
+        &self.kvs
+    }
+
+    /// Returns `true` if there *might* be more key-value pairs in
+    /// the range after this batch.
+    pub fn get_more(&self) -> bool {
+        self.more
+    }
+
+    /// Extract [`KeyValue`]s from [`KeyValueBatch`].
+    pub fn into_key_values(self) -> Vec<KeyValue> {
+        self.kvs
+    }
+
+    /// Extract [`KeyValue`]s and more flag from [`KeyValueBatch`].
+    pub fn into_parts(self) -> (Vec<KeyValue>, bool) {
+        (self.kvs, self.more)
+    }
+
+    pub(crate) fn new(kvs: Vec<KeyValue>, more: bool) -> KeyValueBatch {
+        KeyValueBatch { kvs, more }
+    }
 }
 
 // Java API refers to this type `RangeResult` and Go API has something
@@ -239,6 +667,7 @@ impl KeyValueArray {
 enum RangeResultStateMachineState {
     Fetching,
     KeyValueArrayAvailable,
+    KeyValueArrayPrefetching,
     Error,
     Done,
 }
@@ -254,6 +683,13 @@ enum RangeResultStateMachineData {
         count: i32,
         more: bool,
     },
+    KeyValueArrayPrefetching {
+        kvs: Vec<KeyValue>,
+        index: i32,
+        count: i32,
+        more: bool,
+        fdb_future_key_value_array: FdbFutureKeyValueArray,
+    },
     Error {
         fdb_error: FdbError,
     },
@@ -291,6 +727,11 @@ pub(crate) struct RangeResultStateMachine {
     mode: StreamingMode,
     reverse: bool,
 
+    // When `prefetch` is `true`, the next batch is requested as soon
+    // as the current batch is received, instead of waiting for the
+    // current batch to be consumed.
+    prefetch: bool,
+
     // This is *only* used in case of `StreamingMode::Iterator`. In
     // other cases, we set it to `None`.
     iteration: Option<i32>,
@@ -305,11 +746,21 @@ pub(crate) struct RangeResultStateMachine {
     // specified. However, we don't check for this as binding tester
     // checks for `2210` errors.
     limit: Option<i32>,
+
+    // Soft cap on the number of bytes returned in each batch. `0`
+    // means no limit.
+    target_bytes: i32,
     begin_sel: KeySelector,
     end_sel: KeySelector,
 
     range_result_state_machine_state: RangeResultStateMachineState,
     range_result_state_machine_data: RangeResultStateMachineData,
+
+    // Span for the range read of the current batch. It is created
+    // when the batch is first polled, and closed when the batch is
+    // received.
+    #[cfg(feature = "tracing")]
+    batch_span: Option<tracing::Span>,
 }
 
 impl RangeResultStateMachine {
@@ -325,6 +776,8 @@ impl RangeResultStateMachine {
         iteration: Option<i32>,
         reverse: bool,
         limit: Option<i32>,
+        target_bytes: i32,
+        prefetch: bool,
         snapshot: bool,
         fdb_future_key_value_array: FdbFutureKeyValueArray,
     ) -> RangeResultStateMachine {
@@ -333,14 +786,18 @@ impl RangeResultStateMachine {
             snapshot,
             mode,
             reverse,
+            prefetch,
             iteration,
             limit,
+            target_bytes,
             begin_sel,
             end_sel,
             range_result_state_machine_state: RangeResultStateMachineState::Fetching,
             range_result_state_machine_data: RangeResultStateMachineData::Fetching {
                 fdb_future_key_value_array,
             },
+            #[cfg(feature = "tracing")]
+            batch_span: None,
         }
     }
 
@@ -351,125 +808,117 @@ impl RangeResultStateMachine {
         loop {
             match self.range_result_state_machine_state {
                 RangeResultStateMachineState::Fetching => {
-                    if let RangeResultStateMachineData::Fetching {
-                        ref mut fdb_future_key_value_array,
-                    } = self.range_result_state_machine_data
-                    {
-                        match Pin::new(fdb_future_key_value_array).poll(cx) {
-                            Poll::Ready(res) => match res {
-                                Ok(key_value_array) => {
-                                    let KeyValueArray {
-                                        kvs,
-                                        index,
-                                        count,
-                                        more,
-                                    } = key_value_array;
-                                    if count == 0 {
-                                        // In case count is zero, we are done.
-                                        self.step_once_with_event(
-                                            RangeResultStateMachineEvent::FetchDone,
-                                        );
-                                    } else {
-                                        self.step_once_with_event(
-                                            RangeResultStateMachineEvent::FetchOk {
-                                                kvs,
-                                                index,
-                                                count,
-                                                more,
-                                            },
-                                        );
-                                    }
-                                }
-                                Err(fdb_error) => {
-                                    self.step_once_with_event(
-                                        RangeResultStateMachineEvent::FetchError { fdb_error },
-                                    );
-                                }
-                            },
-                            Poll::Pending => return Poll::Pending,
+                    if self.poll_fetching(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                }
+                RangeResultStateMachineState::KeyValueArrayAvailable
+                | RangeResultStateMachineState::KeyValueArrayPrefetching => {
+                    let (kvs, index, count) = match self.range_result_state_machine_data {
+                        RangeResultStateMachineData::KeyValueArrayAvailable {
+                            ref kvs,
+                            ref mut index,
+                            count,
+                            ..
                         }
+                        | RangeResultStateMachineData::KeyValueArrayPrefetching {
+                            ref kvs,
+                            ref mut index,
+                            count,
+                            ..
+                        } => (kvs, index, count),
+                        _ => panic!("invalid range_result_state_machine_data"),
+                    };
+
+                    // Unlike in Python, where the `index == count`
+                    // check is done when returning the last element,
+                    // in our case the last element gets returned and
+                    // in the next call to `poll_next`, we do our
+                    // check.
+                    if *index == count {
+                        self.fetch_next_batch_or_done();
                     } else {
-                        panic!("invalid range_result_state_machine_data");
+                        // We need to remove elements from the
+                        // beginning. If we used `Vec::remove` that
+                        // would keep shifting elements to the
+                        // left. Instead of modifying `kvs`, we just
+                        // clone the element that we need.
+                        //
+                        // Safety: `index` starts with `0` (set in
+                        //          `KeyValueArray::new`) and is
+                        //          incremented till it reaches
+                        //          `count`.
+                        let result = kvs[TryInto::<usize>::try_into(*index).unwrap()].clone();
+                        *index += 1;
+
+                        return Poll::Ready(Some(Ok(result)));
                     }
                 }
-                RangeResultStateMachineState::KeyValueArrayAvailable => {
-                    if let RangeResultStateMachineData::KeyValueArrayAvailable {
-                        ref kvs,
-                        ref mut index,
-                        count,
-                        more,
-                    } = self.range_result_state_machine_data
+                RangeResultStateMachineState::Error => {
+                    if let RangeResultStateMachineData::Error { fdb_error } =
+                        self.range_result_state_machine_data
                     {
-                        // Unlike in Python, where the `index ==
-                        // count` check is done when returning the
-                        // last element, in our case the last element
-                        // gets returned and in the next call to
-                        // `poll_next`, we do our check.
-                        if *index == count {
-                            // Should we get more?
-                            if more {
-                                if let Some(0) = self.limit {
-                                    self.step_once_with_event(
-                                        RangeResultStateMachineEvent::FetchDone,
-                                    );
-                                } else {
-                                    // `limit` is either `None` or
-                                    // non-zero.
-
-                                    // iteration, limit, begin_sel and
-                                    // end_sel have already been updated
-                                    // in the transition action.
-                                    let options = match self.limit {
-                                        Some(limit) => RangeOptions {
-                                            limit,
-                                            mode: self.mode,
-                                            reverse: self.reverse,
-                                        },
-                                        None => RangeOptions {
-                                            limit: 0,
-                                            mode: self.mode,
-                                            reverse: self.reverse,
-                                        },
-                                    };
-
-                                    let fdb_future_key_value_array = fdb_transaction_get_range(
-                                        self.transaction.get_c_api_ptr(),
-                                        self.begin_sel.clone(),
-                                        self.end_sel.clone(),
-                                        options,
-                                        self.iteration.unwrap_or(0),
-                                        self.snapshot,
-                                    );
-
-                                    self.step_once_with_event(
-                                        RangeResultStateMachineEvent::FetchNextBatch {
-                                            fdb_future_key_value_array,
-                                        },
-                                    );
-                                }
-                            } else {
-                                self.step_once_with_event(RangeResultStateMachineEvent::FetchDone);
-                            }
-                        } else {
-                            // We need to remove elements from the
-                            // beginning. If we used `Vec::remove`
-                            // that would keep shifting elements to
-                            // the left. Instead of modifying `kvs`,
-                            // we just clone the element that we need.
-                            //
-                            // Safety: `index` starts with `0` (set in
-                            //          `KeyValueArray::new`) and is
-                            //          incremented till it reaches
-                            //          `count`.
-                            let result = kvs[TryInto::<usize>::try_into(*index).unwrap()].clone();
-                            *index += 1;
-
-                            return Poll::Ready(Some(Ok(result)));
-                        }
+                        return Poll::Ready(Some(Err(fdb_error)));
                     } else {
                         panic!("invalid range_result_state_machine_data");
                     }
                 }
+                RangeResultStateMachineState::Done => return Poll::Ready(None),
+            }
+        }
+    }
+
+    // Same as `poll_next`, except that instead of returning the
+    // key-value pairs one at a time, all the remaining key-value
+    // pairs in the current `KeyValueArray` are returned together.
+    pub(crate) fn poll_next_batch(
+        mut self: Pin<&mut RangeResultStateMachine>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<KeyValueBatch>>> {
+        loop {
+            match self.range_result_state_machine_state {
+                RangeResultStateMachineState::Fetching => {
+                    if self.poll_fetching(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+                }
+                RangeResultStateMachineState::KeyValueArrayAvailable
+                | RangeResultStateMachineState::KeyValueArrayPrefetching => {
+                    let (kvs, index, count, more) = match self.range_result_state_machine_data {
+                        RangeResultStateMachineData::KeyValueArrayAvailable {
+                            ref mut kvs,
+                            ref mut index,
+                            count,
+                            more,
+                        }
+                        | RangeResultStateMachineData::KeyValueArrayPrefetching {
+                            ref mut kvs,
+                            ref mut index,
+                            count,
+                            more,
+                            ..
+                        } => (kvs, index, count, more),
+                        _ => panic!("invalid range_result_state_machine_data"),
+                    };
+
+                    if *index == count {
+                        self.fetch_next_batch_or_done();
+                    } else {
+                        // Safety: `index` starts with `0` (set in
+                        //          `KeyValueArray::new`) and is
+                        //          only ever incremented till it
+                        //          reaches `count`.
+                        let batch = kvs.split_off(TryInto::<usize>::try_into(*index).unwrap());
+                        *index = count;
+
+                        // `limit` has already been updated in the
+                        // transition action. If it has reached `0`,
+                        // there won't be any more batches.
+                        let more = has_next_batch(more, self.limit);
+
+                        return Poll::Ready(Some(Ok(KeyValueBatch::new(batch, more))));
+                    }
+                }
                 RangeResultStateMachineState::Error => {
                     if let RangeResultStateMachineData::Error { fdb_error } =
                         self.range_result_state_machine_data
@@ -484,6 +933,137 @@ impl RangeResultStateMachine {
         }
     }
 
+    // Poll the `FdbFutureKeyValueArray` in `Fetching` state and step
+    // the state machine once the future is ready.
+    fn poll_fetching(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        #[cfg(feature = "tracing")]
+        let span = {
+            let (snapshot, reverse) = (self.snapshot, self.reverse);
+            self.batch_span
+                .get_or_insert_with(|| batch_span(snapshot, reverse))
+                .clone()
+        };
+
+        #[cfg(feature = "tracing")]
+        let _enter = span.enter();
+
+        if let RangeResultStateMachineData::Fetching {
+            ref mut fdb_future_key_value_array,
+        } = self.range_result_state_machine_data
+        {
+            match Pin::new(fdb_future_key_value_array).poll(cx) {
+                Poll::Ready(res) => {
+                    #[cfg(feature = "tracing")]
+                    {
+                        match res {
+                            Ok(ref key_value_array) => {
+                                span.record("count", key_value_array.count);
+                                span.record("more", key_value_array.more);
+                            }
+                            Err(ref fdb_error) => {
+                                span.record("error", fdb_error.code());
+                            }
+                        }
+                        self.batch_span = None;
+                    }
+
+                    match res {
+                        Ok(key_value_array) => {
+                            let KeyValueArray {
+                                kvs,
+                                index,
+                                count,
+                                more,
+                            } = key_value_array;
+                            if count == 0 {
+                                // In case count is zero, we are done.
+                                self.step_once_with_event(RangeResultStateMachineEvent::FetchDone);
+                            } else {
+                                self.step_once_with_event(RangeResultStateMachineEvent::FetchOk {
+                                    kvs,
+                                    index,
+                                    count,
+                                    more,
+                                });
+                            }
+                        }
+                        Err(fdb_error) => {
+                            self.step_once_with_event(RangeResultStateMachineEvent::FetchError {
+                                fdb_error,
+                            });
+                        }
+                    }
+                    Poll::Ready(())
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        } else {
+            panic!("invalid range_result_state_machine_data");
+        }
+    }
+
+    // Called in `KeyValueArrayAvailable` and
+    // `KeyValueArrayPrefetching` states once all the
+    // key-value pairs in the current `KeyValueArray` have been
+    // returned.
+    fn fetch_next_batch_or_done(&mut self) {
+        match std::mem::replace(
+            &mut self.range_result_state_machine_data,
+            RangeResultStateMachineData::Done,
+        ) {
+            RangeResultStateMachineData::KeyValueArrayAvailable { more, .. } => {
+                // Should we get more?
+                if has_next_batch(more, self.limit) {
+                    // iteration, limit, begin_sel and end_sel have
+                    // already been updated in the transition action.
+                    let fdb_future_key_value_array = self.get_next_batch();
+
+                    self.step_once_with_event(RangeResultStateMachineEvent::FetchNextBatch {
+                        fdb_future_key_value_array,
+                    });
+                } else {
+                    self.step_once_with_event(RangeResultStateMachineEvent::FetchDone);
+                }
+            }
+            RangeResultStateMachineData::KeyValueArrayPrefetching {
+                more,
+                fdb_future_key_value_array,
+                ..
+            } => {
+                // The next batch has already been requested in the
+                // transition action.
+                debug_assert!(has_next_batch(more, self.limit));
+
+                self.step_once_with_event(RangeResultStateMachineEvent::FetchNextBatch {
+                    fdb_future_key_value_array,
+                });
+            }
+            _ => panic!("invalid range_result_state_machine_data"),
+        }
+    }
+
+    // Issue a range read for the next batch, using the updated
+    // iteration, limit, begin_sel and end_sel.
+    fn get_next_batch(&self) -> FdbFutureKeyValueArray {
+        let options = RangeOptions {
+            limit: self.limit.unwrap_or(0),
+            target_bytes: self.target_bytes,
+            mode: self.mode,
+            reverse: self.reverse,
+            prefetch: self.prefetch,
+            iteration: self.iteration.unwrap_or(0),
+        };
+
+        fdb_transaction_get_range(
+            self.transaction.get_c_api_ptr(),
+            self.begin_sel.clone(),
+            self.end_sel.clone(),
+            options,
+            self.iteration.unwrap_or(0),
+            self.snapshot,
+        )
+    }
+
     fn step_once_with_event(&mut self, event: RangeResultStateMachineEvent) {
         self.range_result_state_machine_state = match self.range_result_state_machine_state {
             RangeResultStateMachineState::Fetching => match event {
@@ -502,17 +1082,15 @@ impl RangeResultStateMachine {
                     // needed. This would be used by `FetchNextBatch`
                     // event.
 
-                    if more {
-                        // This assumes that we have mode to be
-                        // `StreamingMode::Iterator`.
-                        if let Some(iteration) = self.iteration.as_mut() {
-                            *iteration += 1;
-                        }
-
-                        if let Some(limit) = self.limit.as_mut() {
-                            *limit -= count;
-                        }
+                    let prefetching = fetch_ok_transition(
+                        more,
+                        count,
+                        self.prefetch,
+                        &mut self.limit,
+                        &mut self.iteration,
+                    );
 
+                    if more {
                         // Safety: We only generate the `FetchOk` event
                         // when count > 0, otherwise we go to `FetchDone`.
                         let last_index = TryInto::<usize>::try_into(count - 1).unwrap();
@@ -528,14 +1106,31 @@ impl RangeResultStateMachine {
                         }
                     }
 
-                    self.range_result_state_machine_data =
-                        RangeResultStateMachineData::KeyValueArrayAvailable {
-                            kvs,
-                            index,
-                            count,
-                            more,
-                        };
-                    RangeResultStateMachineState::KeyValueArrayAvailable
+                    if prefetching {
+                        // Request the next batch right away, so that
+                        // it is fetched while `kvs` is being
+                        // consumed.
+                        let fdb_future_key_value_array = self.get_next_batch();
+
+                        self.range_result_state_machine_data =
+                            RangeResultStateMachineData::KeyValueArrayPrefetching {
+                                kvs,
+                                index,
+                                count,
+                                more,
+                                fdb_future_key_value_array,
+                            };
+                        RangeResultStateMachineState::KeyValueArrayPrefetching
+                    } else {
+                        self.range_result_state_machine_data =
+                            RangeResultStateMachineData::KeyValueArrayAvailable {
+                                kvs,
+                                index,
+                                count,
+                                more,
+                            };
+                        RangeResultStateMachineState::KeyValueArrayAvailable
+                    }
                 }
                 RangeResultStateMachineEvent::FetchDone => {
                     self.range_result_state_machine_data = RangeResultStateMachineData::Done;
@@ -563,6 +1158,17 @@ impl RangeResultStateMachine {
                 }
                 _ => panic!("Invalid event!"),
             },
+            RangeResultStateMachineState::KeyValueArrayPrefetching => match event {
+                RangeResultStateMachineEvent::FetchNextBatch {
+                    fdb_future_key_value_array,
+                } => {
+                    self.range_result_state_machine_data = RangeResultStateMachineData::Fetching {
+                        fdb_future_key_value_array,
+                    };
+                    RangeResultStateMachineState::Fetching
+                }
+                _ => panic!("Invalid event!"),
+            },
             RangeResultStateMachineState::Error | RangeResultStateMachineState::Done => {
                 panic!("Invalid event!");
             }
@@ -570,6 +1176,56 @@ impl RangeResultStateMachine {
     }
 }
 
+// Creates the span for the range read of a single batch. `count`
+// and `more` (or `error`) are recorded once the batch is received.
+#[cfg(feature = "tracing")]
+pub(crate) fn batch_span(snapshot: bool, reverse: bool) -> tracing::Span {
+    tracing::trace_span!(
+        "range_batch",
+        snapshot,
+        reverse,
+        count = tracing::field::Empty,
+        more = tracing::field::Empty,
+        error = tracing::field::Empty
+    )
+}
+
+// Returns `true` when there is a next batch that needs to be
+// requested. `limit` must be the limit that has been updated in the
+// `FetchOk` transition action.
+pub(crate) fn has_next_batch(more: bool, limit: Option<i32>) -> bool {
+    more && limit != Some(0)
+}
+
+// Transition action of `FetchOk` event that is common to
+// `RangeResultStateMachine` and `MappedRangeResultStateMachine`.
+//
+// When `more` is `true`, `limit` and `iteration` are updated for the
+// next batch. Returns `true` when the next batch needs to be
+// requested right away, that is when the target state is
+// `KeyValueArrayPrefetching` (or `MappedKeyValueArrayPrefetching`).
+pub(crate) fn fetch_ok_transition(
+    more: bool,
+    count: i32,
+    prefetch: bool,
+    limit: &mut Option<i32>,
+    iteration: &mut Option<i32>,
+) -> bool {
+    if more {
+        // This assumes that we have mode to be
+        // `StreamingMode::Iterator`.
+        if let Some(iteration) = iteration.as_mut() {
+            *iteration += 1;
+        }
+
+        if let Some(limit) = limit.as_mut() {
+            *limit -= count;
+        }
+    }
+
+    prefetch && has_next_batch(more, *limit)
+}
+
 pub(crate) fn fdb_transaction_get_range(
     transaction: *mut fdb_sys::FDBTransaction,
     begin_key: KeySelector,
@@ -590,9 +1246,7 @@ pub(crate) fn fdb_transaction_get_range(
     let end_key_name_length = ek.as_ref().len().try_into().unwrap();
     let end_or_equal = if end_or_equal { 1 } else { 0 };
 
-    // This is similar to Java, where calls to `tr.getRange_internal`
-    // sets the `target_bytes` to `0`.
-    let target_bytes = 0;
+    let target_bytes = options.get_target_bytes();
 
     let limit = options.get_limit();
     let mode = options.get_mode().code();
@@ -600,7 +1254,7 @@ pub(crate) fn fdb_transaction_get_range(
 
     let s = if snapshot { 1 } else { 0 };
 
-    FdbFuture::new(unsafe {
+    let fut = FdbFuture::new(unsafe {
         fdb_sys::fdb_transaction_get_range(
             transaction,
             begin_key_name,
@@ -618,14 +1272,24 @@ pub(crate) fn fdb_transaction_get_range(
             s,
             reverse,
         )
-    })
+    });
+
+    #[cfg(feature = "metrics")]
+    let fut = fut.with_latency_metric(Operation::GetRange);
+
+    fut
 }
 
 #[cfg(test)]
 mod tests {
+    use bytes::Bytes;
     use impls::impls;
 
-    use super::RangeOptions;
+    use crate::error::{FdbError, RANGE_CONTINUATION_INVALID};
+    use crate::tuple::Tuple;
+    use crate::{Key, KeySelector};
+
+    use super::{RangeContinuation, RangeCursor, RangeOptions, StreamingMode};
 
     #[test]
     fn impls() {
@@ -633,5 +1297,282 @@ mod tests {
         assert!(impls!(
 	    RangeOptions:
 	    Default));
+
+        #[rustfmt::skip]
+        assert!(impls!(
+	    RangeCursor:
+	        Send &
+		!Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+        assert!(impls!(
+	    RangeContinuation:
+	        Send &
+		Clone &
+		!Copy));
+    }
+
+    #[test]
+    fn has_next_batch() {
+        assert!(super::has_next_batch(true, None));
+        assert!(super::has_next_batch(true, Some(1)));
+        assert!(!super::has_next_batch(true, Some(0)));
+        assert!(!super::has_next_batch(false, None));
+        assert!(!super::has_next_batch(false, Some(1)));
+    }
+
+    #[test]
+    fn fetch_ok_transition() {
+        // (more, count, prefetch, limit, iteration) and the expected
+        // (prefetching, limit, iteration).
+        #[allow(clippy::type_complexity)]
+        let cases: Vec<(
+            (bool, i32, bool, Option<i32>, Option<i32>),
+            (bool, Option<i32>, Option<i32>),
+        )> = vec![
+            // No more batches. `limit` and `iteration` are not
+            // updated.
+            ((false, 5, false, None, Some(1)), (false, None, Some(1))),
+            ((false, 5, true, None, Some(1)), (false, None, Some(1))),
+            ((false, 5, true, Some(10), None), (false, Some(10), None)),
+            // More batches, without a limit.
+            ((true, 5, false, None, Some(1)), (false, None, Some(2))),
+            ((true, 5, true, None, Some(1)), (true, None, Some(2))),
+            ((true, 5, true, None, None), (true, None, None)),
+            // More batches, with a limit that is not reached.
+            (
+                (true, 5, false, Some(10), Some(3)),
+                (false, Some(5), Some(4)),
+            ),
+            ((true, 5, true, Some(10), Some(3)), (true, Some(5), Some(4))),
+            // More batches, with a limit that is reached. The next
+            // batch is not requested.
+            ((true, 5, true, Some(5), Some(1)), (false, Some(0), Some(2))),
+        ];
+
+        for ((more, count, prefetch, limit, iteration), expected) in cases {
+            let (mut limit, mut iteration) = (limit, iteration);
+
+            let prefetching =
+                super::fetch_ok_transition(more, count, prefetch, &mut limit, &mut iteration);
+
+            assert_eq!((prefetching, limit, iteration), expected);
+
+            // `KeyValueArrayPrefetching` state is entered only when
+            // there is a next batch.
+            if prefetching {
+                assert!(super::has_next_batch(more, limit));
+            }
+        }
+    }
+
+    #[test]
+    fn range_options_prefetch() {
+        let mut options = RangeOptions::default();
+        assert!(!options.get_prefetch());
+
+        options.set_prefetch(true);
+        assert!(options.get_prefetch());
+    }
+
+    #[test]
+    fn range_continuation_advance() {
+        let mut c = RangeContinuation::new(
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"a")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"z")),
+            RangeOptions::new(2, 0, StreamingMode::Iterator, false),
+        );
+
+        assert!(!c.is_end());
+        assert_eq!(c.get_last_key_ref(), None);
+
+        c.advance(Bytes::from_static(b"b").into());
+
+        assert!(!c.is_end());
+        assert_eq!(c.get_limit(), 1);
+        assert_eq!(
+            c.get_last_key_ref(),
+            Some(&Key::from(Bytes::from_static(b"b")))
+        );
+
+        c.advance(Bytes::from_static(b"c").into());
+
+        assert!(c.is_end());
+        assert_eq!(c.get_limit(), 0);
+
+        // When there is no limit, we never reach the end by
+        // advancing.
+        let mut c = RangeContinuation::new(
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"a")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"z")),
+            RangeOptions::default(),
+        );
+
+        c.advance(Bytes::from_static(b"b").into());
+
+        assert!(!c.is_end());
+        assert_eq!(c.get_limit(), 0);
+    }
+
+    #[test]
+    fn range_continuation_get_resume_parameters() {
+        let mut c = RangeContinuation::new(
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"a")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"z")),
+            RangeOptions::default(),
+        );
+
+        let (begin, end, _) = c.get_resume_parameters();
+        assert_eq!(
+            begin.deconstruct(),
+            (Bytes::from_static(b"a").into(), false, 1)
+        );
+        assert_eq!(
+            end.deconstruct(),
+            (Bytes::from_static(b"z").into(), false, 1)
+        );
+
+        c.advance(Bytes::from_static(b"m").into());
+
+        let (begin, end, _) = c.get_resume_parameters();
+        assert_eq!(
+            begin.deconstruct(),
+            (Bytes::from_static(b"m").into(), true, 1)
+        );
+        assert_eq!(
+            end.deconstruct(),
+            (Bytes::from_static(b"z").into(), false, 1)
+        );
+
+        let mut c = RangeContinuation::new(
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"a")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"z")),
+            RangeOptions::new(10, 0, StreamingMode::WantAll, true),
+        );
+
+        c.advance(Bytes::from_static(b"m").into());
+
+        let (begin, end, options) = c.get_resume_parameters();
+        assert_eq!(
+            begin.deconstruct(),
+            (Bytes::from_static(b"a").into(), false, 1)
+        );
+        assert_eq!(
+            end.deconstruct(),
+            (Bytes::from_static(b"m").into(), false, 1)
+        );
+        assert_eq!(options.get_limit(), 9);
+        assert_eq!(options.get_mode(), StreamingMode::WantAll);
+        assert!(options.get_reverse());
+    }
+
+    #[test]
+    fn range_continuation_to_bytes_from_bytes() {
+        let mut options = RangeOptions::new(5, 1024, StreamingMode::Small, true);
+        options.set_prefetch(true);
+        options.set_iteration(3);
+
+        let mut c = RangeContinuation::new(
+            KeySelector::new(Bytes::from_static(b"a"), true, 3),
+            KeySelector::last_less_than(Bytes::from_static(b"z")),
+            options,
+        );
+
+        let c1 = RangeContinuation::from_bytes(c.to_bytes()).unwrap();
+
+        assert_eq!(
+            c1.begin.clone().deconstruct(),
+            (Bytes::from_static(b"a").into(), true, 3)
+        );
+        assert_eq!(
+            c1.end.clone().deconstruct(),
+            (Bytes::from_static(b"z").into(), false, 0)
+        );
+        assert_eq!(c1.get_last_key_ref(), None);
+        assert_eq!(c1.get_limit(), 5);
+        assert_eq!(c1.options.get_target_bytes(), 1024);
+        assert_eq!(c1.options.get_mode(), StreamingMode::Small);
+        assert!(c1.get_reverse());
+        assert!(c1.options.get_prefetch());
+        // `iteration` is not encoded.
+        assert_eq!(c1.options.get_iteration(), 1);
+        assert!(!c1.is_end());
+
+        c.advance(Bytes::from_static(b"y").into());
+
+        let c1 = RangeContinuation::from_bytes(c.to_bytes()).unwrap();
+
+        assert_eq!(
+            c1.get_last_key_ref(),
+            Some(&Key::from(Bytes::from_static(b"y")))
+        );
+        assert_eq!(c1.get_limit(), 4);
+
+        c.done = true;
+
+        assert!(RangeContinuation::from_bytes(c.to_bytes())
+            .unwrap()
+            .is_end());
+    }
+
+    #[test]
+    fn range_continuation_from_bytes_invalid() {
+        assert_eq!(
+            RangeContinuation::from_bytes(Bytes::from_static(b"\xFF")).unwrap_err(),
+            FdbError::new(RANGE_CONTINUATION_INVALID)
+        );
+
+        assert_eq!(
+            RangeContinuation::from_bytes({
+                let mut t = Tuple::new();
+                t.add_string("hello".to_string());
+                t.pack()
+            })
+            .unwrap_err(),
+            FdbError::new(RANGE_CONTINUATION_INVALID)
+        );
+
+        let c = RangeContinuation::new(
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"a")),
+            KeySelector::first_greater_or_equal(Bytes::from_static(b"z")),
+            RangeOptions::default(),
+        );
+
+        // Returns the encoded `c` with the element at `index` replaced
+        // by `value`.
+        let replace = |index, value| {
+            let mut t = Tuple::new();
+            Tuple::from_bytes(c.to_bytes())
+                .unwrap()
+                .into_elements()
+                .into_iter()
+                .enumerate()
+                .for_each(|(i, e)| {
+                    if i == index {
+                        t.add_i8(value);
+                    } else {
+                        t.append(Tuple::from_elements(vec![e]));
+                    }
+                });
+            t
+        };
+
+        // Unknown version and invalid streaming mode.
+        for (index, value) in vec![(0, 1), (10, 100)] {
+            assert_eq!(
+                RangeContinuation::from_bytes(replace(index, value).pack()).unwrap_err(),
+                FdbError::new(RANGE_CONTINUATION_INVALID)
+            );
+        }
+
+        // Extra element.
+        let mut t = Tuple::from_bytes(c.to_bytes()).unwrap();
+        t.add_i32(1);
+
+        assert_eq!(
+            RangeContinuation::from_bytes(t.pack()).unwrap_err(),
+            FdbError::new(RANGE_CONTINUATION_INVALID)
+        );
     }
 }
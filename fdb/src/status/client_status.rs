@@ -0,0 +1,89 @@
+use serde::Deserialize;
+
+use crate::status::StatusMessage;
+
+/// Status of the cluster, as seen by the client that read the status.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ClientStatus {
+    coordinators: Option<CoordinatorsStatus>,
+    database_status: Option<DatabaseStatus>,
+    messages: Vec<StatusMessage>,
+}
+
+impl ClientStatus {
+    /// Gets the status of the coordinators.
+    pub fn get_coordinators(&self) -> Option<&CoordinatorsStatus> {
+        self.coordinators.as_ref()
+    }
+
+    /// Gets the availability and health of the database.
+    pub fn get_database_status(&self) -> Option<&DatabaseStatus> {
+        self.database_status.as_ref()
+    }
+
+    /// Gets the messages reported by the client.
+    pub fn get_messages(&self) -> &[StatusMessage] {
+        &self.messages
+    }
+}
+
+/// Status of the coordinators of the cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CoordinatorsStatus {
+    coordinators: Vec<CoordinatorStatus>,
+    quorum_reachable: Option<bool>,
+}
+
+impl CoordinatorsStatus {
+    /// Gets the status of each coordinator.
+    pub fn get_coordinators(&self) -> &[CoordinatorStatus] {
+        &self.coordinators
+    }
+
+    /// Gets whether a quorum of coordinators is reachable.
+    pub fn get_quorum_reachable(&self) -> Option<bool> {
+        self.quorum_reachable
+    }
+}
+
+/// Status of a single coordinator.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct CoordinatorStatus {
+    address: String,
+    reachable: Option<bool>,
+}
+
+impl CoordinatorStatus {
+    /// Gets the address of the coordinator.
+    pub fn get_address(&self) -> &str {
+        &self.address
+    }
+
+    /// Gets whether the coordinator is reachable from the client.
+    pub fn get_reachable(&self) -> Option<bool> {
+        self.reachable
+    }
+}
+
+/// Availability and health of the database, as seen by the client.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DatabaseStatus {
+    available: Option<bool>,
+    healthy: Option<bool>,
+}
+
+impl DatabaseStatus {
+    /// Gets whether the database is available.
+    pub fn get_available(&self) -> Option<bool> {
+        self.available
+    }
+
+    /// Gets whether the database is healthy.
+    pub fn get_healthy(&self) -> Option<bool> {
+        self.healthy
+    }
+}
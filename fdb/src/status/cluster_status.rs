@@ -0,0 +1,283 @@
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+
+use crate::status::{ProcessStatus, WorkloadStatus};
+
+/// Status of the cluster, as reported by the cluster controller.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ClusterStatus {
+    database_available: Option<bool>,
+    data: Option<DataStatus>,
+    latency_probe: Option<LatencyProbe>,
+    workload: Option<WorkloadStatus>,
+    processes: BTreeMap<String, ProcessStatus>,
+    messages: Vec<StatusMessage>,
+    recovery_state: Option<RecoveryState>,
+    fault_tolerance: Option<FaultTolerance>,
+    generation: Option<i64>,
+    protocol_version: Option<String>,
+}
+
+impl ClusterStatus {
+    /// Gets whether the database is available.
+    pub fn get_database_available(&self) -> Option<bool> {
+        self.database_available
+    }
+
+    /// Gets the status of data distribution.
+    pub fn get_data(&self) -> Option<&DataStatus> {
+        self.data.as_ref()
+    }
+
+    /// Gets the latencies measured by the cluster controller.
+    pub fn get_latency_probe(&self) -> Option<&LatencyProbe> {
+        self.latency_probe.as_ref()
+    }
+
+    /// Gets the workload of the cluster.
+    pub fn get_workload(&self) -> Option<&WorkloadStatus> {
+        self.workload.as_ref()
+    }
+
+    /// Gets the status of the processes in the cluster, by process id.
+    pub fn get_processes(&self) -> &BTreeMap<String, ProcessStatus> {
+        &self.processes
+    }
+
+    /// Gets the messages reported by the cluster.
+    pub fn get_messages(&self) -> &[StatusMessage] {
+        &self.messages
+    }
+
+    /// Gets the recovery state of the cluster.
+    pub fn get_recovery_state(&self) -> Option<&RecoveryState> {
+        self.recovery_state.as_ref()
+    }
+
+    /// Gets the fault tolerance of the cluster.
+    pub fn get_fault_tolerance(&self) -> Option<&FaultTolerance> {
+        self.fault_tolerance.as_ref()
+    }
+
+    /// Gets the generation of the cluster, which increases with each
+    /// recovery.
+    pub fn get_generation(&self) -> Option<i64> {
+        self.generation
+    }
+
+    /// Gets the protocol version of the cluster.
+    pub fn get_protocol_version(&self) -> Option<&str> {
+        self.protocol_version.as_deref()
+    }
+}
+
+/// Status of data distribution.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DataStatus {
+    state: Option<DataState>,
+    total_kv_size_bytes: Option<i64>,
+    total_disk_used_bytes: Option<i64>,
+    partitions_count: Option<i64>,
+    moving_data: Option<MovingData>,
+    least_operating_space_bytes_storage_server: Option<i64>,
+    least_operating_space_bytes_log_server: Option<i64>,
+}
+
+impl DataStatus {
+    /// Gets the state of data distribution.
+    pub fn get_state(&self) -> Option<&DataState> {
+        self.state.as_ref()
+    }
+
+    /// Gets the total size of key-values in the database, in bytes.
+    pub fn get_total_kv_size_bytes(&self) -> Option<i64> {
+        self.total_kv_size_bytes
+    }
+
+    /// Gets the total disk space used by the cluster, in bytes.
+    pub fn get_total_disk_used_bytes(&self) -> Option<i64> {
+        self.total_disk_used_bytes
+    }
+
+    /// Gets the number of partitions (shards) in the database.
+    pub fn get_partitions_count(&self) -> Option<i64> {
+        self.partitions_count
+    }
+
+    /// Gets the status of data being moved between servers.
+    pub fn get_moving_data(&self) -> Option<&MovingData> {
+        self.moving_data.as_ref()
+    }
+
+    /// Gets the smallest operating space on a storage server, in bytes.
+    pub fn get_least_operating_space_bytes_storage_server(&self) -> Option<i64> {
+        self.least_operating_space_bytes_storage_server
+    }
+
+    /// Gets the smallest operating space on a log server, in bytes.
+    pub fn get_least_operating_space_bytes_log_server(&self) -> Option<i64> {
+        self.least_operating_space_bytes_log_server
+    }
+}
+
+/// State of data distribution.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DataState {
+    healthy: Option<bool>,
+    name: Option<String>,
+    description: Option<String>,
+    min_replicas_remaining: Option<i64>,
+}
+
+impl DataState {
+    /// Gets whether data distribution is healthy.
+    pub fn get_healthy(&self) -> Option<bool> {
+        self.healthy
+    }
+
+    /// Gets the name of the state, such as `healthy` or
+    /// `healthy_repartitioning`.
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Gets the human readable description of the state.
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// Gets the smallest number of replicas remaining for any data.
+    pub fn get_min_replicas_remaining(&self) -> Option<i64> {
+        self.min_replicas_remaining
+    }
+}
+
+/// Status of data being moved between servers.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct MovingData {
+    in_flight_bytes: Option<i64>,
+    in_queue_bytes: Option<i64>,
+    total_written_bytes: Option<i64>,
+}
+
+impl MovingData {
+    /// Gets the number of bytes being moved.
+    pub fn get_in_flight_bytes(&self) -> Option<i64> {
+        self.in_flight_bytes
+    }
+
+    /// Gets the number of bytes waiting to be moved.
+    pub fn get_in_queue_bytes(&self) -> Option<i64> {
+        self.in_queue_bytes
+    }
+
+    /// Gets the total number of bytes written by data movement.
+    pub fn get_total_written_bytes(&self) -> Option<i64> {
+        self.total_written_bytes
+    }
+}
+
+/// Latencies measured by the cluster controller, in seconds.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LatencyProbe {
+    transaction_start_seconds: Option<f64>,
+    immediate_priority_transaction_start_seconds: Option<f64>,
+    batch_priority_transaction_start_seconds: Option<f64>,
+    read_seconds: Option<f64>,
+    commit_seconds: Option<f64>,
+}
+
+impl LatencyProbe {
+    /// Gets the latency of getting a read version at default priority.
+    pub fn get_transaction_start_seconds(&self) -> Option<f64> {
+        self.transaction_start_seconds
+    }
+
+    /// Gets the latency of getting a read version at immediate priority.
+    pub fn get_immediate_priority_transaction_start_seconds(&self) -> Option<f64> {
+        self.immediate_priority_transaction_start_seconds
+    }
+
+    /// Gets the latency of getting a read version at batch priority.
+    pub fn get_batch_priority_transaction_start_seconds(&self) -> Option<f64> {
+        self.batch_priority_transaction_start_seconds
+    }
+
+    /// Gets the latency of reading a key.
+    pub fn get_read_seconds(&self) -> Option<f64> {
+        self.read_seconds
+    }
+
+    /// Gets the latency of committing a transaction.
+    pub fn get_commit_seconds(&self) -> Option<f64> {
+        self.commit_seconds
+    }
+}
+
+/// Recovery state of the cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RecoveryState {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+impl RecoveryState {
+    /// Gets the name of the recovery state, such as `fully_recovered`.
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Gets the human readable description of the recovery state.
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Fault tolerance of the cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FaultTolerance {
+    max_zone_failures_without_losing_availability: Option<i64>,
+    max_zone_failures_without_losing_data: Option<i64>,
+}
+
+impl FaultTolerance {
+    /// Gets the number of zones that can fail without the database
+    /// becoming unavailable.
+    pub fn get_max_zone_failures_without_losing_availability(&self) -> Option<i64> {
+        self.max_zone_failures_without_losing_availability
+    }
+
+    /// Gets the number of zones that can fail without losing data.
+    pub fn get_max_zone_failures_without_losing_data(&self) -> Option<i64> {
+        self.max_zone_failures_without_losing_data
+    }
+}
+
+/// A message reported in status, describing a condition in the cluster,
+/// client or a process.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct StatusMessage {
+    name: String,
+    description: Option<String>,
+}
+
+impl StatusMessage {
+    /// Gets the name of the message, such as `unreachable_processes`.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Gets the human readable description of the message.
+    pub fn get_description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
@@ -0,0 +1,277 @@
+use bytes::Bytes;
+
+use serde::Deserialize;
+
+use crate::database::FdbDatabase;
+use crate::error::{FdbError, FdbResult, STATUS_INVALID_JSON};
+use crate::status::{ClientStatus, ClusterStatus};
+use crate::transaction::ReadTransaction;
+
+const STATUS_JSON_KEY: &[u8; 14] = b"\xFF\xFF/status/json";
+
+/// Status of a FDB cluster, read from the `\xff\xff/status/json`
+/// special key.
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::status
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct FdbStatus {
+    client: Option<ClientStatus>,
+    cluster: Option<ClusterStatus>,
+}
+
+impl FdbStatus {
+    /// Reads the status of the cluster using a transaction created
+    /// on the specified [`FdbDatabase`].
+    pub async fn get_db(db: &FdbDatabase) -> FdbResult<FdbStatus> {
+        db.read(|tr| async move { FdbStatus::get_tr(&tr).await })
+            .await
+    }
+
+    /// Reads the status of the cluster.
+    pub async fn get_tr<T>(tr: &T) -> FdbResult<FdbStatus>
+    where
+        T: ReadTransaction,
+    {
+        let json = tr
+            .get(Bytes::from_static(STATUS_JSON_KEY))
+            .await?
+            .ok_or_else(|| FdbError::new(STATUS_INVALID_JSON))?;
+
+        FdbStatus::from_json(Bytes::from(json).as_ref())
+    }
+
+    /// Parses status JSON, such as the output of `status json`
+    /// command in `fdbcli`.
+    pub fn from_json(json: &[u8]) -> FdbResult<FdbStatus> {
+        serde_json::from_slice(json).map_err(|_| FdbError::new(STATUS_INVALID_JSON))
+    }
+
+    /// Gets the status of the cluster, as seen by the client.
+    pub fn get_client(&self) -> Option<&ClientStatus> {
+        self.client.as_ref()
+    }
+
+    /// Gets the status of the cluster, as reported by the cluster
+    /// controller.
+    ///
+    /// This is `None` when the cluster controller could not be
+    /// reached.
+    pub fn get_cluster(&self) -> Option<&ClusterStatus> {
+        self.cluster.as_ref()
+    }
+
+    /// Returns `true` if the database is available.
+    ///
+    /// Availability reported by the client is used if present,
+    /// otherwise the availability reported by the cluster controller
+    /// is used.
+    pub fn is_available(&self) -> bool {
+        self.client
+            .as_ref()
+            .and_then(|client| client.get_database_status())
+            .and_then(|database_status| database_status.get_available())
+            .or_else(|| {
+                self.cluster
+                    .as_ref()
+                    .and_then(|cluster| cluster.get_database_available())
+            })
+            .unwrap_or(false)
+    }
+
+    /// Returns `true` if the database is healthy.
+    ///
+    /// Health reported by the client is used if present. Otherwise
+    /// the database is healthy when it is available and data
+    /// distribution is healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.client
+            .as_ref()
+            .and_then(|client| client.get_database_status())
+            .and_then(|database_status| database_status.get_healthy())
+            .unwrap_or_else(|| {
+                self.is_available()
+                    && self
+                        .cluster
+                        .as_ref()
+                        .and_then(|cluster| cluster.get_data())
+                        .and_then(|data| data.get_state())
+                        .and_then(|state| state.get_healthy())
+                        .unwrap_or(false)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use crate::error::{FdbError, STATUS_INVALID_JSON};
+
+    use super::FdbStatus;
+
+    // Abridged output of `status json`, with an unknown field
+    // added.
+    const STATUS_JSON: &[u8] = br#"{
+        "client": {
+            "coordinators": {
+                "coordinators": [
+                    { "address": "127.0.0.1:4500", "protocol": "fdb00b071010000", "reachable": true }
+                ],
+                "quorum_reachable": true
+            },
+            "database_status": { "available": true, "healthy": true },
+            "messages": [],
+            "timestamp": 1650000000
+        },
+        "cluster": {
+            "database_available": true,
+            "data": {
+                "state": { "healthy": true, "min_replicas_remaining": 1, "name": "healthy" },
+                "total_kv_size_bytes": 1024,
+                "partitions_count": 2,
+                "moving_data": { "in_flight_bytes": 0, "in_queue_bytes": 0, "total_written_bytes": 0 }
+            },
+            "latency_probe": { "commit_seconds": 0.002, "read_seconds": 0.0001, "transaction_start_seconds": 0.0003 },
+            "workload": {
+                "operations": { "reads": { "counter": 10, "hz": 1.5, "roughness": 1.0 } },
+                "transactions": { "committed": { "counter": 3, "hz": 0.5, "roughness": 1.0 } }
+            },
+            "processes": {
+                "0123456789abcdef": {
+                    "address": "127.0.0.1:4500",
+                    "class_type": "unset",
+                    "roles": [ { "role": "storage", "id": "abc" }, { "role": "log" } ],
+                    "excluded": false,
+                    "cpu": { "usage_cores": 0.25 },
+                    "memory": { "used_bytes": 100, "limit_bytes": 1000 }
+                }
+            },
+            "messages": [ { "name": "unknown_message", "description": "Some description" } ],
+            "recovery_state": { "name": "fully_recovered", "description": "Recovery complete." },
+            "fault_tolerance": {
+                "max_zone_failures_without_losing_availability": 0,
+                "max_zone_failures_without_losing_data": 0
+            },
+            "generation": 2,
+            "protocol_version": "fdb00b071010000",
+            "unknown_field": { "nested": [1, 2, 3] }
+        }
+    }"#;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    FdbStatus:
+	        Send &
+		Sync &
+		Clone &
+		PartialEq &
+		!Copy));
+    }
+
+    #[test]
+    fn from_json() {
+        let status = FdbStatus::from_json(STATUS_JSON).unwrap();
+
+        assert!(status.is_available());
+        assert!(status.is_healthy());
+
+        let client = status.get_client().unwrap();
+        let coordinators = client.get_coordinators().unwrap();
+        assert_eq!(coordinators.get_quorum_reachable(), Some(true));
+        assert_eq!(coordinators.get_coordinators().len(), 1);
+        assert_eq!(
+            coordinators.get_coordinators()[0].get_address(),
+            "127.0.0.1:4500"
+        );
+
+        let cluster = status.get_cluster().unwrap();
+        let data = cluster.get_data().unwrap();
+        assert_eq!(data.get_total_kv_size_bytes(), Some(1024));
+        assert_eq!(data.get_total_disk_used_bytes(), None);
+        assert_eq!(data.get_state().unwrap().get_name(), Some("healthy"));
+        assert_eq!(
+            cluster.get_latency_probe().unwrap().get_commit_seconds(),
+            Some(0.002)
+        );
+        assert_eq!(
+            cluster
+                .get_workload()
+                .unwrap()
+                .get_operations()
+                .unwrap()
+                .get_reads()
+                .unwrap()
+                .get_counter(),
+            Some(10)
+        );
+
+        let process = cluster.get_processes().get("0123456789abcdef").unwrap();
+        assert_eq!(process.get_roles().len(), 2);
+        assert_eq!(process.get_roles()[0].get_role(), "storage");
+        assert_eq!(process.get_roles()[1].get_id(), None);
+        assert_eq!(process.get_cpu().unwrap().get_usage_cores(), Some(0.25));
+        assert_eq!(process.get_excluded(), Some(false));
+
+        assert_eq!(cluster.get_messages()[0].get_name(), "unknown_message");
+        assert_eq!(
+            cluster.get_recovery_state().unwrap().get_name(),
+            Some("fully_recovered")
+        );
+        assert_eq!(cluster.get_generation(), Some(2));
+    }
+
+    #[test]
+    fn is_available_is_healthy() {
+        // Nothing reported.
+        let status = FdbStatus::from_json(b"{}").unwrap();
+        assert!(!status.is_available());
+        assert!(!status.is_healthy());
+
+        // Client status is used when present.
+        let status = FdbStatus::from_json(
+            br#"{
+                "client": { "database_status": { "available": true, "healthy": false } },
+                "cluster": { "database_available": false, "data": { "state": { "healthy": true } } }
+            }"#,
+        )
+        .unwrap();
+        assert!(status.is_available());
+        assert!(!status.is_healthy());
+
+        // Otherwise cluster status is used.
+        let status = FdbStatus::from_json(
+            br#"{
+                "cluster": { "database_available": true, "data": { "state": { "healthy": true } } }
+            }"#,
+        )
+        .unwrap();
+        assert!(status.is_available());
+        assert!(status.is_healthy());
+
+        let status = FdbStatus::from_json(
+            br#"{
+                "cluster": { "database_available": true, "data": { "state": { "healthy": false } } }
+            }"#,
+        )
+        .unwrap();
+        assert!(status.is_available());
+        assert!(!status.is_healthy());
+    }
+
+    #[test]
+    fn from_json_invalid() {
+        assert_eq!(
+            FdbStatus::from_json(b"not json"),
+            Err(FdbError::new(STATUS_INVALID_JSON))
+        );
+        assert_eq!(
+            FdbStatus::from_json(br#"{ "cluster": { "generation": "two" } }"#),
+            Err(FdbError::new(STATUS_INVALID_JSON))
+        );
+    }
+}
@@ -0,0 +1,47 @@
+//! Provides [`FdbStatus`] type, for reading the status of a FDB
+//! cluster.
+//!
+//! FDB reports the status of the cluster as a JSON document in the
+//! `\xff\xff/status/json` special key. This is the same document that
+//! is printed by `status json` command in `fdbcli`. This module is
+//! available when the `status` feature is enabled.
+//!
+//! [`FdbStatus::get_db`] and [`FdbStatus::get_tr`] read the special
+//! key and deserialize the document into typed structs. Commonly used
+//! parts of the document are provided.
+//!
+//! - [`ClientStatus`] - Coordinators and database availability, as
+//!   seen by the client.
+//!
+//! - [`ClusterStatus`] - Data distribution ([`DataStatus`]), latency
+//!   probes ([`LatencyProbe`]), workload ([`WorkloadStatus`]),
+//!   processes and their roles ([`ProcessStatus`]), recovery state
+//!   and fault tolerance.
+//!
+//! The schema of the document changes between FDB versions, and the
+//! cluster omits sections that it cannot compute. Unknown fields are
+//! ignored, and missing fields are returned as `None` or as an empty
+//! collection. [`FdbStatus::is_available`] and
+//! [`FdbStatus::is_healthy`] summarize the status.
+//!
+//! **Note:** Reading the status is expensive, as the cluster
+//! controller contacts every process in the cluster. It is meant for
+//! monitoring, and should not be read in a hot path.
+
+mod client_status;
+mod cluster_status;
+mod fdb_status;
+mod process_status;
+mod workload_status;
+
+pub use client_status::{ClientStatus, CoordinatorStatus, CoordinatorsStatus, DatabaseStatus};
+pub use cluster_status::{
+    ClusterStatus, DataState, DataStatus, FaultTolerance, LatencyProbe, MovingData, RecoveryState,
+    StatusMessage,
+};
+pub use fdb_status::FdbStatus;
+pub use process_status::{ProcessCpu, ProcessMemory, ProcessRole, ProcessStatus};
+pub use workload_status::{
+    WorkloadBytes, WorkloadKeys, WorkloadOperations, WorkloadRate, WorkloadStatus,
+    WorkloadTransactions,
+};
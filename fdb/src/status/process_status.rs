@@ -0,0 +1,137 @@
+use serde::Deserialize;
+
+use crate::status::StatusMessage;
+
+/// Status of a process in the cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProcessStatus {
+    address: Option<String>,
+    machine_id: Option<String>,
+    class_type: Option<String>,
+    roles: Vec<ProcessRole>,
+    excluded: Option<bool>,
+    degraded: Option<bool>,
+    version: Option<String>,
+    uptime_seconds: Option<f64>,
+    cpu: Option<ProcessCpu>,
+    memory: Option<ProcessMemory>,
+    messages: Vec<StatusMessage>,
+}
+
+impl ProcessStatus {
+    /// Gets the address of the process.
+    pub fn get_address(&self) -> Option<&str> {
+        self.address.as_deref()
+    }
+
+    /// Gets the id of the machine that the process runs on.
+    pub fn get_machine_id(&self) -> Option<&str> {
+        self.machine_id.as_deref()
+    }
+
+    /// Gets the process class, such as `storage` or `transaction`.
+    pub fn get_class_type(&self) -> Option<&str> {
+        self.class_type.as_deref()
+    }
+
+    /// Gets the roles of the process.
+    pub fn get_roles(&self) -> &[ProcessRole] {
+        &self.roles
+    }
+
+    /// Gets whether the process is excluded.
+    pub fn get_excluded(&self) -> Option<bool> {
+        self.excluded
+    }
+
+    /// Gets whether the process is degraded.
+    pub fn get_degraded(&self) -> Option<bool> {
+        self.degraded
+    }
+
+    /// Gets the FDB version of the process.
+    pub fn get_version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Gets the uptime of the process, in seconds.
+    pub fn get_uptime_seconds(&self) -> Option<f64> {
+        self.uptime_seconds
+    }
+
+    /// Gets the CPU usage of the process.
+    pub fn get_cpu(&self) -> Option<&ProcessCpu> {
+        self.cpu.as_ref()
+    }
+
+    /// Gets the memory usage of the process.
+    pub fn get_memory(&self) -> Option<&ProcessMemory> {
+        self.memory.as_ref()
+    }
+
+    /// Gets the messages reported by the process.
+    pub fn get_messages(&self) -> &[StatusMessage] {
+        &self.messages
+    }
+}
+
+/// A role of a process, such as `storage`, `log` or `proxy`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProcessRole {
+    role: String,
+    id: Option<String>,
+}
+
+impl ProcessRole {
+    /// Gets the name of the role.
+    pub fn get_role(&self) -> &str {
+        &self.role
+    }
+
+    /// Gets the id of the role.
+    pub fn get_id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+}
+
+/// CPU usage of a process.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProcessCpu {
+    usage_cores: Option<f64>,
+}
+
+impl ProcessCpu {
+    /// Gets the number of CPU cores used by the process.
+    pub fn get_usage_cores(&self) -> Option<f64> {
+        self.usage_cores
+    }
+}
+
+/// Memory usage of a process.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProcessMemory {
+    used_bytes: Option<i64>,
+    available_bytes: Option<i64>,
+    limit_bytes: Option<i64>,
+}
+
+impl ProcessMemory {
+    /// Gets the memory used by the process, in bytes.
+    pub fn get_used_bytes(&self) -> Option<i64> {
+        self.used_bytes
+    }
+
+    /// Gets the memory available to the process, in bytes.
+    pub fn get_available_bytes(&self) -> Option<i64> {
+        self.available_bytes
+    }
+
+    /// Gets the memory limit of the process, in bytes.
+    pub fn get_limit_bytes(&self) -> Option<i64> {
+        self.limit_bytes
+    }
+}
@@ -0,0 +1,153 @@
+use serde::Deserialize;
+
+/// Workload of the cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkloadStatus {
+    operations: Option<WorkloadOperations>,
+    bytes: Option<WorkloadBytes>,
+    keys: Option<WorkloadKeys>,
+    transactions: Option<WorkloadTransactions>,
+}
+
+impl WorkloadStatus {
+    /// Gets the rates of read and write operations.
+    pub fn get_operations(&self) -> Option<&WorkloadOperations> {
+        self.operations.as_ref()
+    }
+
+    /// Gets the rates of bytes read and written.
+    pub fn get_bytes(&self) -> Option<&WorkloadBytes> {
+        self.bytes.as_ref()
+    }
+
+    /// Gets the rate of keys read.
+    pub fn get_keys(&self) -> Option<&WorkloadKeys> {
+        self.keys.as_ref()
+    }
+
+    /// Gets the rates of transactions started, committed and conflicted.
+    pub fn get_transactions(&self) -> Option<&WorkloadTransactions> {
+        self.transactions.as_ref()
+    }
+}
+
+/// Rates of read and write operations.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkloadOperations {
+    reads: Option<WorkloadRate>,
+    writes: Option<WorkloadRate>,
+    read_requests: Option<WorkloadRate>,
+}
+
+impl WorkloadOperations {
+    /// Gets the rate of read operations.
+    pub fn get_reads(&self) -> Option<&WorkloadRate> {
+        self.reads.as_ref()
+    }
+
+    /// Gets the rate of write operations.
+    pub fn get_writes(&self) -> Option<&WorkloadRate> {
+        self.writes.as_ref()
+    }
+
+    /// Gets the rate of read requests.
+    pub fn get_read_requests(&self) -> Option<&WorkloadRate> {
+        self.read_requests.as_ref()
+    }
+}
+
+/// Rates of bytes read and written.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkloadBytes {
+    read: Option<WorkloadRate>,
+    written: Option<WorkloadRate>,
+}
+
+impl WorkloadBytes {
+    /// Gets the rate of bytes read.
+    pub fn get_read(&self) -> Option<&WorkloadRate> {
+        self.read.as_ref()
+    }
+
+    /// Gets the rate of bytes written.
+    pub fn get_written(&self) -> Option<&WorkloadRate> {
+        self.written.as_ref()
+    }
+}
+
+/// Rate of keys read.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkloadKeys {
+    read: Option<WorkloadRate>,
+}
+
+impl WorkloadKeys {
+    /// Gets the rate of keys read.
+    pub fn get_read(&self) -> Option<&WorkloadRate> {
+        self.read.as_ref()
+    }
+}
+
+/// Rates of transactions started, committed and conflicted.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkloadTransactions {
+    started: Option<WorkloadRate>,
+    committed: Option<WorkloadRate>,
+    conflicted: Option<WorkloadRate>,
+    rejected_for_queued_too_long: Option<WorkloadRate>,
+}
+
+impl WorkloadTransactions {
+    /// Gets the rate of transactions started.
+    pub fn get_started(&self) -> Option<&WorkloadRate> {
+        self.started.as_ref()
+    }
+
+    /// Gets the rate of transactions committed.
+    pub fn get_committed(&self) -> Option<&WorkloadRate> {
+        self.committed.as_ref()
+    }
+
+    /// Gets the rate of transactions that conflicted.
+    pub fn get_conflicted(&self) -> Option<&WorkloadRate> {
+        self.conflicted.as_ref()
+    }
+
+    /// Gets the rate of transactions rejected because they were queued
+    /// for too long.
+    pub fn get_rejected_for_queued_too_long(&self) -> Option<&WorkloadRate> {
+        self.rejected_for_queued_too_long.as_ref()
+    }
+}
+
+/// A rate in the workload of the cluster.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct WorkloadRate {
+    counter: Option<i64>,
+    hz: Option<f64>,
+    roughness: Option<f64>,
+}
+
+impl WorkloadRate {
+    /// Gets the total count since the cluster started.
+    pub fn get_counter(&self) -> Option<i64> {
+        self.counter
+    }
+
+    /// Gets the rate per second.
+    pub fn get_hz(&self) -> Option<f64> {
+        self.hz
+    }
+
+    /// Gets the roughness of the rate. A roughness of `1.0` means events
+    /// are randomly distributed in time.
+    pub fn get_roughness(&self) -> Option<f64> {
+        self.roughness
+    }
+}
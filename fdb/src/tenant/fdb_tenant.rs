@@ -2,10 +2,15 @@ use std::future::Future;
 use std::ptr::{self, NonNull};
 use std::sync::Arc;
 
-use crate::error::{check, FdbError, FdbResult};
-use crate::transaction::{FdbReadTransaction, FdbTransaction, ReadTransaction, Transaction};
+use crate::error::{check, FdbResult};
+use crate::range::Range;
+use crate::transaction::retry_loop;
+use crate::transaction::{FdbReadTransaction, FdbTransaction};
 use crate::Tenant;
 
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
 /// [`FdbTenant`] provides APIs for transactionally interacting with
 /// [`Tenant`]s.
 ///
@@ -18,7 +23,7 @@ use crate::Tenant;
 ///
 /// A [`FdbTenant`] can be created using [`open_tenant`] method.
 ///
-/// [`commit`]: FdbTransaction::commit
+/// [`commit`]: crate::transaction::Transaction::commit
 /// [`read`]: FdbTenant::read
 /// [`run`]: FdbTenant::run
 /// [`open_tenant`]: crate::database::FdbDatabase::open_tenant
@@ -70,47 +75,67 @@ impl FdbTenant {
     /// encountered. Therefore the closure should be prepared to be
     /// called more than once. This consideration means that the
     /// closure should use caution when modifying state.
-    pub async fn run<T, F, Fut>(&self, mut f: F) -> FdbResult<T>
+    pub async fn run<T, F, Fut>(&self, f: F) -> FdbResult<T>
     where
         F: FnMut(FdbTransaction) -> Fut,
         Fut: Future<Output = FdbResult<T>>,
     {
         let t = self.create_transaction()?;
 
-        loop {
-            let ret_val = f(t.clone()).await;
-
-            // Closure returned an error
-            if let Err(e) = ret_val {
-                if FdbError::layer_error(e.code()) {
-                    // Check if it is a layer error. If so, just
-                    // return it.
-                    return Err(e);
-                } else if let Err(e1) = unsafe { t.on_error(e) }.await {
-                    // Check if `on_error` returned an error. This
-                    // means we have a non-retryable error.
-                    return Err(e1);
-                } else {
-                    continue;
-                }
-            }
+        let fut = retry_loop::run(t, f);
 
-            // No error from closure. Attempt to commit the
-            // transaction.
-            if let Err(e) = unsafe { t.commit() }.await {
-                // Commit returned an error
-                if let Err(e1) = unsafe { t.on_error(e) }.await {
-                    // Check if `on_error` returned an error. This
-                    // means we have a non-retryable error.
-                    return Err(e1);
-                } else {
-                    continue;
-                }
-            }
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(tracing::debug_span!("run", tenant = ?self.name));
 
-            // Commit successful, return `Ok(T)`
-            return ret_val;
-        }
+        fut.await
+    }
+
+    /// Runs a closure in the context that takes a [`FdbTransaction`],
+    /// and reports the key ranges that caused the transaction to
+    /// conflict.
+    ///
+    /// This is the same as [`run`], except that
+    /// [`TransactionOption::ReportConflictingKeys`] is set on the
+    /// transaction, and `on_conflict` is called with the conflicting
+    /// key ranges each time [`commit`] fails with a `not_committed`
+    /// (1020) error, before the closure is retried. If retries are
+    /// exhausted (for example, when
+    /// [`TransactionOption::RetryLimit`] is set), the last call to
+    /// `on_conflict` has the key ranges that caused the returned
+    /// error.
+    ///
+    /// Errors while getting the conflicting key ranges are ignored.
+    ///
+    /// # Note
+    ///
+    /// The closure `FnMut: FnMut(FdbTransaction) -> Fut` will run
+    /// multiple times (retry) when certain errors are
+    /// encountered. Therefore the closure should be prepared to be
+    /// called more than once. This consideration means that the
+    /// closure should use caution when modifying state.
+    ///
+    /// [`run`]: FdbTenant::run
+    /// [`commit`]: crate::transaction::Transaction::commit
+    /// [`TransactionOption::ReportConflictingKeys`]: crate::transaction::TransactionOption::ReportConflictingKeys
+    /// [`TransactionOption::RetryLimit`]: crate::transaction::TransactionOption::RetryLimit
+    pub async fn run_with_conflicting_keys<T, F, Fut, C>(
+        &self,
+        f: F,
+        on_conflict: C,
+    ) -> FdbResult<T>
+    where
+        F: FnMut(FdbTransaction) -> Fut,
+        Fut: Future<Output = FdbResult<T>>,
+        C: FnMut(Vec<Range>),
+    {
+        let t = self.create_transaction()?;
+
+        let fut = retry_loop::run_with_conflicting_keys(t, f, Some(on_conflict));
+
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(tracing::debug_span!("run", tenant = ?self.name));
+
+        fut.await
     }
 
     /// Runs a closure in the context that takes a
@@ -128,34 +153,19 @@ impl FdbTenant {
     // `FnMut(FdbReadTransaction) -> Fut` because we are not allowing
     // any mutations to occur. We are only concerned about retrying in
     // case of retryable errors.
-    pub async fn read<T, F, Fut>(&self, mut f: F) -> FdbResult<T>
+    pub async fn read<T, F, Fut>(&self, f: F) -> FdbResult<T>
     where
         F: FnMut(FdbReadTransaction) -> Fut,
         Fut: Future<Output = FdbResult<T>>,
     {
         let t = self.create_transaction()?.snapshot();
-        loop {
-            let ret_val = f(t.clone()).await;
-
-            // Closure returned an error
-            if let Err(e) = ret_val {
-                if FdbError::layer_error(e.code()) {
-                    // Check if it is a layer error. If so, just
-                    // return it.
-                    return Err(e);
-                } else if let Err(e1) = unsafe { t.on_error(e) }.await {
-                    // Check if `on_error` returned an error. This
-                    // means we have a non-retryable error.
-                    return Err(e1);
-                } else {
-                    continue;
-                }
-            }
 
-            // We don't need to commit read transaction, return
-            // `Ok(T)`
-            return ret_val;
-        }
+        let fut = retry_loop::read(t, f);
+
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(tracing::debug_span!("read", tenant = ?self.name));
+
+        fut.await
     }
 
     pub(crate) fn new(c_ptr: Option<Arc<NonNull<fdb_sys::FDBTenant>>>, name: Tenant) -> FdbTenant {
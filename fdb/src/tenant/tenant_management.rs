@@ -7,6 +7,9 @@ use crate::error::{FdbError, FdbResult};
 use crate::transaction::{FdbTransaction, ReadTransaction, Transaction, TransactionOption};
 use crate::Tenant;
 
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
+
 const TENANT_MAP_PREFIX: &[u8; 25] = b"\xFF\xFF/management/tenant_map/";
 
 /// The FDB API includes function to manage the set of tenants in a
@@ -21,18 +24,23 @@ impl TenantManagement {
         db: &FdbDatabase,
         tenant_name: impl Into<Tenant>,
     ) -> FdbResult<()> {
+        let tenant_name = tenant_name.into();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("create_tenant", tenant = ?tenant_name);
+
         let checked_existence = AtomicBool::new(false);
         let key = {
             let mut b = BytesMut::new();
             b.put(TENANT_MAP_PREFIX.as_ref());
-            b.put(Into::<Bytes>::into(tenant_name.into()));
+            b.put(Into::<Bytes>::into(tenant_name));
             Into::<Bytes>::into(b)
         };
 
         let checked_existence_ref = &checked_existence;
         let key_ref = &key;
 
-        db.run(|tr| async move {
+        let fut = db.run(|tr| async move {
             tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
 
             if checked_existence_ref.load(Ordering::SeqCst) {
@@ -54,19 +62,28 @@ impl TenantManagement {
                     }
                 }
             }
-        })
-        .await
+        });
+
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(span);
+
+        fut.await
     }
 
     /// Creates a new tenant in the cluster.
     pub fn create_tenant_tr(tr: &FdbTransaction, tenant_name: impl Into<Tenant>) -> FdbResult<()> {
+        let tenant_name = tenant_name.into();
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("create_tenant", tenant = ?tenant_name).entered();
+
         tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
 
         tr.set(
             {
                 let mut b = BytesMut::new();
                 b.put(TENANT_MAP_PREFIX.as_ref());
-                b.put(Into::<Bytes>::into(tenant_name.into()));
+                b.put(Into::<Bytes>::into(tenant_name));
                 Into::<Bytes>::into(b)
             },
             Bytes::new(),
@@ -81,18 +98,23 @@ impl TenantManagement {
         db: &FdbDatabase,
         tenant_name: impl Into<Tenant>,
     ) -> FdbResult<()> {
+        let tenant_name = tenant_name.into();
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!("delete_tenant", tenant = ?tenant_name);
+
         let checked_existence = AtomicBool::new(false);
         let key = {
             let mut b = BytesMut::new();
             b.put(TENANT_MAP_PREFIX.as_ref());
-            b.put(Into::<Bytes>::into(tenant_name.into()));
+            b.put(Into::<Bytes>::into(tenant_name));
             Into::<Bytes>::into(b)
         };
 
         let checked_existence_ref = &checked_existence;
         let key_ref = &key;
 
-        db.run(|tr| async move {
+        let fut = db.run(|tr| async move {
             tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
 
             if checked_existence_ref.load(Ordering::SeqCst) {
@@ -114,18 +136,27 @@ impl TenantManagement {
                     }
                 }
             }
-        })
-        .await
+        });
+
+        #[cfg(feature = "tracing")]
+        let fut = fut.instrument(span);
+
+        fut.await
     }
 
     /// Deletes a tenant from the cluster.
     pub fn delete_tenant_tr(tr: &FdbTransaction, tenant_name: impl Into<Tenant>) -> FdbResult<()> {
+        let tenant_name = tenant_name.into();
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("delete_tenant", tenant = ?tenant_name).entered();
+
         tr.set_option(TransactionOption::SpecialKeySpaceEnableWrites)?;
 
         tr.clear({
             let mut b = BytesMut::new();
             b.put(TENANT_MAP_PREFIX.as_ref());
-            b.put(Into::<Bytes>::into(tenant_name.into()));
+            b.put(Into::<Bytes>::into(tenant_name));
             Into::<Bytes>::into(b)
         });
 
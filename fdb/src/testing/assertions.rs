@@ -0,0 +1,106 @@
+use tokio_stream::StreamExt;
+
+use crate::range::{Range, RangeOptions};
+use crate::transaction::ReadTransaction;
+use crate::{Key, KeyValue, Value};
+
+/// Asserts that the key-values in `range` read using `tr` are equal
+/// to `expected`, in order.
+///
+/// # Panic
+///
+/// Panics if the key-values are not equal, or if the range read
+/// fails. On mismatch, the panic message shows both lists of keys and
+/// values.
+pub async fn assert_range_eq<T, I>(tr: &T, range: Range, expected: I)
+where
+    T: ReadTransaction,
+    I: IntoIterator<Item = KeyValue>,
+{
+    let mut range_stream = range.into_stream(tr, RangeOptions::default());
+
+    let mut actual = Vec::new();
+
+    while let Some(x) = range_stream.next().await {
+        let kv = x.unwrap_or_else(|err| panic!("Error occurred during range read: {:?}", err));
+        actual.push(kv.into_parts());
+    }
+
+    let expected = expected
+        .into_iter()
+        .map(KeyValue::into_parts)
+        .collect::<Vec<(Key, Value)>>();
+
+    assert_eq!(actual, expected, "range contents do not match");
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn assert_range_eq() {
+        use bytes::Bytes;
+
+        use crate::memory::MemoryDatabase;
+        use crate::range::Range;
+        use crate::transaction::Transaction;
+        use crate::KeyValue;
+
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        tr.set(Bytes::from_static(b"a"), Bytes::from_static(b"1"));
+        tr.set(Bytes::from_static(b"b"), Bytes::from_static(b"2"));
+        tr.set(Bytes::from_static(b"c"), Bytes::from_static(b"3"));
+
+        super::assert_range_eq(
+            &tr,
+            Range::new(Bytes::from_static(b"a"), Bytes::from_static(b"c")),
+            vec![
+                KeyValue::new(
+                    Bytes::from_static(b"a").into(),
+                    Bytes::from_static(b"1").into(),
+                ),
+                KeyValue::new(
+                    Bytes::from_static(b"b").into(),
+                    Bytes::from_static(b"2").into(),
+                ),
+            ],
+        )
+        .await;
+
+        super::assert_range_eq(
+            &tr,
+            Range::new(Bytes::from_static(b"x"), Bytes::from_static(b"z")),
+            Vec::new(),
+        )
+        .await;
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    #[should_panic(expected = "range contents do not match")]
+    async fn assert_range_eq_mismatch() {
+        use bytes::Bytes;
+
+        use crate::memory::MemoryDatabase;
+        use crate::range::Range;
+        use crate::transaction::Transaction;
+        use crate::KeyValue;
+
+        let db = MemoryDatabase::new();
+
+        let tr = db.create_transaction().unwrap();
+        tr.set(Bytes::from_static(b"a"), Bytes::from_static(b"1"));
+
+        super::assert_range_eq(
+            &tr,
+            Range::new(Bytes::from_static(b"a"), Bytes::from_static(b"z")),
+            vec![KeyValue::new(
+                Bytes::from_static(b"a").into(),
+                Bytes::from_static(b"2").into(),
+            )],
+        )
+        .await;
+    }
+}
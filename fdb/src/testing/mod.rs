@@ -0,0 +1,45 @@
+//! Provides fixtures and assertion helpers for tests that run against
+//! a shared FDB cluster.
+//!
+//! Tests that share a cluster can interfere with each other when they
+//! use the same keys. Each fixture gives a test its own part of the
+//! keyspace, and clears it when the fixture is dropped. This module
+//! is available when the `testing` feature is enabled.
+//!
+//! - [`TestSubspace`] - A [`Subspace`] with a random prefix.
+//!
+//! - `TestTenant` (7.1 only) - A `FdbTenant` with a unique name.
+//!   The cluster must be configured with `tenant_mode` set to
+//!   `optional_experimental` or `required_experimental`.
+//!
+//! [`assert_range_eq`] compares the key-values in a range to an
+//! expected list of [`KeyValue`]s.
+//!
+//! [`run_test`] sets up the network, the database and a
+//! [`TestSubspace`] for an integration test, runs its body and
+//! cleans up afterwards.
+//!
+//! **Note:** Tests should call the `cleanup` method of a fixture
+//! when they are done with it, which clears its keys and returns any
+//! error. Fixtures that are dropped without calling `cleanup` clear
+//! their keys on a best-effort basis, and errors are ignored. Within
+//! a Tokio runtime, the keys are cleared in a background task, which
+//! might not complete before the runtime shuts down. Otherwise, the
+//! current thread is blocked until the keys are cleared.
+//!
+//! [`Subspace`]: crate::subspace::Subspace
+//! [`KeyValue`]: crate::KeyValue
+
+mod assertions;
+mod run_test;
+mod test_subspace;
+
+#[cfg(feature = "fdb-7_1")]
+mod test_tenant;
+
+pub use assertions::assert_range_eq;
+pub use run_test::run_test;
+pub use test_subspace::TestSubspace;
+
+#[cfg(feature = "fdb-7_1")]
+pub use test_tenant::TestTenant;
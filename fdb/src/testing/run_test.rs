@@ -0,0 +1,79 @@
+use tokio::runtime::Runtime;
+
+use std::env;
+use std::error::Error;
+use std::future::Future;
+
+use crate::database::FdbDatabase;
+use crate::subspace::Subspace;
+use crate::testing::TestSubspace;
+
+/// Run the body of an integration test against the cluster in
+/// `FDB_CLUSTER_FILE` environment variable.
+///
+/// [`run_test`] selects the API version, starts the network, opens
+/// the database and creates a [`TestSubspace`]. It then runs `f`
+/// on a new Tokio runtime with the database and the [`Subspace`] of
+/// the [`TestSubspace`]. When `f` returns, the [`TestSubspace`] is
+/// cleaned up and the network is stopped.
+///
+/// The error returned by `f` is returned. Otherwise, any error from
+/// the cleanup is returned.
+///
+/// # Panics
+///
+/// Panics if `FDB_CLUSTER_FILE` is not defined. As the network can
+/// only be started once, [`run_test`] must be called at most once
+/// per process. So an integration test file should have a single
+/// test that calls [`run_test`].
+pub fn run_test<F, Fut>(f: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(FdbDatabase, Subspace) -> Fut,
+    Fut: Future<Output = Result<(), Box<dyn Error>>>,
+{
+    let fdb_cluster_file = env::var("FDB_CLUSTER_FILE").expect("FDB_CLUSTER_FILE not defined!");
+
+    unsafe {
+        crate::select_api_version(crate::FDB_API_VERSION as i32);
+        crate::start_network();
+    }
+
+    let res = run_test_with_network(fdb_cluster_file, f);
+
+    unsafe {
+        crate::stop_network();
+    }
+
+    res
+}
+
+fn run_test_with_network<F, Fut>(fdb_cluster_file: String, f: F) -> Result<(), Box<dyn Error>>
+where
+    F: FnOnce(FdbDatabase, Subspace) -> Fut,
+    Fut: Future<Output = Result<(), Box<dyn Error>>>,
+{
+    let fdb_database = crate::open_database(fdb_cluster_file)?;
+
+    let rt = Runtime::new()?;
+
+    let cloned_fdb_database = fdb_database.clone();
+
+    let res = rt.block_on(async {
+        let fdb_database = cloned_fdb_database;
+
+        let test_subspace = TestSubspace::new(&fdb_database);
+        let subspace = test_subspace.get_subspace_ref().clone();
+
+        let res = f(fdb_database, subspace).await;
+
+        // Clean up even when `f` fails, but return the error from `f`.
+        let cleanup_res = test_subspace.cleanup().await;
+
+        res.and(cleanup_res.map_err(|e| e.into()))
+    });
+
+    // `FdbDatabase` must be dropped before the network is stopped.
+    drop(fdb_database);
+
+    res
+}
@@ -0,0 +1,115 @@
+use bytes::Bytes;
+
+use uuid::Uuid;
+
+use crate::database::FdbDatabase;
+use crate::error::FdbResult;
+use crate::range::Range;
+use crate::subspace::Subspace;
+use crate::transaction::Transaction;
+use crate::tuple::Tuple;
+
+/// A [`Subspace`] with a random prefix, that is cleared using
+/// [`cleanup`], or when dropped.
+///
+/// The prefix is the packed [`Tuple`] `("fdb_test", uuid)`, where
+/// `uuid` is a random [`Uuid`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::testing
+/// [`cleanup`]: TestSubspace::cleanup
+#[derive(Debug)]
+pub struct TestSubspace {
+    db: FdbDatabase,
+    subspace: Subspace,
+    cleaned_up: bool,
+}
+
+impl TestSubspace {
+    /// Create a new [`TestSubspace`] in `db`.
+    pub fn new(db: &FdbDatabase) -> TestSubspace {
+        let mut t = Tuple::new();
+        t.add_string("fdb_test".to_string());
+        t.add_uuid(Uuid::new_v4());
+
+        TestSubspace {
+            db: db.clone(),
+            subspace: Subspace::new(Bytes::new()).subspace(&t),
+            cleaned_up: false,
+        }
+    }
+
+    /// Gets a reference to the [`Subspace`].
+    pub fn get_subspace_ref(&self) -> &Subspace {
+        &self.subspace
+    }
+
+    /// Returns a [`Range`] containing all the keys in the
+    /// [`Subspace`], including the key equal to its prefix.
+    pub fn range(&self) -> Range {
+        Range::starts_with(self.subspace.pack())
+    }
+
+    /// Clear the keys in the [`Subspace`].
+    ///
+    /// Unlike dropping the [`TestSubspace`], the keys have been
+    /// cleared when this method returns, and errors are returned.
+    pub async fn cleanup(mut self) -> FdbResult<()> {
+        self.cleaned_up = true;
+        clear(&self.db, self.range()).await
+    }
+}
+
+impl Drop for TestSubspace {
+    fn drop(&mut self) {
+        if self.cleaned_up {
+            return;
+        }
+
+        let db = self.db.clone();
+        let range = self.range();
+
+        // Blocking a Tokio worker thread can stall the runtime, so
+        // within a runtime we clear the keys in a background task.
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    let _ = clear(&db, range).await;
+                });
+            }
+            Err(_) => {
+                let _ = futures::executor::block_on(clear(&db, range));
+            }
+        }
+    }
+}
+
+async fn clear(db: &FdbDatabase, range: Range) -> FdbResult<()> {
+    db.run(|tr| {
+        let range = range.clone();
+        async move {
+            tr.clear_range(range);
+            Ok(())
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use super::TestSubspace;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    TestSubspace:
+	        Send &
+		Sync &
+		!Clone &
+		!Copy));
+    }
+}
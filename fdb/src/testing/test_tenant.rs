@@ -0,0 +1,117 @@
+use bytes::Bytes;
+
+use uuid::Uuid;
+
+use crate::database::FdbDatabase;
+use crate::error::FdbResult;
+use crate::range::Range;
+use crate::tenant::{FdbTenant, TenantManagement};
+use crate::transaction::Transaction;
+
+/// A [`FdbTenant`] with a unique name, that is cleared and deleted
+/// using [`cleanup`], or when dropped.
+///
+/// The tenant name is `fdb_test_<uuid>`, where `uuid` is a random
+/// [`Uuid`].
+///
+/// See [module] documentation for details.
+///
+/// [module]: crate::testing
+/// [`cleanup`]: TestTenant::cleanup
+#[derive(Debug)]
+pub struct TestTenant {
+    db: FdbDatabase,
+    tenant: FdbTenant,
+    cleaned_up: bool,
+}
+
+impl TestTenant {
+    /// Create a new tenant in `db` and open it.
+    pub async fn new(db: &FdbDatabase) -> FdbResult<TestTenant> {
+        let name = Bytes::from(format!("fdb_test_{}", Uuid::new_v4().to_simple()));
+
+        TenantManagement::create_tenant_db(db, name.clone()).await?;
+
+        let tenant = match db.open_tenant(name.clone()) {
+            Ok(tenant) => tenant,
+            Err(e) => {
+                let _ = TenantManagement::delete_tenant_db(db, name).await;
+                return Err(e);
+            }
+        };
+
+        Ok(TestTenant {
+            db: db.clone(),
+            tenant,
+            cleaned_up: false,
+        })
+    }
+
+    /// Gets a reference to the [`FdbTenant`].
+    pub fn get_tenant_ref(&self) -> &FdbTenant {
+        &self.tenant
+    }
+
+    /// Clear and delete the tenant.
+    ///
+    /// Unlike dropping the [`TestTenant`], the tenant has been
+    /// deleted when this method returns, and errors are returned.
+    pub async fn cleanup(mut self) -> FdbResult<()> {
+        self.cleaned_up = true;
+        delete(&self.db, &self.tenant).await
+    }
+}
+
+impl Drop for TestTenant {
+    fn drop(&mut self) {
+        if self.cleaned_up {
+            return;
+        }
+
+        let db = self.db.clone();
+        let tenant = self.tenant.clone();
+
+        // Blocking a Tokio worker thread can stall the runtime, so
+        // within a runtime we delete the tenant in a background task.
+        match tokio::runtime::Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn(async move {
+                    let _ = delete(&db, &tenant).await;
+                });
+            }
+            Err(_) => {
+                let _ = futures::executor::block_on(delete(&db, &tenant));
+            }
+        }
+    }
+}
+
+async fn delete(db: &FdbDatabase, tenant: &FdbTenant) -> FdbResult<()> {
+    // A tenant must be empty before it can be deleted.
+    tenant
+        .run(|tr| async move {
+            tr.clear_range(Range::new(Bytes::new(), Bytes::from_static(b"\xff")));
+            Ok(())
+        })
+        .await?;
+
+    TenantManagement::delete_tenant_db(db, tenant.get_name()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use impls::impls;
+
+    use super::TestTenant;
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    TestTenant:
+	        Send &
+		Sync &
+		!Clone &
+		!Copy));
+    }
+}
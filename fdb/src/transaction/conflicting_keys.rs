@@ -0,0 +1,106 @@
+use bytes::Bytes;
+
+use tokio_stream::StreamExt;
+
+use crate::error::FdbResult;
+use crate::range::{Range, RangeOptions};
+use crate::transaction::ReadTransaction;
+use crate::KeyValue;
+
+const CONFLICTING_KEYS_PREFIX: &[u8; 32] = b"\xFF\xFF/transaction/conflicting_keys/";
+
+const CONFLICTING_KEYS_END: &[u8; 33] = b"\xFF\xFF/transaction/conflicting_keys/\xFF";
+
+pub(crate) async fn get_conflicting_keys<T>(tr: &T) -> FdbResult<Vec<Range>>
+where
+    T: ReadTransaction,
+{
+    let mut range_stream = Range::new(
+        Bytes::from_static(CONFLICTING_KEYS_PREFIX),
+        Bytes::from_static(CONFLICTING_KEYS_END),
+    )
+    .into_stream(tr, RangeOptions::default());
+
+    let mut kvs = Vec::new();
+
+    while let Some(x) = range_stream.next().await {
+        kvs.push(x?);
+    }
+
+    Ok(conflicting_key_ranges(kvs))
+}
+
+// Keys in the conflicting keys special key range mark the boundaries
+// of the conflicting ranges. A value of `1` marks the beginning of a
+// range and a value of `0` marks the end.
+fn conflicting_key_ranges(kvs: Vec<KeyValue>) -> Vec<Range> {
+    let mut ranges = Vec::new();
+    let mut begin = None;
+
+    for kv in kvs {
+        let (key, value) = kv.into_parts();
+
+        let key = Bytes::from(key).slice(CONFLICTING_KEYS_PREFIX.len()..);
+
+        match Bytes::from(value).as_ref() {
+            b"1" => begin = Some(key),
+            b"0" => {
+                if let Some(begin) = begin.take() {
+                    ranges.push(Range::new(begin, key));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut, Bytes, BytesMut};
+
+    use crate::range::Range;
+    use crate::KeyValue;
+
+    use super::{conflicting_key_ranges, CONFLICTING_KEYS_PREFIX};
+
+    fn kv(key: &'static [u8], value: &'static [u8]) -> KeyValue {
+        let mut b = BytesMut::new();
+        b.put(CONFLICTING_KEYS_PREFIX.as_ref());
+        b.put(key);
+
+        KeyValue::new(Bytes::from(b).into(), Bytes::from_static(value).into())
+    }
+
+    #[test]
+    fn test_conflicting_key_ranges() {
+        assert!(conflicting_key_ranges(Vec::new()).is_empty());
+
+        let ranges = conflicting_key_ranges(vec![
+            kv(b"a", b"1"),
+            kv(b"a\x00", b"0"),
+            kv(b"c", b"1"),
+            kv(b"e", b"0"),
+        ]);
+
+        assert_eq!(
+            ranges,
+            vec![
+                Range::new(Bytes::from_static(b"a"), Bytes::from_static(b"a\x00")),
+                Range::new(Bytes::from_static(b"c"), Bytes::from_static(b"e")),
+            ]
+        );
+
+        // An end without a beginning is ignored.
+        let ranges = conflicting_key_ranges(vec![kv(b"a", b"0"), kv(b"c", b"1"), kv(b"e", b"0")]);
+
+        assert_eq!(
+            ranges,
+            vec![Range::new(
+                Bytes::from_static(b"c"),
+                Bytes::from_static(b"e")
+            )]
+        );
+    }
+}
@@ -2,8 +2,10 @@ use bytes::Bytes;
 
 use std::convert::TryInto;
 use std::future::Future;
+use std::pin::Pin;
 use std::ptr::NonNull;
 use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use crate::error::{check, FdbError, FdbResult};
 use crate::future::{
@@ -12,6 +14,7 @@ use crate::future::{
 };
 use crate::option::ConflictRangeType;
 use crate::range::{Range, RangeOptions};
+use crate::transaction::conflicting_keys;
 use crate::transaction::{MutationType, ReadTransaction, Transaction, TransactionOption};
 use crate::tuple::key_util;
 use crate::{Key, KeySelector, Value};
@@ -22,6 +25,9 @@ use crate::future::{FdbFutureKeyArray, FdbStreamMappedKeyValue};
 #[cfg(feature = "fdb-7_1")]
 use crate::Mapper;
 
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, Operation};
+
 /// Committed version of the [`Transaction`].
 ///
 /// [`get_committed_version`] provides a value of this type. This
@@ -65,8 +71,8 @@ impl From<CommittedVersion> for FdbResult<i64> {
 ///
 /// [`get_versionstamp`] provides a value of this type. This value can
 /// be returned from the [`run`] method closure. After the transaction
-/// successfully commits, you can use the [`get`] method to get the
-/// versionstamp.
+/// successfully commits, you can use the [`get`] method, or await
+/// the [`TransactionVersionstamp`] directly, to get the versionstamp.
 ///
 /// [`fdb_c`]: https://apple.github.io/foundationdb/data-modeling.html#versionstamps
 /// [`run`]: crate::database::FdbDatabase::run
@@ -110,6 +116,19 @@ impl TransactionVersionstamp {
     }
 }
 
+impl Future for TransactionVersionstamp {
+    type Output = FdbResult<Bytes>;
+
+    fn poll(
+        mut self: Pin<&mut TransactionVersionstamp>,
+        cx: &mut Context<'_>,
+    ) -> Poll<FdbResult<Bytes>> {
+        Pin::new(&mut self.future)
+            .poll(cx)
+            .map(|res| res.map(|k| k.into()))
+    }
+}
+
 /// A handle to a FDB transaction.
 ///
 /// [`create_transaction`] method on [`FdbDatabase`] can be used to
@@ -192,7 +211,32 @@ impl FdbTransaction {
         f(self.clone()).await
     }
 
+    /// Gets the key ranges that caused the last [`commit`] of this
+    /// [`FdbTransaction`] to fail with a `not_committed` (1020)
+    /// error.
+    ///
+    /// [`TransactionOption::ReportConflictingKeys`] must be set
+    /// before [`commit`]. The conflicting key ranges are cleared when
+    /// the transaction is reset, so this must be called before
+    /// [`on_error`].
+    ///
+    /// [`run_with_conflicting_keys`] method on [`FdbDatabase`] can be
+    /// used to get the conflicting key ranges in a retry loop.
+    ///
+    /// [`commit`]: Transaction::commit
+    /// [`on_error`]: ReadTransaction::on_error
+    /// [`run_with_conflicting_keys`]: crate::database::FdbDatabase::run_with_conflicting_keys
+    /// [`FdbDatabase`]: crate::database::FdbDatabase
+    pub async fn get_conflicting_keys(&self) -> FdbResult<Vec<Range>> {
+        conflicting_keys::get_conflicting_keys(self).await
+    }
+
     pub(crate) fn new(c_ptr: Option<Arc<NonNull<fdb_sys::FDBTransaction>>>) -> FdbTransaction {
+        #[cfg(feature = "metrics")]
+        if c_ptr.is_some() {
+            metrics::transaction_created();
+        }
+
         FdbTransaction { c_ptr }
     }
 
@@ -209,9 +253,14 @@ impl Drop for FdbTransaction {
     fn drop(&mut self) {
         if let Some(a) = self.c_ptr.take() {
             match Arc::try_unwrap(a) {
-                Ok(a) => unsafe {
-                    fdb_sys::fdb_transaction_destroy(a.as_ptr());
-                },
+                Ok(a) => {
+                    unsafe {
+                        fdb_sys::fdb_transaction_destroy(a.as_ptr());
+                    }
+
+                    #[cfg(feature = "metrics")]
+                    metrics::transaction_destroyed();
+                }
                 Err(at) => {
                     drop(at);
                 }
@@ -223,7 +272,21 @@ impl Drop for FdbTransaction {
 // `snapshot` is `false` below because any reads that we do on
 // `FdbTransaction` is a `non-snapshot` read.
 impl ReadTransaction for FdbTransaction {
+    type FutureUnit = FdbFutureUnit;
+    type FutureMaybeValue = FdbFutureMaybeValue;
+    type FutureCStringArray = FdbFutureCStringArray;
+    type FutureI64 = FdbFutureI64;
+    type FutureKey = FdbFutureKey;
+    #[cfg(feature = "fdb-7_1")]
+    type StreamMappedKeyValue = FdbStreamMappedKeyValue;
+    type StreamKeyValue = FdbStreamKeyValue;
+    #[cfg(feature = "fdb-7_1")]
+    type FutureKeyArray = FdbFutureKeyArray;
+
     unsafe fn on_error(&self, e: FdbError) -> FdbFutureUnit {
+        #[cfg(feature = "metrics")]
+        metrics::record_error(e);
+
         FdbFuture::new(fdb_sys::fdb_transaction_on_error(
             self.get_c_api_ptr(),
             e.code(),
@@ -299,6 +362,10 @@ impl ReadTransaction for FdbTransaction {
 }
 
 impl Transaction for FdbTransaction {
+    type CommittedVersion = CommittedVersion;
+    type TransactionVersionstamp = TransactionVersionstamp;
+    type Snapshot = FdbReadTransaction;
+
     fn add_read_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
         let begin_key = key.into();
         // Add a 0x00 to `end_key`. `begin_key` is inclusive and
@@ -362,6 +429,9 @@ impl Transaction for FdbTransaction {
         let key_name = k.as_ref().as_ptr();
         let key_name_length = k.as_ref().len().try_into().unwrap();
 
+        #[cfg(feature = "metrics")]
+        metrics::record_bytes_written(k.len());
+
         unsafe { fdb_sys::fdb_transaction_clear(self.get_c_api_ptr(), key_name, key_name_length) }
     }
 
@@ -376,6 +446,9 @@ impl Transaction for FdbTransaction {
         let end_key_name = ek.as_ref().as_ptr();
         let end_key_name_length = ek.as_ref().len().try_into().unwrap();
 
+        #[cfg(feature = "metrics")]
+        metrics::record_bytes_written(bk.len() + ek.len());
+
         unsafe {
             fdb_sys::fdb_transaction_clear_range(
                 self.get_c_api_ptr(),
@@ -388,7 +461,12 @@ impl Transaction for FdbTransaction {
     }
 
     unsafe fn commit(&self) -> FdbFutureUnit {
-        FdbFuture::new(fdb_sys::fdb_transaction_commit(self.get_c_api_ptr()))
+        let fut = FdbFuture::new(fdb_sys::fdb_transaction_commit(self.get_c_api_ptr()));
+
+        #[cfg(feature = "metrics")]
+        let fut = fut.with_latency_metric(Operation::Commit);
+
+        fut
     }
 
     fn get_approximate_size(&self) -> FdbFutureI64 {
@@ -420,6 +498,9 @@ impl Transaction for FdbTransaction {
         let param = p.as_ref().as_ptr();
         let param_length = p.as_ref().len().try_into().unwrap();
 
+        #[cfg(feature = "metrics")]
+        metrics::record_bytes_written(k.len() + p.len());
+
         fdb_sys::fdb_transaction_atomic_op(
             self.get_c_api_ptr(),
             key_name,
@@ -445,6 +526,9 @@ impl Transaction for FdbTransaction {
         let value = v.as_ref().as_ptr();
         let value_length = v.as_ref().len().try_into().unwrap();
 
+        #[cfg(feature = "metrics")]
+        metrics::record_bytes_written(k.len() + v.len());
+
         unsafe {
             fdb_sys::fdb_transaction_set(
                 self.get_c_api_ptr(),
@@ -456,6 +540,10 @@ impl Transaction for FdbTransaction {
         }
     }
 
+    fn snapshot(&self) -> FdbReadTransaction {
+        FdbTransaction::snapshot(self)
+    }
+
     fn watch(&self, key: impl Into<Key>) -> FdbFutureUnit {
         let k = Bytes::from(key.into());
         let key_name = k.as_ref().as_ptr();
@@ -543,6 +631,17 @@ impl FdbReadTransaction {
 // `snapshot` is `true` below because any reads that we do on
 // `FdbReadTransaction` is a `snapshot` read.
 impl ReadTransaction for FdbReadTransaction {
+    type FutureUnit = FdbFutureUnit;
+    type FutureMaybeValue = FdbFutureMaybeValue;
+    type FutureCStringArray = FdbFutureCStringArray;
+    type FutureI64 = FdbFutureI64;
+    type FutureKey = FdbFutureKey;
+    #[cfg(feature = "fdb-7_1")]
+    type StreamMappedKeyValue = FdbStreamMappedKeyValue;
+    type StreamKeyValue = FdbStreamKeyValue;
+    #[cfg(feature = "fdb-7_1")]
+    type FutureKeyArray = FdbFutureKeyArray;
+
     unsafe fn on_error(&self, e: FdbError) -> FdbFutureUnit {
         self.inner.on_error(e)
     }
@@ -660,6 +759,9 @@ pub(super) mod internal {
         #[cfg(feature = "fdb-7_1")]
         use crate::future::FdbFutureKeyArray;
 
+        #[cfg(feature = "metrics")]
+        use crate::metrics::Operation;
+
         pub(crate) fn get(
             transaction: *mut fdb_sys::FDBTransaction,
             key: impl Into<Key>,
@@ -670,9 +772,14 @@ pub(super) mod internal {
             let key_name_length = k.as_ref().len().try_into().unwrap();
             let s = if snapshot { 1 } else { 0 };
 
-            FdbFuture::new(unsafe {
+            let fut = FdbFuture::new(unsafe {
                 fdb_sys::fdb_transaction_get(transaction, key_name, key_name_length, s)
-            })
+            });
+
+            #[cfg(feature = "metrics")]
+            let fut = fut.with_latency_metric(Operation::Get);
+
+            fut
         }
 
         pub(crate) fn get_addresses_for_key(
@@ -769,7 +876,13 @@ pub(super) mod internal {
         }
 
         pub(crate) fn get_read_version(transaction: *mut fdb_sys::FDBTransaction) -> FdbFutureI64 {
-            FdbFuture::new(unsafe { fdb_sys::fdb_transaction_get_read_version(transaction) })
+            let fut =
+                FdbFuture::new(unsafe { fdb_sys::fdb_transaction_get_read_version(transaction) });
+
+            #[cfg(feature = "metrics")]
+            let fut = fut.with_latency_metric(Operation::GetReadVersion);
+
+            fut
         }
 
         pub(crate) fn set_option(
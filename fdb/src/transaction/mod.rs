@@ -1,9 +1,13 @@
 //! Provides types and traits for working with FDB Transactions and
 //! Snapshots.
 
+mod conflicting_keys;
 mod fdb_transaction;
+mod prefix_transaction;
 mod read_transaction;
 
+pub(crate) mod retry_loop;
+
 // We do this in order to preserve consistency with Java and Go
 // bindings.
 #[allow(clippy::module_inception)]
@@ -16,5 +20,6 @@ pub use fdb_transaction::{
     CommittedVersion, FdbReadTransaction, FdbTransaction, TransactionVersionstamp,
 };
 
+pub use prefix_transaction::{PrefixFuture, PrefixStream, PrefixTransaction};
 pub use read_transaction::ReadTransaction;
 pub use transaction::Transaction;
@@ -0,0 +1,580 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use futures::Stream;
+
+use std::convert::TryInto;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::error::{FdbError, FdbResult};
+use crate::range::{Range, RangeOptions};
+use crate::transaction::{MutationType, ReadTransaction, Transaction, TransactionOption};
+use crate::{Key, KeySelector, KeyValue, Value};
+
+#[cfg(feature = "fdb-7_1")]
+use futures::future::{self, Ready};
+#[cfg(feature = "fdb-7_1")]
+use futures::stream::{self, Once};
+
+#[cfg(feature = "fdb-7_1")]
+use crate::{MappedKeyValue, Mapper};
+
+#[cfg(feature = "fdb-7_1")]
+const CLIENT_INVALID_OPERATION: i32 = 2000;
+
+/// A transaction whose keys are scoped to a prefix.
+///
+/// [`PrefixTransaction`] implements [`ReadTransaction`] when `T`
+/// implements [`ReadTransaction`], and [`Transaction`] when `T`
+/// implements [`Transaction`]. The prefix is added to every key,
+/// range and key selector before the method is forwarded to `T`, and
+/// removed from the keys that are returned. So code that is generic
+/// over the transaction traits, such as the layers in
+/// [`crate::layer`], can be run within a prefix.
+///
+/// Key selectors that resolve to a key outside of the prefix are
+/// clamped to it. [`get_key`] returns the empty key for keys before
+/// the prefix and `\xff` for keys after it, and range reads skip
+/// key-values outside of the prefix.
+///
+/// Mapped range reads are not supported, as the mapper cannot be
+/// scoped to the prefix. `get_mapped_range` returns an error with
+/// `client_invalid_operation` (`2000`) code.
+///
+/// [`get_key`]: ReadTransaction::get_key
+#[derive(Clone, Debug)]
+pub struct PrefixTransaction<T> {
+    inner: T,
+    prefix: Bytes,
+}
+
+impl<T> PrefixTransaction<T> {
+    /// Create a new [`PrefixTransaction`] that scopes the keys of
+    /// `inner` to `prefix`.
+    pub fn new(inner: T, prefix: impl Into<Bytes>) -> PrefixTransaction<T> {
+        PrefixTransaction {
+            inner,
+            prefix: prefix.into(),
+        }
+    }
+
+    /// Gets a reference to the wrapped transaction.
+    pub fn get_inner_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets a reference to the prefix.
+    pub fn get_prefix_ref(&self) -> &Bytes {
+        &self.prefix
+    }
+
+    /// Extract the wrapped transaction from [`PrefixTransaction`].
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn key(&self, key: impl Into<Key>) -> Key {
+        let key = Bytes::from(key.into());
+
+        let mut b = BytesMut::with_capacity(self.prefix.len() + key.len());
+        b.put(self.prefix.clone());
+        b.put(key);
+
+        Bytes::from(b).into()
+    }
+
+    fn range(&self, range: Range) -> Range {
+        let (begin, end) = range.into_parts();
+        Range::new(self.key(begin), self.key(end))
+    }
+
+    fn key_selector(&self, selector: KeySelector) -> KeySelector {
+        let (key, or_equal, offset) = selector.deconstruct();
+        KeySelector::new(self.key(key), or_equal, offset)
+    }
+
+    // The offset of the incomplete versionstamp is in the last four
+    // bytes of `key`. It is moved by the length of the prefix.
+    fn versionstamped_key(&self, key: impl Into<Key>) -> Key {
+        let key = Bytes::from(key.into());
+
+        if key.len() < 4 {
+            // Invalid key. Let `T` report the error.
+            return self.key(key);
+        }
+
+        let (body, offset) = key.split_at(key.len() - 4);
+        let offset = u32::from_le_bytes(offset.try_into().unwrap());
+        let prefix_len = TryInto::<u32>::try_into(self.prefix.len()).unwrap();
+
+        let mut b = BytesMut::with_capacity(self.prefix.len() + key.len());
+        b.put(self.prefix.clone());
+        b.put(body);
+        b.put_u32_le(offset.saturating_add(prefix_len));
+
+        Bytes::from(b).into()
+    }
+}
+
+impl<T> ReadTransaction for PrefixTransaction<T>
+where
+    T: ReadTransaction,
+{
+    type FutureUnit = T::FutureUnit;
+    type FutureMaybeValue = T::FutureMaybeValue;
+    type FutureCStringArray = T::FutureCStringArray;
+    type FutureI64 = T::FutureI64;
+    type FutureKey = PrefixFuture<T::FutureKey>;
+    #[cfg(feature = "fdb-7_1")]
+    type StreamMappedKeyValue = Once<Ready<FdbResult<MappedKeyValue>>>;
+    type StreamKeyValue = PrefixStream<T::StreamKeyValue>;
+    #[cfg(feature = "fdb-7_1")]
+    type FutureKeyArray = PrefixFuture<T::FutureKeyArray>;
+
+    unsafe fn on_error(&self, e: FdbError) -> T::FutureUnit {
+        self.inner.on_error(e)
+    }
+
+    fn get(&self, key: impl Into<Key>) -> T::FutureMaybeValue {
+        self.inner.get(self.key(key))
+    }
+
+    fn get_addresses_for_key(&self, key: impl Into<Key>) -> T::FutureCStringArray {
+        self.inner.get_addresses_for_key(self.key(key))
+    }
+
+    fn get_estimated_range_size_bytes(&self, range: Range) -> T::FutureI64 {
+        self.inner.get_estimated_range_size_bytes(self.range(range))
+    }
+
+    fn get_key(&self, selector: KeySelector) -> PrefixFuture<T::FutureKey> {
+        PrefixFuture::new(
+            self.inner.get_key(self.key_selector(selector)),
+            self.prefix.clone(),
+            |prefix, res| res.map(|key| strip_key(prefix, key)),
+        )
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_mapped_range(
+        &self,
+        _begin: KeySelector,
+        _end: KeySelector,
+        _mapper: impl Into<Mapper>,
+        _options: RangeOptions,
+    ) -> Once<Ready<FdbResult<MappedKeyValue>>> {
+        stream::once(future::ready(Err(FdbError::new(CLIENT_INVALID_OPERATION))))
+    }
+
+    fn get_range(
+        &self,
+        begin: KeySelector,
+        end: KeySelector,
+        options: RangeOptions,
+    ) -> PrefixStream<T::StreamKeyValue> {
+        PrefixStream::new(
+            self.inner
+                .get_range(self.key_selector(begin), self.key_selector(end), options),
+            self.prefix.clone(),
+        )
+    }
+
+    #[cfg(feature = "fdb-7_1")]
+    fn get_range_split_points(
+        &self,
+        begin: impl Into<Key>,
+        end: impl Into<Key>,
+        chunk_size: i64,
+    ) -> PrefixFuture<T::FutureKeyArray> {
+        PrefixFuture::new(
+            self.inner
+                .get_range_split_points(self.key(begin), self.key(end), chunk_size),
+            self.prefix.clone(),
+            |prefix, res| {
+                res.map(|keys| keys.into_iter().map(|key| strip_key(prefix, key)).collect())
+            },
+        )
+    }
+
+    unsafe fn get_read_version(&self) -> T::FutureI64 {
+        self.inner.get_read_version()
+    }
+
+    fn set_option(&self, option: TransactionOption) -> FdbResult<()> {
+        self.inner.set_option(option)
+    }
+
+    unsafe fn set_read_version(&self, version: i64) {
+        self.inner.set_read_version(version)
+    }
+}
+
+impl<T> Transaction for PrefixTransaction<T>
+where
+    T: Transaction,
+{
+    type CommittedVersion = T::CommittedVersion;
+    type TransactionVersionstamp = T::TransactionVersionstamp;
+    type Snapshot = PrefixTransaction<T::Snapshot>;
+
+    fn add_read_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
+        self.inner.add_read_conflict_key(self.key(key))
+    }
+
+    fn add_read_conflict_range(&self, range: Range) -> FdbResult<()> {
+        self.inner.add_read_conflict_range(self.range(range))
+    }
+
+    fn add_write_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()> {
+        self.inner.add_write_conflict_key(self.key(key))
+    }
+
+    fn add_write_conflict_range(&self, range: Range) -> FdbResult<()> {
+        self.inner.add_write_conflict_range(self.range(range))
+    }
+
+    unsafe fn cancel(&self) {
+        self.inner.cancel()
+    }
+
+    fn clear(&self, key: impl Into<Key>) {
+        self.inner.clear(self.key(key))
+    }
+
+    fn clear_range(&self, range: Range) {
+        self.inner.clear_range(self.range(range))
+    }
+
+    unsafe fn commit(&self) -> T::FutureUnit {
+        self.inner.commit()
+    }
+
+    fn get_approximate_size(&self) -> T::FutureI64 {
+        self.inner.get_approximate_size()
+    }
+
+    unsafe fn get_committed_version(&self) -> T::CommittedVersion {
+        self.inner.get_committed_version()
+    }
+
+    unsafe fn get_versionstamp(&self) -> T::TransactionVersionstamp {
+        self.inner.get_versionstamp()
+    }
+
+    unsafe fn mutate(&self, optype: MutationType, key: impl Into<Key>, param: Bytes) {
+        let key = match optype {
+            MutationType::SetVersionstampedKey => self.versionstamped_key(key),
+            _ => self.key(key),
+        };
+
+        self.inner.mutate(optype, key, param)
+    }
+
+    unsafe fn reset(&self) {
+        self.inner.reset()
+    }
+
+    fn set(&self, key: impl Into<Key>, value: impl Into<Value>) {
+        self.inner.set(self.key(key), value)
+    }
+
+    fn snapshot(&self) -> PrefixTransaction<T::Snapshot> {
+        PrefixTransaction::new(self.inner.snapshot(), self.prefix.clone())
+    }
+
+    fn watch(&self, key: impl Into<Key>) -> T::FutureUnit {
+        self.inner.watch(self.key(key))
+    }
+}
+
+/// Future returned by [`PrefixTransaction`] methods that return
+/// keys.
+///
+/// The prefix is removed from the keys returned by the wrapped
+/// future.
+//
+// The wrapped future is boxed, so that `PrefixFuture` is `Unpin` even
+// when `F` is not.
+pub struct PrefixFuture<F>
+where
+    F: Future,
+{
+    inner: Pin<Box<F>>,
+    prefix: Bytes,
+    strip: fn(&Bytes, F::Output) -> F::Output,
+}
+
+impl<F> PrefixFuture<F>
+where
+    F: Future,
+{
+    fn new(inner: F, prefix: Bytes, strip: fn(&Bytes, F::Output) -> F::Output) -> PrefixFuture<F> {
+        PrefixFuture {
+            inner: Box::pin(inner),
+            prefix,
+            strip,
+        }
+    }
+}
+
+impl<F> fmt::Debug for PrefixFuture<F>
+where
+    F: Future,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PrefixFuture")
+            .field("prefix", &self.prefix)
+            .finish()
+    }
+}
+
+impl<F> Future for PrefixFuture<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut PrefixFuture<F>>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.get_mut();
+
+        match this.inner.as_mut().poll(cx) {
+            Poll::Ready(res) => Poll::Ready((this.strip)(&this.prefix, res)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Stream returned by [`PrefixTransaction`] range reads.
+///
+/// The prefix is removed from the keys returned by the wrapped
+/// stream. Key-values outside of the prefix are skipped.
+#[derive(Debug)]
+pub struct PrefixStream<S> {
+    inner: S,
+    prefix: Bytes,
+}
+
+impl<S> PrefixStream<S> {
+    fn new(inner: S, prefix: Bytes) -> PrefixStream<S> {
+        PrefixStream { inner, prefix }
+    }
+}
+
+impl<S> Stream for PrefixStream<S>
+where
+    S: Stream<Item = FdbResult<KeyValue>> + Unpin,
+{
+    type Item = FdbResult<KeyValue>;
+
+    fn poll_next(
+        mut self: Pin<&mut PrefixStream<S>>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<FdbResult<KeyValue>>> {
+        loop {
+            match Pin::new(&mut self.inner).poll_next(cx) {
+                Poll::Ready(Some(Ok(kv))) => {
+                    let (key, value) = kv.into_parts();
+                    let key = Bytes::from(key);
+
+                    if key.starts_with(&self.prefix) {
+                        let key = key.slice(self.prefix.len()..);
+                        return Poll::Ready(Some(Ok(KeyValue::new(key.into(), value))));
+                    }
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+// Removes `prefix` from `key`, clamping keys outside of the prefix.
+fn strip_key(prefix: &Bytes, key: Key) -> Key {
+    let key = Bytes::from(key);
+
+    if key.starts_with(prefix) {
+        key.slice(prefix.len()..).into()
+    } else if key < *prefix {
+        Bytes::new().into()
+    } else {
+        Bytes::from_static(b"\xff").into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use impls::impls;
+
+    use crate::future::{FdbFutureKey, FdbStreamKeyValue};
+    use crate::transaction::FdbTransaction;
+    use crate::Key;
+
+    use super::{PrefixFuture, PrefixStream, PrefixTransaction};
+
+    #[test]
+    fn impls() {
+        #[rustfmt::skip]
+	assert!(impls!(
+	    PrefixTransaction<FdbTransaction>:
+	        Send &
+		Sync &
+		Clone &
+		!Copy));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    PrefixFuture<FdbFutureKey>:
+	        Send &
+		Unpin));
+
+        #[rustfmt::skip]
+	assert!(impls!(
+	    PrefixStream<FdbStreamKeyValue>:
+	        Send &
+		Unpin));
+    }
+
+    #[test]
+    fn strip_key() {
+        let prefix = Bytes::from_static(b"p");
+
+        assert_eq!(
+            super::strip_key(&prefix, Key::from(Bytes::from_static(b"pa"))),
+            Key::from(Bytes::from_static(b"a"))
+        );
+        assert_eq!(
+            super::strip_key(&prefix, Key::from(Bytes::from_static(b"a"))),
+            Key::from(Bytes::new())
+        );
+        assert_eq!(
+            super::strip_key(&prefix, Key::from(Bytes::from_static(b"q"))),
+            Key::from(Bytes::from_static(b"\xff"))
+        );
+    }
+
+    #[test]
+    fn versionstamped_key() {
+        let tr = PrefixTransaction::new((), Bytes::from_static(b"pp"));
+
+        assert_eq!(
+            tr.versionstamped_key(Bytes::from_static(b"k0123456789\x01\x00\x00\x00")),
+            Key::from(Bytes::from_static(b"ppk0123456789\x03\x00\x00\x00"))
+        );
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn memory_transaction() {
+        use tokio_stream::StreamExt;
+
+        use crate::error::FdbError;
+        use crate::layer::counter::Counter;
+        use crate::memory::MemoryDatabase;
+        use crate::range::{Range, RangeOptions};
+        use crate::subspace::Subspace;
+        use crate::transaction::{MutationType, ReadTransaction, Transaction};
+        use crate::tuple::{Tuple, Versionstamp};
+        use crate::{KeySelector, Value};
+
+        let db = MemoryDatabase::new();
+
+        db.run(|tr| async move {
+            tr.set(Bytes::from_static(b"a"), Bytes::from_static(b"outside"));
+            tr.set(Bytes::from_static(b"q"), Bytes::from_static(b"outside"));
+
+            let tr = PrefixTransaction::new(tr, Bytes::from_static(b"p"));
+            tr.set(Bytes::from_static(b"a"), Bytes::from_static(b"1"));
+            tr.set(Bytes::from_static(b"b"), Bytes::from_static(b"2"));
+
+            let mut t = Tuple::new();
+            t.add_versionstamp(Versionstamp::incomplete(0));
+
+            unsafe {
+                tr.mutate(
+                    MutationType::SetVersionstampedKey,
+                    t.pack_with_versionstamp(Bytes::from_static(b"v"))?,
+                    Bytes::from_static(b"3"),
+                )
+            };
+
+            // Layers can run within the prefix.
+            Counter::new(Subspace::new(Bytes::from_static(b"c")), 1).add(&tr, 5);
+
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let (value, outside, key, kvs, counter) = db
+            .read(|tr| async move {
+                let tr = PrefixTransaction::new(tr, Bytes::from_static(b"p"));
+
+                let value = tr.get(Bytes::from_static(b"a")).await?;
+                let outside = tr.get(Bytes::from_static(b"q")).await?;
+                let key = tr
+                    .get_key(KeySelector::first_greater_than(Bytes::from_static(b"a")))
+                    .await?;
+
+                let kvs = tr
+                    .get_range(
+                        KeySelector::first_greater_or_equal(Bytes::new()),
+                        KeySelector::first_greater_or_equal(Bytes::from_static(b"\xff")),
+                        RangeOptions::default(),
+                    )
+                    .map(|kv| kv.map(|kv| Bytes::from(kv.into_key())))
+                    .collect::<Result<Vec<_>, FdbError>>()
+                    .await?;
+
+                let counter = Counter::new(Subspace::new(Bytes::from_static(b"c")), 1)
+                    .get(&tr)
+                    .await?;
+
+                Ok((value, outside, key, kvs, counter))
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(value, Some(Value::from(Bytes::from_static(b"1"))));
+        assert_eq!(outside, None);
+        assert_eq!(key, Key::from(Bytes::from_static(b"b")));
+        assert_eq!(counter, 5);
+
+        assert_eq!(kvs.len(), 4);
+        assert_eq!(
+            &kvs[0..2],
+            &[Bytes::from_static(b"a"), Bytes::from_static(b"b")]
+        );
+        assert!(kvs[2].starts_with(b"c"));
+        assert!(kvs[3].starts_with(b"v"));
+
+        // Clearing within the prefix leaves the keys outside of it.
+        db.run(|tr| async move {
+            PrefixTransaction::new(tr, Bytes::from_static(b"p"))
+                .clear_range(Range::new(Bytes::new(), Bytes::from_static(b"\xff")));
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+        let kvs = db
+            .read(|tr| async move {
+                tr.get_range(
+                    KeySelector::first_greater_or_equal(Bytes::new()),
+                    KeySelector::first_greater_or_equal(Bytes::from_static(b"\xff")),
+                    RangeOptions::default(),
+                )
+                .map(|kv| kv.map(|kv| Bytes::from(kv.into_key())))
+                .collect::<Result<Vec<_>, FdbError>>()
+                .await
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            kvs,
+            vec![Bytes::from_static(b"a"), Bytes::from_static(b"q")]
+        );
+    }
+}
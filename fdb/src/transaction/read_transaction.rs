@@ -1,21 +1,33 @@
+use futures::Stream;
+
+use std::ffi::CString;
+use std::future::Future;
+
 use crate::error::{FdbError, FdbResult};
-use crate::future::{
-    FdbFutureCStringArray, FdbFutureI64, FdbFutureKey, FdbFutureMaybeValue, FdbFutureUnit,
-    FdbStreamKeyValue,
-};
+use crate::future::{FdbStreamKey, FdbStreamMaybeValue};
 use crate::range::{Range, RangeOptions};
 use crate::transaction::TransactionOption;
-use crate::{Key, KeySelector};
-
-#[cfg(feature = "fdb-7_1")]
-use crate::future::{FdbFutureKeyArray, FdbStreamMappedKeyValue};
+use crate::{Key, KeySelector, KeyValue, Value};
 
 #[cfg(feature = "fdb-7_1")]
-use crate::Mapper;
+use crate::{MappedKeyValue, Mapper};
 
 /// A read-only subset of a FDB [`Transaction`].
 ///
+/// The futures and streams returned by [`ReadTransaction`] methods
+/// are associated types, so that types other than
+/// [`FdbTransaction`] and [`FdbReadTransaction`] can implement
+/// [`ReadTransaction`]. For [`FdbTransaction`] and
+/// [`FdbReadTransaction`], these are the types in the [`future`]
+/// module, such as [`FdbFutureMaybeValue`] and
+/// [`FdbStreamKeyValue`].
+///
 /// [`Transaction`]: crate::transaction::Transaction
+/// [`FdbTransaction`]: crate::transaction::FdbTransaction
+/// [`FdbReadTransaction`]: crate::transaction::FdbReadTransaction
+/// [`future`]: crate::future
+/// [`FdbFutureMaybeValue`]: crate::future::FdbFutureMaybeValue
+/// [`FdbStreamKeyValue`]: crate::future::FdbStreamKeyValue
 //
 // NOTE: Unlike Java API, `ReadTransaction` does not extend (i.e., is
 //       a subtrait of) `ReadTransactionContext` (There is no
@@ -30,6 +42,57 @@ use crate::Mapper;
 //       `addReadConflictKeyIfNotSnapshot` and
 //       `addReadConflictRangeIfNotSnapshot`.
 pub trait ReadTransaction {
+    /// Future returned by [`on_error`], and by [`commit`] and
+    /// [`watch`] of [`Transaction`].
+    ///
+    /// [`on_error`]: ReadTransaction::on_error
+    /// [`commit`]: crate::transaction::Transaction::commit
+    /// [`watch`]: crate::transaction::Transaction::watch
+    /// [`Transaction`]: crate::transaction::Transaction
+    type FutureUnit: Future<Output = FdbResult<()>> + Send;
+
+    /// Future returned by [`get`].
+    ///
+    /// [`get`]: ReadTransaction::get
+    type FutureMaybeValue: Future<Output = FdbResult<Option<Value>>> + Send + Unpin;
+
+    /// Future returned by [`get_addresses_for_key`].
+    ///
+    /// [`get_addresses_for_key`]: ReadTransaction::get_addresses_for_key
+    type FutureCStringArray: Future<Output = FdbResult<Vec<CString>>> + Send;
+
+    /// Future returned by [`get_estimated_range_size_bytes`] and
+    /// [`get_read_version`], and by [`get_approximate_size`] of
+    /// [`Transaction`].
+    ///
+    /// [`get_estimated_range_size_bytes`]: ReadTransaction::get_estimated_range_size_bytes
+    /// [`get_read_version`]: ReadTransaction::get_read_version
+    /// [`get_approximate_size`]: crate::transaction::Transaction::get_approximate_size
+    /// [`Transaction`]: crate::transaction::Transaction
+    type FutureI64: Future<Output = FdbResult<i64>> + Send;
+
+    /// Future returned by [`get_key`].
+    ///
+    /// [`get_key`]: ReadTransaction::get_key
+    type FutureKey: Future<Output = FdbResult<Key>> + Send + Unpin;
+
+    #[cfg(feature = "fdb-7_1")]
+    /// Stream returned by [`get_mapped_range`].
+    ///
+    /// [`get_mapped_range`]: ReadTransaction::get_mapped_range
+    type StreamMappedKeyValue: Stream<Item = FdbResult<MappedKeyValue>> + Send + Unpin;
+
+    /// Stream returned by [`get_range`].
+    ///
+    /// [`get_range`]: ReadTransaction::get_range
+    type StreamKeyValue: Stream<Item = FdbResult<KeyValue>> + Send + Unpin;
+
+    #[cfg(feature = "fdb-7_1")]
+    /// Future returned by [`get_range_split_points`].
+    ///
+    /// [`get_range_split_points`]: ReadTransaction::get_range_split_points
+    type FutureKeyArray: Future<Output = FdbResult<Vec<Key>>> + Send;
+
     /// Determines whether an error returned by a [`Transaction`]
     /// or [`ReadTransaction`] method is retryable. Waiting on the returned future will
     /// return the same error when fatal, or return `()` for retryable
@@ -53,7 +116,7 @@ pub trait ReadTransaction {
     /// [`run`]: crate::database::FdbDatabase::run
     /// [`read`]: crate::database::FdbDatabase::read
     /// [C API]: https://apple.github.io/foundationdb/api-c.html#c.fdb_transaction_on_error
-    unsafe fn on_error(&self, e: FdbError) -> FdbFutureUnit;
+    unsafe fn on_error(&self, e: FdbError) -> Self::FutureUnit;
 
     /// Gets a value from the database.    
     ///
@@ -62,7 +125,28 @@ pub trait ReadTransaction {
     /// ```ignore
     /// async fn get(&self, key: impl Into<Key>) -> FdbResult<Option<Value>>
     /// ```
-    fn get(&self, key: impl Into<Key>) -> FdbFutureMaybeValue;
+    fn get(&self, key: impl Into<Key>) -> Self::FutureMaybeValue;
+
+    /// Gets values for multiple keys from the database, with at most
+    /// `limit` reads in flight at any point in time. A `limit` of
+    /// `0` is treated as `1`.
+    ///
+    /// The returned stream yields an `Option<Value>` item for each
+    /// key, in the same order as `keys`. If a read fails, the error
+    /// is yielded and the stream ends.
+    ///
+    /// The reads are issued using [`get`], when the returned stream
+    /// is polled.
+    ///
+    /// [`get`]: ReadTransaction::get
+    fn get_many<I, K>(&self, keys: I, limit: usize) -> FdbStreamMaybeValue<'_, Self>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = K>,
+        K: Into<Key>,
+    {
+        FdbStreamMaybeValue::new(self, keys.into_iter().map(Into::into).collect(), limit)
+    }
 
     /// Get a list of public network addresses as [`CString`], one for
     /// each of the storage servers responsible for storing [`Key`]
@@ -75,7 +159,7 @@ pub trait ReadTransaction {
     /// ```
     ///
     /// [`CString`]: std::ffi::CString
-    fn get_addresses_for_key(&self, key: impl Into<Key>) -> FdbFutureCStringArray;
+    fn get_addresses_for_key(&self, key: impl Into<Key>) -> Self::FutureCStringArray;
 
     /// Gets an estimate for the number of bytes stored in the given
     /// range.
@@ -97,7 +181,7 @@ pub trait ReadTransaction {
     /// large ranges for accuracy considerations. For a rough
     /// reference, if the returned size is larger than 3MB, one can
     /// consider the size to be accurate.
-    fn get_estimated_range_size_bytes(&self, range: Range) -> FdbFutureI64;
+    fn get_estimated_range_size_bytes(&self, range: Range) -> Self::FutureI64;
 
     /// Returns the key referenced by the specificed [`KeySelector`].
     ///
@@ -106,7 +190,27 @@ pub trait ReadTransaction {
     /// ```ignore
     /// async fn get_key(&self, selector: KeySelector) -> FdbResult<Key>
     /// ```
-    fn get_key(&self, selector: KeySelector) -> FdbFutureKey;
+    fn get_key(&self, selector: KeySelector) -> Self::FutureKey;
+
+    /// Returns the keys referenced by multiple [`KeySelector`]s, with
+    /// at most `limit` reads in flight at any point in time. A
+    /// `limit` of `0` is treated as `1`.
+    ///
+    /// The returned stream yields a [`Key`] item for each selector,
+    /// in the same order as `selectors`. If a read fails, the error
+    /// is yielded and the stream ends.
+    ///
+    /// The reads are issued using [`get_key`], when the returned
+    /// stream is polled.
+    ///
+    /// [`get_key`]: ReadTransaction::get_key
+    fn get_key_many<I>(&self, selectors: I, limit: usize) -> FdbStreamKey<'_, Self>
+    where
+        Self: Sized,
+        I: IntoIterator<Item = KeySelector>,
+    {
+        FdbStreamKey::new(self, selectors.into_iter().collect(), limit)
+    }
 
     #[cfg(feature = "fdb-7_1")]
     /// WARNING: This feature is considered experimental at this time.
@@ -114,32 +218,24 @@ pub trait ReadTransaction {
     /// Gets an ordered range of mapped keys and values from the
     /// database.
     ///
-    /// The returned [`FdbStreamMappedKeyValue`] implements [`Stream`]
-    /// trait that yields a [`MappedKeyValue`] item.
-    ///
-    /// [`Stream`]: futures::Stream
-    /// [`MappedKeyValue`]: crate::MappedKeyValue
+    /// The returned stream yields a [`MappedKeyValue`] item.
     fn get_mapped_range(
         &self,
         begin: KeySelector,
         end: KeySelector,
         mapper: impl Into<Mapper>,
         options: RangeOptions,
-    ) -> FdbStreamMappedKeyValue;
+    ) -> Self::StreamMappedKeyValue;
 
     /// Gets an ordered range of keys and values from the database.
     ///
-    /// The returned [`FdbStreamKeyValue`] implements [`Stream`] trait
-    /// that yields a [`KeyValue`] item.
-    ///
-    /// [`Stream`]: futures::Stream
-    /// [`KeyValue`]: crate::KeyValue
+    /// The returned stream yields a [`KeyValue`] item.
     fn get_range(
         &self,
         begin: KeySelector,
         end: KeySelector,
         options: RangeOptions,
-    ) -> FdbStreamKeyValue;
+    ) -> Self::StreamKeyValue;
 
     #[cfg(feature = "fdb-7_1")]
     /// Gets a list of keys that can split the given range into
@@ -160,7 +256,7 @@ pub trait ReadTransaction {
         begin: impl Into<Key>,
         end: impl Into<Key>,
         chunk_size: i64,
-    ) -> FdbFutureKeyArray;
+    ) -> Self::FutureKeyArray;
 
     /// Gets the version at which the reads for this [`Transaction`]
     /// or [`ReadTransaction`] will access the database.
@@ -173,7 +269,7 @@ pub trait ReadTransaction {
     ///
     /// # Safety
     ///
-    /// The [`FdbFuture`] resolves to an [`i64`] instead of a [`u64`]
+    /// The future resolves to an [`i64`] instead of a [`u64`]
     /// because of [internal representation]. Even though it is an
     /// [`i64`], the future will always return a positive
     /// number. Negative GRV numbers are used internally within FDB.
@@ -182,9 +278,8 @@ pub trait ReadTransaction {
     /// read-write transactions you should use commit version.
     ///
     /// [`Transaction`]: crate::transaction::Transaction
-    /// [`FdbFuture`]: crate::future::FdbFuture
     /// [internal representation]: https://github.com/apple/foundationdb/blob/6.3.22/fdbclient/FDBTypes.h#L32
-    unsafe fn get_read_version(&self) -> FdbFutureI64;
+    unsafe fn get_read_version(&self) -> Self::FutureI64;
 
     /// Set options on a [`Transaction`] or [`ReadTransaction`]
     ///
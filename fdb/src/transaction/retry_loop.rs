@@ -0,0 +1,241 @@
+// Retry loops used by the `run` and `read` methods of `FdbDatabase`,
+// `FdbTenant` and `MemoryDatabase`, and by `FaultInjector::run`.
+//
+// When `tracing` feature is enabled, each attempt runs inside an
+// `attempt` span, and calls to `commit` and `on_error` get their own
+// spans. Fields that need a round trip to the cluster (read version,
+// approximate size) are only fetched when the span is enabled.
+
+use std::future::Future;
+
+use crate::error::{FdbError, FdbResult};
+use crate::range::Range;
+use crate::transaction::{conflicting_keys, ReadTransaction, Transaction, TransactionOption};
+
+#[cfg(feature = "tracing")]
+use tracing::{field, Instrument, Span};
+
+pub(crate) async fn run<T, Tr, F, Fut>(t: Tr, f: F) -> FdbResult<T>
+where
+    Tr: Transaction + Clone,
+    F: FnMut(Tr) -> Fut,
+    Fut: Future<Output = FdbResult<T>>,
+{
+    run_with_conflicting_keys(t, f, None::<fn(Vec<Range>)>).await
+}
+
+// When `on_conflict` is `Some(...)`, `ReportConflictingKeys` option
+// is set on each attempt, and `on_conflict` is called with the
+// conflicting key ranges when `commit` fails with `not_committed`
+// error.
+pub(crate) async fn run_with_conflicting_keys<T, Tr, F, Fut, C>(
+    t: Tr,
+    mut f: F,
+    mut on_conflict: Option<C>,
+) -> FdbResult<T>
+where
+    Tr: Transaction + Clone,
+    F: FnMut(Tr) -> Fut,
+    Fut: Future<Output = FdbResult<T>>,
+    C: FnMut(Vec<Range>),
+{
+    #[cfg(feature = "tracing")]
+    let mut attempt: u64 = 0;
+
+    loop {
+        let fut = run_attempt(&t, &mut f, on_conflict.as_mut());
+
+        #[cfg(feature = "tracing")]
+        let fut = {
+            attempt += 1;
+            fut.instrument(attempt_span(attempt))
+        };
+
+        if let Some(ret_val) = fut.await {
+            return ret_val;
+        }
+    }
+}
+
+pub(crate) async fn read<T, Tr, F, Fut>(t: Tr, mut f: F) -> FdbResult<T>
+where
+    Tr: ReadTransaction + Clone,
+    F: FnMut(Tr) -> Fut,
+    Fut: Future<Output = FdbResult<T>>,
+{
+    #[cfg(feature = "tracing")]
+    let mut attempt: u64 = 0;
+
+    loop {
+        let fut = read_attempt(&t, &mut f);
+
+        #[cfg(feature = "tracing")]
+        let fut = {
+            attempt += 1;
+            fut.instrument(attempt_span(attempt))
+        };
+
+        if let Some(ret_val) = fut.await {
+            return ret_val;
+        }
+    }
+}
+
+// Runs the closure once and commits the transaction. Returns `None`
+// when the closure needs to be retried.
+async fn run_attempt<T, Tr, F, Fut, C>(
+    t: &Tr,
+    f: &mut F,
+    on_conflict: Option<&mut C>,
+) -> Option<FdbResult<T>>
+where
+    Tr: Transaction + Clone,
+    F: FnMut(Tr) -> Fut,
+    Fut: Future<Output = FdbResult<T>>,
+    C: FnMut(Vec<Range>),
+{
+    if on_conflict.is_some() {
+        if let Err(e) = t.set_option(TransactionOption::ReportConflictingKeys) {
+            return Some(Err(e));
+        }
+    }
+
+    let ret_val = f(t.clone()).await;
+
+    // Closure returned an error
+    if let Err(e) = ret_val {
+        if FdbError::layer_error(e.code()) {
+            // Check if it is a layer error. If so, just return it.
+            return Some(Err(e));
+        } else {
+            return on_error(t, e).await;
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    record_read_version(t).await;
+
+    // No error from closure. Attempt to commit the transaction.
+    if let Err(e) = commit(t).await {
+        // `not_committed` error. Conflicting keys are cleared by
+        // `on_error`, so we need to get them before calling it.
+        if let (Some(on_conflict), 1020) = (on_conflict, e.code()) {
+            if let Ok(ranges) = conflicting_keys::get_conflicting_keys(t).await {
+                on_conflict(ranges);
+            }
+        }
+
+        return on_error(t, e).await;
+    }
+
+    // Commit successful, return `Ok(T)`
+    Some(ret_val)
+}
+
+// Runs the closure once. Returns `None` when the closure needs to be
+// retried.
+async fn read_attempt<T, Tr, F, Fut>(t: &Tr, f: &mut F) -> Option<FdbResult<T>>
+where
+    Tr: ReadTransaction + Clone,
+    F: FnMut(Tr) -> Fut,
+    Fut: Future<Output = FdbResult<T>>,
+{
+    let ret_val = f(t.clone()).await;
+
+    // Closure returned an error
+    if let Err(e) = ret_val {
+        if FdbError::layer_error(e.code()) {
+            // Check if it is a layer error. If so, just return it.
+            return Some(Err(e));
+        } else {
+            return on_error(t, e).await;
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    record_read_version(t).await;
+
+    // We don't need to commit read transaction, return `Ok(T)`
+    Some(ret_val)
+}
+
+// Returns `None` when `on_error` resets the transaction so that it
+// can be retried.
+async fn on_error<T, Tr>(t: &Tr, e: FdbError) -> Option<FdbResult<T>>
+where
+    Tr: ReadTransaction,
+{
+    let fut = unsafe { t.on_error(e) };
+
+    #[cfg(feature = "tracing")]
+    let fut = fut.instrument(tracing::debug_span!("on_error", code = e.code()));
+
+    // Check if `on_error` returned an error. This means we have a
+    // non-retryable error.
+    let res = fut.await.err().map(Err);
+
+    #[cfg(feature = "metrics")]
+    if res.is_none() {
+        crate::metrics::record_retry();
+    }
+
+    res
+}
+
+#[cfg(not(feature = "tracing"))]
+async fn commit<Tr>(t: &Tr) -> FdbResult<()>
+where
+    Tr: Transaction,
+{
+    unsafe { t.commit() }.await
+}
+
+#[cfg(feature = "tracing")]
+async fn commit<Tr>(t: &Tr) -> FdbResult<()>
+where
+    Tr: Transaction,
+{
+    let span = tracing::debug_span!(
+        "commit",
+        approximate_size = field::Empty,
+        committed_version = field::Empty
+    );
+
+    if !span.is_disabled() {
+        if let Ok(approximate_size) = t.get_approximate_size().await {
+            span.record("approximate_size", approximate_size);
+        }
+    }
+
+    let res = unsafe { t.commit() }.instrument(span.clone()).await;
+
+    if res.is_ok() && !span.is_disabled() {
+        if let Ok(committed_version) = unsafe { t.get_committed_version() }.into() {
+            span.record("committed_version", committed_version);
+        }
+    }
+
+    res
+}
+
+#[cfg(feature = "tracing")]
+fn attempt_span(attempt: u64) -> Span {
+    tracing::debug_span!("attempt", attempt, read_version = field::Empty)
+}
+
+// Records the read version in the current `attempt` span. If the
+// closure did not read anything, this gets a new read version from
+// the cluster.
+#[cfg(feature = "tracing")]
+async fn record_read_version<Tr>(t: &Tr)
+where
+    Tr: ReadTransaction,
+{
+    let span = Span::current();
+
+    if !span.is_disabled() {
+        if let Ok(read_version) = unsafe { t.get_read_version() }.await {
+            span.record("read_version", read_version);
+        }
+    }
+}
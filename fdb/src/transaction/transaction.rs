@@ -1,11 +1,10 @@
 use bytes::Bytes;
 
+use std::future::Future;
+
 use crate::error::FdbResult;
-use crate::future::{FdbFutureI64, FdbFutureUnit};
 use crate::range::Range;
-use crate::transaction::{
-    CommittedVersion, MutationType, ReadTransaction, TransactionVersionstamp,
-};
+use crate::transaction::{MutationType, ReadTransaction};
 use crate::{Key, Value};
 
 /// A [`Transaction`] represents a FDB database transaction.
@@ -54,6 +53,30 @@ use crate::{Key, Value};
 //       loop in `FdbDatabase::read`. There is no `getDatabase`
 //       method, as we don't implement `Database` interface/trait.
 pub trait Transaction: ReadTransaction {
+    /// Value returned by [`get_committed_version`]. For
+    /// [`FdbTransaction`], this is [`CommittedVersion`].
+    ///
+    /// [`get_committed_version`]: Transaction::get_committed_version
+    /// [`FdbTransaction`]: crate::transaction::FdbTransaction
+    /// [`CommittedVersion`]: crate::transaction::CommittedVersion
+    type CommittedVersion: Into<FdbResult<i64>>;
+
+    /// Future returned by [`get_versionstamp`]. For
+    /// [`FdbTransaction`], this is [`TransactionVersionstamp`].
+    ///
+    /// [`get_versionstamp`]: Transaction::get_versionstamp
+    /// [`FdbTransaction`]: crate::transaction::FdbTransaction
+    /// [`TransactionVersionstamp`]: crate::transaction::TransactionVersionstamp
+    type TransactionVersionstamp: Future<Output = FdbResult<Bytes>> + Send;
+
+    /// Read transaction returned by [`snapshot`]. For
+    /// [`FdbTransaction`], this is [`FdbReadTransaction`].
+    ///
+    /// [`snapshot`]: Transaction::snapshot
+    /// [`FdbTransaction`]: crate::transaction::FdbTransaction
+    /// [`FdbReadTransaction`]: crate::transaction::FdbReadTransaction
+    type Snapshot: ReadTransaction + Send + Sync;
+
     /// Adds a key to the transaction's read conflict ranges as if you
     /// had read the key.
     fn add_read_conflict_key(&self, key: impl Into<Key>) -> FdbResult<()>;
@@ -97,7 +120,7 @@ pub trait Transaction: ReadTransaction {
     /// See [C API] for more details.
     ///
     /// [C API]: https://apple.github.io/foundationdb/api-c.html#c.fdb_transaction_commit
-    unsafe fn commit(&self) -> FdbFutureUnit;
+    unsafe fn commit(&self) -> Self::FutureUnit;
 
     /// Returns a future that will contain the approximated size of
     /// the commit, which is the summation of mutations, read conflict
@@ -106,7 +129,7 @@ pub trait Transaction: ReadTransaction {
     /// ```ignore
     /// async fn get_approximate_size(&self) -> FdbResult<i64>
     /// ```
-    fn get_approximate_size(&self) -> FdbFutureI64;
+    fn get_approximate_size(&self) -> Self::FutureI64;
 
     /// Gets the version number at which a successful commit modified
     /// the database.
@@ -116,18 +139,19 @@ pub trait Transaction: ReadTransaction {
     /// See [C API] for more details.
     ///
     /// [C API]: https://apple.github.io/foundationdb/api-c.html#c.fdb_transaction_get_committed_version
-    unsafe fn get_committed_version(&self) -> CommittedVersion;
+    unsafe fn get_committed_version(&self) -> Self::CommittedVersion;
 
-    /// Returns [`TransactionVersionstamp`] from which you can get the
-    /// versionstamp which was used by any versionstamp operations in
-    /// this transaction.
+    /// Returns a future from which you can get the versionstamp
+    /// which was used by any versionstamp operations in this
+    /// transaction. The future resolves after the transaction is
+    /// committed.
     ///
     /// # Safety
     ///
     /// See [C API] for more details.
     ///
     /// [C API]: https://apple.github.io/foundationdb/api-c.html#c.fdb_transaction_get_versionstamp
-    unsafe fn get_versionstamp(&self) -> TransactionVersionstamp;
+    unsafe fn get_versionstamp(&self) -> Self::TransactionVersionstamp;
 
     /// An atomic operation is a single database command that carries
     /// out several logical steps: reading the value of a key,
@@ -151,10 +175,19 @@ pub trait Transaction: ReadTransaction {
     /// Sets the value for a given key.
     fn set(&self, key: impl Into<Key>, value: impl Into<Value>);
 
+    /// Returns a read transaction that does snapshot reads on this
+    /// [`Transaction`].
+    ///
+    /// Snapshot reads do not add read conflict ranges. See
+    /// [`FdbTransaction::snapshot`] for details.
+    ///
+    /// [`FdbTransaction::snapshot`]: crate::transaction::FdbTransaction::snapshot
+    fn snapshot(&self) -> Self::Snapshot;
+
     /// Creates a watch that will become ready when it reports a
     /// change to the value of the specified key.
     ///
     /// A watch's behavior is relative to the transaction that created
     /// it.
-    fn watch(&self, key: impl Into<Key>) -> FdbFutureUnit;
+    fn watch(&self, key: impl Into<Key>) -> Self::FutureUnit;
 }
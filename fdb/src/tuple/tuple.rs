@@ -320,6 +320,24 @@ impl Tuple {
         self.elements.append(&mut t.elements);
     }
 
+    // Adds a copy of the element at `index` of `t`.
+    #[cfg(all(feature = "memory", feature = "fdb-7_1"))]
+    pub(crate) fn add_element_from(&mut self, t: &Tuple, index: usize) -> FdbResult<()> {
+        let element = t
+            .elements
+            .get(index)
+            .cloned()
+            .ok_or_else(|| FdbError::new(TUPLE_GET))?;
+
+        if let TupleValue::Versionstamp96Bit(ref v) = element {
+            self.has_incomplete_versionstamp =
+                self.has_incomplete_versionstamp || (!v.is_complete());
+        }
+
+        self.elements.push(element);
+        Ok(())
+    }
+
     /// Determines if there is a [`Versionstamp`] included in this
     /// [`Tuple`] that has not had its transaction version set.
     pub fn has_incomplete_versionstamp(&self) -> bool {
@@ -0,0 +1,114 @@
+use bytes::Bytes;
+
+use fdb::layer::blob::{Blob, CHUNK_SIZE};
+use fdb::range::RangeOptions;
+use fdb::testing::run_test;
+use fdb::tuple::Tuple;
+
+use futures::TryStreamExt;
+
+use std::error::Error;
+
+const DATA_SIZE: usize = 1_000_000;
+const TRANSACTION_SIZE: usize = 100_000;
+
+fn test_data(len: usize) -> Bytes {
+    (0..len)
+        .map(|i| (i % 251) as u8)
+        .collect::<Vec<u8>>()
+        .into()
+}
+
+#[test]
+fn blob_append_db_write_db() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let blob = Blob::new(subspace);
+
+        // Returns the size, the contents, the length of each chunk
+        // and the metadata of `blob`.
+        let read_blob = || {
+            let fdb_database = fdb_database.clone();
+            let blob = blob.clone();
+            async move {
+                fdb_database
+                    .read(|tr| {
+                        let blob = blob.clone();
+                        async move {
+                            let size = blob.get_size(&tr).await?;
+                            let data = blob.read_all(&tr).await?;
+                            let metadata = blob.get_metadata(&tr).await?;
+
+                            // Every key in the subspace other than
+                            // the metadata and size keys is a chunk.
+                            let chunk_lens = blob
+                                .get_subspace_ref()
+                                .range(&Tuple::new())
+                                .into_stream(&tr, RangeOptions::default())
+                                .map_ok(|kv| Bytes::from(kv.into_value()).len())
+                                .try_collect::<Vec<usize>>()
+                                .await?;
+
+                            Ok((size, data, chunk_lens, metadata))
+                        }
+                    })
+                    .await
+            }
+        };
+
+        fdb_database
+            .run(|tr| {
+                let blob = blob.clone();
+                async move {
+                    blob.set_metadata(&tr, Bytes::from_static(b"metadata"));
+                    Ok(())
+                }
+            })
+            .await?;
+
+        // `write_db` splits the data into multiple transactions and
+        // each transaction into chunks. Metadata is removed.
+        let data = test_data(DATA_SIZE);
+        blob.write_db(&fdb_database, data.clone(), TRANSACTION_SIZE)
+            .await?;
+
+        let (size, read_data, chunk_lens, metadata) = read_blob().await?;
+        assert_eq!(size, DATA_SIZE as i64);
+        assert_eq!(read_data, data);
+        assert_eq!(metadata, None);
+
+        // One size key, and `DATA_SIZE / CHUNK_SIZE` full chunks.
+        let (chunk_lens, size_len) = chunk_lens.split_at(chunk_lens.len() - 1);
+        assert!(size_len[0] < CHUNK_SIZE);
+        assert_eq!(chunk_lens.len(), DATA_SIZE / CHUNK_SIZE);
+        assert!(chunk_lens.iter().all(|len| *len == CHUNK_SIZE));
+
+        // `transaction_size` that is smaller than `CHUNK_SIZE` is
+        // rounded up to `CHUNK_SIZE`. The last chunk is partial.
+        let more_data = test_data(CHUNK_SIZE * 2 + 5);
+        blob.append_db(&fdb_database, more_data.clone(), 1).await?;
+
+        let (size, read_data, chunk_lens, _) = read_blob().await?;
+        assert_eq!(size, (DATA_SIZE + more_data.len()) as i64);
+        assert_eq!(read_data.slice(..DATA_SIZE), data);
+        assert_eq!(read_data.slice(DATA_SIZE..), more_data);
+
+        let (chunk_lens, _) = chunk_lens.split_at(chunk_lens.len() - 1);
+        assert_eq!(chunk_lens.len(), (DATA_SIZE / CHUNK_SIZE) + 3);
+        assert_eq!(chunk_lens[chunk_lens.len() - 1], 5);
+
+        // `write_db` replaces the contents.
+        let data = test_data(CHUNK_SIZE + 1);
+        blob.write_db(&fdb_database, data.clone(), TRANSACTION_SIZE)
+            .await?;
+
+        let (size, read_data, chunk_lens, _) = read_blob().await?;
+        assert_eq!(size, data.len() as i64);
+        assert_eq!(read_data, data);
+
+        // Two chunks and the size key.
+        assert_eq!(chunk_lens.len(), 3);
+        assert_eq!(&chunk_lens[..2], &[CHUNK_SIZE, 1]);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,95 @@
+use bytes::Bytes;
+
+use fdb::bulk_writer::{BulkMutation, BulkWriter, BulkWriterOptions};
+use fdb::range::{Range, RangeOptions};
+use fdb::testing::run_test;
+use fdb::tuple::Tuple;
+use fdb::{Key, Value};
+
+use futures::{stream, TryStreamExt};
+
+use std::collections::HashSet;
+use std::error::Error;
+
+const ITEMS: usize = 5000;
+const VALUE_SIZE: usize = 100;
+const TRANSACTION_SIZE: i64 = 50_000;
+
+#[test]
+fn bulk_writer_splits_transactions() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let key = |i: usize| {
+            let mut t = Tuple::new();
+            t.add_i64(i as i64);
+            Key::from(subspace.subspace(&t).pack())
+        };
+
+        let bulk_writer = BulkWriter::new(fdb_database.clone(), {
+            let mut options = BulkWriterOptions::default();
+            options.set_transaction_size(TRANSACTION_SIZE);
+            options.set_max_in_flight(2);
+            options
+        });
+
+        // Every key is set, and every other key is cleared later in
+        // the stream.
+        let mutations = (0..ITEMS)
+            .map(|i| BulkMutation::Set(key(i), Value::from(Bytes::from(vec![0; VALUE_SIZE]))))
+            .chain(
+                (0..ITEMS)
+                    .filter(|i| i % 2 == 1)
+                    .map(|i| BulkMutation::Clear(key(i))),
+            )
+            .collect::<Vec<_>>();
+
+        let mut reported = Vec::new();
+
+        let progress = bulk_writer
+            .write_with_progress(stream::iter(mutations), |progress| {
+                reported.push((progress.get_mutations(), progress.get_transactions()));
+            })
+            .await?;
+
+        // Mutations of the stream do not fit in a single
+        // transaction.
+        let total_mutations = (ITEMS + (ITEMS / 2)) as u64;
+        assert_eq!(progress.get_mutations(), total_mutations);
+        assert!(progress.get_transactions() > 1);
+        assert!(progress.get_bytes() > (ITEMS * VALUE_SIZE) as u64);
+
+        // Progress is reported once for each transaction.
+        assert_eq!(reported.len() as u64, progress.get_transactions());
+        for (i, (mutations, transactions)) in reported.iter().enumerate() {
+            assert_eq!(*transactions, i as u64 + 1);
+            assert!(*mutations <= total_mutations);
+        }
+        assert_eq!(
+            reported.last().unwrap(),
+            &(total_mutations, progress.get_transactions())
+        );
+
+        // As transactions are committed concurrently, a key that is
+        // set and cleared in different transactions might have either
+        // value. Keys that are only set are always present.
+        let keys = fdb_database
+            .read(|tr| {
+                let range = Range::starts_with(subspace.pack());
+                async move {
+                    range
+                        .into_stream(&tr, RangeOptions::default())
+                        .map_ok(|kv| kv.into_key())
+                        .try_collect::<Vec<Key>>()
+                        .await
+                }
+            })
+            .await?;
+
+        let keys = keys.into_iter().map(Bytes::from).collect::<HashSet<_>>();
+
+        for i in (0..ITEMS).filter(|i| i % 2 == 0) {
+            assert!(keys.contains(&Bytes::from(key(i))));
+        }
+
+        Ok(())
+    })
+}
@@ -0,0 +1,98 @@
+use bytes::Bytes;
+
+use fdb::error::FdbError;
+use fdb::management::{ClusterManagement, ThrottlePriority};
+use fdb::testing::run_test;
+use fdb::transaction::Transaction;
+
+use uuid::Uuid;
+
+use std::error::Error;
+use std::time::Duration;
+
+#[test]
+fn cluster_management() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        // Coordinators and process classes of the test cluster.
+        let coordinators = ClusterManagement::get_coordinators_db(&fdb_database).await?;
+        assert!(!coordinators.is_empty());
+        assert!(coordinators.iter().all(|address| address.contains(':')));
+
+        let process_classes = ClusterManagement::get_process_classes_db(&fdb_database).await?;
+        assert!(!process_classes.is_empty());
+
+        // Lock and unlock.
+        let uid = Uuid::new_v4();
+        let key = subspace.pack();
+
+        assert_eq!(
+            ClusterManagement::get_database_lock_db(&fdb_database).await?,
+            None
+        );
+
+        ClusterManagement::lock_database_db(&fdb_database, uid).await?;
+
+        // Only lock aware transactions can access a locked database,
+        // so unlock it before checking the results.
+        let lock_res = ClusterManagement::get_database_lock_db(&fdb_database).await;
+        let write_res = fdb_database
+            .run(|tr| {
+                let key = key.clone();
+                async move {
+                    tr.set(key, Bytes::from_static(b"value"));
+                    Ok(())
+                }
+            })
+            .await;
+        let other_unlock_res =
+            ClusterManagement::unlock_database_db(&fdb_database, Uuid::new_v4()).await;
+
+        ClusterManagement::unlock_database_db(&fdb_database, uid).await?;
+
+        // `database_locked` error
+        assert_eq!(lock_res, Ok(Some(uid)));
+        assert_eq!(write_res, Err(FdbError::new(1038)));
+        assert_eq!(other_unlock_res, Err(FdbError::new(1038)));
+
+        assert_eq!(
+            ClusterManagement::get_database_lock_db(&fdb_database).await?,
+            None
+        );
+
+        // Unlocking a database that is not locked does nothing.
+        ClusterManagement::unlock_database_db(&fdb_database, uid).await?;
+
+        // Throttle and unthrottle.
+        let tag = format!("test_{}", &uid.to_simple().to_string()[..8]);
+
+        ClusterManagement::throttle_tag_db(
+            &fdb_database,
+            &tag,
+            10.0,
+            Duration::from_secs(60),
+            ThrottlePriority::Default,
+        )
+        .await?;
+
+        let throttled_tags = ClusterManagement::get_throttled_tags_db(&fdb_database).await?;
+        let throttle = throttled_tags
+            .iter()
+            .find(|throttle| throttle.get_tag() == tag)
+            .expect("tag must be throttled");
+
+        assert_eq!(throttle.get_priority(), ThrottlePriority::Default);
+        assert!(!throttle.is_auto_throttled());
+        assert_eq!(throttle.get_tps_rate(), 10.0);
+        assert_eq!(throttle.get_duration(), Duration::from_secs(60));
+
+        assert!(ClusterManagement::unthrottle_tag_db(&fdb_database, &tag).await?);
+        assert!(!ClusterManagement::unthrottle_tag_db(&fdb_database, &tag).await?);
+
+        let throttled_tags = ClusterManagement::get_throttled_tags_db(&fdb_database).await?;
+        assert!(throttled_tags
+            .iter()
+            .all(|throttle| throttle.get_tag() != tag));
+
+        Ok(())
+    })
+}
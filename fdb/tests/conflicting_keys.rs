@@ -0,0 +1,66 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use fdb::range::Range;
+use fdb::testing::run_test;
+use fdb::transaction::{ReadTransaction, Transaction};
+use fdb::tuple::Tuple;
+
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[test]
+fn run_with_conflicting_keys() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let key = subspace
+            .subspace(&{
+                let mut t = Tuple::new();
+                t.add_string("key".to_string());
+                t
+            })
+            .pack();
+
+        let first_attempt = AtomicBool::new(true);
+        let mut conflicts = Vec::new();
+
+        fdb_database
+            .run_with_conflicting_keys(
+                |tr| {
+                    let (fdb_database, key, first_attempt) =
+                        (fdb_database.clone(), key.clone(), &first_attempt);
+                    async move {
+                        tr.get(key.clone()).await?;
+
+                        // Write to `key` from another transaction, so
+                        // that the first attempt conflicts.
+                        if first_attempt.swap(false, Ordering::SeqCst) {
+                            fdb_database
+                                .run(|other_tr| {
+                                    let key = key.clone();
+                                    async move {
+                                        other_tr.set(key, Bytes::from_static(b"other"));
+                                        Ok(())
+                                    }
+                                })
+                                .await?;
+                        }
+
+                        tr.set(key, Bytes::from_static(b"value"));
+                        Ok(())
+                    }
+                },
+                |ranges| conflicts.push(ranges),
+            )
+            .await?;
+
+        let key_end = {
+            let mut b = BytesMut::new();
+            b.put(key.as_ref());
+            b.put_u8(0x00);
+            Bytes::from(b)
+        };
+
+        assert_eq!(conflicts, vec![vec![Range::new(key, key_end)]]);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,35 @@
+use fdb::testing::run_test;
+
+use tokio::time;
+
+use std::error::Error;
+use std::time::Duration;
+
+#[test]
+fn database_admin() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, _| async move {
+        let busyness = fdb_database.get_main_thread_busyness();
+        assert!((0.0..=1.0).contains(&busyness));
+
+        let protocol = fdb_database.get_server_protocol(0).await?;
+        assert_ne!(protocol, 0);
+
+        // With the current protocol version as `expected_version`,
+        // this waits until the cluster is upgraded.
+        assert!(time::timeout(
+            Duration::from_millis(500),
+            fdb_database.get_server_protocol(protocol)
+        )
+        .await
+        .is_err());
+
+        // There is no worker at this address.
+        assert!(
+            !fdb_database
+                .reboot_worker("127.0.0.1:1", false, Duration::from_secs(0))
+                .await?
+        );
+
+        Ok(())
+    })
+}
@@ -0,0 +1,201 @@
+use bytes::Bytes;
+
+use fdb::error::{FdbResult, LEADER_ELECTION_NOT_LEADER};
+use fdb::layer::leader_election::{Candidacy, LeaderElection, LeaderEvent};
+use fdb::testing::run_test;
+use fdb::transaction::{MutationType, Transaction};
+use fdb::tuple::{Tuple, Versionstamp};
+
+use futures::StreamExt;
+
+use tokio::time;
+
+use std::error::Error;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const LEASE: Duration = Duration::from_millis(600);
+
+// Returns the next event of `candidacy`, failing if there is none
+// within a few leases.
+async fn next_event(candidacy: &mut Candidacy) -> FdbResult<LeaderEvent> {
+    time::timeout(LEASE * 5, candidacy.next())
+        .await
+        .expect("timed out waiting for event")
+        .expect("candidacy stream ended")
+}
+
+#[test]
+fn leader_election() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let leader_election = LeaderElection::new(fdb_database.clone(), subspace.clone());
+
+        let check_leader = |token: i64| {
+            let fdb_database = fdb_database.clone();
+            let leader_election = leader_election.clone();
+            async move {
+                fdb_database
+                    .run(|tr| {
+                        let leader_election = leader_election.clone();
+                        async move { leader_election.check_leader(&tr, token).await }
+                    })
+                    .await
+            }
+        };
+
+        assert_eq!(leader_election.get_leader().await?, None);
+
+        // The first candidate is elected.
+        let mut candidacy_a = leader_election.campaign(Bytes::from_static(b"a"), LEASE);
+
+        let token_a = match next_event(&mut candidacy_a).await? {
+            LeaderEvent::Elected(token) => token,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert_eq!(
+            leader_election.get_leader().await?,
+            Some(Bytes::from_static(b"a"))
+        );
+        check_leader(token_a).await?;
+
+        // Later candidates observe the leader.
+        let mut candidacy_b = leader_election.campaign(Bytes::from_static(b"b"), LEASE);
+        let mut candidacy_c = leader_election.campaign(Bytes::from_static(b"c"), LEASE);
+
+        assert_eq!(
+            next_event(&mut candidacy_b).await?,
+            LeaderEvent::LeaderIs(Bytes::from_static(b"a"))
+        );
+        assert_eq!(
+            next_event(&mut candidacy_c).await?,
+            LeaderEvent::LeaderIs(Bytes::from_static(b"a"))
+        );
+
+        // The leader keeps its lease alive well past its initial
+        // expiry, without leadership changing.
+        time::sleep(LEASE * 3).await;
+
+        assert_eq!(
+            leader_election.get_leader().await?,
+            Some(Bytes::from_static(b"a"))
+        );
+        check_leader(token_a).await?;
+
+        // When the leader resigns, the earliest registered candidate
+        // becomes the leader, with a new fencing token.
+        drop(candidacy_a);
+
+        let token_b = match next_event(&mut candidacy_b).await? {
+            LeaderEvent::Elected(token) => token,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert!(token_b > token_a);
+
+        assert_eq!(
+            next_event(&mut candidacy_c).await?,
+            LeaderEvent::LeaderIs(Bytes::from_static(b"b"))
+        );
+
+        check_leader(token_b).await?;
+        assert_eq!(
+            check_leader(token_a).await.unwrap_err().code(),
+            LEADER_ELECTION_NOT_LEADER
+        );
+
+        drop(candidacy_b);
+
+        let token_c = match next_event(&mut candidacy_c).await? {
+            LeaderEvent::Elected(token) => token,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert!(token_c > token_b);
+        assert_eq!(
+            leader_election.get_leader().await?,
+            Some(Bytes::from_static(b"c"))
+        );
+
+        // The leader lease is released in the background, when the
+        // candidate resigns.
+        drop(candidacy_c);
+
+        time::timeout(LEASE * 5, async {
+            while leader_election.get_leader().await?.is_some() {
+                time::sleep(LEASE / 10).await;
+            }
+            FdbResult::Ok(())
+        })
+        .await
+        .expect("timed out waiting for resignation")?;
+
+        // A candidate that is registered, but is no longer
+        // campaigning, is never elected by the other candidates. They
+        // wait for its registration to expire instead.
+        let stale_subspace = subspace.subspace(&{
+            let mut t = Tuple::new();
+            t.add_string("stale".to_string());
+            t
+        });
+        let leader_election = LeaderElection::new(fdb_database.clone(), stale_subspace.clone());
+
+        let stale_expiry = now_millis() + 2 * LEASE.as_millis() as i64;
+
+        fdb_database
+            .run(|tr| {
+                let stale_subspace = stale_subspace.clone();
+                async move {
+                    let key = stale_subspace.pack_with_versionstamp(&{
+                        let mut t = Tuple::new();
+                        t.add_string("C".to_string());
+                        t.add_versionstamp(Versionstamp::incomplete(0));
+                        t
+                    })?;
+                    let value = {
+                        let mut t = Tuple::new();
+                        t.add_bytes(Bytes::from_static(b"stale"));
+                        t.add_i64(stale_expiry);
+                        t.pack()
+                    };
+
+                    // Safety: `SetVersionstampedKey` is not
+                    // `AppendIfFits`.
+                    unsafe { tr.mutate(MutationType::SetVersionstampedKey, key, value) };
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let mut candidacy_d = leader_election.campaign(Bytes::from_static(b"d"), LEASE);
+
+        time::sleep(LEASE).await;
+        assert_eq!(leader_election.get_leader().await?, None);
+
+        match next_event(&mut candidacy_d).await? {
+            LeaderEvent::Elected(_) => {}
+            event => panic!("unexpected event: {:?}", event),
+        }
+        assert!(now_millis() >= stale_expiry);
+        assert_eq!(
+            leader_election.get_leader().await?,
+            Some(Bytes::from_static(b"d"))
+        );
+
+        drop(candidacy_d);
+
+        time::timeout(LEASE * 5, async {
+            while leader_election.get_leader().await?.is_some() {
+                time::sleep(LEASE / 10).await;
+            }
+            FdbResult::Ok(())
+        })
+        .await
+        .expect("timed out waiting for resignation")?;
+
+        Ok(())
+    })
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
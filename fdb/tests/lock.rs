@@ -0,0 +1,70 @@
+use bytes::Bytes;
+
+use fdb::error::LOCK_LEASE_LOST;
+use fdb::layer::lock::Lock;
+use fdb::testing::run_test;
+
+use tokio::time;
+
+use std::error::Error;
+use std::time::Duration;
+
+const LEASE: Duration = Duration::from_millis(600);
+
+#[test]
+fn lock_guard() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let lock = Lock::new(fdb_database.clone(), subspace, String::from("lock"));
+
+        let owner_a = Bytes::from_static(b"a");
+        let owner_b = Bytes::from_static(b"b");
+
+        // The guard renews the lease in the background, so it is held
+        // well past its initial expiry.
+        let guard = lock.acquire_guard(owner_a.clone(), LEASE).await?;
+        let initial = guard.get_lease();
+
+        time::sleep(LEASE * 3).await;
+
+        assert!(!guard.is_lost());
+        assert_eq!(guard.get_renew_error(), None);
+        assert!(guard.get_lease().get_expiry() > initial.get_expiry());
+        assert_eq!(guard.get_token(), initial.get_token());
+        assert_eq!(lock.get_lease().await?, Some(guard.get_lease()));
+        assert_eq!(lock.try_acquire(owner_b.clone(), LEASE).await?, None);
+
+        // When the guard is dropped, the lease is released in the
+        // background and the `Lock` can be acquired by another owner.
+        drop(guard);
+
+        let lease_b = time::timeout(LEASE, lock.acquire(owner_b.clone(), LEASE)).await??;
+        assert_eq!(lease_b.get_token(), initial.get_token() + 1);
+        lock.release(&lease_b).await?;
+
+        // When the lease is released by someone else, the next renewal
+        // finds that it is lost.
+        let guard = lock.acquire_guard(owner_a.clone(), LEASE).await?;
+        lock.release(&guard.get_lease()).await?;
+
+        time::sleep(LEASE).await;
+
+        assert!(guard.is_lost());
+        assert_eq!(
+            guard.get_renew_error().map(|e| e.code()),
+            Some(LOCK_LEASE_LOST)
+        );
+
+        let lease_b = lock
+            .try_acquire(owner_b.clone(), LEASE)
+            .await?
+            .expect("lock must be available");
+        assert!(lease_b.get_token() > guard.get_token());
+
+        // Releasing a lost guard fails.
+        assert_eq!(guard.release().await.unwrap_err().code(), LOCK_LEASE_LOST);
+
+        lock.release(&lease_b).await?;
+
+        Ok(())
+    })
+}
@@ -0,0 +1,135 @@
+use bytes::Bytes;
+
+use fdb::layer::queue::Queue;
+use fdb::testing::run_test;
+
+use std::error::Error;
+
+const ITEMS: usize = 500;
+const POPPERS: usize = 8;
+
+#[test]
+fn queue_concurrent_poppers() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let queue = Queue::new(subspace);
+
+        fdb_database
+            .run(|tr| {
+                let queue = queue.clone();
+                async move {
+                    queue.push_many(&tr, (0..ITEMS).map(|i| Bytes::from(format!("{:08}", i))))?;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let len = fdb_database
+            .read(|tr| {
+                let queue = queue.clone();
+                async move { queue.get_len(&tr).await }
+            })
+            .await?;
+        assert_eq!(len, ITEMS as i64);
+
+        let mut join_handles = Vec::new();
+
+        for i in 0..POPPERS {
+            let fdb_database = fdb_database.clone();
+            let queue = queue.clone();
+
+            join_handles.push(tokio::spawn(async move {
+                let mut popped = Vec::new();
+
+                loop {
+                    // Alternate between single and batch pops.
+                    let items = fdb_database
+                        .run(|tr| {
+                            let queue = queue.clone();
+                            async move {
+                                if i % 2 == 0 {
+                                    Ok(queue.pop(&tr).await?.into_iter().collect::<Vec<_>>())
+                                } else {
+                                    queue.pop_many(&tr, 3).await
+                                }
+                            }
+                        })
+                        .await?;
+
+                    if items.is_empty() {
+                        break;
+                    }
+
+                    popped.extend(items);
+                }
+
+                fdb::error::FdbResult::Ok(popped)
+            }));
+        }
+
+        let mut all_popped = Vec::new();
+
+        for join_handle in join_handles {
+            all_popped.extend(join_handle.await??);
+        }
+
+        // Every item must be popped exactly once.
+        all_popped.sort();
+        assert_eq!(
+            all_popped,
+            (0..ITEMS)
+                .map(|i| Bytes::from(format!("{:08}", i)))
+                .collect::<Vec<_>>()
+        );
+
+        let (len, peeked) = fdb_database
+            .read(|tr| {
+                let queue = queue.clone();
+                async move { Ok((queue.get_len(&tr).await?, queue.peek(&tr).await?)) }
+            })
+            .await?;
+        assert_eq!(len, 0);
+        assert_eq!(peeked, None);
+
+        // With a pop window of `1`, items are popped in strict queue
+        // order.
+        let strict_queue = Queue::new_with_pop_window(queue.get_subspace_ref().clone(), 1);
+
+        fdb_database
+            .run(|tr| {
+                let strict_queue = strict_queue.clone();
+                async move {
+                    for i in 0..ITEMS {
+                        strict_queue.push(&tr, Bytes::from(format!("{:08}", i)))?;
+                    }
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let mut popped = Vec::new();
+
+        loop {
+            let items = fdb_database
+                .run(|tr| {
+                    let strict_queue = strict_queue.clone();
+                    async move { strict_queue.pop_many(&tr, 7).await }
+                })
+                .await?;
+
+            if items.is_empty() {
+                break;
+            }
+
+            popped.extend(items);
+        }
+
+        assert_eq!(
+            popped,
+            (0..ITEMS)
+                .map(|i| Bytes::from(format!("{:08}", i)))
+                .collect::<Vec<_>>()
+        );
+
+        Ok(())
+    })
+}
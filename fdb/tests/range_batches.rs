@@ -0,0 +1,168 @@
+use bytes::Bytes;
+
+use fdb::range::{KeyValueBatch, Range, RangeOptions, StreamingMode};
+use fdb::testing::run_test;
+use fdb::transaction::Transaction;
+use fdb::tuple::Tuple;
+use fdb::KeyValue;
+
+use futures::TryStreamExt;
+
+use std::error::Error;
+
+const ITEMS: usize = 1000;
+const VALUE_SIZE: usize = 100;
+
+#[test]
+fn range_batches() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        fdb_database
+            .run(|tr| {
+                let subspace = subspace.clone();
+                async move {
+                    for i in 0..ITEMS {
+                        let key = subspace.subspace(&{
+                            let mut t = Tuple::new();
+                            t.add_i64(i as i64);
+                            t
+                        });
+
+                        tr.set(key.pack(), Bytes::from(vec![0; VALUE_SIZE]));
+                    }
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let expected = fdb_database
+            .read(|tr| {
+                let range = Range::starts_with(subspace.pack());
+                async move {
+                    range
+                        .into_stream(&tr, RangeOptions::default())
+                        .try_collect::<Vec<KeyValue>>()
+                        .await
+                }
+            })
+            .await?;
+        assert_eq!(expected.len(), ITEMS);
+
+        // Returns the batches read using `options`.
+        let read_batches = |options: RangeOptions| {
+            let fdb_database = fdb_database.clone();
+            let range = Range::starts_with(subspace.pack());
+            async move {
+                fdb_database
+                    .read(|tr| {
+                        let range = range.clone();
+                        let options = options.clone();
+                        async move {
+                            range
+                                .into_stream(&tr, options)
+                                .into_batches()
+                                .try_collect::<Vec<KeyValueBatch>>()
+                                .await
+                        }
+                    })
+                    .await
+            }
+        };
+
+        // Checks that the batches contain `expected` key-value pairs
+        // and only the last batch has `more` set to `false`.
+        let check_batches = |batches: &Vec<KeyValueBatch>, expected: &[KeyValue]| {
+            assert!(!batches.is_empty());
+
+            for (i, batch) in batches.iter().enumerate() {
+                assert!(!batch.get_key_values_ref().is_empty());
+                assert_eq!(batch.get_more(), i != batches.len() - 1);
+            }
+
+            let keys = batches
+                .iter()
+                .flat_map(|batch| batch.get_key_values_ref())
+                .map(|kv| kv.get_key_ref().clone())
+                .collect::<Vec<_>>();
+            let expected_keys = expected
+                .iter()
+                .map(|kv| kv.get_key_ref().clone())
+                .collect::<Vec<_>>();
+            assert_eq!(keys, expected_keys);
+        };
+
+        // Small target bytes splits the range into many batches.
+        let batches = read_batches({
+            let mut options = RangeOptions::default();
+            options.set_target_bytes(1000);
+            options
+        })
+        .await?;
+        check_batches(&batches, &expected);
+        assert!(batches.len() > 1);
+
+        // Prefetch does not change the batches that are returned.
+        let prefetched_batches = read_batches({
+            let mut options = RangeOptions::default();
+            options.set_target_bytes(1000);
+            options.set_prefetch(true);
+            options
+        })
+        .await?;
+        check_batches(&prefetched_batches, &expected);
+
+        // Larger iteration returns larger batches in iterator
+        // streaming mode.
+        let iteration_batches = read_batches({
+            let mut options = RangeOptions::default();
+            options.set_iteration(10);
+            options
+        })
+        .await?;
+        check_batches(&iteration_batches, &expected);
+        assert!(
+            iteration_batches[0].get_key_values_ref().len()
+                > read_batches(RangeOptions::default()).await?[0]
+                    .get_key_values_ref()
+                    .len()
+        );
+
+        // When limit is reached, the last batch has `more` set to
+        // `false`, even though there are key-value pairs left in the
+        // range.
+        let batches = read_batches({
+            let mut options = RangeOptions::default();
+            options.set_limit(250);
+            options.set_target_bytes(1000);
+            options
+        })
+        .await?;
+        check_batches(&batches, &expected[..250]);
+
+        // Reverse
+        let batches = read_batches({
+            let mut options = RangeOptions::default();
+            options.set_target_bytes(1000);
+            options.set_reverse(true);
+            options
+        })
+        .await?;
+        check_batches(
+            &batches,
+            &expected.iter().rev().cloned().collect::<Vec<_>>(),
+        );
+
+        // Exact streaming mode returns `limit` key-value pairs in a
+        // single batch.
+        let batches = read_batches({
+            let mut options = RangeOptions::default();
+            options.set_limit(100);
+            options.set_mode(StreamingMode::Exact);
+            options
+        })
+        .await?;
+        assert_eq!(batches.len(), 1);
+        check_batches(&batches, &expected[..100]);
+
+        Ok(())
+    })
+}
@@ -0,0 +1,197 @@
+use bytes::Bytes;
+
+use fdb::database::FdbDatabase;
+use fdb::error::FdbResult;
+use fdb::layer::ranked_set::RankedSet;
+use fdb::testing::run_test;
+
+use std::collections::BTreeSet;
+use std::error::Error;
+
+const KEYS: usize = 300;
+const BATCH_SIZE: usize = 25;
+
+// Deterministic pseudo-random keys, so that failures can be
+// reproduced.
+fn test_keys(n: usize) -> Vec<Bytes> {
+    let mut x: u64 = 0x2545_f491_4f6c_dd1d;
+
+    (0..n)
+        .map(|_| {
+            x = x
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            Bytes::from(format!("key-{:016x}", x >> 16))
+        })
+        .collect()
+}
+
+async fn insert(
+    fdb_database: &FdbDatabase,
+    ranked_set: &RankedSet,
+    keys: &[Bytes],
+) -> FdbResult<()> {
+    fdb_database
+        .run(|tr| async move {
+            for key in keys {
+                ranked_set.insert(&tr, key.clone()).await?;
+            }
+            Ok(())
+        })
+        .await
+}
+
+async fn delete(
+    fdb_database: &FdbDatabase,
+    ranked_set: &RankedSet,
+    keys: &[Bytes],
+) -> FdbResult<()> {
+    fdb_database
+        .run(|tr| async move {
+            for key in keys {
+                ranked_set.delete(&tr, key.clone()).await?;
+            }
+            Ok(())
+        })
+        .await
+}
+
+// Checks `rank`, `get_nth`, `get_range_by_rank` and `get_size` of
+// `ranked_set` against `expected`, and that `rank` of each of `absent`
+// is `None`.
+async fn check(
+    fdb_database: &FdbDatabase,
+    ranked_set: &RankedSet,
+    expected: &BTreeSet<Bytes>,
+    absent: &[Bytes],
+) -> FdbResult<()> {
+    let expected = expected.iter().cloned().collect::<Vec<_>>();
+
+    let (size, ranks, nths, past_end, range, absent_ranks) = fdb_database
+        .read(|tr| {
+            let expected = expected.clone();
+            async move {
+                let size = ranked_set.get_size(&tr).await?;
+
+                let mut ranks = Vec::new();
+                for key in &expected {
+                    ranks.push(ranked_set.rank(&tr, key.clone()).await?);
+                }
+
+                let mut nths = Vec::new();
+                for i in 0..expected.len() {
+                    nths.push(ranked_set.get_nth(&tr, i as i64).await?);
+                }
+
+                let past_end = ranked_set.get_nth(&tr, expected.len() as i64).await?;
+
+                let range = ranked_set
+                    .get_range_by_rank(&tr, 10, 10 + BATCH_SIZE as i64)
+                    .await?;
+
+                let mut absent_ranks = Vec::new();
+                for key in absent {
+                    absent_ranks.push(ranked_set.rank(&tr, key.clone()).await?);
+                }
+
+                Ok((size, ranks, nths, past_end, range, absent_ranks))
+            }
+        })
+        .await?;
+
+    assert_eq!(size, expected.len() as i64);
+    assert_eq!(
+        ranks,
+        (0..expected.len())
+            .map(|i| Some(i as i64))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(nths, expected.iter().cloned().map(Some).collect::<Vec<_>>());
+    assert_eq!(past_end, None);
+    assert_eq!(
+        range,
+        expected
+            .iter()
+            .skip(10)
+            .take(BATCH_SIZE)
+            .cloned()
+            .collect::<Vec<_>>()
+    );
+    assert!(absent_ranks.iter().all(Option::is_none));
+
+    Ok(())
+}
+
+#[test]
+fn ranked_set_rank_nth() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let ranked_set = RankedSet::new(subspace);
+
+        let keys = test_keys(KEYS);
+        let mut expected = BTreeSet::new();
+
+        check(&fdb_database, &ranked_set, &expected, &keys[..1]).await?;
+
+        // Insert keys in batches, checking against the sorted keys
+        // after every batch.
+        for batch in keys.chunks(BATCH_SIZE) {
+            insert(&fdb_database, &ranked_set, batch).await?;
+            expected.extend(batch.iter().cloned());
+
+            check(&fdb_database, &ranked_set, &expected, &[]).await?;
+        }
+
+        // Inserting keys again has no effect.
+        insert(&fdb_database, &ranked_set, &keys[..BATCH_SIZE]).await?;
+        check(&fdb_database, &ranked_set, &expected, &[]).await?;
+
+        // Delete every third key, in batches.
+        let deleted = keys.iter().step_by(3).cloned().collect::<Vec<_>>();
+
+        for batch in deleted.chunks(BATCH_SIZE) {
+            delete(&fdb_database, &ranked_set, batch).await?;
+            for key in batch {
+                expected.remove(key);
+            }
+
+            check(&fdb_database, &ranked_set, &expected, batch).await?;
+        }
+
+        // Deleting keys that are not in the set has no effect.
+        delete(&fdb_database, &ranked_set, &deleted[..BATCH_SIZE]).await?;
+        check(&fdb_database, &ranked_set, &expected, &deleted).await?;
+
+        // Insert and delete in the same transaction.
+        let reinserted = &deleted[..BATCH_SIZE];
+        let removed = expected
+            .iter()
+            .take(BATCH_SIZE)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        fdb_database
+            .run(|tr| {
+                let ranked_set = ranked_set.clone();
+                let removed = removed.clone();
+                async move {
+                    for key in reinserted {
+                        ranked_set.insert(&tr, key.clone()).await?;
+                    }
+                    for key in removed {
+                        ranked_set.delete(&tr, key).await?;
+                    }
+                    Ok(())
+                }
+            })
+            .await?;
+
+        expected.extend(reinserted.iter().cloned());
+        for key in &removed {
+            expected.remove(key);
+        }
+
+        check(&fdb_database, &ranked_set, &expected, &removed).await?;
+
+        Ok(())
+    })
+}
@@ -0,0 +1,142 @@
+use bytes::Bytes;
+
+use fdb::error::FdbResult;
+use fdb::testing::{assert_range_eq, run_test, TestSubspace};
+use fdb::transaction::{ReadTransaction, Transaction};
+use fdb::tuple::Tuple;
+use fdb::KeyValue;
+
+use tokio::time;
+
+use std::error::Error;
+use std::time::Duration;
+
+#[test]
+fn test_fixtures() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, _| async move {
+        let test_subspace = TestSubspace::new(&fdb_database);
+        let other_test_subspace = TestSubspace::new(&fdb_database);
+        assert_ne!(
+            test_subspace.get_subspace_ref(),
+            other_test_subspace.get_subspace_ref()
+        );
+
+        let key = test_subspace.get_subspace_ref().subspace(&{
+            let mut t = Tuple::new();
+            t.add_string("key".to_string());
+            t
+        });
+        let key = key.pack();
+
+        fdb_database
+            .run(|tr| {
+                let key = key.clone();
+                async move {
+                    tr.set(key, Bytes::from_static(b"value"));
+                    Ok(())
+                }
+            })
+            .await?;
+
+        let range = test_subspace.range();
+
+        fdb_database
+            .read(|tr| {
+                let (key, range) = (key.clone(), range.clone());
+                async move {
+                    assert_range_eq(
+                        &tr,
+                        range,
+                        vec![KeyValue::new(
+                            key.into(),
+                            Bytes::from_static(b"value").into(),
+                        )],
+                    )
+                    .await;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        // Keys are cleared when `cleanup` returns.
+        test_subspace.cleanup().await?;
+
+        fdb_database
+            .read(|tr| {
+                let range = range.clone();
+                async move {
+                    assert_range_eq(&tr, range, Vec::new()).await;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        // When the fixture is dropped within a runtime, keys are
+        // cleared in the background.
+        let other_key = other_test_subspace.get_subspace_ref().pack();
+        let other_range = other_test_subspace.range();
+
+        fdb_database
+            .run(|tr| {
+                let other_key = other_key.clone();
+                async move {
+                    tr.set(other_key, Bytes::from_static(b"value"));
+                    Ok(())
+                }
+            })
+            .await?;
+
+        drop(other_test_subspace);
+
+        time::timeout(Duration::from_secs(5), async {
+            while fdb_database
+                .read(|tr| {
+                    let other_key = other_key.clone();
+                    async move { tr.get(other_key).await }
+                })
+                .await?
+                .is_some()
+            {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+            FdbResult::Ok(())
+        })
+        .await
+        .expect("timed out waiting for keys to be cleared")?;
+
+        fdb_database
+            .read(|tr| {
+                let other_range = other_range.clone();
+                async move {
+                    assert_range_eq(&tr, other_range, Vec::new()).await;
+                    Ok(())
+                }
+            })
+            .await?;
+
+        #[cfg(feature = "fdb-7_1")]
+        {
+            use fdb::testing::TestTenant;
+
+            let test_tenant = TestTenant::new(&fdb_database).await?;
+            let tenant_name = test_tenant.get_tenant_ref().get_name();
+
+            test_tenant
+                .get_tenant_ref()
+                .run(|tr| async move {
+                    tr.set(Bytes::from_static(b"key"), Bytes::from_static(b"value"));
+                    Ok(())
+                })
+                .await?;
+
+            test_tenant.cleanup().await?;
+
+            // The tenant is deleted when `cleanup` returns.
+            let res =
+                fdb::tenant::TenantManagement::delete_tenant_db(&fdb_database, tenant_name).await;
+            assert_eq!(res, Err(fdb::error::FdbError::new(2131)));
+        }
+
+        Ok(())
+    })
+}
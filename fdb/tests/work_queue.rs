@@ -0,0 +1,159 @@
+use bytes::Bytes;
+
+use fdb::error::WORK_QUEUE_LEASE_LOST;
+use fdb::layer::work_queue::WorkQueue;
+use fdb::testing::run_test;
+
+use tokio::time::{self, Instant};
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::time::Duration;
+
+const ITEMS: usize = 200;
+const WORKERS: usize = 8;
+
+const LEASE: Duration = Duration::from_millis(500);
+const LONG_LEASE: Duration = Duration::from_secs(60);
+
+#[test]
+fn work_queue() -> Result<(), Box<dyn Error>> {
+    run_test(|fdb_database, subspace| async move {
+        let work_queue = WorkQueue::new(fdb_database.clone(), subspace);
+
+        // Claim and ack
+        work_queue.push(Bytes::from_static(b"a")).await?;
+        work_queue.push(Bytes::from_static(b"b")).await?;
+
+        let first = work_queue.claim(LEASE).await?.unwrap();
+        let second = work_queue.claim(LEASE).await?.unwrap();
+        assert_ne!(first.get_id_ref(), second.get_id_ref());
+        assert_eq!(
+            [first.get_item_ref().clone(), second.get_item_ref().clone()]
+                .iter()
+                .cloned()
+                .collect::<HashSet<_>>(),
+            [Bytes::from_static(b"a"), Bytes::from_static(b"b")]
+                .iter()
+                .cloned()
+                .collect::<HashSet<_>>()
+        );
+
+        // Leased items are not visible.
+        assert_eq!(work_queue.claim(LEASE).await?, None);
+
+        work_queue.ack(&first).await?;
+
+        // An acknowledged item is no longer leased.
+        assert_eq!(
+            work_queue.ack(&first).await.unwrap_err().code(),
+            WORK_QUEUE_LEASE_LOST
+        );
+
+        // Lease expiry
+        time::sleep(LEASE * 2).await;
+
+        let reclaimed = work_queue.claim(LONG_LEASE).await?.unwrap();
+        assert_eq!(reclaimed.get_id_ref(), second.get_id_ref());
+        assert_eq!(reclaimed.get_item_ref(), second.get_item_ref());
+        assert!(reclaimed.get_deadline() > second.get_deadline());
+
+        // The worker whose lease expired can neither extend nor
+        // acknowledge the item.
+        assert_eq!(
+            work_queue
+                .extend(&second, LONG_LEASE)
+                .await
+                .unwrap_err()
+                .code(),
+            WORK_QUEUE_LEASE_LOST
+        );
+        assert_eq!(
+            work_queue.ack(&second).await.unwrap_err().code(),
+            WORK_QUEUE_LEASE_LOST
+        );
+
+        let extended = work_queue.extend(&reclaimed, LONG_LEASE * 2).await?;
+        assert!(extended.get_deadline() > reclaimed.get_deadline());
+        assert_eq!(
+            work_queue.ack(&reclaimed).await.unwrap_err().code(),
+            WORK_QUEUE_LEASE_LOST
+        );
+        work_queue.ack(&extended).await?;
+
+        assert_eq!(work_queue.claim(LEASE).await?, None);
+
+        // Delayed items are not visible until the delay has passed.
+        work_queue
+            .push_delayed(Bytes::from_static(b"c"), LEASE)
+            .await?;
+        assert_eq!(work_queue.claim(LEASE).await?, None);
+
+        // `claim_wait` wakes up when the delayed item becomes visible.
+        let start = Instant::now();
+        let delayed = work_queue.claim_wait(LEASE).await?;
+        assert_eq!(delayed.get_item_ref(), &Bytes::from_static(b"c"));
+        assert!(start.elapsed() >= LEASE / 2);
+
+        // `claim_wait` wakes up when the lease of the item expires.
+        let start = Instant::now();
+        let expired = work_queue.claim_wait(LEASE).await?;
+        assert_eq!(expired.get_id_ref(), delayed.get_id_ref());
+        assert!(start.elapsed() >= LEASE / 2);
+        work_queue.ack(&expired).await?;
+
+        // `claim_wait` wakes up when an item is pushed.
+        let join_handle = tokio::spawn({
+            let work_queue = work_queue.clone();
+            async move { work_queue.claim_wait(LEASE).await }
+        });
+
+        time::sleep(Duration::from_millis(100)).await;
+        work_queue.push(Bytes::from_static(b"d")).await?;
+
+        let pushed = join_handle.await??;
+        assert_eq!(pushed.get_item_ref(), &Bytes::from_static(b"d"));
+        work_queue.ack(&pushed).await?;
+
+        // Concurrent workers
+        for i in 0..ITEMS {
+            work_queue.push(Bytes::from(format!("{:08}", i))).await?;
+        }
+
+        let mut join_handles = Vec::new();
+
+        for _ in 0..WORKERS {
+            let work_queue = work_queue.clone();
+
+            join_handles.push(tokio::spawn(async move {
+                let mut acked = Vec::new();
+
+                while let Some(work_item) = work_queue.claim(LONG_LEASE).await? {
+                    work_queue.ack(&work_item).await?;
+                    acked.push(work_item.into_item());
+                }
+
+                fdb::error::FdbResult::Ok(acked)
+            }));
+        }
+
+        let mut all_acked = Vec::new();
+
+        for join_handle in join_handles {
+            all_acked.extend(join_handle.await??);
+        }
+
+        // Every item must be claimed and acknowledged exactly once.
+        all_acked.sort();
+        assert_eq!(
+            all_acked,
+            (0..ITEMS)
+                .map(|i| Bytes::from(format!("{:08}", i)))
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(work_queue.claim(LEASE).await?, None);
+
+        Ok(())
+    })
+}
@@ -21,9 +21,9 @@ cargo build --example hello_world --features=fdb-6_3
 cargo build --example open_database --features=fdb-6_3
 cargo build --example watch --features=fdb-6_3
 
-RUSTDOCFLAGS="--deny warnings" cargo doc --lib --features=fdb-6_3
+RUSTDOCFLAGS="--deny warnings" cargo doc --lib --features=fdb-6_3,fault,memory,metrics,status,testing,tracing
 
-cargo test --lib --tests --features=fdb-6_3
+cargo test --lib --tests --features=fdb-6_3,fault,memory,metrics,status,testing,tracing
 
 echo ""
 echo "+-------------------------------------------+"
@@ -43,7 +43,7 @@ cargo clippy --lib --bins --examples --tests --features=fdb-6_3 -- --deny warnin
 cd ../ || { echo "cd failure"; exit 1; }
 
 cd fdb || { echo "cd failure"; exit 1; }
-cargo clippy --lib --bins --tests --features=fdb-6_3 -- --deny warnings
+cargo clippy --lib --bins --tests --features=fdb-6_3,fault,memory,metrics,status,testing,tracing -- --deny warnings
 
 cargo clippy --example get_committed_version --features=fdb-6_3 -- --deny warnings
 cargo clippy --example get_range --features=fdb-6_3 -- --deny warnings
@@ -22,9 +22,9 @@ cargo build --example hello_world --features=fdb-7_1
 cargo build --example open_database --features=fdb-7_1
 cargo build --example watch --features=fdb-7_1
 
-RUSTDOCFLAGS="--deny warnings" cargo doc --lib --features=fdb-7_1
+RUSTDOCFLAGS="--deny warnings" cargo doc --lib --features=fdb-7_1,fault,memory,metrics,status,testing,tracing
 
-cargo test --lib --tests --features=fdb-7_1
+cargo test --lib --tests --features=fdb-7_1,fault,memory,metrics,status,testing,tracing
 
 echo ""
 echo "+-------------------------------------------+"
@@ -44,7 +44,7 @@ cargo clippy --lib --bins --examples --tests --features=fdb-7_1 -- --deny warnin
 cd ../ || { echo "cd failure"; exit 1; }
 
 cd fdb || { echo "cd failure"; exit 1; }
-cargo clippy --lib --bins --tests --features=fdb-7_1 -- --deny warnings
+cargo clippy --lib --bins --tests --features=fdb-7_1,fault,memory,metrics,status,testing,tracing -- --deny warnings
 
 cargo clippy --example get_committed_version --features=fdb-7_1 -- --deny warnings
 cargo clippy --example get_mapped_range --features=fdb-7_1 -- --deny warnings
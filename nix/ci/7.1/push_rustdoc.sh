@@ -12,19 +12,19 @@ cd ../../ || { echo "cd failure"; exit 1; }
 
 cd fdb-gen || { echo "cd failure"; exit 1; }
 
-cargo doc --lib --no-deps --features=fdb-7_1
+cargo doc --lib --no-deps --features=fdb-7_1,fault,memory,metrics,status,testing,tracing
 
 cd ../ || { echo "cd failure"; exit 1; }
 
 cd fdb-sys || { echo "cd failure"; exit 1; }
 
-cargo doc --lib --no-deps --features=fdb-7_1
+cargo doc --lib --no-deps --features=fdb-7_1,fault,memory,metrics,status,testing,tracing
 
 cd ../ || { echo "cd failure"; exit 1; }
 
 cd fdb || { echo "cd failure"; exit 1; }
 
-cargo doc --lib --no-deps --features=fdb-7_1
+cargo doc --lib --no-deps --features=fdb-7_1,fault,memory,metrics,status,testing,tracing
 
 cd ../ || { echo "cd failure"; exit 1; }
 